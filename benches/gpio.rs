@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+use bcm283x_linux_gpio::{Gpio, GpioConfig, GpioState, PinFunction, PinMask};
+
+/// Memory standing in for the GPIO control block.
+#[repr(align(4))]
+struct Registers([u32; 0x40]);
+
+fn fake_gpio(registers: &mut Registers) -> Gpio {
+	unsafe { Gpio::from_memory(registers.0.as_mut_ptr().cast()) }
+}
+
+fn set_level(c: &mut Criterion) {
+	let mut registers = Registers([0; 0x40]);
+	let mut gpio = fake_gpio(&mut registers);
+	c.bench_function("set_level", |b| b.iter(|| gpio.set_level(black_box(17), black_box(true))));
+}
+
+fn mask_writes(c: &mut Criterion) {
+	let mut registers = Registers([0; 0x40]);
+	let mut gpio = fake_gpio(&mut registers);
+	let mask: PinMask = [2, 3, 4, 17, 22, 27, 40].iter().copied().collect();
+	c.bench_function("PinMask::set_all", |b| b.iter(|| black_box(mask).set_all(&mut gpio)));
+	c.bench_function("PinMask::write_levels", |b| b.iter(|| black_box(mask).write_levels(&mut gpio, PinMask::from_bits(1 << 17))));
+}
+
+fn read_all(c: &mut Criterion) {
+	let mut registers = Registers([0; 0x40]);
+	let gpio = fake_gpio(&mut registers);
+	let mut state = GpioState::new();
+	c.bench_function("read_all", |b| b.iter(|| black_box(gpio.read_all())));
+	c.bench_function("read_all_into", |b| b.iter(|| gpio.read_all_into(black_box(&mut state))));
+	c.bench_function("read_levels_and_events", |b| b.iter(|| black_box(gpio.read_levels_and_events())));
+}
+
+fn config_apply(c: &mut Criterion) {
+	let mut registers = Registers([0; 0x40]);
+	let mut gpio = fake_gpio(&mut registers);
+	let mut config = GpioConfig::new();
+	for pin in 2..10 {
+		config.set_function(pin, PinFunction::Output).unwrap();
+		config.set_level(pin, pin % 2 == 0).unwrap();
+	}
+	c.bench_function("GpioConfig::register_ops", |b| b.iter(|| black_box(config.register_ops())));
+	c.bench_function("GpioConfig::apply", |b| b.iter(|| config.apply(&mut gpio)));
+}
+
+criterion_group!(benches, set_level, mask_writes, read_all, config_apply);
+criterion_main!(benches);
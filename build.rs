@@ -0,0 +1,19 @@
+fn main() {
+	println!("cargo:rerun-if-changed=build.rs");
+	#[cfg(feature = "grpc")]
+	compile_grpc();
+}
+
+/// Generate the gRPC client and server from proto/gpio.proto.
+///
+/// The proto file is parsed with protox, so protoc does not need to be installed.
+#[cfg(feature = "grpc")]
+fn compile_grpc() {
+	println!("cargo:rerun-if-changed=proto/gpio.proto");
+	let descriptors = protox::compile(["proto/gpio.proto"], ["proto"]).expect("failed to parse proto/gpio.proto");
+	// The generated connect functions need the prelude of edition 2021, so connect through an Endpoint instead.
+	tonic_build::configure()
+		.build_transport(false)
+		.compile_fds(descriptors)
+		.expect("failed to generate the gRPC code");
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+use bcm283x_linux_gpio::platform::{parse_cell_count, GpioNode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)| {
+	let (compatible, reg, address_cells, size_cells) = input;
+	let address_cells = parse_cell_count(&address_cells).unwrap_or(2);
+	let size_cells    = parse_cell_count(&size_cells).unwrap_or(1);
+	if let Ok(node) = GpioNode::parse(&compatible, &reg, address_cells, size_cells) {
+		let _ = node.soc();
+	}
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use bcm283x_linux_gpio::platform::{find_gpio, parse_iomem};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(entries) = parse_iomem(data) {
+		for entry in &entries {
+			assert!(entry.start <= entry.end);
+			let _ = entry.size();
+		}
+		if let Ok(gpio) = find_gpio(&entries) {
+			assert!(gpio.is_gpio());
+		}
+	}
+});
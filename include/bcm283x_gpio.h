@@ -0,0 +1,176 @@
+#ifndef BCM283X_GPIO_H
+#define BCM283X_GPIO_H
+
+/* Generated with cbindgen from src/capi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define RPI_GPIO_OK 0
+
+#define RPI_GPIO_ERROR_PERMISSION_DENIED -1
+
+#define RPI_GPIO_ERROR_PERIPHERAL_NOT_FOUND -2
+
+#define RPI_GPIO_ERROR_UNSUPPORTED_SOC -3
+
+#define RPI_GPIO_ERROR_INVALID_PIN_INDEX -4
+
+#define RPI_GPIO_ERROR_IOMEM_PARSE -5
+
+#define RPI_GPIO_ERROR_DEVICE_TREE -6
+
+#define RPI_GPIO_ERROR_MMAP -7
+
+#define RPI_GPIO_ERROR_UNSUPPORTED -8
+
+#define RPI_GPIO_ERROR_IO -9
+
+#define RPI_GPIO_ERROR_INVALID_ARGUMENT -10
+
+#define RPI_GPIO_ERROR_UNSAFE_REGISTER -11
+
+#define RPI_GPIO_ERROR_NOT_ALLOWED -12
+
+#define RPI_GPIO_ERROR_VERIFICATION_FAILED -13
+
+#define RPI_GPIO_ERROR_TIMEOUT -14
+
+#define RPI_GPIO_ERROR_NO_RESPONSE -15
+
+#define RPI_GPIO_ERROR_PROTOCOL -16
+
+#define RPI_GPIO_ERROR_OTHER -17
+
+#define RPI_GPIO_PULL_FLOAT 0
+
+#define RPI_GPIO_PULL_DOWN 1
+
+#define RPI_GPIO_PULL_UP 2
+
+/**
+ * The number of register words filled in by [`rpi_gpio_read_all`].
+ */
+#define RPI_GPIO_STATE_WORDS 256
+
+typedef struct rpi_gpio_t rpi_gpio_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open the GPIO peripheral through /dev/mem.
+ *
+ * # Safety
+ * `gpio` must be a valid pointer to write the handle to.
+ * The handle must be closed with [`rpi_gpio_close`].
+ */
+int rpi_gpio_open(rpi_gpio_t **gpio);
+
+/**
+ * Open the GPIO peripheral through /dev/gpiomem, which usually does not require root permission.
+ *
+ * # Safety
+ * See [`rpi_gpio_open`].
+ */
+int rpi_gpio_open_gpiomem(rpi_gpio_t **gpio);
+
+/**
+ * Open the GPIO peripheral through the GPIO character device.
+ *
+ * Alternate functions are not supported by the handle.
+ *
+ * # Safety
+ * See [`rpi_gpio_open`].
+ */
+int rpi_gpio_open_cdev(rpi_gpio_t **gpio);
+
+/**
+ * Close a handle and release its resources.
+ *
+ * Passing a null pointer does nothing.
+ *
+ * # Safety
+ * `gpio` must be null or a handle returned by one of the open functions, and must not be used afterwards.
+ */
+void rpi_gpio_close(rpi_gpio_t *gpio);
+
+/**
+ * Read the level of a pin.
+ *
+ * # Safety
+ * `gpio` must be a valid handle and `level` a valid pointer to write the level to.
+ */
+int rpi_gpio_read_level(const rpi_gpio_t *gpio, unsigned int pin, bool *level);
+
+/**
+ * Read the levels of all pins, with bit N holding the level of pin N.
+ *
+ * # Safety
+ * `gpio` must be a valid handle and `levels` a valid pointer to write the levels to.
+ */
+int rpi_gpio_read_levels(const rpi_gpio_t *gpio, uint64_t *levels);
+
+/**
+ * Set the level of a pin.
+ *
+ * # Safety
+ * `gpio` must be a valid handle.
+ */
+int rpi_gpio_set_level(rpi_gpio_t *gpio, unsigned int pin, bool level);
+
+/**
+ * Read the function of a pin, as the three function select bits: 0 for input, 1 for output, 4 for alt0, and so on.
+ *
+ * # Safety
+ * `gpio` must be a valid handle and `function` a valid pointer to write the function to.
+ */
+int rpi_gpio_get_function(const rpi_gpio_t *gpio, unsigned int pin, uint8_t *function);
+
+/**
+ * Set the function of a pin, as the three function select bits: 0 for input, 1 for output, 4 for alt0, and so on.
+ *
+ * # Safety
+ * `gpio` must be a valid handle.
+ */
+int rpi_gpio_set_function(rpi_gpio_t *gpio, unsigned int pin, uint8_t function);
+
+/**
+ * Set the pull up/down mode of a pin to one of the `RPI_GPIO_PULL_*` values.
+ *
+ * # Safety
+ * `gpio` must be a valid handle.
+ * Changing the pull up/down mode is not atomic, see [`GpioPullConfig::apply`].
+ */
+int rpi_gpio_set_pull_mode(rpi_gpio_t *gpio, unsigned int pin, int mode);
+
+/**
+ * Read all GPIO registers at once.
+ *
+ * `registers` must have room for `RPI_GPIO_STATE_WORDS` values.
+ *
+ * # Safety
+ * `gpio` must be a valid handle and `registers` must be valid for writes of `len` values.
+ */
+int rpi_gpio_read_all(const rpi_gpio_t *gpio, uint32_t *registers, size_t len);
+
+/**
+ * Get a description of the last error on the calling thread, or null if there was none.
+ *
+ * The string remains valid until the next failing call on the same thread.
+ */
+const char *rpi_gpio_last_error(void);
+
+/**
+ * Get a static description of an error code.
+ */
+const char *rpi_gpio_strerror(int code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BCM283X_GPIO_H */
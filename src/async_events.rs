@@ -0,0 +1,158 @@
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{Edge, Error, ErrorKind, EventNotifier, GpioEvent};
+
+/// A file descriptor registered with the reactor of an async runtime.
+///
+/// This is the only part of [`AsyncEvents`] that depends on a runtime.
+/// Adapters are available for tokio with the `tokio` feature,
+/// and for async-std and smol with the `async-io` feature.
+pub trait AsyncFd: Sized + Unpin {
+	/// Register a non-blocking file descriptor with the reactor, taking ownership of it.
+	fn new(fd: OwnedFd) -> std::io::Result<Self>;
+
+	/// Read from the file descriptor, or register the task to be woken when it becomes readable.
+	fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>>;
+}
+
+/// Await edges on GPIO pins, on any async runtime.
+///
+/// The pins are polled by an [`EventNotifier`] thread,
+/// which wakes the waiting task through an eventfd registered with the reactor of the runtime by `F`.
+///
+/// Events can be awaited one by one with [`next_event`](Self::next_event),
+/// or consumed as a [`Stream`](futures_core::Stream) that never ends.
+pub struct AsyncEvents<F> {
+	// The notifier is declared first so it is dropped first,
+	// stopping its thread before the eventfd is closed.
+	notifier: EventNotifier,
+	eventfd: F,
+}
+
+impl<F: AsyncFd> AsyncEvents<F> {
+	/// Start polling the pins at an interval.
+	///
+	/// No events are detected until edges are added with [`watch`](Self::watch).
+	/// Some adapters, like the one for tokio, must be created from within the runtime.
+	pub fn start(interval: Duration) -> Result<Self, Error> {
+		use nix::sys::eventfd::{eventfd, EfdFlags};
+
+		let fd = eventfd(0, EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK)
+			.map_err(|e| Error::from_nix(ErrorKind::Io, "failed to create eventfd", e))?;
+		// Safety: the file descriptor was just created and is not owned by anything else.
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+		// Register the eventfd before starting the notifier,
+		// so a failure never leaves the notifier writing to a closed file descriptor.
+		let raw_fd = fd.as_raw_fd();
+		let eventfd = F::new(fd).map_err(|e| Error::from_io(ErrorKind::Io, "failed to register eventfd with the async runtime", e))?;
+		let notifier = EventNotifier::start(raw_fd, interval)?;
+
+		Ok(Self { notifier, eventfd })
+	}
+
+	/// Start watching a pin for an edge.
+	pub fn watch(&self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.notifier.watch(pin, edge)
+	}
+
+	/// Stop watching a pin for an edge.
+	///
+	/// Events that were already queued for the edge are kept.
+	pub fn unwatch(&self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.notifier.unwatch(pin, edge)
+	}
+
+	/// Take the next event, or register the task to be woken when an event is detected.
+	pub fn poll_next_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<GpioEvent, Error>> {
+		loop {
+			if let Some(event) = self.notifier.pop() {
+				return Poll::Ready(Ok(event));
+			}
+
+			// The events are queued in the notifier, reading only resets the eventfd counter.
+			let mut counter = [0; 8];
+			match self.eventfd.poll_read(cx, &mut counter) {
+				Poll::Ready(Ok(_))  => continue,
+				Poll::Ready(Err(e)) => return Poll::Ready(Err(Error::from_io(ErrorKind::Io, "failed to read eventfd", e))),
+				Poll::Pending       => return Poll::Pending,
+			}
+		}
+	}
+
+	/// Wait for the next event.
+	///
+	/// Dropping the future before it completes does not lose any events.
+	pub async fn next_event(&mut self) -> Result<GpioEvent, Error> {
+		std::future::poll_fn(|cx| self.poll_next_event(cx)).await
+	}
+}
+
+impl<F: AsyncFd> futures_core::Stream for AsyncEvents<F> {
+	type Item = Result<GpioEvent, Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.get_mut().poll_next_event(cx).map(Some)
+	}
+}
+
+/// An [`AsyncFd`] for the tokio runtime.
+#[cfg(feature = "tokio")]
+pub struct TokioFd(tokio::io::unix::AsyncFd<OwnedFd>);
+
+#[cfg(feature = "tokio")]
+impl AsyncFd for TokioFd {
+	/// Register a file descriptor with the reactor of the current tokio runtime.
+	///
+	/// # Panics
+	/// This function panics when called outside of a tokio runtime.
+	fn new(fd: OwnedFd) -> std::io::Result<Self> {
+		tokio::io::unix::AsyncFd::with_interest(fd, tokio::io::Interest::READABLE).map(Self)
+	}
+
+	fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+		loop {
+			let mut guard = std::task::ready!(self.0.poll_read_ready(cx))?;
+			if let Ok(result) = guard.try_io(|fd| read(fd.as_raw_fd(), buf)) {
+				return Poll::Ready(result);
+			}
+		}
+	}
+}
+
+/// An [`AsyncFd`] for async-io, the reactor of async-std and smol.
+#[cfg(feature = "async-io")]
+pub struct AsyncIoFd(async_io::Async<OwnedFd>);
+
+#[cfg(feature = "async-io")]
+impl AsyncFd for AsyncIoFd {
+	fn new(fd: OwnedFd) -> std::io::Result<Self> {
+		async_io::Async::new(fd).map(Self)
+	}
+
+	fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+		loop {
+			match read(self.0.get_ref().as_raw_fd(), buf) {
+				Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => (),
+				result => return Poll::Ready(result),
+			}
+			std::task::ready!(self.0.poll_readable(cx))?;
+		}
+	}
+}
+
+/// Read from a non-blocking file descriptor.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn read(fd: RawFd, buf: &mut [u8]) -> std::io::Result<usize> {
+	let read = unsafe { nix::libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+	if read < 0 {
+		Err(std::io::Error::last_os_error())
+	} else {
+		Ok(read as usize)
+	}
+}
@@ -0,0 +1,280 @@
+#[cfg(feature = "std")]
+use std::convert::TryFrom;
+
+#[cfg(feature = "std")]
+use crate::platform::GpioController;
+#[cfg(feature = "std")]
+use crate::{Error, ErrorKind};
+use crate::{Register, Soc};
+
+/// The device file that maps only the GPIO peripheral, accessible without root.
+#[cfg(feature = "std")]
+const GPIOMEM : &str = "/dev/gpiomem";
+
+/// The features supported by a [`GpioBackend`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+	/// All registers can be read and written as-is.
+	///
+	/// Backends without raw register access only emulate the registers needed for the other capabilities.
+	pub registers: bool,
+
+	/// Pins can be set to an alternate function.
+	pub alt_functions: bool,
+
+	/// The pull up/down mode of pins can be changed.
+	pub pull: bool,
+
+	/// The event detect registers are supported.
+	pub events: bool,
+
+	/// All writes are ignored, so the backend can only be used to inspect the pins.
+	pub read_only: bool,
+}
+
+impl Capabilities {
+	/// Get the capabilities of a backend that supports everything.
+	pub fn all() -> Self {
+		Self {
+			registers:     true,
+			alt_functions: true,
+			pull:          true,
+			events:        true,
+			read_only:     false,
+		}
+	}
+}
+
+/// A source of GPIO registers for a [`Gpio`](crate::Gpio) handle.
+///
+/// Backends that map the peripheral into memory return the mapping from [`control_block`](Self::control_block),
+/// which the handle then accesses directly.
+/// Other backends emulate the registers they support in [`read_register`](Self::read_register)
+/// and [`write_register`](Self::write_register), as described by their [`Capabilities`].
+pub trait GpioBackend: Send {
+	/// Get a short description of the backend, like `/dev/mem`.
+	fn name(&self) -> &str;
+
+	/// Get the features supported by the backend.
+	fn capabilities(&self) -> Capabilities;
+
+	/// Get the SoC family of the GPIO peripheral.
+	fn soc(&self) -> Soc {
+		Soc::Bcm2835
+	}
+
+	/// Get the memory mapped control block, if the backend maps the GPIO peripheral.
+	///
+	/// The mapping must stay valid for the lifetime of the backend.
+	fn control_block(&self) -> Option<*mut core::ffi::c_void> {
+		None
+	}
+
+	/// Read a value from a register.
+	fn read_register(&self, reg: Register) -> u32;
+
+	/// Write a value to a register.
+	///
+	/// # Safety
+	/// The same precautions as for [`Gpio::write_register`](crate::Gpio::write_register) apply.
+	unsafe fn write_register(&mut self, reg: Register, value: u32);
+
+	/// Read the current level of all GPIO pins.
+	///
+	/// Bit N of the returned value holds the level of pin N.
+	fn read_levels(&self) -> u64 {
+		let low  = u64::from(self.read_register(Register::GPLEV0));
+		let high = u64::from(self.read_register(Register::GPLEV1));
+		(low | high << 32) & ((1 << 54) - 1)
+	}
+
+	/// Set the pins for all set bits of `high` high, and all set bits of `low` low.
+	///
+	/// Bit N of the values corresponds to pin N.
+	fn write_levels(&mut self, high: u64, low: u64) {
+		for (reg, bits) in [
+			(Register::GPSET0, high as u32),
+			(Register::GPSET1, (high >> 32) as u32),
+			(Register::GPCLR0, low as u32),
+			(Register::GPCLR1, (low >> 32) as u32),
+		] {
+			if bits != 0 {
+				// Writing the set and clear registers only affects the written pins.
+				unsafe { self.write_register(reg, bits) };
+			}
+		}
+	}
+}
+
+/// A backend that accesses the GPIO registers at a caller-provided address.
+///
+/// This is part of the `no_std` core, for bare-metal code that has the peripheral at a known address.
+pub struct MmioBackend {
+	control_block: *mut core::ffi::c_void,
+	soc: Soc,
+}
+
+// The caller of MmioBackend::new guarantees that the registers can be accessed from any thread.
+unsafe impl Send for MmioBackend {}
+
+impl MmioBackend {
+	/// Create a backend for the GPIO registers at an address.
+	///
+	/// # Safety
+	/// The address must point to the GPIO peripheral of the given SoC, or to memory that is valid
+	/// for reads and writes of 0x100 bytes and suitably aligned for `u32`, for the lifetime of the backend.
+	pub unsafe fn new(control_block: *mut core::ffi::c_void, soc: Soc) -> Self {
+		Self { control_block, soc }
+	}
+
+	fn register_address(&self, reg: Register) -> *mut u32 {
+		self.control_block.wrapping_add(reg as usize) as *mut u32
+	}
+}
+
+impl GpioBackend for MmioBackend {
+	fn name(&self) -> &str {
+		"mmio"
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities::all()
+	}
+
+	fn soc(&self) -> Soc {
+		self.soc
+	}
+
+	fn control_block(&self) -> Option<*mut core::ffi::c_void> {
+		Some(self.control_block)
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		let value = unsafe { self.register_address(reg).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		crate::peripheral_barrier();
+		self.register_address(reg).write_volatile(value)
+	}
+}
+
+/// A backend that maps the GPIO peripheral into memory, through /dev/mem or /dev/gpiomem.
+#[cfg(feature = "std")]
+pub struct MappedBackend {
+	control_block: *mut core::ffi::c_void,
+	name: &'static str,
+	soc: Soc,
+	mapped: bool,
+}
+
+// The mapping is owned by the backend and valid for its whole lifetime, regardless of the thread using it.
+#[cfg(feature = "std")]
+unsafe impl Send for MappedBackend {}
+
+#[cfg(feature = "std")]
+impl MappedBackend {
+	/// Map the GPIO peripheral through /dev/mem.
+	///
+	/// This may fail if:
+	///  - we don't have root permission.
+	///  - the kernel was compiled with CONFIG_IO_STRICT_DEVMEM.
+	///  - the kernel was compiled with CONFIG_STRICT_DEVMEM,
+	///    and not started with `iomem=relaxed` on the kernel command line.
+	///  - the address in /proc/iomem does not match the GPIO node in the device tree.
+	pub fn dev_mem() -> Result<Self, Error> {
+		let address = crate::read_gpio_address()?;
+		crate::devicetree::verify_gpio_address(address)?;
+		let control_block = crate::map_dev_mem("GPIO", address, crate::CONTROL_BLOCK_SIZE)?;
+		Ok(Self::from_parts(control_block, "/dev/mem", true))
+	}
+
+	/// Map the registers of a specific GPIO controller through /dev/mem.
+	///
+	/// The controller must be memory mapped and compatible with the BCM283x GPIO peripheral,
+	/// see [`platform::gpio_controllers`](crate::platform::gpio_controllers).
+	pub fn open(controller: &GpioController) -> Result<Self, Error> {
+		let soc = controller.soc().ok_or_else(|| Error::new(ErrorKind::UnsupportedSoC, format!(
+			"GPIO controller {} is not compatible with the BCM283x GPIO peripheral: {}",
+			controller.path,
+			controller.compatible.join(", "),
+		)))?;
+		let address = controller.address
+			.ok_or_else(|| Error::new(ErrorKind::PeripheralNotFound, format!("GPIO controller {} is not memory mapped", controller.path)))?;
+		let address = i64::try_from(address)
+			.map_err(|_| Error::new(ErrorKind::DeviceTree, format!("address 0x{:X} of GPIO controller {} is out of range", address, controller.path)))?;
+		let control_block = crate::map_dev_mem("GPIO", address, crate::CONTROL_BLOCK_SIZE)?;
+		Ok(Self { control_block, name: "/dev/mem", soc, mapped: true })
+	}
+
+	/// Map the GPIO peripheral through /dev/gpiomem.
+	///
+	/// The Raspberry Pi kernel provides /dev/gpiomem to map only the GPIO registers,
+	/// so it is usually accessible to the `gpio` group without root permission.
+	pub fn gpiomem() -> Result<Self, Error> {
+		let control_block = crate::map_memory(GPIOMEM, "GPIO", 0, crate::CONTROL_BLOCK_SIZE)?;
+		Ok(Self::from_parts(control_block, GPIOMEM, true))
+	}
+
+	/// Create a backend that operates on ordinary memory instead of the peripheral.
+	///
+	/// # Safety
+	/// The memory must be valid for reads and writes of 0x100 bytes, suitably aligned for `u32`,
+	/// and must outlive the backend.
+	#[cfg(feature = "bench")]
+	pub unsafe fn from_memory(memory: *mut core::ffi::c_void) -> Self {
+		Self { control_block: memory, name: "memory", soc: Soc::Bcm2835, mapped: false }
+	}
+
+	fn from_parts(control_block: *mut core::ffi::c_void, name: &'static str, mapped: bool) -> Self {
+		let soc = Soc::detect().unwrap_or(Soc::Bcm2835);
+		Self { control_block, name, soc, mapped }
+	}
+
+	fn register_address(&self, reg: Register) -> *mut u32 {
+		self.control_block.wrapping_add(reg as usize) as *mut u32
+	}
+}
+
+#[cfg(feature = "std")]
+impl GpioBackend for MappedBackend {
+	fn name(&self) -> &str {
+		self.name
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities::all()
+	}
+
+	fn soc(&self) -> Soc {
+		self.soc
+	}
+
+	fn control_block(&self) -> Option<*mut core::ffi::c_void> {
+		Some(self.control_block)
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		let value = unsafe { self.register_address(reg).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		crate::peripheral_barrier();
+		self.register_address(reg).write_volatile(value)
+	}
+}
+
+#[cfg(feature = "std")]
+impl Drop for MappedBackend {
+	fn drop(&mut self) {
+		if self.mapped {
+			unsafe {
+				crate::procfs::unmap_memory(self.control_block, crate::CONTROL_BLOCK_SIZE);
+			}
+		}
+	}
+}
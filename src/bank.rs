@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use crate::{Error, ErrorKind, Gpio, Level, PinMask, Register};
+
+/// Keeps the GPIO mapping alive while bank handles exist.
+struct SharedGpio(Gpio);
+
+// The bank handles only access disjoint registers through the mapping,
+// and the mapping itself is only unmapped when the last handle is dropped.
+unsafe impl Sync for SharedGpio {}
+
+/// A handle to one bank of GPIO pins: pins 0-31 or pins 32-53.
+///
+/// Bank handles are created with [`Gpio::split_banks`].
+/// Each handle only touches the GPSET, GPCLR and GPLEV registers of its own bank,
+/// so the two handles can be used from different threads without locking.
+///
+/// Function select and other configuration registers are shared between banks,
+/// so they can not be changed through a bank handle.
+pub struct GpioBank {
+	gpio: Arc<SharedGpio>,
+	bank: usize,
+}
+
+impl Gpio {
+	/// Split the handle into two independent handles for pins 0-31 and pins 32-53.
+	///
+	/// The GPIO mapping stays valid until both handles are dropped.
+	/// This requires a backend that maps the GPIO peripheral into memory.
+	pub fn split_banks(self) -> Result<(GpioBank, GpioBank), Error> {
+		if self.mapping().is_none() {
+			return Err(Error::new(ErrorKind::Unsupported, format!("the {} backend can not be split into banks", self.backend.name())));
+		}
+		let gpio = Arc::new(SharedGpio(self));
+		Ok((GpioBank { gpio: gpio.clone(), bank: 0 }, GpioBank { gpio, bank: 1 }))
+	}
+}
+
+impl GpioBank {
+	/// Get the bank index: 0 for pins 0-31, 1 for pins 32-53.
+	pub fn bank(&self) -> usize {
+		self.bank
+	}
+
+	/// Get the range of pins in the bank.
+	pub fn pins(&self) -> std::ops::Range<usize> {
+		match self.bank {
+			0 => 0..32,
+			_ => 32..54,
+		}
+	}
+
+	/// Read the current level of a GPIO pin in the bank.
+	pub fn read_level(&self, index: usize) -> Result<Level, Error> {
+		let bit = self.bit(index)?;
+		Ok((self.read_levels() & bit != 0).into())
+	}
+
+	/// Read the current level of all pins in the bank.
+	///
+	/// Bit N of the returned value holds the level of the Nth pin of the bank.
+	pub fn read_levels(&self) -> u32 {
+		let value = unsafe { self.register_address(Register::lev(self.bank)).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	/// Atomically set the level of a GPIO pin in the bank.
+	pub fn set_level(&mut self, index: usize, value: impl Into<Level>) -> Result<(), Error> {
+		let bit = self.bit(index)?;
+		match value.into() {
+			Level::High => self.set_bits(bit),
+			Level::Low  => self.clear_bits(bit),
+		}
+		Ok(())
+	}
+
+	/// Set the pins for all set bits high.
+	///
+	/// Bit N of the value corresponds to the Nth pin of the bank.
+	pub fn set_bits(&mut self, bits: u32) {
+		self.write(Register::set(self.bank), bits);
+	}
+
+	/// Set the pins for all set bits low.
+	///
+	/// Bit N of the value corresponds to the Nth pin of the bank.
+	pub fn clear_bits(&mut self, bits: u32) {
+		self.write(Register::clr(self.bank), bits);
+	}
+
+	/// Set all pins of the mask that are in this bank high.
+	pub fn set_pins(&mut self, mask: PinMask) {
+		self.set_bits(mask.words()[self.bank]);
+	}
+
+	/// Set all pins of the mask that are in this bank low.
+	pub fn clear_pins(&mut self, mask: PinMask) {
+		self.clear_bits(mask.words()[self.bank]);
+	}
+
+	fn bit(&self, index: usize) -> Result<u32, Error> {
+		if !self.pins().contains(&index) {
+			return Err(Error::new(ErrorKind::InvalidPinIndex, format!("gpio pin {} is not in bank {} (pins {:?})", index, self.bank, self.pins())));
+		}
+		Ok(1 << (index % 32))
+	}
+
+	fn write(&mut self, register: Register, value: u32) {
+		crate::trace_write(register, value);
+		crate::peripheral_barrier();
+		unsafe { (self.register_address(register) as *mut u32).write_volatile(value) }
+	}
+
+	fn register_address(&self, register: Register) -> *const u32 {
+		self.gpio.0.control_block.wrapping_add(register as usize) as *const u32
+	}
+}
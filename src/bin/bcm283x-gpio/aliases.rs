@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use structopt::StructOpt;
+use yansi::Paint;
+
+/// The alias file used when the environment variable is not set.
+const DEFAULT_ALIAS_FILE: &str = "/etc/bcm283x-gpio/aliases.toml";
+
+/// The environment variable with the path of the alias file.
+const ALIAS_FILE_VAR: &str = "BCM283X_GPIO_ALIASES";
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+The alias file maps names to pin numbers:
+
+    status-led = 17
+    button = 22
+
+It is read from $BCM283X_GPIO_ALIASES, or /etc/bcm283x-gpio/aliases.toml if that is not set.
+The aliases can be used everywhere a single pin is expected.
+")]
+pub struct AliasesOptions {
+	/// Print only the names, one per line, for shell completion.
+	#[structopt(long = "names")]
+	names: bool,
+}
+
+/// Print the pin aliases.
+pub fn run(command: &AliasesOptions) {
+	let aliases = match load() {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	for (name, pin) in aliases {
+		match command.names {
+			true  => println!("{}", name),
+			false => println!("{:<2} {}", Paint::yellow(pin), Paint::cyan(name)),
+		}
+	}
+}
+
+/// Look up the pin of an alias.
+pub fn resolve(name: &str) -> Result<Option<usize>, String> {
+	Ok(load()?.iter().find(|(x, _)| x == name).map(|(_, pin)| *pin))
+}
+
+/// Get the path of the alias file.
+fn path() -> PathBuf {
+	match std::env::var_os(ALIAS_FILE_VAR) {
+		Some(x) => PathBuf::from(x),
+		None    => PathBuf::from(DEFAULT_ALIAS_FILE),
+	}
+}
+
+/// Load the aliases, sorted by name.
+///
+/// The file is read once, and a missing file means there are no aliases.
+fn load() -> Result<&'static [(String, usize)], String> {
+	static ALIASES: OnceLock<Result<Vec<(String, usize)>, String>> = OnceLock::new();
+	let aliases = ALIASES.get_or_init(|| {
+		let path = path();
+		match std::fs::read_to_string(&path) {
+			Ok(data) => parse(&data).map_err(|e| format!("invalid alias file {}: {}", path.display(), e)),
+			Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+			Err(error) => Err(format!("failed to read {}: {}", path.display(), error)),
+		}
+	});
+	aliases.as_deref().map_err(Clone::clone)
+}
+
+fn parse(data: &str) -> Result<Vec<(String, usize)>, String> {
+	let root: toml::Value = toml::from_str(data).map_err(|e| e.to_string())?;
+	let root = root.as_table().ok_or("expected a table")?;
+
+	let mut aliases = Vec::new();
+	for (name, pin) in root {
+		let pin = match pin.as_integer() {
+			Some(x) => crate::parse_pin_number(&x.to_string())?,
+			None    => return Err(format!("the pin of alias `{}` must be an integer", name)),
+		};
+		aliases.push((name.clone(), pin));
+	}
+	aliases.sort();
+	Ok(aliases)
+}
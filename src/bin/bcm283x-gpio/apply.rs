@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use yansi::Paint;
+
+use crate::{Options, PinCommand, PinCommandList};
+use crate::cron::CronSchedule;
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+The file contains a table per pin, using the same options as --set-pin:
+
+    [pins.17]
+    name = \"status-led\"
+    function = \"output\"
+    level = false
+
+    [pins.22]
+    name = \"button\"
+    function = \"input\"
+    pull = \"up\"
+
+The file can also contain a schedule for the daemon, which is ignored by apply.
+Each entry maps a crontab schedule to a set command of the daemon:
+
+    [schedule]
+    \"0 6 * * *\" = \"set 27,level=on\"
+    \"30 22 * * *\" = \"set 27,level=off\"
+")]
+pub struct ApplyOptions {
+	/// The TOML file with the pin configuration.
+	#[structopt(value_name = "FILE", parse(from_os_str))]
+	file: PathBuf,
+}
+
+/// A pin from the configuration file.
+pub struct NamedPin {
+	pub name: Option<String>,
+	pub command: PinCommand,
+}
+
+/// A scheduled command from the configuration file.
+pub struct ScheduleEntry {
+	/// The schedule as written in the file.
+	pub spec: String,
+	pub schedule: CronSchedule,
+	/// The daemon command to execute, like `set 27,level=on`.
+	pub command: String,
+	/// The pins configured by the command.
+	pub pins: Vec<usize>,
+}
+
+/// The contents of a configuration file.
+pub struct Config {
+	pub pins: Vec<NamedPin>,
+	pub schedule: Vec<ScheduleEntry>,
+}
+
+pub fn run(options: &Options, command: &ApplyOptions) {
+	let pins = match load(&command.file) {
+		Ok(x) => x.pins,
+		Err(error) => crate::exit_with_error(format!("invalid pin configuration in {}: {}", command.file.display(), error)),
+	};
+
+	// Options from the command line are applied after the file, so they take precedence.
+	let commands = pins.iter()
+		.map(|pin| pin.command.clone())
+		.chain(options.pin_commands())
+		.collect::<Vec<_>>();
+	crate::open_gpio_with(options, &commands);
+
+	if options.verbose {
+		for pin in &pins {
+			match &pin.name {
+				Some(name) => eprintln!("configured pin {} ({})", Paint::yellow(pin.command.index), Paint::cyan(name)),
+				None       => eprintln!("configured pin {}", Paint::yellow(pin.command.index)),
+			}
+		}
+	}
+}
+
+/// Load a configuration file.
+pub fn load(path: &Path) -> Result<Config, String> {
+	let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+	let root: toml::Value = toml::from_str(&data).map_err(|e| e.to_string())?;
+
+	let root = root.as_table().ok_or("expected a table")?;
+	if let Some(key) = root.keys().find(|x| *x != "pins" && *x != "schedule") {
+		return Err(format!("unknown key `{}`", key));
+	}
+
+	let mut config = Config {
+		pins: Vec::new(),
+		schedule: Vec::new(),
+	};
+
+	if let Some(pins) = root.get("pins") {
+		let pins = pins.as_table().ok_or("`pins` must be a table")?;
+		for (index, options) in pins {
+			let index = crate::parse_pin_index(index)?;
+			let options = options.as_table().ok_or_else(|| format!("pins.{} must be a table", index))?;
+			config.pins.push(parse_pin(index, options).map_err(|e| format!("pins.{}: {}", index, e))?);
+		}
+	}
+
+	if let Some(schedule) = root.get("schedule") {
+		let schedule = schedule.as_table().ok_or("`schedule` must be a table")?;
+		for (spec, command) in schedule {
+			let command = command.as_str().ok_or_else(|| format!("schedule.\"{}\" must be a string", spec))?;
+			config.schedule.push(parse_schedule_entry(spec, command).map_err(|e| format!("schedule.\"{}\": {}", spec, e))?);
+		}
+	}
+
+	Ok(config)
+}
+
+fn parse_schedule_entry(spec: &str, command: &str) -> Result<ScheduleEntry, String> {
+	let schedule = spec.parse::<CronSchedule>()?;
+	let pins = match crate::partition(command.trim(), ' ') {
+		("set", Some(settings)) => settings.trim().parse::<PinCommandList>()?.0.iter().map(|x| x.index).collect(),
+		_ => return Err(format!("invalid command: `{}`, expected a set command like `set 27,level=on`", command)),
+	};
+
+	Ok(ScheduleEntry {
+		spec: spec.to_string(),
+		schedule,
+		command: command.trim().to_string(),
+		pins,
+	})
+}
+
+fn parse_pin(index: usize, options: &toml::value::Table) -> Result<NamedPin, String> {
+	let mut name = None;
+	let mut command = PinCommand::new(index);
+
+	for (key, value) in options {
+		if key == "name" {
+			name = Some(value.as_str().ok_or("`name` must be a string")?.to_string());
+			continue;
+		}
+
+		let value = match value {
+			toml::Value::String(x)  => x.clone(),
+			toml::Value::Integer(x) => x.to_string(),
+			toml::Value::Boolean(true)  => String::from("on"),
+			toml::Value::Boolean(false) => String::from("off"),
+			_ => return Err(format!("invalid value for option `{}`", key)),
+		};
+
+		command.set_option(key, &value)?;
+	}
+
+	Ok(NamedPin { name, command })
+}
@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{GpioState, Level, PinFunction};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct AssertOptions {
+	/// The expected pin states, such as 17=high or 4=output.
+	#[structopt(value_name = "PIN=STATE", raw(required = "true"))]
+	expectations: Vec<Expectation>,
+
+	/// Keep checking for this long until all expectations hold, for example 2s or 500ms.
+	#[structopt(long = "within", value_name = "DURATION", parse(try_from_str = "crate::parse_duration"))]
+	within: Option<Duration>,
+
+	/// The polling interval while waiting.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "1ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+}
+
+/// The expected state of a pin.
+struct Expectation {
+	pin: usize,
+	state: State,
+}
+
+enum State {
+	Level(Level),
+	Function(PinFunction),
+}
+
+impl std::str::FromStr for Expectation {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let (pin, value) = match crate::split_key_value(data) {
+			(pin, Some(value)) => (crate::parse_pin_index(pin)?, value),
+			(_, None) => return Err(format!("invalid expectation: {}, expected PIN=STATE", data)),
+		};
+
+		let mut level = None;
+		let mut function = None;
+		let state = if crate::set_bool(&mut level, "level", value).is_ok() {
+			State::Level(level.unwrap().into())
+		} else if crate::set_function(&mut function, "function", value).is_ok() {
+			State::Function(function.unwrap())
+		} else {
+			return Err(format!("invalid pin state: {}, expected a level like high or low, or a function like output", value));
+		};
+
+		Ok(Self { pin, state })
+	}
+}
+
+impl Expectation {
+	/// Check the expectation, returning a description of the mismatch if it fails.
+	fn check(&self, state: &GpioState) -> Result<(), String> {
+		match self.state {
+			State::Level(expected) => {
+				let actual = state.pin_level(self.pin).map_err(|e| e.to_string())?;
+				if actual != expected {
+					return Err(format!("pin {} is {}, expected {}", self.pin, actual, expected));
+				}
+			},
+			State::Function(expected) => {
+				let actual = state.pin_function(self.pin).map_err(|e| e.to_string())?;
+				if actual != expected {
+					return Err(format!("pin {} has function {:?}, expected {:?}", self.pin, actual, expected));
+				}
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Check that pins are in the expected state, optionally waiting for them to get there.
+///
+/// Exits with status 0 when all expectations hold, or 1 with a message for each mismatch.
+pub fn run(options: &Options, command: &AssertOptions) {
+	let gpio = crate::open_gpio(options);
+	let deadline = command.within.map(|x| Instant::now() + x);
+
+	loop {
+		let state = gpio.read_all();
+		let failures: Vec<_> = command.expectations.iter().filter_map(|x| x.check(&state).err()).collect();
+		if failures.is_empty() {
+			return;
+		}
+
+		if deadline.is_none_or(|deadline| Instant::now() >= deadline) {
+			for failure in &failures {
+				eprintln!("{}: {}", yansi::Paint::red("Assertion failed").bold(), failure);
+			}
+			std::process::exit(1);
+		}
+
+		std::thread::sleep(command.interval);
+	}
+}
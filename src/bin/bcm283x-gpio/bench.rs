@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{Error, Gpio, PinFunction};
+use bcm283x_linux_gpio::realtime;
+
+use crate::Options;
+
+/// The number of writes timed together as one sample.
+const BATCH_SIZE: usize = 1000;
+
+#[derive(StructOpt)]
+pub struct BenchOptions {
+	/// The pin to toggle. It must already be configured as output.
+	#[structopt(value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	/// The duration of the benchmark in seconds.
+	#[structopt(long = "seconds", value_name = "SECONDS", default_value = "2")]
+	seconds: f64,
+
+	/// Run the benchmark only on this CPU core, ideally one isolated with the isolcpus kernel parameter.
+	#[structopt(long = "cpu", value_name = "CPU")]
+	cpu: Option<usize>,
+}
+
+pub fn run(options: &Options, command: &BenchOptions) {
+	if !command.seconds.is_finite() || command.seconds <= 0.0 {
+		crate::exit_with_error("the benchmark duration must be positive");
+	}
+
+	if let Some(cpu) = command.cpu {
+		if let Err(error) = realtime::pin_current_thread(cpu) {
+			crate::exit_with_error(error);
+		}
+	}
+
+	let mut gpio = crate::open_gpio(options);
+	match gpio.read_all().pin_function(command.pin) {
+		Ok(PinFunction::Output) => (),
+		Ok(_) => crate::exit_with_error(format!("pin {} is not configured as output, use --set-pin {},function=output", command.pin, command.pin)),
+		Err(error) => crate::exit_with_error(error),
+	}
+
+	let initial = match gpio.read_level(command.pin) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	let duration = Duration::from_secs_f64(command.seconds);
+	let samples  = toggle(&mut gpio, command.pin, duration);
+	let samples  = match samples.and_then(|x| gpio.set_level(command.pin, initial).map(|()| x)) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	let writes = samples.len() * BATCH_SIZE;
+	let total  = samples.iter().sum::<Duration>();
+	let min    = samples.iter().min().cloned().unwrap_or_default() / BATCH_SIZE as u32;
+	let max    = samples.iter().max().cloned().unwrap_or_default() / BATCH_SIZE as u32;
+	let mean   = total / writes.max(1) as u32;
+
+	// Two writes make one period of the square wave.
+	let frequency = writes as f64 / 2.0 / total.as_secs_f64();
+
+	println!("writes:    {}", Paint::cyan(writes));
+	println!("frequency: {}", Paint::cyan(format!("{:.3} MHz", frequency / 1e6)));
+	println!("per write: min={}   mean={}   max={}",
+		Paint::green(format!("{}ns", min.as_nanos())),
+		Paint::cyan(format!("{}ns", mean.as_nanos())),
+		Paint::red(format!("{}ns", max.as_nanos())),
+	);
+}
+
+/// Toggle a pin for the given duration.
+///
+/// Returns the time taken by each batch of writes.
+fn toggle(gpio: &mut Gpio, pin: usize, duration: Duration) -> Result<Vec<Duration>, Error> {
+	let mut samples = Vec::new();
+	let start = Instant::now();
+	while start.elapsed() < duration {
+		let batch_start = Instant::now();
+		for _ in 0..BATCH_SIZE / 2 {
+			gpio.set_level(pin, true)?;
+			gpio.set_level(pin, false)?;
+		}
+		samples.push(batch_start.elapsed());
+	}
+	Ok(samples)
+}
@@ -0,0 +1,102 @@
+use std::io::Write;
+use structopt::StructOpt;
+use structopt::clap::Shell;
+
+use crate::{Options, PIN_OPTIONS};
+
+const BIN_NAME: &str = "bcm283x-gpio";
+
+/// Wraps the generated bash completion to complete pin options after a comma, and pin aliases.
+const BASH_PIN_OPTIONS: &str = r#"
+_bcm283x-gpio-set-pin() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ ( "${prev}" == "-s" || "${prev}" == "--set-pin" ) && "${cur}" == *,* ]] ; then
+        COMPREPLY=( $(compgen -P "${cur%,*}," -W "@KEYS@" -- "${cur##*,}") )
+        compopt -o nospace
+        return 0
+    fi
+    _bcm283x-gpio "$@"
+    if [[ "${cur}" != -* ]] ; then
+        COMPREPLY+=( $(compgen -W "$(bcm283x-gpio aliases --names 2>/dev/null)" -- "${cur}") )
+    fi
+}
+
+complete -F _bcm283x-gpio-set-pin -o bashdefault -o default bcm283x-gpio
+"#;
+
+/// Completes pin options after a comma, and pin aliases, for fish.
+const FISH_PIN_OPTIONS: &str = r#"
+function __bcm283x_gpio_pin_options
+    set -l token (commandline -ct)
+    if string match -q -- '*,*' $token
+        set -l head (string replace -r -- ',[^,]*$' ',' $token)
+        for key in @KEYS@
+            echo $head$key
+        end
+    else
+        bcm283x-gpio aliases --names 2>/dev/null
+    end
+end
+
+complete -c bcm283x-gpio -s s -l set-pin -x -a '(__bcm283x_gpio_pin_options)'
+complete -c bcm283x-gpio -a '(bcm283x-gpio aliases --names 2>/dev/null)'
+"#;
+
+/// Wraps the generated zsh completion to complete pin options after a comma, and pin aliases.
+///
+/// This replaces the call of the generated function at the end of the script.
+const ZSH_PIN_OPTIONS: &str = r#"
+_bcm283x-gpio-set-pin() {
+    local cur="${words[CURRENT]}"
+    local prev="${words[CURRENT-1]}"
+    if [[ ( "${prev}" == "-s" || "${prev}" == "--set-pin" ) && "${cur}" == *,* ]] ; then
+        compset -P '[^,]#,'
+        _values -s , 'pin option' @KEYS@
+        return
+    fi
+    _bcm283x-gpio "$@"
+    if [[ "${cur}" != -* ]] ; then
+        local -a aliases
+        aliases=( ${(f)"$(bcm283x-gpio aliases --names 2>/dev/null)"} )
+        compadd -a aliases
+    fi
+}
+
+_bcm283x-gpio-set-pin "$@"
+"#;
+
+#[derive(StructOpt)]
+pub struct CompletionsOptions {
+	/// The shell to generate completions for.
+	#[structopt(value_name = "SHELL", raw(possible_values = "&Shell::variants()"))]
+	shell: Shell,
+}
+
+pub fn run(options: &CompletionsOptions) {
+	let mut script = Vec::new();
+	Options::clap().gen_completions_to(BIN_NAME, options.shell, &mut script);
+	let mut script = String::from_utf8_lossy(&script).into_owned();
+
+	// Clap can not complete the individual options of --set-pin or the pin aliases,
+	// so add that manually for the shells that make it easy.
+	let keys = PIN_OPTIONS.iter().map(|x| format!("{}=", x)).collect::<Vec<_>>().join(" ");
+	match options.shell {
+		Shell::Bash => script.push_str(&BASH_PIN_OPTIONS.replace("@KEYS@", &keys)),
+		Shell::Fish => script.push_str(&FISH_PIN_OPTIONS.replace("@KEYS@", &keys)),
+		Shell::Zsh  => {
+			// The values of _values are specs with a name and an action for the value, like level: :.
+			let keys = PIN_OPTIONS.iter().map(|x| format!("'{}: :'", x)).collect::<Vec<_>>().join(" ");
+			let call = format!("_{} \"$@\"", BIN_NAME);
+			if let Some(position) = script.rfind(&call) {
+				script.truncate(position);
+			}
+			script.push_str(&ZSH_PIN_OPTIONS.replace("@KEYS@", &keys));
+		},
+		_ => (),
+	}
+
+	if let Err(error) = std::io::stdout().write_all(script.as_bytes()) {
+		crate::exit_with_error(error);
+	}
+}
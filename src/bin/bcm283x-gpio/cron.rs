@@ -0,0 +1,150 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use nix::libc;
+
+/// A schedule in the format of a crontab entry: minute, hour, day of month, month and day of week.
+///
+/// Every field is `*`, a number, a range like `1-5`, a step like `*/15` or `8-18/2`, or a list of those separated by commas.
+/// The day of week is 0-7, where both 0 and 7 are Sunday.
+/// Like cron, a time matches if either the day of month or the day of week matches when both are restricted.
+/// The macros `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly` are also supported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CronSchedule {
+	minutes: u64,
+	hours: u64,
+	days: u64,
+	months: u64,
+	weekdays: u64,
+	any_day: bool,
+	any_weekday: bool,
+}
+
+/// The fields of a local time that a [`CronSchedule`] matches against.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LocalTime {
+	pub minute: u32,
+	pub hour: u32,
+	pub day: u32,
+	pub month: u32,
+	pub weekday: u32,
+}
+
+impl CronSchedule {
+	/// Check if the schedule matches a local time.
+	pub fn matches(&self, time: &LocalTime) -> bool {
+		let day     = self.days >> time.day & 1 == 1;
+		let weekday = self.weekdays >> time.weekday & 1 == 1;
+		let day = match (self.any_day, self.any_weekday) {
+			(false, false) => day || weekday,
+			_ => day && weekday,
+		};
+
+		self.minutes >> time.minute & 1 == 1
+			&& self.hours >> time.hour & 1 == 1
+			&& self.months >> time.month & 1 == 1
+			&& day
+	}
+}
+
+impl std::str::FromStr for CronSchedule {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let data = match data.trim() {
+			"@yearly" | "@annually"  => "0 0 1 1 *",
+			"@monthly"               => "0 0 1 * *",
+			"@weekly"                => "0 0 * * 0",
+			"@daily" | "@midnight"   => "0 0 * * *",
+			"@hourly"                => "0 * * * *",
+			x if x.starts_with('@')  => return Err(format!("unknown schedule: {}", x)),
+			x => x,
+		};
+
+		let fields = data.split_whitespace().collect::<Vec<_>>();
+		let [minutes, hours, days, months, weekdays] = match fields.as_slice() {
+			[a, b, c, d, e] => [*a, *b, *c, *d, *e],
+			_ => return Err(format!("invalid schedule: `{}`, expected five fields: minute, hour, day of month, month and day of week", data)),
+		};
+
+		let mut weekdays_mask = parse_field(weekdays, 0, 7).map_err(|e| format!("invalid day of week: {}", e))?;
+		// Sunday can be written as 7 too.
+		if weekdays_mask >> 7 & 1 == 1 {
+			weekdays_mask = weekdays_mask & 0x7F | 1;
+		}
+
+		Ok(Self {
+			minutes:     parse_field(minutes, 0, 59).map_err(|e| format!("invalid minute: {}", e))?,
+			hours:       parse_field(hours, 0, 23).map_err(|e| format!("invalid hour: {}", e))?,
+			days:        parse_field(days, 1, 31).map_err(|e| format!("invalid day of month: {}", e))?,
+			months:      parse_field(months, 1, 12).map_err(|e| format!("invalid month: {}", e))?,
+			weekdays:    weekdays_mask,
+			any_day:     days.starts_with('*'),
+			any_weekday: weekdays.starts_with('*'),
+		})
+	}
+}
+
+/// Parse a single field of a schedule into a bitmask of the matching values.
+fn parse_field(data: &str, min: u32, max: u32) -> Result<u64, String> {
+	let mut mask = 0;
+	for item in data.split(',') {
+		let (range, step) = crate::partition(item, '/');
+		let step = match step {
+			None => 1,
+			Some(x) => match x.parse::<u32>() {
+				Ok(x) if x > 0 => x,
+				_ => return Err(format!("invalid step: `{}`", x)),
+			},
+		};
+
+		let (start, end) = match crate::partition(range, '-') {
+			("*", None) => (min, max),
+			(start, None) => {
+				let start = parse_value(start, min, max)?;
+				// A single value with a step runs to the end of the range, like in cron.
+				match step {
+					1 => (start, start),
+					_ => (start, max),
+				}
+			},
+			(start, Some(end)) => (parse_value(start, min, max)?, parse_value(end, min, max)?),
+		};
+		if end < start {
+			return Err(format!("range ends before it starts: `{}`", range));
+		}
+
+		for value in (start..=end).step_by(step as usize) {
+			mask |= 1 << value;
+		}
+	}
+	Ok(mask)
+}
+
+fn parse_value(data: &str, min: u32, max: u32) -> Result<u32, String> {
+	match data.parse::<u32>() {
+		Ok(x) if (min..=max).contains(&x) => Ok(x),
+		_ => Err(format!("invalid value: `{}`, expected a number in the range [{}-{}]", data, min, max)),
+	}
+}
+
+/// Get the start of the minute after a point in time.
+pub fn next_minute(time: SystemTime) -> SystemTime {
+	let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+	UNIX_EPOCH + Duration::from_secs((seconds / 60 + 1) * 60)
+}
+
+/// Convert a point in time to the local time zone.
+pub fn local_time(time: SystemTime) -> LocalTime {
+	let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as libc::time_t;
+	let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+	unsafe {
+		libc::localtime_r(&seconds, &mut tm);
+	}
+	LocalTime {
+		minute:  tm.tm_min as u32,
+		hour:    tm.tm_hour as u32,
+		day:     tm.tm_mday as u32,
+		month:   tm.tm_mon as u32 + 1,
+		weekday: tm.tm_wday as u32,
+	}
+}
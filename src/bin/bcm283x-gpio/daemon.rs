@@ -0,0 +1,493 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, SystemTime};
+use nix::sys::stat::{self, Mode};
+use structopt::StructOpt;
+
+#[cfg(feature = "prometheus")]
+use std::net::{TcpListener, TcpStream};
+
+use bcm283x_linux_gpio::{Gpio, Register};
+#[cfg(feature = "prometheus")]
+use bcm283x_linux_gpio::GpioMetrics;
+
+use crate::{Options, PinCommandList, PinList};
+use crate::apply::ScheduleEntry;
+
+/// The default path of the control socket.
+const DEFAULT_SOCKET: &str = "/run/bcm283x-gpiod.sock";
+
+/// The maximum length of a command line, including the line ending.
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// The maximum number of headers of a metrics request.
+#[cfg(feature = "prometheus")]
+const MAX_HEADERS: usize = 100;
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+The daemon accepts one command per line and answers each with a single line,
+starting with `ok` or `error`:
+    levels                     Read the levels of all pins as hexadecimal bitmask.
+    get PIN                    Read the function, level and event status of a pin.
+    set PINS,OPTION,OPTION...  Configure pins, using the same syntax as --set-pin.
+    reg REGISTER               Read a register.
+
+Only the pins given with --writable can be configured.
+
+With --config, the pins are configured from a file in the format of the apply command,
+and the set commands in its [schedule] table are executed at the given times in the local time zone.
+Scheduled commands can only configure the pins given with --writable too.
+
+With --metrics, the daemon also serves the pin levels, functions and edge counts
+in the Prometheus text format on http://ADDRESS/metrics.
+")]
+pub struct DaemonOptions {
+	/// The path of the control socket.
+	#[structopt(long = "socket", value_name = "PATH", raw(default_value = "DEFAULT_SOCKET"), parse(from_os_str))]
+	socket: PathBuf,
+
+	/// The permissions of the control socket, in octal.
+	#[structopt(long = "mode", value_name = "MODE", default_value = "660", parse(try_from_str = "parse_mode"))]
+	mode: u32,
+
+	/// The pins that may be configured by clients, separated by commas.
+	#[structopt(long = "writable", value_name = "PIN,PIN...")]
+	writable: Option<PinList>,
+
+	/// The maximum number of clients connected at the same time, including metrics scrapes.
+	///
+	/// Further clients are answered with an error and disconnected.
+	#[structopt(long = "max-connections", value_name = "COUNT", default_value = "16")]
+	max_connections: usize,
+
+	/// Configure the pins and run the schedule from a TOML file, see the apply command.
+	#[structopt(long = "config", value_name = "FILE", parse(from_os_str))]
+	config: Option<PathBuf>,
+
+	/// Serve Prometheus metrics on /metrics at this address, for example 0.0.0.0:9100.
+	#[cfg(feature = "prometheus")]
+	#[structopt(long = "metrics", value_name = "ADDRESS")]
+	metrics: Option<String>,
+
+	/// How often to sample the pin levels to count edges for the metrics.
+	#[cfg(feature = "prometheus")]
+	#[structopt(long = "metrics-interval", value_name = "DURATION", default_value = "10ms", parse(try_from_str = "crate::parse_duration"))]
+	metrics_interval: Duration,
+}
+
+#[derive(StructOpt)]
+pub struct ClientOptions {
+	/// The path of the control socket of the daemon.
+	#[structopt(long = "socket", value_name = "PATH", raw(default_value = "DEFAULT_SOCKET"), parse(from_os_str))]
+	socket: PathBuf,
+
+	/// The command to send, for example: get 17.
+	#[structopt(value_name = "COMMAND", raw(required = "true"))]
+	command: Vec<String>,
+}
+
+/// Work for the main thread of the daemon.
+enum Job {
+	/// A command line received from a client, with a channel for the reply.
+	Command(String, mpsc::Sender<Result<String, String>>),
+
+	/// A scrape of the metrics, with a channel for the rendered metrics.
+	#[cfg(feature = "prometheus")]
+	Metrics(mpsc::Sender<String>),
+}
+
+/// Counts the connections being handled, to limit them.
+#[derive(Clone)]
+struct Connections {
+	count: Arc<AtomicUsize>,
+	max: usize,
+}
+
+/// Decrements the connection count when the connection is done.
+struct ConnectionGuard {
+	count: Arc<AtomicUsize>,
+}
+
+impl Connections {
+	fn new(max: usize) -> Self {
+		Self { count: Arc::new(AtomicUsize::new(0)), max }
+	}
+
+	/// Count a new connection, unless the maximum is reached.
+	fn try_add(&self) -> Option<ConnectionGuard> {
+		if self.count.fetch_add(1, Ordering::Relaxed) >= self.max {
+			self.count.fetch_sub(1, Ordering::Relaxed);
+			return None;
+		}
+		Some(ConnectionGuard { count: self.count.clone() })
+	}
+}
+
+impl Drop for ConnectionGuard {
+	fn drop(&mut self) {
+		self.count.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+/// Run a daemon that shares the GPIO mapping with local clients over a Unix socket.
+///
+/// All commands are executed by the main thread, so register writes from different clients never interleave.
+pub fn run(options: &Options, command: &DaemonOptions) {
+	let config = command.config.as_ref().map(|path| match crate::apply::load(path) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("invalid configuration in {}: {}", path.display(), error)),
+	});
+
+	// Options from the command line are applied after the file, so they take precedence.
+	let commands = config.iter()
+		.flat_map(|x| x.pins.iter().map(|pin| pin.command.clone()))
+		.chain(options.pin_commands())
+		.collect::<Vec<_>>();
+	let mut gpio = crate::open_gpio_with(options, &commands);
+	let schedule = config.map(|x| x.schedule).unwrap_or_default();
+	let writable = command.writable.iter().flat_map(|x| x.iter()).fold(0u64, |mask, pin| mask | 1 << pin);
+	for entry in &schedule {
+		if let Some(pin) = entry.pins.iter().find(|&&pin| writable & 1 << pin == 0) {
+			crate::exit_with_error(format!("scheduled command `{}` ({}) configures pin {}, which is not given with --writable", entry.command, entry.spec, pin));
+		}
+	}
+
+	// Remove a stale socket from a previous run, but never another kind of file.
+	match std::fs::symlink_metadata(&command.socket) {
+		Ok(metadata) if metadata.file_type().is_socket() => {
+			if let Err(error) = std::fs::remove_file(&command.socket) {
+				crate::exit_with_error(format!("failed to remove {}: {}", command.socket.display(), error));
+			}
+		},
+		Ok(_) => crate::exit_with_error(format!("{} exists and is not a socket", command.socket.display())),
+		Err(_) => (),
+	}
+
+	// Create the socket with the requested permissions, so no other user can connect before they are set.
+	let umask = Mode::from_bits_truncate(!command.mode & 0o777);
+	let umask = stat::umask(umask);
+	let listener = UnixListener::bind(&command.socket);
+	stat::umask(umask);
+
+	let listener = match listener {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("failed to listen on {}: {}", command.socket.display(), error)),
+	};
+	// The umask can only remove permissions, so set them explicitly too.
+	if let Err(error) = std::fs::set_permissions(&command.socket, std::fs::Permissions::from_mode(command.mode)) {
+		crate::exit_with_error(format!("failed to set permissions of {}: {}", command.socket.display(), error));
+	}
+
+	if options.verbose {
+		eprintln!("listening on {}", command.socket.display());
+	}
+
+	let (job_tx, job_rx) = mpsc::channel::<Job>();
+	let connections = Connections::new(command.max_connections);
+
+	#[cfg(feature = "prometheus")]
+	let mut metrics = command.metrics.as_ref().map(|address| {
+		serve_metrics(address, job_tx.clone(), connections.clone(), options.verbose);
+		GpioMetrics::new()
+	});
+
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			match stream {
+				Ok(mut stream) => {
+					let guard = match connections.try_add() {
+						Some(x) => x,
+						None => {
+							let _ = writeln!(stream, "error too many connections");
+							continue;
+						},
+					};
+					let job_tx = job_tx.clone();
+					std::thread::spawn(move || {
+						let _guard = guard;
+						handle_client(stream, job_tx)
+					});
+				},
+				Err(error) => eprintln!("failed to accept connection: {}", error),
+			}
+		}
+	});
+
+	let mut next_minute = crate::cron::next_minute(SystemTime::now());
+
+	loop {
+		// Wake up for the next scheduled minute, and with metrics enabled, to sample the levels to count edges.
+		let mut timeout = None::<Duration>;
+		if !schedule.is_empty() {
+			timeout = Some(next_minute.duration_since(SystemTime::now()).unwrap_or_default());
+		}
+		#[cfg(feature = "prometheus")]
+		if metrics.is_some() {
+			timeout = Some(timeout.map_or(command.metrics_interval, |x| x.min(command.metrics_interval)));
+		}
+
+		let job = match timeout {
+			Some(timeout) => match job_rx.recv_timeout(timeout) {
+				Ok(job) => Some(job),
+				Err(mpsc::RecvTimeoutError::Timeout) => None,
+				Err(mpsc::RecvTimeoutError::Disconnected) => break,
+			},
+			None => match job_rx.recv() {
+				Ok(job) => Some(job),
+				Err(_) => break,
+			},
+		};
+
+		let now = SystemTime::now();
+		if !schedule.is_empty() && now >= next_minute {
+			run_schedule(&mut gpio, options, writable, &schedule, next_minute);
+			next_minute = crate::cron::next_minute(next_minute);
+			// Skip minutes that were missed by more than a minute, like after the clock was set.
+			if now >= next_minute {
+				next_minute = crate::cron::next_minute(now);
+			}
+		}
+
+		let job = match job {
+			Some(job) => job,
+			None => {
+				#[cfg(feature = "prometheus")]
+				if let Some(metrics) = metrics.as_mut() {
+					metrics.update(gpio.read_levels());
+				}
+				continue;
+			},
+		};
+
+		match job {
+			Job::Command(line, reply) => {
+				let _ = reply.send(execute(&mut gpio, options, writable, &line));
+			},
+			#[cfg(feature = "prometheus")]
+			Job::Metrics(reply) => {
+				let metrics = metrics.get_or_insert_with(GpioMetrics::new);
+				metrics.update(gpio.read_levels());
+				let _ = reply.send(metrics.render(&gpio.read_all()));
+			},
+		}
+	}
+}
+
+/// Execute the scheduled commands that match the minute starting at a point in time.
+fn run_schedule(gpio: &mut Gpio, options: &Options, writable: u64, schedule: &[ScheduleEntry], minute: SystemTime) {
+	let time = crate::cron::local_time(minute);
+	for entry in schedule.iter().filter(|x| x.schedule.matches(&time)) {
+		match execute(gpio, options, writable, &entry.command) {
+			Ok(_) => {
+				if options.verbose {
+					eprintln!("executed scheduled command `{}` ({})", entry.command, entry.spec);
+				}
+			},
+			Err(error) => eprintln!("scheduled command `{}` ({}) failed: {}", entry.command, entry.spec, error),
+		}
+	}
+}
+
+/// Serve the metrics over HTTP from a background thread.
+///
+/// The metrics are rendered by the main thread, which owns the GPIO handle.
+/// Each scrape is handled by its own thread, so a slow client can not block the others.
+#[cfg(feature = "prometheus")]
+fn serve_metrics(address: &str, jobs: mpsc::Sender<Job>, connections: Connections, verbose: bool) {
+	let listener = match TcpListener::bind(address) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("failed to listen on {}: {}", address, error)),
+	};
+
+	if verbose {
+		eprintln!("serving metrics on http://{}/metrics", address);
+	}
+
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			match stream {
+				Ok(mut stream) => {
+					let guard = match connections.try_add() {
+						Some(x) => x,
+						None => {
+							let _ = write_scrape_response(&mut stream, "503 Service Unavailable", "too many connections\n");
+							continue;
+						},
+					};
+					let jobs = jobs.clone();
+					std::thread::spawn(move || {
+						let _guard = guard;
+						let _ = handle_scrape(stream, &jobs);
+					});
+				},
+				Err(error) => eprintln!("failed to accept connection: {}", error),
+			}
+		}
+	});
+}
+
+#[cfg(feature = "prometheus")]
+fn handle_scrape(mut stream: TcpStream, jobs: &mpsc::Sender<Job>) -> std::io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let request = crate::read_line_bounded(&mut reader, MAX_LINE_LENGTH)?.unwrap_or_default();
+
+	// Skip the headers, the request has no body.
+	let mut headers = 0;
+	while crate::read_line_bounded(&mut reader, MAX_LINE_LENGTH)?.is_some_and(|x| x.len() > 2) {
+		headers += 1;
+		if headers > MAX_HEADERS {
+			return write_scrape_response(&mut stream, "431 Request Header Fields Too Large", "too many headers\n");
+		}
+	}
+
+	let mut parts = request.split_whitespace();
+	let (status, body) = match (parts.next(), parts.next()) {
+		(Some("GET"), Some("/metrics")) => {
+			let (reply_tx, reply_rx) = mpsc::channel();
+			let _ = jobs.send(Job::Metrics(reply_tx));
+			match reply_rx.recv() {
+				Ok(body) => ("200 OK", body),
+				Err(_)   => ("500 Internal Server Error", String::from("daemon stopped\n")),
+			}
+		},
+		_ => ("404 Not Found", String::from("not found\n")),
+	};
+
+	write_scrape_response(&mut stream, status, &body)
+}
+
+#[cfg(feature = "prometheus")]
+fn write_scrape_response(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+	write!(
+		stream,
+		"HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		status,
+		body.len(),
+		body,
+	)
+}
+
+/// Send a single command to the daemon and print the reply.
+pub fn run_client(command: &ClientOptions) {
+	let socket = &command.socket;
+	let reply = match request(socket, &command.command.join(" ")) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("failed to talk to daemon on {}: {}", socket.display(), error)),
+	};
+
+	if let Some(reply) = reply.strip_prefix("ok") {
+		let reply = reply.trim_start();
+		if !reply.is_empty() {
+			println!("{}", reply);
+		}
+	} else {
+		crate::exit_with_error(reply.strip_prefix("error").unwrap_or(&reply).trim_start());
+	}
+}
+
+fn request(socket: &Path, line: &str) -> std::io::Result<String> {
+	let mut stream = UnixStream::connect(socket)?;
+	writeln!(stream, "{}", line)?;
+	let mut reply = String::new();
+	BufReader::new(stream).read_line(&mut reply)?;
+	Ok(reply.trim_end().to_string())
+}
+
+fn handle_client(stream: UnixStream, jobs: mpsc::Sender<Job>) {
+	let mut writer = match stream.try_clone() {
+		Ok(x) => x,
+		Err(_) => return,
+	};
+
+	let mut reader = BufReader::new(stream);
+	loop {
+		let line = match crate::read_line_bounded(&mut reader, MAX_LINE_LENGTH) {
+			Ok(Some(x)) => x,
+			Ok(None) => return,
+			Err(error) => {
+				let _ = writeln!(writer, "error {}", error);
+				return;
+			},
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let (reply_tx, reply_rx) = mpsc::channel();
+		if jobs.send(Job::Command(line, reply_tx)).is_err() {
+			return;
+		}
+		let result = match reply_rx.recv() {
+			Ok(Ok(x))      => writeln!(writer, "ok {}", x),
+			Ok(Err(error)) => writeln!(writer, "error {}", error),
+			Err(_)         => return,
+		};
+		if result.is_err() {
+			return;
+		}
+	}
+}
+
+fn execute(gpio: &mut Gpio, options: &Options, writable: u64, line: &str) -> Result<String, String> {
+	let line = line.trim();
+	let (command, argument) = crate::partition(line, ' ');
+	let argument = argument.map(str::trim).unwrap_or("");
+
+	match (command, argument) {
+		("levels", "") => Ok(format!("0x{:014X}", gpio.read_levels())),
+		("get", pin) => {
+			let pin = crate::parse_pin_index(pin)?;
+			let info = gpio.read_pin(pin).map_err(|e| e.to_string())?;
+			Ok(format!(
+				"pin={} function={} level={} event={}",
+				pin,
+				format!("{:?}", info.function).to_lowercase(),
+				on_off(info.level.is_high()),
+				on_off(info.event),
+			))
+		},
+		("set", settings) => {
+			let commands = settings.parse::<PinCommandList>()?.0;
+			if let Some(pin) = commands.iter().find(|x| writable & 1 << x.index == 0) {
+				return Err(format!("pin {} is not writable", pin.index));
+			}
+
+			crate::check_interface_pins(options, &commands)?;
+			let config = crate::config_from_commands(&commands, options)?;
+			if config.pads.iter().any(|x| !x.is_empty()) {
+				return Err(String::from("pad options are not supported by the daemon"));
+			}
+			config.gpio.apply(gpio).map_err(|e| e.to_string())?;
+			unsafe {
+				config.pull.apply(gpio).map_err(|e| e.to_string())?;
+			}
+			Ok(String::new())
+		},
+		("reg", register) => {
+			let register = register.parse::<Register>().map_err(|e| e.to_string())?;
+			Ok(format!("0x{:08X}", gpio.read_register(register)))
+		},
+		_ => Err(format!("unknown command: {}", line)),
+	}
+}
+
+fn on_off(value: bool) -> &'static str {
+	match value {
+		true  => "on",
+		false => "off",
+	}
+}
+
+fn parse_mode(data: &str) -> Result<u32, String> {
+	match u32::from_str_radix(data, 8) {
+		Ok(x) if x <= 0o777 => Ok(x),
+		_ => Err(format!("invalid file mode: {}, expected an octal number like 660", data)),
+	}
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+
+use bcm283x_linux_gpio::{Edge, EventListener, Gpio, Level, Permissions};
+
+use crate::{Options, PinCommand, PinList};
+
+/// The well-known bus name of the service.
+const BUS_NAME: &str = "engineering.fusion.Gpio1";
+
+/// The object path of the service.
+const OBJECT_PATH: &str = "/engineering/fusion/Gpio1";
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+The service is registered as engineering.fusion.Gpio1 at /engineering/fusion/Gpio1,
+with the interface engineering.fusion.Gpio1:
+    ReadLevel(u pin) -> b            Read the level of a pin.
+    ReadLevels() -> t                Read the levels of all pins as bitmask.
+    ReadFunction(u pin) -> s         Read the function of a pin.
+    WriteLevel(u pin, b level)       Set the level of a writable pin.
+    WriteFunction(u pin, s function) Set the function of a writable pin: input, output or alt0..5.
+    signal Edge(u pin, s edge, t microseconds)
+
+The Read methods never change pins and the Write methods do,
+so a D-Bus policy or polkit rule can grant them to different users.
+Only the pins given with --writable can be changed.
+")]
+pub struct DbusOptions {
+	/// Connect to the session bus instead of the system bus.
+	#[structopt(long = "session")]
+	session: bool,
+
+	/// The pins to send Edge signals for, separated by commas.
+	#[structopt(long = "pins", value_name = "PIN,PIN...")]
+	pins: Option<PinList>,
+
+	/// The pins that may be changed through the service, separated by commas.
+	#[structopt(long = "writable", value_name = "PIN,PIN...")]
+	writable: Option<PinList>,
+
+	/// The polling interval for edges.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "10ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+}
+
+/// The object served on the bus.
+struct GpioService {
+	gpio: Arc<Mutex<Gpio>>,
+	writable: u64,
+	permissions: Permissions,
+}
+
+#[zbus::interface(name = "engineering.fusion.Gpio1")]
+impl GpioService {
+	fn read_level(&self, pin: u32) -> fdo::Result<bool> {
+		self.lock().read_level(pin as usize).map(Level::is_high).map_err(|e| fdo::Error::InvalidArgs(e.to_string()))
+	}
+
+	fn read_levels(&self) -> u64 {
+		self.lock().read_levels()
+	}
+
+	fn read_function(&self, pin: u32) -> fdo::Result<String> {
+		let info = self.lock().read_pin(pin as usize).map_err(|e| fdo::Error::InvalidArgs(e.to_string()))?;
+		Ok(format!("{:?}", info.function).to_lowercase())
+	}
+
+	fn write_level(&self, pin: u32, level: bool) -> fdo::Result<()> {
+		self.check_writable(pin)?;
+		self.lock().set_level(pin as usize, level).map_err(|e| fdo::Error::Failed(e.to_string()))
+	}
+
+	fn write_function(&self, pin: u32, function: &str) -> fdo::Result<()> {
+		self.check_writable(pin)?;
+		let mut command = PinCommand::new(pin as usize);
+		command.set_option("function", function).map_err(fdo::Error::InvalidArgs)?;
+		if let Some(function) = command.set_function {
+			crate::check_function(self.permissions, pin as usize, function).map_err(fdo::Error::AccessDenied)?;
+			self.lock().set_function(pin as usize, function).map_err(|e| fdo::Error::Failed(e.to_string()))?;
+		}
+		Ok(())
+	}
+
+	#[zbus(signal)]
+	async fn edge(emitter: &SignalEmitter<'_>, pin: u32, edge: &str, microseconds: u64) -> zbus::Result<()>;
+}
+
+impl GpioService {
+	fn lock(&self) -> MutexGuard<'_, Gpio> {
+		self.gpio.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn check_writable(&self, pin: u32) -> fdo::Result<()> {
+		match pin < 54 && self.writable & 1 << pin != 0 {
+			true  => Ok(()),
+			false => Err(fdo::Error::AccessDenied(format!("pin {} is not writable", pin))),
+		}
+	}
+}
+
+/// Serve the GPIO pins on D-Bus.
+pub fn run(options: &Options, command: &DbusOptions) {
+	let gpio = crate::open_gpio(options);
+	if let Err(error) = serve(gpio, command, options.permissions()) {
+		crate::exit_with_error(error);
+	}
+}
+
+fn serve(gpio: Gpio, command: &DbusOptions, permissions: Permissions) -> Result<(), String> {
+	let gpio = Arc::new(Mutex::new(gpio));
+	let service = GpioService {
+		gpio: gpio.clone(),
+		writable: command.writable.iter().flat_map(|x| x.iter()).fold(0, |mask, pin| mask | 1 << pin),
+		permissions,
+	};
+
+	let builder = match command.session {
+		true  => zbus::blocking::connection::Builder::session(),
+		false => zbus::blocking::connection::Builder::system(),
+	};
+	let connection = builder
+		.and_then(|x| x.name(BUS_NAME))
+		.and_then(|x| x.serve_at(OBJECT_PATH, service))
+		.and_then(|x| x.build())
+		.map_err(|e| format!("failed to register {} on the bus: {}", BUS_NAME, e))?;
+	let interface = connection.object_server().interface::<_, GpioService>(OBJECT_PATH).map_err(|e| e.to_string())?;
+
+	let mut listener = EventListener::new();
+	for pin in command.pins.iter().flat_map(|x| x.iter()) {
+		listener.watch(pin, Edge::Rise).map_err(|e| e.to_string())?;
+		listener.watch(pin, Edge::Fall).map_err(|e| e.to_string())?;
+	}
+
+	// Method calls are handled by the executor of the connection, the main thread only polls for edges.
+	let start = Instant::now();
+	loop {
+		listener.poll(&gpio.lock().unwrap_or_else(|e| e.into_inner()));
+		while let Some(event) = listener.pop() {
+			let edge = match event.edge {
+				Edge::Rise => "rise",
+				Edge::Fall => "fall",
+			};
+			let time = event.time.duration_since(start).as_micros() as u64;
+			zbus::block_on(GpioService::edge(interface.signal_emitter(), event.pin as u32, edge, time))
+				.map_err(|e| format!("failed to send Edge signal: {}", e))?;
+		}
+		std::thread::sleep(command.interval);
+	}
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{GpioState, PinInfo, Soc};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct DiffOptions {
+	/// Compare a saved state against the live GPIO state.
+	#[structopt(long = "live")]
+	live: bool,
+
+	/// The saved state files to compare: BEFORE AFTER, or only BEFORE with --live.
+	#[structopt(value_name = "FILE", parse(from_os_str))]
+	files: Vec<PathBuf>,
+}
+
+pub fn run(options: &Options, command: &DiffOptions) {
+	let (before, after) = match (command.live, command.files.as_slice()) {
+		(false, [before, after]) => (load(before), load(after)),
+		(true, [before]) => (load(before), crate::open_gpio(options).read_all()),
+		(false, _) => crate::exit_with_error("expected exactly two state files"),
+		(true, _) => crate::exit_with_error("expected exactly one state file with --live"),
+	};
+
+	// The pull up/down mode can only be compared on SoCs that can read it back.
+	let soc = Soc::detect().unwrap_or(Soc::Bcm2835);
+
+	let mut changed = false;
+	for index in 0..54 {
+		let changes = match (before.pin_for(index, soc), after.pin_for(index, soc)) {
+			(Ok(old), Ok(new)) => pin_changes(&old, &new),
+			(Err(error), _) | (_, Err(error)) => crate::exit_with_error(error),
+		};
+		if changes.is_empty() {
+			continue;
+		}
+
+		changed = true;
+		print!("pin={:<2}", Paint::yellow(index));
+		for (name, old, new) in changes {
+			print!("   {}={}->{}", name, Paint::red(old), Paint::green(new));
+		}
+		println!();
+	}
+
+	if !changed {
+		println!("no changes");
+	}
+}
+
+fn load(path: &Path) -> GpioState {
+	match crate::state::load(path) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	}
+}
+
+/// Get the changed fields of a pin as (name, old, new) tuples.
+fn pin_changes(before: &PinInfo, after: &PinInfo) -> Vec<(&'static str, String, String)> {
+	let mut changes = Vec::new();
+
+	if before.function != after.function {
+		changes.push(("function", format!("{:?}", before.function), format!("{:?}", after.function)));
+	}
+
+	if let (Some(old), Some(new)) = (before.pull_mode, after.pull_mode) {
+		if old != new {
+			changes.push(("pull", crate::pull_name(old).to_string(), crate::pull_name(new).to_string()));
+		}
+	}
+
+	let mut compare = |name, old: bool, new: bool| {
+		if old != new {
+			changes.push((name, on_off(old).to_string(), on_off(new).to_string()));
+		}
+	};
+
+	compare("level",             before.level.is_high(),   after.level.is_high());
+	compare("event",             before.event,             after.event);
+	compare("detect-rise",       before.detect_rise,       after.detect_rise);
+	compare("detect-fall",       before.detect_fall,       after.detect_fall);
+	compare("detect-high",       before.detect_high,       after.detect_high);
+	compare("detect-low",        before.detect_low,        after.detect_low);
+	compare("detect-async-rise", before.detect_async_rise, after.detect_async_rise);
+	compare("detect-async-fall", before.detect_async_fall, after.detect_async_fall);
+
+	changes
+}
+
+fn on_off(value: bool) -> &'static str {
+	match value {
+		true  => "on",
+		false => "off",
+	}
+}
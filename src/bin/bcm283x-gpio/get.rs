@@ -0,0 +1,54 @@
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Level, PinFunction};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct GetOptions {
+	/// The pin to read.
+	#[structopt(value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	/// Print the function of the pin instead of the level. Requires --quiet.
+	#[structopt(long = "function", requires = "quiet")]
+	function: bool,
+
+	/// Print only the level as 0 or 1, or the function name with --function, without colors.
+	///
+	/// This is meant for scripts, like LEVEL=$(bcm283x-gpio get 17 -q).
+	#[structopt(long = "quiet", short = "q")]
+	quiet: bool,
+}
+
+/// Print the state of a single pin.
+pub fn run(options: &Options, command: &GetOptions) {
+	let gpio = crate::open_gpio(options);
+	let pin = match gpio.read_pin(command.pin) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	match (command.quiet, command.function) {
+		(false, _)    => crate::print_pin(command.pin, &pin, None, options.verbose),
+		(true, false) => match pin.level {
+			Level::Low  => println!("0"),
+			Level::High => println!("1"),
+		},
+		(true, true)  => println!("{}", function_name(pin.function)),
+	}
+}
+
+/// Get the name of a pin function as accepted by the function option of --set-pin.
+fn function_name(function: PinFunction) -> &'static str {
+	match function {
+		PinFunction::Input  => "input",
+		PinFunction::Output => "output",
+		PinFunction::Alt0   => "alt0",
+		PinFunction::Alt1   => "alt1",
+		PinFunction::Alt2   => "alt2",
+		PinFunction::Alt3   => "alt3",
+		PinFunction::Alt4   => "alt4",
+		PinFunction::Alt5   => "alt5",
+	}
+}
@@ -0,0 +1,22 @@
+use std::net::SocketAddr;
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::GrpcServer;
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct GrpcOptions {
+	/// The address to listen on.
+	#[structopt(long = "listen", value_name = "ADDRESS", default_value = "127.0.0.1:50051")]
+	listen: SocketAddr,
+}
+
+/// Serve the GPIO registers over gRPC.
+pub fn run(options: &Options, command: &GrpcOptions) {
+	let gpio = crate::open_gpio(options);
+	eprintln!("serving gRPC on {}", command.listen);
+	if let Err(error) = GrpcServer::new(gpio).serve(command.listen) {
+		crate::exit_with_error(error);
+	}
+}
@@ -0,0 +1,57 @@
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{Error, Gpio, SoftI2c};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct I2cScanOptions {
+	/// The pin to use as data line.
+	#[structopt(long = "sda", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	sda: usize,
+
+	/// The pin to use as clock line.
+	#[structopt(long = "scl", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	scl: usize,
+
+	/// The clock frequency of the bus.
+	#[structopt(long = "freq", value_name = "FREQUENCY", default_value = "100kHz", parse(try_from_str = "crate::parse_frequency"))]
+	frequency: f64,
+}
+
+/// Probe the addresses 0x03 to 0x77 and print a table like i2cdetect.
+pub fn run(options: &Options, command: &I2cScanOptions) {
+	let mut gpio = crate::open_gpio(options);
+
+	// Remember the pin state so we can restore it afterwards.
+	let restore = crate::save_pins(&gpio, &[command.sda, command.scl]);
+
+	let result = scan(&mut gpio, command);
+	restore.apply_unchecked(&mut gpio);
+
+	if let Err(error) = result {
+		crate::exit_with_error(error);
+	}
+}
+
+fn scan(gpio: &mut Gpio, command: &I2cScanOptions) -> Result<(), Error> {
+	let mut bus = SoftI2c::new(gpio, command.sda, command.scl, command.frequency)?;
+
+	println!("     0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f");
+	for row in (0..0x80u8).step_by(16) {
+		print!("{:02x}:", row);
+		for address in row..row + 16 {
+			if !(0x03..=0x77).contains(&address) {
+				print!("   ");
+			} else if bus.probe(address)? {
+				print!(" {}", Paint::green(format!("{:02x}", address)));
+			} else {
+				print!(" --");
+			}
+		}
+		println!();
+	}
+
+	Ok(())
+}
@@ -0,0 +1,23 @@
+use nix::sys::signal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(_: nix::libc::c_int) {
+	INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catch SIGINT and SIGTERM so long running commands can clean up before exiting.
+pub fn install_handler() {
+	let action = signal::SigAction::new(signal::SigHandler::Handler(handle_signal), signal::SaFlags::empty(), signal::SigSet::empty());
+	for &sig in &[signal::Signal::SIGINT, signal::Signal::SIGTERM] {
+		if let Err(error) = unsafe { signal::sigaction(sig, &action) } {
+			crate::exit_with_error(format!("failed to install signal handler: {}", error));
+		}
+	}
+}
+
+/// Check if SIGINT or SIGTERM was received since the handler was installed.
+pub fn interrupted() -> bool {
+	INTERRUPTED.load(Ordering::SeqCst)
+}
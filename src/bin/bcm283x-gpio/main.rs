@@ -0,0 +1,1228 @@
+// vi: sw=4 ts=4 noexpandtab
+use yansi::Paint;
+use bcm283x_linux_gpio::{
+	check_bcm283x_gpio,
+	Edge,
+	pad_bank,
+	DriveStrength,
+	ErrorKind,
+	GpioConfig,
+	GpioPullConfig,
+	Gpio,
+	Interface,
+	KernelClaims,
+	Level,
+	PadConfig,
+	Pads,
+	Permissions,
+	PinInfo,
+	PinFunction,
+	PullMode,
+	Soc,
+	SpecialPin,
+};
+
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+
+mod aliases;
+mod apply;
+mod assert;
+mod bench;
+mod completions;
+mod cron;
+mod daemon;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod diff;
+mod get;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod i2c_scan;
+mod interrupt;
+mod monitor;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod onewire;
+mod pwm;
+mod reg;
+mod reset;
+mod sample;
+mod sequence;
+mod serve;
+#[cfg(not(feature = "stub"))]
+mod signal;
+mod spi;
+mod state;
+mod wait;
+mod watch;
+mod ws2812;
+
+/// The keys accepted in the options of --set-pin.
+const PIN_OPTIONS: &[&str] = &[
+	"level",
+	"function",
+	"pull",
+	"detect-rise",
+	"detect-fall",
+	"detect-high",
+	"detect-low",
+	"detect-async-rise",
+	"detect-async-fall",
+	"drive",
+	"slew",
+	"hysteresis",
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct PinCommand {
+	index                 : usize,
+	set_level             : Option<bool>,
+	set_function          : Option<PinFunction>,
+	set_pull_mode         : Option<PullMode>,
+	set_detect_rise       : Option<bool>,
+	set_detect_fall       : Option<bool>,
+	set_detect_high       : Option<bool>,
+	set_detect_low        : Option<bool>,
+	set_detect_async_rise : Option<bool>,
+	set_detect_async_fall : Option<bool>,
+	set_drive             : Option<DriveStrength>,
+	set_slew_limited      : Option<bool>,
+	set_hysteresis        : Option<bool>,
+}
+
+/// The configuration parsed from all --set-pin options.
+struct Config {
+	gpio : GpioConfig,
+	pull : GpioPullConfig,
+	pads : [PadCommand; 3],
+}
+
+/// Changes to the configuration of a pad bank.
+#[derive(Copy, Clone, Debug, Default)]
+struct PadCommand {
+	drive        : Option<DriveStrength>,
+	slew_limited : Option<bool>,
+	hysteresis   : Option<bool>,
+}
+
+impl PadCommand {
+	fn is_empty(&self) -> bool {
+		self.drive.is_none() && self.slew_limited.is_none() && self.hysteresis.is_none()
+	}
+
+	fn apply_to(&self, config: PadConfig) -> PadConfig {
+		PadConfig {
+			drive        : self.drive.unwrap_or(config.drive),
+			slew_limited : self.slew_limited.unwrap_or(config.slew_limited),
+			hysteresis   : self.hysteresis.unwrap_or(config.hysteresis),
+		}
+	}
+}
+
+impl PinCommand {
+	pub fn new(index: usize) -> Self {
+		Self {
+			index,
+			.. Default::default()
+		}
+	}
+
+	/// Parse and set a single pin option.
+	pub fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+		match key {
+			"level"             => set_bool(&mut self.set_level, key, value)?,
+			"function"          => set_function(&mut self.set_function, key, value)?,
+			"pull"              => set_pull(&mut self.set_pull_mode, key, value)?,
+			"detect-rise"       => set_bool(&mut self.set_detect_rise, key, value)?,
+			"detect-fall"       => set_bool(&mut self.set_detect_fall, key, value)?,
+			"detect-high"       => set_bool(&mut self.set_detect_high, key, value)?,
+			"detect-low"        => set_bool(&mut self.set_detect_low, key, value)?,
+			"detect-async-rise" => set_bool(&mut self.set_detect_async_rise, key, value)?,
+			"detect-async-fall" => set_bool(&mut self.set_detect_async_fall, key, value)?,
+			"drive"             => set_drive(&mut self.set_drive, key, value)?,
+			"slew"              => set_slew(&mut self.set_slew_limited, key, value)?,
+			"hysteresis"        => set_bool(&mut self.set_hysteresis, key, value)?,
+			_ => return Err(format!("unknown pin option: `{}`", key)),
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(StructOpt)]
+#[structopt(author = "Fusion Engineering <oss@fusion.engineering>")]
+#[structopt(about = "Inspect and modify BCM2835/7 GPIO state.")]
+#[structopt(max_term_width = 120)]
+#[structopt(raw(setting = "structopt::clap::AppSettings::DeriveDisplayOrder"))]
+#[structopt(raw(setting = "structopt::clap::AppSettings::UnifiedHelpMessage"))]
+#[structopt(raw(setting = "structopt::clap::AppSettings::ColoredHelp"))]
+#[structopt(after_help = "
+The pins of --set-pin can be a single pin, a range like 2-9,
+or several pins or ranges joined with a plus sign like 17+22+27.
+A single pin can also be an alias, see the aliases subcommand.
+
+Allowed pin options:
+    level=on/off
+    function=input/output/alt0..5
+    detect-rise=on/off
+    detect-fall=on/off
+    detect-high=on/off
+    detect-low=on/off
+    detect-async-high=on/off
+    detect-async-low=on/off
+    drive=2mA..16mA
+    slew=limited/unlimited
+    hysteresis=on/off
+
+The drive, slew and hysteresis options apply to the whole pad bank of a pin:
+pins 0-27, 28-45 or 46-53.
+")]
+struct Options {
+	/// Show more information.
+	#[structopt(long = "verbose", short = "v")]
+	verbose: bool,
+
+	/// Allow all unsafe operations: implies all --allow-* options and allows writing raw registers.
+	#[structopt(long = "unsafe")]
+	allow_unsafe: bool,
+
+	/// Allow changing the pull up/down mode of pins.
+	#[structopt(long = "allow-pull")]
+	allow_pull: bool,
+
+	/// Allow changing the event detection of pins.
+	#[structopt(long = "allow-detect")]
+	allow_detect: bool,
+
+	/// Allow setting pins to an alternate function.
+	#[structopt(long = "allow-alt-functions")]
+	allow_alt_functions: bool,
+
+	/// Allow changing the pad drive strength, slew rate and hysteresis.
+	#[structopt(long = "allow-pads")]
+	allow_pads: bool,
+
+	/// Print the register operations for the pin configuration instead of applying it.
+	#[structopt(long = "dry-run")]
+	dry_run: bool,
+
+	/// The output format for the pin listing: text or csv.
+	#[structopt(long = "format", value_name = "FORMAT", default_value = "text")]
+	format: OutputFormat,
+
+	/// Dangerous: skip the verification of the CPU.
+	#[structopt(long = "no-verify-cpu")]
+	no_verify_cpu: bool,
+
+	/// Allow changing the function of pins used by interfaces that are enabled in the device tree,
+	/// such as I2C, SPI or the serial console.
+	#[structopt(long = "force")]
+	force: bool,
+
+	/// Hold an exclusive lock on /run/lock/bcm283x-gpio.lock while running,
+	/// to coordinate with other processes that take the same lock.
+	#[structopt(long = "lock")]
+	lock: bool,
+
+	/// How to access the GPIO peripheral: auto, mem, gpiomem, cdev, sysfs or pread.
+	///
+	/// The cdev and sysfs backends work without access to the peripheral memory,
+	/// but do not support alternate functions or raw register access, and sysfs does not support pull up/down modes either.
+	/// The pread backend reads /dev/mem without mapping it, for kernels that refuse to map it, but can not change anything.
+	/// The auto backend uses the first backend that works, in the order listed, except pread which must be picked explicitly.
+	#[structopt(long = "backend", value_name = "BACKEND", default_value = "auto")]
+	backend: BackendKind,
+
+	/// Configure one or more GPIO pins.
+	/// May be specified multiple times.
+	///
+	#[structopt(
+		long = "set-pin",
+		short = "s",
+		value_name = "PINS,OPTION,OPTION...",
+		number_of_values = 1,
+	)]
+	pins: Vec<PinCommandList>,
+
+	#[structopt(subcommand)]
+	command: Option<Command>,
+}
+
+impl Options {
+	/// Get the permissions for pin configuration from the --allow-* and --unsafe options.
+	pub fn permissions(&self) -> Permissions {
+		Permissions {
+			pull:          self.allow_unsafe || self.allow_pull,
+			detect:        self.allow_unsafe || self.allow_detect,
+			alt_functions: self.allow_unsafe || self.allow_alt_functions,
+		}
+	}
+
+	/// Check if changing pad settings is allowed.
+	pub fn allow_pads(&self) -> bool {
+		self.allow_unsafe || self.allow_pads
+	}
+
+	/// Get the pin commands from all --set-pin options, one per pin.
+	pub fn pin_commands(&self) -> Vec<PinCommand> {
+		self.pins.iter().flat_map(|x| x.0.iter().cloned()).collect()
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum OutputFormat {
+	Text,
+	Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"text" => Ok(OutputFormat::Text),
+			"csv"  => Ok(OutputFormat::Csv),
+			_ => Err(format!("unknown output format: {}, expected text or csv", data)),
+		}
+	}
+}
+
+/// The backends selectable with --backend.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum BackendKind {
+	Auto,
+	Mem,
+	Gpiomem,
+	Cdev,
+	Sysfs,
+	Pread,
+}
+
+impl std::str::FromStr for BackendKind {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"auto"    => Ok(BackendKind::Auto),
+			"mem"     => Ok(BackendKind::Mem),
+			"gpiomem" => Ok(BackendKind::Gpiomem),
+			"cdev"    => Ok(BackendKind::Cdev),
+			"sysfs"   => Ok(BackendKind::Sysfs),
+			"pread"   => Ok(BackendKind::Pread),
+			_ => Err(format!("unknown backend: {}, expected auto, mem, gpiomem, cdev, sysfs or pread", data)),
+		}
+	}
+}
+
+impl BackendKind {
+	/// Open a GPIO handle with the backend.
+	///
+	/// The auto backend reports the error of /dev/mem if no backend works,
+	/// since that is the only one with full support.
+	/// It never picks the read-only pread backend, and warns when it falls back to a backend without register access.
+	fn open(self) -> Result<Gpio, bcm283x_linux_gpio::Error> {
+		match self {
+			BackendKind::Auto => {
+				let gpio = Gpio::new().or_else(|error| {
+					Gpio::new_gpiomem()
+						.or_else(|_| Gpio::new_cdev())
+						.or_else(|_| Gpio::new_sysfs())
+						.map_err(|_| error)
+				})?;
+				if !gpio.capabilities().registers {
+					eprintln!("{}: /dev/mem and /dev/gpiomem are not available, falling back to the {} backend with limited support", Paint::yellow("Warning").bold(), gpio.backend().name());
+				}
+				Ok(gpio)
+			},
+			BackendKind::Mem     => Gpio::new(),
+			BackendKind::Gpiomem => Gpio::new_gpiomem(),
+			BackendKind::Cdev    => Gpio::new_cdev(),
+			BackendKind::Sysfs   => Gpio::new_sysfs(),
+			BackendKind::Pread   => Gpio::new_pread(),
+		}
+	}
+}
+
+#[derive(StructOpt)]
+enum Command {
+	/// Print a timestamped line for every edge on the given pins.
+	#[structopt(name = "monitor")]
+	Monitor(monitor::MonitorOptions),
+
+	/// Print the state of a single pin, or only its level or function with --quiet.
+	#[structopt(name = "get")]
+	Get(get::GetOptions),
+
+	/// Show the pin table and redraw it periodically, or print a line for every change with --changes.
+	#[structopt(name = "watch")]
+	Watch(watch::WatchOptions),
+
+	/// Generate a shell completion script.
+	#[structopt(name = "completions")]
+	Completions(completions::CompletionsOptions),
+
+	/// Print the pin aliases from the alias file.
+	#[structopt(name = "aliases")]
+	Aliases(aliases::AliasesOptions),
+
+	/// Save the current GPIO state to a file.
+	#[structopt(name = "save")]
+	Save(state::SaveOptions),
+
+	/// Show the differences between two saved GPIO states.
+	#[structopt(name = "diff")]
+	Diff(diff::DiffOptions),
+
+	/// Read or write raw GPIO registers.
+	#[structopt(name = "reg")]
+	Reg(reg::RegOptions),
+
+	/// Toggle a pin as fast as possible and report the achieved speed.
+	#[structopt(name = "bench")]
+	Bench(bench::BenchOptions),
+
+	/// Generate a PWM signal on a pin until interrupted, with the PWM peripheral if the pin supports it.
+	#[structopt(name = "pwm")]
+	Pwm(pwm::PwmOptions),
+
+	/// Run a sequence of pin writes and waits, with repeat and loop blocks.
+	#[structopt(name = "sequence")]
+	Sequence(sequence::SequenceOptions),
+
+	/// Wait for an edge or a level on a pin. Exits with status 2 on timeout.
+	#[structopt(name = "wait")]
+	Wait(wait::WaitOptions),
+
+	/// Send signals to a process on pin edges, until interrupted or until the process exits.
+	#[cfg(not(feature = "stub"))]
+	#[structopt(name = "signal")]
+	Signal(signal::SignalOptions),
+
+	/// Record pin levels at a fixed interval as CSV.
+	#[structopt(name = "sample")]
+	Sample(sample::SampleOptions),
+
+	/// Apply a pin configuration from a TOML file.
+	#[structopt(name = "apply")]
+	Apply(apply::ApplyOptions),
+
+	/// Reset pins to their power-on default function and pull mode.
+	#[structopt(name = "reset")]
+	Reset(reset::ResetOptions),
+
+	/// Scan for devices on a bit-banged I2C bus.
+	#[structopt(name = "i2c-scan")]
+	I2cScan(i2c_scan::I2cScanOptions),
+
+	/// Perform transfers on a bit-banged SPI bus.
+	#[structopt(name = "spi")]
+	Spi(spi::SpiOptions),
+
+	/// Scan a bit-banged 1-Wire bus or read DS18B20 temperature sensors.
+	#[structopt(name = "onewire")]
+	OneWire(onewire::OneWireOptions),
+
+	/// Show a test pattern on a WS2812 (NeoPixel) LED strip.
+	#[structopt(name = "ws2812")]
+	Ws2812(ws2812::Ws2812Options),
+
+	/// Serve a REST API for the GPIO pins over HTTP.
+	#[structopt(name = "serve")]
+	Serve(serve::ServeOptions),
+
+	/// Share the GPIO peripheral with unprivileged local processes over a Unix socket.
+	#[structopt(name = "daemon")]
+	Daemon(daemon::DaemonOptions),
+
+	/// Send a command to a running daemon.
+	#[structopt(name = "client")]
+	Client(daemon::ClientOptions),
+
+	/// Check that pins are in an expected state, for use in test scripts.
+	#[structopt(name = "assert")]
+	Assert(assert::AssertOptions),
+
+	/// Publish pin levels to an MQTT broker and accept commands for outputs.
+	#[cfg(feature = "mqtt")]
+	#[structopt(name = "mqtt")]
+	Mqtt(mqtt::MqttOptions),
+
+	/// Serve pin reads and writes on D-Bus and send signals for edges.
+	#[cfg(feature = "dbus")]
+	#[structopt(name = "dbus")]
+	Dbus(dbus::DbusOptions),
+
+	/// Serve the GPIO registers over gRPC, for remote clients using the GrpcBackend of the library.
+	#[cfg(feature = "grpc")]
+	#[structopt(name = "grpc")]
+	Grpc(grpc::GrpcOptions),
+}
+
+fn main() {
+	let options = Options::from_args();
+
+	match &options.command {
+		None => {
+			let gpio = open_gpio(&options);
+			let mut pins = gpio.read_pins();
+			match options.format {
+				OutputFormat::Text => {
+					let pads = match options.verbose {
+						false => None,
+						true  => Pads::new().map_err(|e| eprintln!("{}: {}", Paint::yellow("Warning").bold(), e)).ok(),
+					};
+					if options.verbose {
+						match KernelClaims::read() {
+							Ok(claims) => claims.annotate(&mut pins),
+							Err(e) => eprintln!("{}: {}", Paint::yellow("Warning").bold(), e),
+						}
+						match Interface::read_all() {
+							Ok(interfaces) => Interface::annotate(&interfaces, &mut pins),
+							Err(e) => eprintln!("{}: {}", Paint::yellow("Warning").bold(), e),
+						}
+					}
+					for (index, pin) in pins.iter().enumerate() {
+						let pad = pads.as_ref().and_then(|x| x.read_pin(index).ok());
+						print_pin(index, pin, pad, options.verbose);
+					}
+				},
+				OutputFormat::Csv => print_csv(&pins),
+			}
+		},
+		Some(Command::Monitor(command)) => monitor::run(&open_gpio(&options), command),
+		Some(Command::Get(command)) => get::run(&options, command),
+		Some(Command::Watch(command)) => watch::run(&options, command),
+		Some(Command::Completions(command)) => completions::run(command),
+		Some(Command::Aliases(command)) => aliases::run(command),
+		Some(Command::Save(command)) => state::save(&open_gpio(&options), command),
+		Some(Command::Diff(command)) => diff::run(&options, command),
+		Some(Command::Reg(command)) => reg::run(&options, command),
+		Some(Command::Bench(command)) => bench::run(&options, command),
+		Some(Command::Pwm(command)) => pwm::run(&options, command),
+		Some(Command::Sequence(command)) => sequence::run(&options, command),
+		Some(Command::Wait(command)) => wait::run(&options, command),
+		#[cfg(not(feature = "stub"))]
+		Some(Command::Signal(command)) => signal::run(&options, command),
+		Some(Command::Sample(command)) => sample::run(&options, command),
+		Some(Command::Apply(command)) => apply::run(&options, command),
+		Some(Command::Reset(command)) => reset::run(&options, command),
+		Some(Command::I2cScan(command)) => i2c_scan::run(&options, command),
+		Some(Command::Spi(command)) => spi::run(&options, command),
+		Some(Command::OneWire(command)) => onewire::run(&options, command),
+		Some(Command::Ws2812(command)) => ws2812::run(&options, command),
+		Some(Command::Serve(command)) => serve::run(&options, command),
+		Some(Command::Daemon(command)) => daemon::run(&options, command),
+		Some(Command::Client(command)) => daemon::run_client(command),
+		Some(Command::Assert(command)) => assert::run(&options, command),
+		#[cfg(feature = "mqtt")]
+		Some(Command::Mqtt(command)) => mqtt::run(&options, command),
+		#[cfg(feature = "dbus")]
+		Some(Command::Dbus(command)) => dbus::run(&options, command),
+		#[cfg(feature = "grpc")]
+		Some(Command::Grpc(command)) => grpc::run(&options, command),
+	}
+}
+
+/// Print the register operations needed to apply a pin configuration.
+fn print_register_ops(config: &Config) {
+	let soc = Soc::detect().unwrap_or(Soc::Bcm2835);
+	for op in config.gpio.register_ops().into_iter().chain(config.pull.register_ops_for(soc)) {
+		println!("{}", op);
+	}
+
+	for (bank, pad) in config.pads.iter().enumerate().filter(|(_, pad)| !pad.is_empty()) {
+		print!("pad bank {}:", bank);
+		if let Some(drive) = pad.drive {
+			print!(" drive={}mA", drive.milliamps());
+		}
+		if let Some(slew_limited) = pad.slew_limited {
+			print!(" slew={}", if slew_limited { "limited" } else { "unlimited" });
+		}
+		if let Some(hysteresis) = pad.hysteresis {
+			print!(" hysteresis={}", if hysteresis { "on" } else { "off" });
+		}
+		println!();
+	}
+}
+
+/// Open the GPIO peripheral and apply the pin configuration from the command line.
+fn open_gpio(options: &Options) -> Gpio {
+	open_gpio_with(options, &options.pin_commands())
+}
+
+/// Open the GPIO peripheral and apply a pin configuration.
+///
+/// With --dry-run, this prints the register operations and exits instead.
+fn open_gpio_with(options: &Options, commands: &[PinCommand]) -> Gpio {
+	let config = match config_from_commands(commands, options) {
+		Ok(x) => x,
+		Err(error) => exit_with_error(error),
+	};
+
+	if let Err(error) = config.gpio.validate() {
+		exit_with_error(error);
+	}
+
+	if let Err(error) = check_interface_pins(options, commands) {
+		exit_with_error(error);
+	}
+
+	if options.dry_run {
+		print_register_ops(&config);
+		std::process::exit(0);
+	}
+
+	if !options.no_verify_cpu {
+		if let Some(error) = check_bcm283x_gpio().err() {
+			eprintln!("{}: {}", Paint::red("Error").bold(), error);
+			eprintln!();
+			eprintln!("Failed to verify the CPU type. Make sure the program is being run on a BCM2835/7 CPU.");
+			eprintln!("Alternatively, add --no-verify-cpu to the command line, but note that this could be dangerous.");
+			std::process::exit(1);
+		}
+	}
+
+	let gpio = match options.backend.open() {
+		Ok(mut gpio) if options.lock => gpio.acquire_lock(true).map(|()| gpio),
+		other => other,
+	};
+
+	let mut gpio = match gpio {
+		Ok(x) => x,
+		Err(error) => {
+			eprintln!("{}: {}", Paint::red("Error").bold(), error);
+			eprintln!();
+			match error.kind() {
+				_ if options.backend == BackendKind::Cdev => {
+					eprintln!("Make sure the GPIO character device of the BCM283x exists and that you have permission to open it.");
+				},
+				_ if options.backend == BackendKind::Sysfs => {
+					eprintln!("Make sure the kernel was built with CONFIG_GPIO_SYSFS and that you have permission to export pins.");
+				},
+				_ if options.backend == BackendKind::Pread => {
+					eprintln!("Make sure to run the application as root and that the kernel allows reading the GPIO peripheral from /dev/mem.");
+				},
+				_ if options.backend == BackendKind::Gpiomem => {
+					eprintln!("Make sure /dev/gpiomem exists and that you have permission to open it, usually by being in the gpio group.");
+				},
+				ErrorKind::PermissionDenied => {
+					eprintln!("Make sure to run the application as root.");
+					eprintln!("You may need to disable CONFIG_IO_STRICT_DEVMEM and add iomem=relaxed to the kernel command line.");
+				},
+				_ => {
+					eprintln!("Make sure to run the application as root on a BCM2835/7 CPU and that your kernel was configured properly.");
+					eprintln!("You may need to disable CONFIG_IO_STRICT_DEVMEM and add iomem=relaxed to the kernel command line.");
+				},
+			}
+			std::process::exit(1);
+		}
+	};
+
+	if options.verbose {
+		match gpio.control_block() {
+			Some(address) => eprintln!("mapped IO control block at: 0x{:X}", address as usize),
+			None => eprintln!("using the {} backend", gpio.backend().name()),
+		}
+	}
+
+	if !commands.is_empty() {
+		let capabilities = gpio.capabilities();
+		if let Err(error) = config.gpio.check_capabilities(capabilities).and(config.pull.check_capabilities(capabilities)) {
+			exit_with_error(error);
+		}
+		warn_kernel_claims(commands);
+		config.gpio.apply_unchecked(&mut gpio);
+		if let Err(error) = unsafe { config.pull.apply(&mut gpio) } {
+			exit_with_error(error);
+		}
+	}
+
+	if config.pads.iter().any(|x| !x.is_empty()) {
+		let mut pads = match Pads::new() {
+			Ok(x) => x,
+			Err(error) => exit_with_error(error),
+		};
+		for (bank, pad) in config.pads.iter().enumerate().filter(|(_, pad)| !pad.is_empty()) {
+			let result = pads.read_bank(bank).and_then(|current| unsafe {
+				pads.write_bank(bank, pad.apply_to(current))
+			});
+			if let Err(error) = result {
+				exit_with_error(error);
+			}
+		}
+	}
+
+	gpio
+}
+
+/// Refuse to change the function of pins used by an enabled interface, unless --force is given.
+fn check_interface_pins(options: &Options, commands: &[PinCommand]) -> Result<(), String> {
+	if options.force || commands.iter().all(|x| x.set_function.is_none()) {
+		return Ok(());
+	}
+
+	let interfaces = match Interface::read_enabled() {
+		Ok(x) => x,
+		Err(e) => {
+			eprintln!("{}: failed to check for enabled interfaces: {}", Paint::yellow("Warning").bold(), e);
+			return Ok(());
+		},
+	};
+
+	for command in commands.iter().filter(|x| x.set_function.is_some()) {
+		if let Some(interface) = interfaces.iter().find(|x| x.pins.contains(&command.index)) {
+			return Err(format!(
+				"pin {} is used by {} ({}), add --force to change its function anyway",
+				command.index,
+				interface.name,
+				interface.path,
+			));
+		}
+	}
+
+	Ok(())
+}
+
+/// Warn about pins that are about to be reconfigured while a kernel driver claimed them.
+fn warn_kernel_claims(commands: &[PinCommand]) {
+	let claims = match KernelClaims::read() {
+		Ok(x) => x,
+		Err(e) => {
+			eprintln!("{}: failed to check for pins claimed by the kernel: {}", Paint::yellow("Warning").bold(), e);
+			return;
+		},
+	};
+
+	let mut warned = [false; 54];
+	for command in commands {
+		if let Some(owner) = claims.owner(command.index) {
+			if !std::mem::replace(&mut warned[command.index], true) {
+				eprintln!("{}: pin {} is claimed by kernel driver {}", Paint::yellow("Warning").bold(), command.index, owner);
+			}
+		}
+	}
+}
+
+/// Get a configuration that restores the current function and level of some pins.
+fn save_pins(gpio: &Gpio, pins: &[usize]) -> GpioConfig {
+	let state = gpio.read_all();
+	let mut config = GpioConfig::new();
+	for &pin in pins {
+		let result = state.pin(pin).and_then(|info| {
+			config.set_level(pin, info.level)?;
+			config.set_function(pin, info.function)
+		});
+		if let Err(error) = result {
+			exit_with_error(error);
+		}
+	}
+	config
+}
+
+/// Print an error and exit the process.
+fn exit_with_error(error: impl std::fmt::Display) -> ! {
+	eprintln!("{}: {}", Paint::red("Error").bold(), error);
+	std::process::exit(1);
+}
+
+fn print_pin(index: usize, pin: &PinInfo, pad: Option<PadConfig>, verbose: bool) {
+	let level = match pin.level {
+		Level::High => Paint::green("HIGH"),
+		Level::Low  => Paint::red("LOW"),
+	};
+
+	let function = match &pin.function_name {
+		Some(name) => format!("{:?} ({})", pin.function, name),
+		None       => format!("{:?}", pin.function),
+	};
+	print!("pin={:<2}   level={:4}   function={:18}", Paint::yellow(index), level, Paint::cyan(function));
+
+	if let Some(mode) = pin.pull_mode {
+		print!("   pull={:5}", Paint::cyan(pull_name(mode)));
+	}
+
+	if let Some(special) = pin.special {
+		let special = match special {
+			SpecialPin::IdEeprom => "id-eeprom",
+			SpecialPin::SdCard   => "sd-card",
+		};
+		print!("   special={}", Paint::magenta(special));
+	}
+
+	if verbose {
+		let event = match pin.event {
+			true  => Paint::green("yes"),
+			false => Paint::red("no "),
+		};
+		print!("   event={}   detect=", event);
+
+		let mut detect_any = false;
+		if pin.detect_rise {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("rise"));
+		}
+		if pin.detect_fall {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("fall"));
+		}
+		if pin.detect_high {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("high"));
+		}
+		if pin.detect_low {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("low"));
+		}
+		if pin.detect_async_rise {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("async_rise"));
+		}
+		if pin.detect_async_fall {
+			if std::mem::replace(&mut detect_any, true) {
+				print!(",");
+			}
+			print!("{}", Paint::cyan("async_fall"));
+		}
+		if !detect_any {
+			print!("{}", Paint::magenta("nothing"));
+		}
+
+		if let Some(owner) = &pin.kernel_owner {
+			print!("   owner={}", Paint::magenta(owner));
+		}
+
+		if let Some(interface) = &pin.interface {
+			print!("   used_by={}", Paint::magenta(interface));
+		}
+
+		if let Some(pad) = pad {
+			let slew = match pad.slew_limited {
+				true  => "limited",
+				false => "unlimited",
+			};
+			let hysteresis = match pad.hysteresis {
+				true  => "on",
+				false => "off",
+			};
+			print!(
+				"   drive={}   slew={}   hysteresis={}",
+				Paint::cyan(format!("{}mA", pad.drive.milliamps())),
+				Paint::cyan(slew),
+				Paint::cyan(hysteresis),
+			);
+		}
+	}
+
+	println!();
+}
+
+fn print_csv(pins: &[PinInfo]) {
+	println!("pin,function,level,event,detect_rise,detect_fall,detect_high,detect_low,detect_async_rise,detect_async_fall");
+	for (index, pin) in pins.iter().enumerate() {
+		println!(
+			"{},{:?},{},{},{},{},{},{},{},{}",
+			index,
+			pin.function,
+			u8::from(pin.level.is_high()),
+			u8::from(pin.event),
+			u8::from(pin.detect_rise),
+			u8::from(pin.detect_fall),
+			u8::from(pin.detect_high),
+			u8::from(pin.detect_low),
+			u8::from(pin.detect_async_rise),
+			u8::from(pin.detect_async_fall),
+		);
+	}
+}
+
+/// Read a line of at most `max_length` bytes, including the line ending.
+///
+/// Returns `None` at the end of the stream, and an error of kind `InvalidData` if the line is longer.
+fn read_line_bounded(reader: &mut impl std::io::BufRead, max_length: usize) -> std::io::Result<Option<String>> {
+	use std::io::{BufRead, Read};
+
+	let mut line = Vec::new();
+	reader.by_ref().take(max_length as u64).read_until(b'\n', &mut line)?;
+	if line.is_empty() {
+		return Ok(None);
+	}
+	if line.len() == max_length && !line.ends_with(b"\n") {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "line too long"));
+	}
+	Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+fn partition(input: &str, split_on: char) -> (&str, Option<&str>) {
+	let mut parts = input.splitn(2, split_on);
+	(parts.next().unwrap(), parts.next())
+}
+
+fn split_key_value(input: &str) -> (&str, Option<&str>) {
+	let (key, value) = partition(input, '=');
+	let key = key.trim();
+	let value = value.map(str::trim);
+	(key, value)
+}
+
+/// A list of pin indices or ranges, separated by commas.
+#[derive(Clone, Debug)]
+pub struct PinList(Vec<usize>);
+
+impl PinList {
+	pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+		self.0.iter().cloned()
+	}
+}
+
+/// Parse a pin index or an alias from the alias file.
+fn parse_pin_index(data: &str) -> Result<usize, String> {
+	if data.parse::<i64>().is_err() {
+		if let Some(index) = aliases::resolve(data)? {
+			return Ok(index);
+		}
+		return Err(format!("invalid pin index or unknown alias: {}", data));
+	}
+	parse_pin_number(data)
+}
+
+/// Parse a pin index, without looking up aliases.
+fn parse_pin_number(data: &str) -> Result<usize, String> {
+	let index = usize::from_str(data).map_err(|_| format!("invalid pin index: {}", data))?;
+	if index > 53 {
+		return Err(format!("pin index out of range [0-53]: {}", index));
+	}
+	Ok(index)
+}
+
+/// Parse a single pin index or an inclusive range of pins, like 2-9.
+fn parse_pin_range(data: &str) -> Result<Vec<usize>, String> {
+	// Aliases may contain a dash, so try the whole string as a single pin first.
+	if let Ok(index) = parse_pin_index(data.trim()) {
+		return Ok(vec![index]);
+	}
+
+	let (first, last) = match partition(data, '-') {
+		(first, Some(last)) => (parse_pin_index(first.trim())?, parse_pin_index(last.trim())?),
+		(index, None) => {
+			let index = parse_pin_index(index)?;
+			(index, index)
+		},
+	};
+
+	if first > last {
+		return Err(format!("invalid pin range: {}, the first pin must not be higher than the last", data));
+	}
+
+	Ok((first..=last).collect())
+}
+
+impl std::str::FromStr for PinList {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let mut pins = Vec::new();
+		for part in data.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+			pins.extend(parse_pin_range(part)?);
+		}
+
+		if pins.is_empty() {
+			return Err(String::from("empty pin list"));
+		}
+
+		Ok(PinList(pins))
+	}
+}
+
+/// A list of edges, separated by commas.
+#[derive(Clone, Debug)]
+pub struct EdgeList(Vec<Edge>);
+
+impl EdgeList {
+	pub fn iter(&self) -> impl Iterator<Item = Edge> + '_ {
+		self.0.iter().cloned()
+	}
+}
+
+impl Default for EdgeList {
+	/// Both edges.
+	fn default() -> Self {
+		EdgeList(vec![Edge::Rise, Edge::Fall])
+	}
+}
+
+impl std::str::FromStr for EdgeList {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let mut edges = Vec::new();
+		for edge in data.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+			match edge {
+				"rise" | "rising"  => edges.push(Edge::Rise),
+				"fall" | "falling" => edges.push(Edge::Fall),
+				"both"             => edges.extend_from_slice(&[Edge::Rise, Edge::Fall]),
+				_ => return Err(format!("unknown edge: {}, expected rise, fall or both", edge)),
+			}
+		}
+
+		if edges.is_empty() {
+			return Err(String::from("empty edge list"));
+		}
+
+		Ok(EdgeList(edges))
+	}
+}
+
+/// Parse a duration with a unit, such as 5s, 100ms, 250us or 2m.
+///
+/// A number without unit is interpreted as seconds.
+pub fn parse_duration(data: &str) -> Result<Duration, String> {
+	let (number, multiplier) = if let Some(x) = data.strip_suffix("us") {
+		(x, 1e-6)
+	} else if let Some(x) = data.strip_suffix("ms") {
+		(x, 1e-3)
+	} else if let Some(x) = data.strip_suffix('s') {
+		(x, 1.0)
+	} else if let Some(x) = data.strip_suffix('m') {
+		(x, 60.0)
+	} else {
+		(data, 1.0)
+	};
+
+	match number.trim().parse::<f64>() {
+		Ok(x) if x.is_finite() && x >= 0.0 => Ok(Duration::from_secs_f64(x * multiplier)),
+		_ => Err(format!("invalid duration: {}, expected a number with unit us, ms, s or m", data)),
+	}
+}
+
+/// Parse a frequency with an optional unit, such as 50Hz, 1kHz or 2.5MHz.
+pub fn parse_frequency(data: &str) -> Result<f64, String> {
+	let lower = data.to_ascii_lowercase();
+	let (number, multiplier) = if let Some(x) = lower.strip_suffix("mhz") {
+		(x, 1e6)
+	} else if let Some(x) = lower.strip_suffix("khz") {
+		(x, 1e3)
+	} else if let Some(x) = lower.strip_suffix("hz") {
+		(x, 1.0)
+	} else {
+		(lower.as_str(), 1.0)
+	};
+
+	match number.trim().parse::<f64>() {
+		Ok(x) if x.is_finite() && x > 0.0 => Ok(x * multiplier),
+		_ => Err(format!("invalid frequency: {}", data)),
+	}
+}
+
+/// The same pin command applied to one or more pins, as given to --set-pin.
+pub struct PinCommandList(Vec<PinCommand>);
+
+impl std::str::FromStr for PinCommandList {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let mut fields = data.split(",").map(str::trim).filter(|x| !x.is_empty());
+
+		let mut pins = Vec::new();
+		for part in fields.next().unwrap_or("").split('+') {
+			pins.extend(parse_pin_range(part.trim())?);
+		}
+
+		let mut commands: Vec<_> = pins.into_iter().map(PinCommand::new).collect();
+		for field in fields {
+			let (key, value) = split_key_value(field);
+
+			let value = match value {
+				Some(x) => x,
+				None => return Err(format!("missing value for option `{}`", key)),
+			};
+
+			for command in &mut commands {
+				command.set_option(key, value)?;
+			}
+		}
+
+		Ok(PinCommandList(commands))
+	}
+}
+
+fn set_bool(dest: &mut Option<bool>, key: &str, value: &str) -> Result<(), String> {
+	if dest.is_some() {
+		return Err(format!("option `{}` already set", key))
+	}
+
+	dest.replace(match value {
+		"on"  | "high" | "true"  | "1" => true,
+		"off" | "low"  | "false" | "0" => false,
+		_ => return Err(format!("invalid boolean: {}, expected on, high, true, 1, off, low, false or 0", value)),
+	});
+
+	Ok(())
+}
+
+fn set_function(dest: &mut Option<PinFunction>, key: &str, value: &str) -> Result<(), String> {
+	if dest.is_some() {
+		return Err(format!("option `{}` already set", key))
+	}
+
+	dest.replace(match value {
+		"input"  | "in"  => PinFunction::Input,
+		"output" | "out" => PinFunction::Output,
+		"alt0"           => PinFunction::Alt0,
+		"alt1"           => PinFunction::Alt1,
+		"alt2"           => PinFunction::Alt2,
+		"alt3"           => PinFunction::Alt3,
+		"alt4"           => PinFunction::Alt4,
+		"alt5"           => PinFunction::Alt5,
+		_ => return Err(format!("unknown pin function: {}, expected input, output or alt0..5", value)),
+	});
+
+	Ok(())
+}
+
+/// Get the name of a pull up/down mode, as accepted by the `pull` pin option.
+fn pull_name(mode: PullMode) -> &'static str {
+	match mode {
+		PullMode::Float    => "float",
+		PullMode::PullDown => "down",
+		PullMode::PullUp   => "up",
+	}
+}
+
+fn set_pull(dest: &mut Option<PullMode>, key: &str, value: &str) -> Result<(), String> {
+	if dest.is_some() {
+		return Err(format!("option `{}` already set", key))
+	}
+
+	dest.replace(match value {
+		"up"   => PullMode::PullUp,
+		"down" => PullMode::PullDown,
+		"float"=> PullMode::Float,
+		_ => return Err(format!("unknown pull up/down mode: {}, expected up, down or float", value)),
+	});
+
+	Ok(())
+}
+
+fn set_drive(dest: &mut Option<DriveStrength>, key: &str, value: &str) -> Result<(), String> {
+	if dest.is_some() {
+		return Err(format!("option `{}` already set", key))
+	}
+
+	let milliamps = value.trim_end_matches("mA").trim_end_matches("ma");
+	let drive = u8::from_str(milliamps).ok().and_then(DriveStrength::from_milliamps);
+	dest.replace(match drive {
+		Some(x) => x,
+		None => return Err(format!("invalid drive strength: {}, expected 2mA, 4mA, ... or 16mA", value)),
+	});
+
+	Ok(())
+}
+
+fn set_slew(dest: &mut Option<bool>, key: &str, value: &str) -> Result<(), String> {
+	if dest.is_some() {
+		return Err(format!("option `{}` already set", key))
+	}
+
+	dest.replace(match value {
+		"limited"   => true,
+		"unlimited" => false,
+		_ => return Err(format!("invalid slew rate: {}, expected limited or unlimited", value)),
+	});
+
+	Ok(())
+}
+
+/// Merge a pad setting for a pin into the settings for its bank.
+fn merge_pad_setting<T>(dest: &mut Option<T>, value: Option<T>, name: &str, pin: usize) -> Result<(), String>
+where
+	T: Copy + PartialEq,
+{
+	match (*dest, value) {
+		(_, None) => Ok(()),
+		(None, Some(value)) => {
+			*dest = Some(value);
+			Ok(())
+		},
+		(Some(old), Some(value)) if old == value => Ok(()),
+		(Some(_), Some(_)) => Err(format!("conflicting values for pad option `{}` in the pad bank of pin {}", name, pin)),
+	}
+}
+
+/// Check that setting a pin function is allowed by the permissions.
+///
+/// This uses the same check as the --set-pin options, see [`GpioConfig::check_permissions`].
+pub fn check_function(permissions: Permissions, pin: usize, function: PinFunction) -> Result<(), String> {
+	let mut config = GpioConfig::new();
+	config.set_function(pin, function).map_err(|e| e.to_string())?;
+	config.check_permissions(permissions).map_err(permission_error)
+}
+
+/// Add a hint about the options that grant permissions to a permission error.
+fn permission_error(error: bcm283x_linux_gpio::Error) -> String {
+	format!("{}, see the --allow-* options", error)
+}
+
+fn config_from_commands(commands: &[PinCommand], options: &Options) -> Result<Config, String> {
+	let mut gpio = GpioConfig::new();
+	let mut pud  = GpioPullConfig::new();
+	let mut pads = [PadCommand::default(); 3];
+
+	let permissions = options.permissions();
+	let check = |allowed: bool, name: &str, flag: &str| {
+		if allowed {
+			Ok(())
+		} else {
+			Err(format!("trying to set unsafe pin option `{}` without {}", name, flag))
+		}
+	};
+
+	for pin in commands {
+		if let Some(value) = pin.set_level {
+			gpio.set_level(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_function {
+			gpio.set_function(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_pull_mode {
+			pud.set_pull_mode(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_rise {
+			gpio.set_detect_rise(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_fall {
+			gpio.set_detect_fall(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_high {
+			gpio.set_detect_high(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_low {
+			gpio.set_detect_low(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_async_rise {
+			gpio.set_detect_async_rise(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if let Some(value) = pin.set_detect_async_fall {
+			gpio.set_detect_async_fall(pin.index, value).map_err(|e| e.to_string())?;
+		}
+		if pin.set_drive.is_some() || pin.set_slew_limited.is_some() || pin.set_hysteresis.is_some() {
+			check(options.allow_pads(), "pad options", "--allow-pads")?;
+			let pad = &mut pads[pad_bank(pin.index).map_err(|e| e.to_string())?];
+			merge_pad_setting(&mut pad.drive,        pin.set_drive,        "drive",      pin.index)?;
+			merge_pad_setting(&mut pad.slew_limited, pin.set_slew_limited, "slew",       pin.index)?;
+			merge_pad_setting(&mut pad.hysteresis,   pin.set_hysteresis,   "hysteresis", pin.index)?;
+		}
+	}
+
+	// Use the checks of the library, so the CLI can not allow more than the library does.
+	gpio.check_permissions(permissions).map_err(permission_error)?;
+	pud.check_permissions(permissions).map_err(permission_error)?;
+
+	Ok(Config { gpio, pull: pud, pads })
+}
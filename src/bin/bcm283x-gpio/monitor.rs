@@ -0,0 +1,61 @@
+use std::time::Duration;
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{Edge, EventListener, Gpio};
+
+use crate::{EdgeList, PinList};
+
+#[derive(StructOpt)]
+pub struct MonitorOptions {
+	/// The pins to monitor, separated by commas.
+	#[structopt(value_name = "PIN,PIN...")]
+	pins: PinList,
+
+	/// The edges to report, separated by commas.
+	#[structopt(long = "edges", value_name = "EDGE,EDGE", default_value = "rise,fall")]
+	edges: EdgeList,
+
+	/// The polling interval, for example 100us or 1ms.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "100us", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+}
+
+pub fn run(gpio: &Gpio, options: &MonitorOptions) {
+	let mut listener = EventListener::new();
+	for pin in options.pins.iter() {
+		for edge in options.edges.iter() {
+			if let Err(error) = listener.watch(pin, edge) {
+				crate::exit_with_error(error);
+			}
+		}
+	}
+
+	listener.poll(gpio);
+	let start = std::time::Instant::now();
+	let mut previous = start;
+
+	loop {
+		let event = match listener.wait(gpio, options.interval, None) {
+			Some(x) => x,
+			None    => continue,
+		};
+
+		let time  = event.time.duration_since(start);
+		let delta = event.time.duration_since(previous);
+		previous  = event.time;
+
+		let edge = match event.edge {
+			Edge::Rise => Paint::green("rise"),
+			Edge::Fall => Paint::red("fall"),
+		};
+
+		println!(
+			"[{:>6}.{:06}]   pin={:<2}   edge={:4}   delta=+{}.{:06}",
+			time.as_secs(), time.subsec_micros(),
+			Paint::yellow(event.pin),
+			edge,
+			delta.as_secs(), delta.subsec_micros(),
+		);
+	}
+}
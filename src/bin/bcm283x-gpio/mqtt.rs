@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Gpio, Permissions};
+
+use crate::{Options, PinCommand, PinList};
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+The level of each published pin is sent to PREFIX/PIN/level as on or off, retained.
+Writable pins can be changed by publishing to PREFIX/PIN/level/set (on or off)
+or PREFIX/PIN/function/set (input, output or alt0..5).
+
+The password is read from --password-file, or from $BCM283X_GPIO_MQTT_PASSWORD,
+so it does not show up in the process list.
+")]
+pub struct MqttOptions {
+	/// The address of the MQTT broker.
+	#[structopt(long = "broker", value_name = "HOST:PORT", default_value = "localhost:1883")]
+	broker: String,
+
+	/// The client identifier to connect with.
+	#[structopt(long = "client-id", value_name = "ID", default_value = "bcm283x-gpio")]
+	client_id: String,
+
+	/// The user name to authenticate with.
+	#[structopt(long = "username", value_name = "NAME")]
+	username: Option<String>,
+
+	/// Read the password to authenticate with from a file. A trailing newline is ignored.
+	#[structopt(long = "password-file", value_name = "PATH", parse(from_os_str))]
+	password_file: Option<PathBuf>,
+
+	/// The prefix of all topics.
+	#[structopt(long = "prefix", value_name = "PREFIX", default_value = "gpio")]
+	prefix: String,
+
+	/// The quality of service for published messages and subscriptions: 0 or 1.
+	///
+	/// With 1, level messages that are not acknowledged by the broker are sent again.
+	#[structopt(long = "qos", value_name = "QOS", default_value = "0", parse(try_from_str = "parse_qos"))]
+	qos: u8,
+
+	/// The pins to publish the level of, separated by commas. Defaults to the header pins 0-27.
+	#[structopt(long = "pins", value_name = "PIN,PIN...")]
+	pins: Option<PinList>,
+
+	/// The pins that may be changed through command topics, separated by commas.
+	#[structopt(long = "writable", value_name = "PIN,PIN...")]
+	writable: Option<PinList>,
+
+	/// The polling interval for level changes.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "10ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+}
+
+/// The keep alive interval announced to the broker.
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+/// How long to wait for the acknowledgement of a QoS 1 message before sending it again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The environment variable with the password, if --password-file is not given.
+const PASSWORD_VAR: &str = "BCM283X_GPIO_MQTT_PASSWORD";
+
+/// Bridge pin levels and commands to an MQTT broker.
+pub fn run(options: &Options, command: &MqttOptions) {
+	let mut gpio = crate::open_gpio(options);
+	if let Err(error) = bridge(&mut gpio, command, options.permissions()) {
+		crate::exit_with_error(error);
+	}
+}
+
+/// Read the password from --password-file or the environment.
+fn read_password(command: &MqttOptions) -> Result<Option<String>, String> {
+	if let Some(path) = &command.password_file {
+		let data = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+		return Ok(Some(data.trim_end_matches(&['\r', '\n'][..]).to_string()));
+	}
+	match std::env::var(PASSWORD_VAR) {
+		Ok(x) => Ok(Some(x)),
+		Err(std::env::VarError::NotPresent) => Ok(None),
+		Err(std::env::VarError::NotUnicode(_)) => Err(format!("{} is not valid UTF-8", PASSWORD_VAR)),
+	}
+}
+
+fn bridge(gpio: &mut Gpio, command: &MqttOptions, permissions: Permissions) -> Result<(), String> {
+	let published = match &command.pins {
+		Some(pins) => pins.iter().fold(0u64, |mask, pin| mask | 1 << pin),
+		None => (1 << 28) - 1,
+	};
+	let writable = command.writable.iter().flat_map(|x| x.iter()).fold(0u64, |mask, pin| mask | 1 << pin);
+	let password = read_password(command)?;
+
+	let mut stream = TcpStream::connect(&command.broker).map_err(|e| format!("failed to connect to {}: {}", command.broker, e))?;
+	let mut reader = stream.try_clone().map_err(|e| e.to_string())?;
+	let _ = stream.set_nodelay(true);
+
+	stream.write_all(&packet::connect(&command.client_id, command.username.as_deref(), password.as_deref(), KEEP_ALIVE))
+		.map_err(|e| format!("failed to send connect packet: {}", e))?;
+	match packet::read(&mut reader).map_err(|e| format!("failed to read connect acknowledgement: {}", e))? {
+		(0x20, body) if body.len() == 2 && body[1] == 0 => (),
+		(0x20, body) if body.len() == 2 => return Err(format!("broker refused connection with code {}", body[1])),
+		_ => return Err(String::from("expected connect acknowledgement from broker")),
+	}
+
+	let mut packet_id = 0u16;
+	let mut next_id = || {
+		packet_id = packet_id.wrapping_add(1).max(1);
+		packet_id
+	};
+
+	if writable != 0 {
+		let filters = [format!("{}/+/level/set", command.prefix), format!("{}/+/function/set", command.prefix)];
+		stream.write_all(&packet::subscribe(next_id(), &filters, command.qos)).map_err(|e| e.to_string())?;
+	}
+
+	// Read packets on a separate thread, so the main loop can keep polling the pin levels.
+	let (packet_tx, packet_rx) = mpsc::channel();
+	std::thread::spawn(move || {
+		while let Ok(packet) = packet::read(&mut reader) {
+			if packet_tx.send(packet).is_err() {
+				break;
+			}
+		}
+	});
+
+	// The QoS 1 messages that are not acknowledged yet, by packet identifier.
+	let mut unacked = HashMap::<u16, Unacked>::new();
+
+	let mut levels: Option<u64> = None;
+	let mut last_sent = Instant::now();
+	loop {
+		match packet_rx.recv_timeout(command.interval) {
+			Ok((header, body)) if header & 0xF0 == 0x30 => {
+				let (topic, payload, id) = match packet::parse_publish(header, &body) {
+					Ok(x) => x,
+					Err(error) => {
+						eprintln!("ignoring malformed message: {}", error);
+						continue;
+					},
+				};
+				if let Some(id) = id {
+					stream.write_all(&packet::puback(id)).map_err(|e| e.to_string())?;
+				}
+				if let Err(error) = handle_command(gpio, command, permissions, writable, &topic, &payload) {
+					eprintln!("ignoring message on {}: {}", topic, error);
+				}
+			},
+			Ok((0x40, body)) if body.len() == 2 => {
+				unacked.remove(&u16::from_be_bytes([body[0], body[1]]));
+			},
+			Ok(_) => (),
+			Err(mpsc::RecvTimeoutError::Timeout) => (),
+			Err(mpsc::RecvTimeoutError::Disconnected) => return Err(String::from("connection to broker closed")),
+		}
+
+		let current = gpio.read_levels();
+		let changed = match levels {
+			Some(previous) => (previous ^ current) & published,
+			None => published,
+		};
+		levels = Some(current);
+
+		for pin in (0..54).filter(|pin| changed & 1 << pin != 0) {
+			let topic   = format!("{}/{}/level", command.prefix, pin);
+			let payload = if current & 1 << pin != 0 { "on" } else { "off" };
+			let id      = if command.qos > 0 { next_id() } else { 0 };
+			stream.write_all(&packet::publish(&topic, payload.as_bytes(), command.qos, true, false, id)).map_err(|e| e.to_string())?;
+			last_sent = Instant::now();
+			if command.qos > 0 {
+				// The level is retained, so a new level replaces an unacknowledged older one.
+				unacked.retain(|_, x| x.topic != topic);
+				unacked.insert(id, Unacked { topic, payload, sent: last_sent });
+			}
+		}
+
+		for (&id, message) in unacked.iter_mut().filter(|(_, x)| x.sent.elapsed() >= RETRY_INTERVAL) {
+			stream.write_all(&packet::publish(&message.topic, message.payload.as_bytes(), command.qos, true, true, id)).map_err(|e| e.to_string())?;
+			message.sent = Instant::now();
+			last_sent = message.sent;
+		}
+
+		if last_sent.elapsed() > KEEP_ALIVE / 2 {
+			stream.write_all(&packet::pingreq()).map_err(|e| e.to_string())?;
+			last_sent = Instant::now();
+		}
+	}
+}
+
+/// A published QoS 1 message that is not acknowledged by the broker yet.
+struct Unacked {
+	topic: String,
+	payload: &'static str,
+	sent: Instant,
+}
+
+/// Handle a message on a command topic.
+fn handle_command(gpio: &mut Gpio, command: &MqttOptions, permissions: Permissions, writable: u64, topic: &str, payload: &[u8]) -> Result<(), String> {
+	let rest = topic.strip_prefix(&command.prefix).and_then(|x| x.strip_prefix('/')).ok_or("unexpected topic")?;
+	let (pin, key) = match rest.split('/').collect::<Vec<_>>().as_slice() {
+		[pin, key, "set"] => (crate::parse_pin_index(pin)?, key.to_string()),
+		_ => return Err(String::from("unexpected topic")),
+	};
+	if writable & 1 << pin == 0 {
+		return Err(format!("pin {} is not writable", pin));
+	}
+
+	let value = std::str::from_utf8(payload).map_err(|_| "payload is not valid UTF-8")?.trim();
+	let mut pin_command = PinCommand::new(pin);
+	match key.as_str() {
+		"level" | "function" => pin_command.set_option(&key, value)?,
+		_ => return Err(format!("unsupported option: {}", key)),
+	}
+
+	if let Some(level) = pin_command.set_level {
+		gpio.set_level(pin, level).map_err(|e| e.to_string())?;
+	}
+	if let Some(function) = pin_command.set_function {
+		crate::check_function(permissions, pin, function)?;
+		gpio.set_function(pin, function).map_err(|e| e.to_string())?;
+	}
+	Ok(())
+}
+
+fn parse_qos(data: &str) -> Result<u8, String> {
+	match data {
+		"0" => Ok(0),
+		"1" => Ok(1),
+		_ => Err(format!("unsupported QoS level: {}, expected 0 or 1", data)),
+	}
+}
+
+/// Just enough of MQTT 3.1.1 to publish levels and receive commands.
+mod packet {
+	use std::io::Read;
+	use std::time::Duration;
+
+	pub fn connect(client_id: &str, username: Option<&str>, password: Option<&str>, keep_alive: Duration) -> Vec<u8> {
+		let mut flags = 0x02; // clean session
+		if username.is_some() {
+			flags |= 0x80;
+		}
+		if password.is_some() {
+			flags |= 0x40;
+		}
+
+		let mut body = Vec::new();
+		push_string(&mut body, "MQTT");
+		body.push(4); // protocol level 3.1.1
+		body.push(flags);
+		body.extend_from_slice(&(keep_alive.as_secs().min(0xFFFF) as u16).to_be_bytes());
+		push_string(&mut body, client_id);
+		for value in username.iter().chain(password.iter()) {
+			push_string(&mut body, value);
+		}
+		encode(0x10, &body)
+	}
+
+	/// Encode a publish packet, with the duplicate flag set when a QoS 1 message is sent again.
+	pub fn publish(topic: &str, payload: &[u8], qos: u8, retain: bool, dup: bool, packet_id: u16) -> Vec<u8> {
+		let mut body = Vec::new();
+		push_string(&mut body, topic);
+		if qos > 0 {
+			body.extend_from_slice(&packet_id.to_be_bytes());
+		}
+		body.extend_from_slice(payload);
+		encode(0x30 | u8::from(dup) << 3 | qos << 1 | u8::from(retain), &body)
+	}
+
+	pub fn puback(packet_id: u16) -> Vec<u8> {
+		encode(0x40, &packet_id.to_be_bytes())
+	}
+
+	pub fn subscribe(packet_id: u16, filters: &[String], qos: u8) -> Vec<u8> {
+		let mut body = packet_id.to_be_bytes().to_vec();
+		for filter in filters {
+			push_string(&mut body, filter);
+			body.push(qos);
+		}
+		encode(0x82, &body)
+	}
+
+	pub fn pingreq() -> Vec<u8> {
+		encode(0xC0, &[])
+	}
+
+	/// Parse a publish packet into the topic, payload and packet identifier (for QoS > 0).
+	pub fn parse_publish(header: u8, body: &[u8]) -> Result<(String, Vec<u8>, Option<u16>), String> {
+		let qos = header >> 1 & 0b11;
+		let topic_len = match body {
+			[high, low, ..] => usize::from(*high) << 8 | usize::from(*low),
+			_ => return Err(String::from("truncated publish packet")),
+		};
+		let id_len = if qos > 0 { 2 } else { 0 };
+		if body.len() < 2 + topic_len + id_len {
+			return Err(String::from("truncated publish packet"));
+		}
+
+		let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).map_err(|_| "invalid topic in publish packet")?;
+		let id = match qos {
+			0 => None,
+			_ => Some(u16::from(body[2 + topic_len]) << 8 | u16::from(body[3 + topic_len])),
+		};
+		Ok((topic, body[2 + topic_len + id_len..].to_vec(), id))
+	}
+
+	/// Read a packet, returning the first header byte and the body.
+	pub fn read(stream: &mut impl Read) -> std::io::Result<(u8, Vec<u8>)> {
+		let mut byte = [0u8];
+		stream.read_exact(&mut byte)?;
+		let header = byte[0];
+
+		let mut length = 0usize;
+		for i in 0..4 {
+			stream.read_exact(&mut byte)?;
+			length |= usize::from(byte[0] & 0x7F) << (7 * i);
+			if byte[0] & 0x80 == 0 {
+				break;
+			}
+		}
+
+		let mut body = vec![0; length];
+		stream.read_exact(&mut body)?;
+		Ok((header, body))
+	}
+
+	fn encode(header: u8, body: &[u8]) -> Vec<u8> {
+		let mut packet = vec![header];
+		let mut length = body.len();
+		loop {
+			let mut byte = (length % 128) as u8;
+			length /= 128;
+			if length > 0 {
+				byte |= 0x80;
+			}
+			packet.push(byte);
+			if length == 0 {
+				break;
+			}
+		}
+		packet.extend_from_slice(body);
+		packet
+	}
+
+	fn push_string(buffer: &mut Vec<u8>, value: &str) {
+		buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+		buffer.extend_from_slice(value.as_bytes());
+	}
+}
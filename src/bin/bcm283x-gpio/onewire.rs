@@ -0,0 +1,70 @@
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{format_rom, Error, Gpio, OneWire, DS18B20_FAMILY};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct OneWireOptions {
+	/// The pin the 1-Wire bus is connected to.
+	#[structopt(long = "pin", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	#[structopt(subcommand)]
+	command: OneWireCommand,
+}
+
+#[derive(StructOpt)]
+enum OneWireCommand {
+	/// List the ROM IDs of all devices on the bus.
+	#[structopt(name = "scan")]
+	Scan,
+
+	/// Read the temperature of all DS18B20 sensors on the bus.
+	#[structopt(name = "read-temp")]
+	ReadTemp,
+}
+
+pub fn run(options: &Options, command: &OneWireOptions) {
+	let mut gpio = crate::open_gpio(options);
+
+	// Remember the pin state so we can restore it afterwards.
+	let restore = crate::save_pins(&gpio, &[command.pin]);
+
+	let result = execute(&mut gpio, command);
+	restore.apply_unchecked(&mut gpio);
+
+	if let Err(error) = result {
+		crate::exit_with_error(error);
+	}
+}
+
+fn execute(gpio: &mut Gpio, command: &OneWireOptions) -> Result<(), Error> {
+	let mut bus = OneWire::new(gpio, command.pin)?;
+	let roms = bus.search()?;
+
+	match command.command {
+		OneWireCommand::Scan => {
+			if roms.is_empty() {
+				println!("no devices found");
+			}
+			for rom in roms {
+				println!("{}", format_rom(rom));
+			}
+		},
+		OneWireCommand::ReadTemp => {
+			let sensors: Vec<_> = roms.into_iter().filter(|rom| *rom as u8 == DS18B20_FAMILY).collect();
+			if sensors.is_empty() {
+				println!("no DS18B20 sensors found");
+			}
+			for rom in sensors {
+				match bus.read_ds18b20(rom) {
+					Ok(temperature) => println!("{}   {:.3} °C", format_rom(rom), temperature),
+					Err(error)      => eprintln!("{}   {}", format_rom(rom), error),
+				}
+			}
+		},
+	}
+
+	Ok(())
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{ErrorKind, Gpio, GpioConfig, HardwarePwm, PinFunction, SoftPwm};
+use bcm283x_linux_gpio::realtime::ThreadOptions;
+
+use crate::{Options, PinCommand};
+use crate::interrupt;
+
+#[derive(StructOpt)]
+pub struct PwmOptions {
+	/// The pin to generate the PWM signal on.
+	#[structopt(value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	/// The PWM frequency, for example 50Hz or 1kHz.
+	#[structopt(long = "freq", value_name = "FREQUENCY", parse(try_from_str = "crate::parse_frequency"))]
+	frequency: f64,
+
+	/// The duty cycle, for example 30%.
+	#[structopt(long = "duty", value_name = "DUTY", parse(try_from_str = "parse_duty"))]
+	duty: f64,
+
+	/// Use software PWM, even if the pin can output a channel of the PWM peripheral.
+	///
+	/// Hardware PWM is used by default on pins 12, 13, 18, 19, 40, 41 and 45.
+	#[structopt(long = "software")]
+	software: bool,
+
+	/// Run the software PWM thread only on this CPU core, ideally one isolated with the isolcpus kernel parameter.
+	#[structopt(long = "cpu", value_name = "CPU")]
+	cpu: Option<usize>,
+}
+
+/// The PWM signal, generated by the PWM peripheral or a background thread.
+///
+/// The software PWM thread owns the GPIO handle until it is stopped.
+enum Pwm {
+	Hardware(HardwarePwm, Gpio),
+	Software(SoftPwm),
+}
+
+pub fn run(options: &Options, command: &PwmOptions) {
+	let gpio = crate::open_gpio(options);
+
+	// Remember the pin state so we can restore it afterwards.
+	let restore = crate::save_pins(&gpio, &[command.pin]);
+
+	interrupt::install_handler();
+
+	let pwm = match start(options, command, gpio) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	while !interrupt::interrupted() {
+		std::thread::sleep(Duration::from_millis(50));
+	}
+
+	let mut gpio = match pwm {
+		Pwm::Hardware(pwm, gpio) => {
+			pwm.stop();
+			gpio
+		},
+		Pwm::Software(pwm) => match pwm.into_gpio() {
+			Ok(x) => x,
+			Err(error) => crate::exit_with_error(error),
+		},
+	};
+
+	restore.apply_unchecked(&mut gpio);
+}
+
+/// Start hardware PWM if the pin supports it, and software PWM otherwise.
+fn start(options: &Options, command: &PwmOptions, mut gpio: Gpio) -> Result<Pwm, String> {
+	let mut pin_command = PinCommand::new(command.pin);
+	pin_command.set_function = Some(PinFunction::Output);
+
+	match HardwarePwm::function(command.pin).filter(|_| !command.software) {
+		Some(function) => {
+			pin_command.set_function = Some(function);
+			crate::check_interface_pins(options, &[pin_command])?;
+			crate::check_function(options.permissions(), command.pin, function).map_err(|e| format!("{}, or add --software", e))?;
+
+			// The PWM peripheral needs /dev/mem, so fall back to software PWM for the other backends.
+			match HardwarePwm::start(&mut gpio, command.pin, command.frequency, command.duty) {
+				Ok(pwm) => {
+					if options.verbose {
+						eprintln!("using hardware PWM at {:.3} Hz", pwm.frequency());
+					}
+					return Ok(Pwm::Hardware(pwm, gpio));
+				},
+				Err(error) if error.kind() == ErrorKind::InvalidArgument => return Err(error.to_string()),
+				Err(error) => eprintln!("{}: hardware PWM is not available, using software PWM: {}", Paint::yellow("Warning").bold(), error),
+			}
+		},
+		None => crate::check_interface_pins(options, &[pin_command])?,
+	}
+
+	let mut config = GpioConfig::new();
+	config.set_level(command.pin, false).map_err(|e| e.to_string())?;
+	config.set_function(command.pin, PinFunction::Output).map_err(|e| e.to_string())?;
+	config.apply(&mut gpio).map_err(|e| e.to_string())?;
+
+	let thread_options = ThreadOptions { cpu: command.cpu, ..ThreadOptions::default() };
+	SoftPwm::start_with_gpio(gpio, command.pin, command.frequency, command.duty, thread_options)
+		.map(Pwm::Software)
+		.map_err(|e| e.to_string())
+}
+
+fn parse_duty(data: &str) -> Result<f64, String> {
+	let value = match data.strip_suffix('%') {
+		Some(x) => x.trim().parse::<f64>().map(|x| x / 100.0),
+		None    => data.parse::<f64>(),
+	};
+
+	match value {
+		Ok(x) if (0.0..=1.0).contains(&x) => Ok(x),
+		_ => Err(format!("invalid duty cycle: {}, expected a percentage or a fraction in the range [0, 1]", data)),
+	}
+}
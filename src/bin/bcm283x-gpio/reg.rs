@@ -0,0 +1,73 @@
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{Gpio, Register};
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub enum RegOptions {
+	/// Read a register, or all registers if none is given.
+	#[structopt(name = "read")]
+	Read {
+		/// The name of the register, for example GPFSEL1.
+		#[structopt(value_name = "REGISTER")]
+		register: Option<Register>,
+	},
+
+	/// Write a value to a register.
+	/// Requires --unsafe, except for the GPSET, GPCLR and GPEDS registers.
+	#[structopt(name = "write")]
+	Write {
+		/// The name of the register, for example GPREN0.
+		#[structopt(value_name = "REGISTER")]
+		register: Register,
+
+		/// The value to write, in decimal or hexadecimal with a 0x prefix.
+		#[structopt(value_name = "VALUE", parse(try_from_str = "parse_u32"))]
+		value: u32,
+	},
+}
+
+pub fn run(options: &Options, command: &RegOptions) {
+	match command {
+		RegOptions::Read { register: Some(register) } => {
+			let gpio = crate::open_gpio(options);
+			print_register(&gpio, *register);
+		},
+		RegOptions::Read { register: None } => {
+			let gpio = crate::open_gpio(options);
+			for &register in Register::ALL.iter() {
+				print_register(&gpio, register);
+			}
+		},
+		RegOptions::Write { register, value } => {
+			if !options.allow_unsafe && !register.is_write_safe() {
+				crate::exit_with_error(format!("writing to {} requires --unsafe", register));
+			}
+			let mut gpio = crate::open_gpio(options);
+			if register.is_write_safe() {
+				if let Err(error) = gpio.write_register_checked(*register, *value) {
+					crate::exit_with_error(error);
+				}
+			} else {
+				unsafe { gpio.write_register(*register, *value) };
+			}
+			print_register(&gpio, *register);
+		},
+	}
+}
+
+fn print_register(gpio: &Gpio, register: Register) {
+	let value = gpio.read_register(register);
+	println!("{:<9} = {}", Paint::yellow(register), Paint::cyan(format!("0x{:08X}", value)));
+}
+
+fn parse_u32(data: &str) -> Result<u32, String> {
+	let result = if data.starts_with("0x") || data.starts_with("0X") {
+		u32::from_str_radix(&data[2..], 16)
+	} else {
+		data.parse()
+	};
+	result.map_err(|_| format!("invalid 32 bit value: {}", data))
+}
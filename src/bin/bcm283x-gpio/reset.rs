@@ -0,0 +1,39 @@
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Error, PinFunction, PullMode};
+
+use crate::{Options, PinCommand, PinList};
+
+#[derive(StructOpt)]
+pub struct ResetOptions {
+	/// The pins to reset, separated by commas. Defaults to the header pins 0-27.
+	#[structopt(long = "pin", value_name = "PIN,PIN...")]
+	pins: Option<PinList>,
+}
+
+/// Reset pins to their power-on defaults: input with the pull up/down from the datasheet.
+///
+/// Changing the pull mode requires --allow-pull, just like with --set-pin.
+pub fn run(options: &Options, command: &ResetOptions) {
+	let pins: Vec<usize> = match &command.pins {
+		Some(pins) => pins.iter().collect(),
+		None => (0..28).collect(),
+	};
+
+	// Options from the command line are applied after the reset, so they take precedence.
+	let commands = pins.into_iter()
+		.map(|index| Ok(PinCommand {
+			set_function:  Some(PinFunction::Input),
+			set_pull_mode: Some(PullMode::power_on_default(index)?),
+			.. PinCommand::new(index)
+		}))
+		.collect::<Result<Vec<_>, Error>>();
+
+	let mut commands = match commands {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+	commands.extend(options.pin_commands());
+
+	crate::open_gpio_with(options, &commands);
+}
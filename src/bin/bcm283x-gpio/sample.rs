@@ -0,0 +1,80 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::Gpio;
+
+use crate::{interrupt, Options, PinList};
+
+#[derive(StructOpt)]
+pub struct SampleOptions {
+	/// The pins to sample, separated by commas.
+	#[structopt(value_name = "PIN,PIN...")]
+	pins: PinList,
+
+	/// The time between samples, for example 1ms.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "1ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+
+	/// Stop sampling after this long. Without it, sampling continues until interrupted.
+	#[structopt(long = "duration", value_name = "DURATION", parse(try_from_str = "crate::parse_duration"))]
+	duration: Option<Duration>,
+
+	/// Write the samples to a CSV file instead of standard output.
+	#[structopt(long = "output", short = "o", value_name = "FILE", parse(from_os_str))]
+	output: Option<PathBuf>,
+}
+
+pub fn run(options: &Options, command: &SampleOptions) {
+	let gpio = crate::open_gpio(options);
+
+	let output: Box<dyn Write> = match &command.output {
+		None => Box::new(std::io::stdout()),
+		Some(path) => match std::fs::File::create(path) {
+			Ok(x) => Box::new(x),
+			Err(error) => crate::exit_with_error(format!("failed to create {}: {}", path.display(), error)),
+		},
+	};
+	let mut output = std::io::BufWriter::new(output);
+
+	interrupt::install_handler();
+
+	if let Err(error) = sample(&gpio, command, &mut output) {
+		crate::exit_with_error(format!("failed to write samples: {}", error));
+	}
+}
+
+fn sample(gpio: &Gpio, command: &SampleOptions, output: &mut impl Write) -> std::io::Result<()> {
+	write!(output, "time_us")?;
+	for pin in command.pins.iter() {
+		write!(output, ",pin{}", pin)?;
+	}
+	writeln!(output)?;
+
+	let start = Instant::now();
+	let mut next = start;
+	while !interrupt::interrupted() {
+		if command.duration.is_some_and(|x| next - start >= x) {
+			break;
+		}
+
+		let now = Instant::now();
+		if next > now {
+			std::thread::sleep(next - now);
+		}
+
+		let levels = gpio.read_levels();
+		let time   = start.elapsed();
+
+		write!(output, "{}", time.as_micros())?;
+		for pin in command.pins.iter() {
+			write!(output, ",{}", levels >> pin & 1)?;
+		}
+		writeln!(output)?;
+
+		next += command.interval;
+	}
+
+	output.flush()
+}
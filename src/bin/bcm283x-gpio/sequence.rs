@@ -0,0 +1,250 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Error, Gpio, Level, PinFunction};
+
+use crate::{interrupt, Options};
+
+/// Delays shorter than this are busy-waited on the system timer, longer ones sleep first.
+const BUSY_WAIT_LIMIT: Duration = Duration::from_millis(1);
+
+#[derive(StructOpt)]
+pub struct SequenceOptions {
+	/// The steps of the sequence, for example: repeat 100 { 17=high wait 5us 17=low wait 5us }
+	///
+	/// PIN=LEVEL sets the level of a pin, with LEVEL high, low, 1 or 0. The pins must already be configured as output.
+	/// wait DURATION waits before the next step, for example wait 10us.
+	/// repeat N { ... } runs the steps in braces N times, and loop { ... } runs them until interrupted.
+	/// Steps are separated by whitespace or semicolons.
+	#[structopt(value_name = "STEP", required_unless = "file")]
+	steps: Vec<String>,
+
+	/// Read the steps from a file instead. Everything after a # on a line is ignored.
+	#[structopt(long = "file", value_name = "PATH", conflicts_with = "steps")]
+	file: Option<PathBuf>,
+
+	/// Wait this long after every pin write, in addition to explicit waits.
+	#[structopt(long = "step-delay", value_name = "DURATION", parse(try_from_str = "crate::parse_duration"))]
+	step_delay: Option<Duration>,
+}
+
+/// A parsed step of a sequence.
+#[derive(Debug, PartialEq)]
+enum Step {
+	Set(usize, Level),
+	Wait(Duration),
+	Repeat(u64, Vec<Step>),
+	Loop(Vec<Step>),
+}
+
+/// Run a sequence of pin writes and waits in a single process, so the timing is not disturbed by starting processes.
+pub fn run(options: &Options, command: &SequenceOptions) {
+	let source = match &command.file {
+		Some(path) => match std::fs::read_to_string(path) {
+			Ok(x) => strip_comments(&x),
+			Err(error) => crate::exit_with_error(format!("failed to read {}: {}", path.display(), error)),
+		},
+		None => command.steps.join(" "),
+	};
+
+	let steps = match parse(&source) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("invalid sequence: {}", error)),
+	};
+
+	let mut gpio = crate::open_gpio(options);
+	let state = gpio.read_all();
+	let mut pins = Vec::new();
+	collect_pins(&steps, &mut pins);
+	for &pin in &pins {
+		match state.pin_function(pin) {
+			Ok(PinFunction::Output) => (),
+			Ok(_) => crate::exit_with_error(format!("pin {} is not configured as output, use --set-pin {},function=output", pin, pin)),
+			Err(error) => crate::exit_with_error(error),
+		}
+	}
+
+	interrupt::install_handler();
+
+	if let Err(error) = execute(&mut gpio, &steps, command.step_delay) {
+		crate::exit_with_error(error);
+	}
+}
+
+/// Remove everything after a # on each line.
+fn strip_comments(data: &str) -> String {
+	data.lines()
+		.map(|line| line.split('#').next().unwrap_or(""))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Parse the steps of a sequence.
+fn parse(data: &str) -> Result<Vec<Step>, String> {
+	// Allow braces without surrounding whitespace, like repeat 3 {17=high}.
+	let data = data.replace('{', " { ").replace('}', " } ").replace(';', " ");
+	let mut tokens = data.split_whitespace().peekable();
+	let steps = parse_block(&mut tokens)?;
+	match tokens.next() {
+		None      => Ok(steps),
+		Some("}") => Err(String::from("unmatched }")),
+		Some(x)   => Err(format!("unexpected token: {}", x)),
+	}
+}
+
+/// Parse steps until the end of the input or a closing brace, which is left in the iterator.
+fn parse_block<'a>(tokens: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> Result<Vec<Step>, String> {
+	let mut steps = Vec::new();
+	while let Some(&token) = tokens.peek() {
+		if token == "}" {
+			break;
+		}
+		tokens.next();
+
+		let step = match token {
+			"wait" => {
+				let duration = tokens.next().ok_or("missing duration after wait")?;
+				Step::Wait(crate::parse_duration(duration)?)
+			},
+			"repeat" => {
+				let count = tokens.next().ok_or("missing count after repeat")?;
+				let count = count.parse().map_err(|_| format!("invalid repeat count: {}", count))?;
+				Step::Repeat(count, parse_braced(tokens, "repeat")?)
+			},
+			"loop" => Step::Loop(parse_braced(tokens, "loop")?),
+			_ => {
+				let (pin, level) = match token.find('=') {
+					Some(i) => (&token[..i], &token[i + 1..]),
+					None    => return Err(format!("unknown step: {}, expected PIN=LEVEL, wait, repeat or loop", token)),
+				};
+				let pin = crate::parse_pin_index(pin)?;
+				let level = match level {
+					"high" | "1" => Level::High,
+					"low"  | "0" => Level::Low,
+					_ => return Err(format!("invalid level: {}, expected high, low, 1 or 0", level)),
+				};
+				Step::Set(pin, level)
+			},
+		};
+		steps.push(step);
+	}
+	Ok(steps)
+}
+
+/// Parse a block of steps in braces.
+fn parse_braced<'a>(tokens: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>, name: &str) -> Result<Vec<Step>, String> {
+	if tokens.next() != Some("{") {
+		return Err(format!("expected {{ after {}", name));
+	}
+	let steps = parse_block(tokens)?;
+	if tokens.next() != Some("}") {
+		return Err(format!("missing }} to close {}", name));
+	}
+	Ok(steps)
+}
+
+/// Collect the pins written by the steps, without duplicates.
+fn collect_pins(steps: &[Step], pins: &mut Vec<usize>) {
+	for step in steps {
+		match step {
+			Step::Set(pin, _) => if !pins.contains(pin) {
+				pins.push(*pin);
+			},
+			Step::Wait(_) => (),
+			Step::Repeat(_, steps) | Step::Loop(steps) => collect_pins(steps, pins),
+		}
+	}
+}
+
+/// Execute the steps, until they are done or until interrupted.
+///
+/// Returns false if interrupted.
+fn execute(gpio: &mut Gpio, steps: &[Step], step_delay: Option<Duration>) -> Result<bool, Error> {
+	for step in steps {
+		if interrupt::interrupted() {
+			return Ok(false);
+		}
+		match step {
+			Step::Set(pin, level) => {
+				gpio.set_level(*pin, *level)?;
+				if let Some(delay) = step_delay {
+					wait(gpio, delay);
+				}
+			},
+			Step::Wait(duration) => wait(gpio, *duration),
+			Step::Repeat(count, steps) => {
+				for _ in 0..*count {
+					if !execute(gpio, steps, step_delay)? {
+						return Ok(false);
+					}
+				}
+			},
+			Step::Loop(steps) => {
+				// Stop on an empty loop body too, instead of spinning without checking for interrupts.
+				while !steps.is_empty() {
+					if !execute(gpio, steps, step_delay)? {
+						return Ok(false);
+					}
+				}
+			},
+		}
+	}
+	Ok(true)
+}
+
+/// Wait accurately for short durations, and without burning CPU time for long ones.
+fn wait(gpio: &Gpio, duration: Duration) {
+	match duration < BUSY_WAIT_LIMIT {
+		true  => gpio.delay(duration),
+		false => bcm283x_linux_gpio::precise_delay(duration),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_steps() {
+		let steps = parse("repeat 2 {17=high wait 5us 17=0}; loop{4=1;4=low}").unwrap();
+		assert_eq!(steps, [
+			Step::Repeat(2, vec![
+				Step::Set(17, Level::High),
+				Step::Wait(Duration::from_micros(5)),
+				Step::Set(17, Level::Low),
+			]),
+			Step::Loop(vec![
+				Step::Set(4, Level::High),
+				Step::Set(4, Level::Low),
+			]),
+		]);
+
+		let mut pins = Vec::new();
+		collect_pins(&steps, &mut pins);
+		assert_eq!(pins, [17, 4]);
+	}
+
+	#[test]
+	fn parse_invalid_steps() {
+		let invalid = [
+			("17", "unknown step: 17, expected PIN=LEVEL, wait, repeat or loop"),
+			("22=HIGH", "invalid level: HIGH, expected high, low, 1 or 0"),
+			("22=high=1", "invalid level: high=1, expected high, low, 1 or 0"),
+			("wait", "missing duration after wait"),
+			("repeat", "missing count after repeat"),
+			("repeat x { }", "invalid repeat count: x"),
+			("repeat 2 17=high", "expected { after repeat"),
+			("loop { 17=high", "missing } to close loop"),
+			("17=high }", "unmatched }"),
+		];
+		for (data, error) in invalid.iter() {
+			assert_eq!(parse(data).unwrap_err(), *error, "{:?}", data);
+		}
+	}
+
+	#[test]
+	fn comments() {
+		assert_eq!(strip_comments("17=high # on\n# off\nwait 1ms"), "17=high \n\nwait 1ms");
+	}
+}
@@ -0,0 +1,419 @@
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Edge, EventListener, Gpio, Permissions, PinInfo};
+
+use crate::{EdgeList, Options, PinCommand, PinList};
+
+/// The maximum size of a request body.
+const MAX_BODY_SIZE: usize = 1024;
+
+/// The maximum length of the request line and of each header, including the line ending.
+const MAX_LINE_LENGTH: usize = 8192;
+
+/// The maximum number of request headers.
+const MAX_HEADERS: usize = 100;
+
+/// How often to send a comment on an idle event stream, to detect closed connections.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(StructOpt)]
+#[structopt(after_help = "
+Endpoints:
+    GET /pins                  Read the state of all pins.
+    GET /pins/PIN              Read the state of a single pin.
+    PUT /pins/PIN/level        Set the level of a pin: on or off in the request body.
+    PUT /pins/PIN/function     Set the function of a pin: input, output or alt0..5 in the request body.
+    GET /events?pins=PIN,PIN   Stream edges on pins as server-sent events.
+                               Add &edges=rise or &edges=fall to only report one kind of edge.
+
+Only the pins given with --writable can be changed.
+")]
+pub struct ServeOptions {
+	/// The address to listen on.
+	#[structopt(long = "listen", value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+	listen: String,
+
+	/// The pins that may be changed through the API, separated by commas.
+	#[structopt(long = "writable", value_name = "PIN,PIN...")]
+	writable: Option<PinList>,
+
+	/// The polling interval for event streams.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "1ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+
+	/// The maximum number of connections handled at the same time, including event streams.
+	///
+	/// Further connections are answered with 503 Service Unavailable.
+	#[structopt(long = "max-connections", value_name = "COUNT", default_value = "16")]
+	max_connections: usize,
+}
+
+struct Server {
+	/// Bitmask of the pins that may be changed.
+	writable: u64,
+
+	/// The polling interval for event streams.
+	interval: Duration,
+
+	/// The permissions for changing pin functions.
+	permissions: Permissions,
+
+	/// The GPIO handle shared by all connections.
+	///
+	/// The lock also serializes read-modify-write cycles on the function select registers.
+	gpio: Mutex<Gpio>,
+
+	/// The maximum number of connections handled at the same time.
+	max_connections: usize,
+
+	/// The number of connections being handled.
+	connections: AtomicUsize,
+}
+
+impl Server {
+	fn gpio(&self) -> MutexGuard<'_, Gpio> {
+		self.gpio.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Decrements the connection count when the connection is done.
+struct ConnectionGuard<'a> {
+	connections: &'a AtomicUsize,
+}
+
+impl Drop for ConnectionGuard<'_> {
+	fn drop(&mut self) {
+		self.connections.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+struct Request {
+	method: String,
+	path: String,
+	query: String,
+	body: String,
+}
+
+struct Response {
+	status: u16,
+	body: String,
+}
+
+impl Response {
+	fn json(body: String) -> Self {
+		Self { status: 200, body }
+	}
+
+	fn error(status: u16, message: impl AsRef<str>) -> Self {
+		Self {
+			status,
+			body: format!("{{\"error\": {}}}", json_string(message.as_ref())),
+		}
+	}
+}
+
+/// Serve a REST API for the GPIO pins over HTTP.
+pub fn run(options: &Options, command: &ServeOptions) {
+	// Verify the CPU and apply the --set-pin options once, before accepting connections.
+	let gpio = crate::open_gpio(options);
+
+	let listener = match TcpListener::bind(&command.listen) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(format!("failed to listen on {}: {}", command.listen, error)),
+	};
+
+	let server = Arc::new(Server {
+		writable: command.writable.iter().flat_map(|x| x.iter()).fold(0, |mask, pin| mask | 1 << pin),
+		interval: command.interval,
+		permissions: options.permissions(),
+		gpio: Mutex::new(gpio),
+		max_connections: command.max_connections,
+		connections: AtomicUsize::new(0),
+	});
+
+	if options.verbose {
+		eprintln!("listening on http://{}", command.listen);
+	}
+
+	for stream in listener.incoming() {
+		let stream = match stream {
+			Ok(x) => x,
+			Err(error) => {
+				eprintln!("failed to accept connection: {}", error);
+				continue;
+			},
+		};
+
+		if server.connections.fetch_add(1, Ordering::Relaxed) >= server.max_connections {
+			server.connections.fetch_sub(1, Ordering::Relaxed);
+			let mut stream = stream;
+			let _ = write_response(&mut stream, &Response::error(503, "too many connections"));
+			continue;
+		}
+
+		let server = server.clone();
+		std::thread::spawn(move || {
+			let _guard = ConnectionGuard { connections: &server.connections };
+			handle_connection(stream, &server)
+		});
+	}
+}
+
+fn handle_connection(mut stream: TcpStream, server: &Server) {
+	let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+
+	let request = match read_request(&mut stream) {
+		Ok(x) => x,
+		Err(response) => {
+			let _ = write_response(&mut stream, &response);
+			return;
+		},
+	};
+
+	if request.method == "GET" && request.path == "/events" {
+		let _ = stream_events(&mut stream, server, &request);
+		return;
+	}
+
+	let response = route(&mut server.gpio(), server, &request);
+	let _ = write_response(&mut stream, &response);
+}
+
+fn route(gpio: &mut Gpio, server: &Server, request: &Request) -> Response {
+	let segments: Vec<_> = request.path.trim_matches('/').split('/').collect();
+	let pin = match segments.get(1).map(|x| crate::parse_pin_index(x)) {
+		Some(Ok(x)) => Some(x),
+		Some(Err(error)) => return Response::error(404, error),
+		None => None,
+	};
+
+	match (request.method.as_str(), segments.as_slice(), pin) {
+		("GET", ["pins"], None) => {
+			let state = gpio.read_all();
+			let pins: Result<Vec<_>, _> = (0..54).map(|index| state.pin(index).map(|info| pin_json(index, &info))).collect();
+			match pins {
+				Ok(pins) => Response::json(format!("[{}]", pins.join(", "))),
+				Err(error) => Response::error(500, error.to_string()),
+			}
+		},
+		("GET", ["pins", _], Some(pin)) => pin_response(gpio, pin),
+		("PUT", ["pins", _, key], Some(pin)) if *key == "level" || *key == "function" => {
+			if server.writable & 1 << pin == 0 {
+				return Response::error(403, format!("pin {} is not writable", pin));
+			}
+
+			let mut command = PinCommand::new(pin);
+			if let Err(error) = command.set_option(key, request.body.trim()) {
+				return Response::error(400, error);
+			}
+			if let Some(Err(error)) = command.set_function.map(|x| crate::check_function(server.permissions, pin, x)) {
+				return Response::error(403, error);
+			}
+			if let Some(Err(error)) = command.set_level.map(|x| gpio.set_level(pin, x)) {
+				return Response::error(500, error.to_string());
+			}
+			if let Some(function) = command.set_function {
+				if let Err(error) = gpio.set_function(pin, function) {
+					return Response::error(500, error.to_string());
+				}
+			}
+
+			pin_response(gpio, pin)
+		},
+		(_, ["pins"], _) | (_, ["pins", _], _) | (_, ["pins", _, _], _) => Response::error(405, "method not allowed"),
+		_ => Response::error(404, "not found"),
+	}
+}
+
+/// Stream events as server-sent events until the client disconnects.
+///
+/// The GPIO handle is only locked while polling, so other connections are not blocked.
+fn stream_events(stream: &mut TcpStream, server: &Server, request: &Request) -> std::io::Result<()> {
+	let mut pins = None;
+	let mut edges = None;
+	for (key, value) in request.query.split('&').filter(|x| !x.is_empty()).map(crate::split_key_value) {
+		let value = percent_decode(value.unwrap_or(""));
+		let result = match key {
+			"pins"  => value.parse::<PinList>().map(|x| pins = Some(x)),
+			"edges" => value.parse::<EdgeList>().map(|x| edges = Some(x)),
+			_ => Err(format!("unknown query parameter: {}", key)),
+		};
+		if let Err(error) = result {
+			return write_response(stream, &Response::error(400, error));
+		}
+	}
+
+	let pins = match pins {
+		Some(x) => x,
+		None => return write_response(stream, &Response::error(400, "missing query parameter: pins")),
+	};
+	let edges = edges.unwrap_or_else(|| "both".parse().unwrap());
+
+	let mut listener = EventListener::new();
+	for pin in pins.iter() {
+		for edge in edges.iter() {
+			if let Err(error) = listener.watch(pin, edge) {
+				return write_response(stream, &Response::error(400, error.to_string()));
+			}
+		}
+	}
+	listener.poll(&server.gpio());
+
+	stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")?;
+	let mut last_write = Instant::now();
+	loop {
+		std::thread::sleep(server.interval);
+		listener.poll(&server.gpio());
+
+		while let Some(event) = listener.pop() {
+			let edge = match event.edge {
+				Edge::Rise => "rise",
+				Edge::Fall => "fall",
+			};
+			let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+			write!(stream, "data: {{\"pin\": {}, \"edge\": \"{}\", \"time\": {:.6}}}\n\n", event.pin, edge, time)?;
+			last_write = Instant::now();
+		}
+
+		if last_write.elapsed() >= KEEP_ALIVE_INTERVAL {
+			stream.write_all(b": keep-alive\n\n")?;
+			last_write = Instant::now();
+		}
+	}
+}
+
+/// Read a request, or get the error response to send instead.
+fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+	let mut reader = BufReader::new(stream);
+
+	let line = match crate::read_line_bounded(&mut reader, MAX_LINE_LENGTH) {
+		Ok(x) => x.unwrap_or_default(),
+		Err(error) if error.kind() == std::io::ErrorKind::InvalidData => return Err(Response::error(400, "request line too long")),
+		Err(error) => return Err(Response::error(400, format!("failed to read request: {}", error))),
+	};
+	let mut parts = line.split_whitespace();
+	let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+		(Some(method), Some(target), Some(_version)) => (method.to_string(), target),
+		_ => return Err(Response::error(400, "malformed request line")),
+	};
+	let (path, query) = crate::partition(target, '?');
+	let (path, query) = (path.to_string(), query.unwrap_or("").to_string());
+
+	let mut content_length = 0;
+	let mut headers = 0;
+	loop {
+		let header = match crate::read_line_bounded(&mut reader, MAX_LINE_LENGTH) {
+			Ok(x) => x.unwrap_or_default(),
+			Err(error) if error.kind() == std::io::ErrorKind::InvalidData => return Err(Response::error(431, "request header too long")),
+			Err(error) => return Err(Response::error(400, format!("failed to read request: {}", error))),
+		};
+		let header = header.trim_end();
+		if header.is_empty() {
+			break;
+		}
+		headers += 1;
+		if headers > MAX_HEADERS {
+			return Err(Response::error(431, "too many request headers"));
+		}
+		let (name, value) = crate::partition(header, ':');
+		if name.eq_ignore_ascii_case("content-length") {
+			content_length = value.unwrap_or("").trim().parse().map_err(|_| Response::error(400, "invalid Content-Length header"))?;
+		}
+	}
+
+	if content_length > MAX_BODY_SIZE {
+		return Err(Response::error(400, "request body too large"));
+	}
+
+	let mut body = vec![0; content_length];
+	reader.read_exact(&mut body).map_err(|e| Response::error(400, format!("failed to read request body: {}", e)))?;
+	let body = String::from_utf8(body).map_err(|_| Response::error(400, "request body is not valid UTF-8"))?;
+
+	Ok(Request { method, path, query, body })
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+	let reason = match response.status {
+		200 => "OK",
+		400 => "Bad Request",
+		403 => "Forbidden",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		431 => "Request Header Fields Too Large",
+		503 => "Service Unavailable",
+		_   => "Internal Server Error",
+	};
+
+	write!(
+		stream,
+		"HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		response.status,
+		reason,
+		response.body.len(),
+		response.body,
+	)
+}
+
+/// Respond with the current state of a pin.
+fn pin_response(gpio: &Gpio, pin: usize) -> Response {
+	match gpio.read_pin(pin) {
+		Ok(info) => Response::json(pin_json(pin, &info)),
+		Err(error) => Response::error(500, error.to_string()),
+	}
+}
+
+fn pin_json(index: usize, pin: &PinInfo) -> String {
+	format!(
+		"{{\"pin\": {}, \"function\": \"{}\", \"level\": {}, \"event\": {}}}",
+		index,
+		format!("{:?}", pin.function).to_lowercase(),
+		pin.level.is_high(),
+		pin.event,
+	)
+}
+
+fn json_string(value: &str) -> String {
+	let mut result = String::from("\"");
+	for c in value.chars() {
+		match c {
+			'"'  => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+			c => result.push(c),
+		}
+	}
+	result.push('"');
+	result
+}
+
+/// Decode percent-encoded characters in a query parameter.
+fn percent_decode(value: &str) -> String {
+	let bytes = value.as_bytes();
+	let mut result = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		let decoded = match bytes[i] {
+			b'%' if i + 2 < bytes.len() => {
+				std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|x| u8::from_str_radix(x, 16).ok())
+			},
+			_ => None,
+		};
+		match decoded {
+			Some(byte) => {
+				result.push(byte);
+				i += 3;
+			},
+			None => {
+				result.push(bytes[i]);
+				i += 1;
+			},
+		}
+	}
+	String::from_utf8_lossy(&result).into_owned()
+}
@@ -0,0 +1,93 @@
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::SignalBridge;
+
+use crate::{EdgeList, Options};
+use crate::interrupt;
+
+#[derive(StructOpt)]
+pub struct SignalOptions {
+	/// The process to send the signals to.
+	#[structopt(value_name = "PID")]
+	process: i32,
+
+	/// The signals to send, as PIN:EDGE:SIGNAL, for example 17:rise:USR1 or 4:both:SIGHUP.
+	#[structopt(value_name = "PIN:EDGE:SIGNAL", required = true)]
+	rules: Vec<SignalRule>,
+
+	/// The polling interval in microseconds.
+	#[structopt(long = "interval", value_name = "MICROSECONDS", default_value = "1000")]
+	interval: u64,
+}
+
+struct SignalRule {
+	pin: usize,
+	edges: EdgeList,
+	signal: Signal,
+}
+
+/// Send signals to a process on pin edges until interrupted or until the process exits.
+pub fn run(options: &Options, command: &SignalOptions) {
+	// The bridge opens its own handle, but check access first so errors are reported like for other commands.
+	drop(crate::open_gpio(options));
+
+	let process = Pid::from_raw(command.process);
+	if let Err(error) = kill(process, None) {
+		crate::exit_with_error(format!("can not send signals to process {}: {}", process, error));
+	}
+
+	interrupt::install_handler();
+
+	let bridge = match SignalBridge::start(command.process, Duration::from_micros(command.interval)) {
+		Ok(x) => x,
+		Err(error) => crate::exit_with_error(error),
+	};
+
+	for rule in &command.rules {
+		for edge in rule.edges.iter() {
+			if let Err(error) = bridge.signal_on(rule.pin, edge, rule.signal as i32) {
+				crate::exit_with_error(error);
+			}
+		}
+	}
+
+	while !interrupt::interrupted() {
+		if kill(process, None).is_err() {
+			if options.verbose {
+				eprintln!("process {} exited", process);
+			}
+			break;
+		}
+		std::thread::sleep(Duration::from_millis(50));
+	}
+
+	bridge.stop();
+}
+
+impl FromStr for SignalRule {
+	type Err = String;
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let mut parts = data.splitn(3, ':');
+		let (pin, edges, signal) = match (parts.next(), parts.next(), parts.next()) {
+			(Some(pin), Some(edges), Some(signal)) => (pin, edges, signal),
+			_ => return Err(format!("invalid signal rule: {}, expected PIN:EDGE:SIGNAL", data)),
+		};
+
+		Ok(Self {
+			pin:    crate::parse_pin_index(pin.trim())?,
+			edges:  edges.parse()?,
+			signal: parse_signal(signal.trim())?,
+		})
+	}
+}
+
+/// Parse a signal name with or without the SIG prefix, like USR1 or SIGUSR1.
+fn parse_signal(data: &str) -> Result<Signal, String> {
+	let name = data.to_ascii_uppercase();
+	let name = if name.starts_with("SIG") { name } else { format!("SIG{}", name) };
+	Signal::from_str(&name).map_err(|_| format!("unknown signal: {}", data))
+}
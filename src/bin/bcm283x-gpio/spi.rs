@@ -0,0 +1,87 @@
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::SoftSpi;
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct SpiOptions {
+	/// The pin to use as clock line.
+	#[structopt(long = "sclk", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	sclk: usize,
+
+	/// The pin to use as master-out slave-in line.
+	#[structopt(long = "mosi", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	mosi: usize,
+
+	/// The pin to use as master-in slave-out line.
+	#[structopt(long = "miso", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	miso: usize,
+
+	/// The pin to use as (active low) chip select line.
+	#[structopt(long = "cs", value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	cs: usize,
+
+	/// The SPI mode: 0, 1, 2 or 3.
+	#[structopt(long = "mode", value_name = "MODE", default_value = "0")]
+	mode: u8,
+
+	/// The clock frequency of the bus.
+	#[structopt(long = "freq", value_name = "FREQUENCY", default_value = "100kHz", parse(try_from_str = "crate::parse_frequency"))]
+	frequency: f64,
+
+	#[structopt(subcommand)]
+	command: SpiCommand,
+}
+
+#[derive(StructOpt)]
+enum SpiCommand {
+	/// Transfer bytes and print the bytes received.
+	#[structopt(name = "xfer")]
+	Transfer {
+		/// The bytes to send as hexadecimal string.
+		#[structopt(value_name = "HEX")]
+		data: HexData,
+	},
+}
+
+pub fn run(options: &Options, command: &SpiOptions) {
+	let mut gpio = crate::open_gpio(options);
+
+	// Remember the pin state so we can restore it afterwards.
+	let restore = crate::save_pins(&gpio, &[command.sclk, command.mosi, command.miso, command.cs]);
+
+	let result = SoftSpi::new(&mut gpio, command.sclk, command.mosi, command.miso, command.cs, command.mode, command.frequency)
+		.map(|mut bus| match &command.command {
+			SpiCommand::Transfer { data } => {
+				let mut buffer = data.0.clone();
+				bus.transfer(&mut buffer);
+				buffer
+			},
+		});
+	restore.apply_unchecked(&mut gpio);
+
+	match result {
+		Ok(received) => println!("{}", received.iter().map(|x| format!("{:02X}", x)).collect::<String>()),
+		Err(error)   => crate::exit_with_error(error),
+	}
+}
+
+/// Bytes given as a string of hexadecimal digits, with an optional 0x prefix.
+struct HexData(Vec<u8>);
+
+impl std::str::FromStr for HexData {
+	type Err = String;
+
+	fn from_str(input: &str) -> Result<Self, String> {
+		let digits = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")).unwrap_or(input);
+		if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+			return Err(format!("invalid hexadecimal data: {}, expected an even number of hex digits", input));
+		}
+
+		(0..digits.len()).step_by(2)
+			.map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+			.collect::<Result<_, _>>()
+			.map(HexData)
+	}
+}
@@ -0,0 +1,31 @@
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{Gpio, GpioState};
+
+#[derive(StructOpt)]
+pub struct SaveOptions {
+	/// The file to write the state to, or - for standard output.
+	#[structopt(value_name = "FILE", parse(from_os_str))]
+	file: PathBuf,
+}
+
+pub fn save(gpio: &Gpio, options: &SaveOptions) {
+	let data = gpio.read_all().to_json();
+	let result = if options.file == Path::new("-") {
+		use std::io::Write;
+		std::io::stdout().write_all(data.as_bytes())
+	} else {
+		std::fs::write(&options.file, data)
+	};
+
+	if let Err(error) = result {
+		crate::exit_with_error(format!("failed to write {}: {}", options.file.display(), error));
+	}
+}
+
+/// Load a GPIO state saved by the `save` command.
+pub fn load(path: &Path) -> Result<GpioState, String> {
+	let data = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+	GpioState::from_json(&data).map_err(|e| format!("invalid state file {}: {}", path.display(), e))
+}
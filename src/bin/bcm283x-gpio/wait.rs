@@ -0,0 +1,71 @@
+use std::time::Duration;
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::{ErrorKind, EventListener, Level};
+
+use crate::{EdgeList, Options};
+
+/// The exit code used when the timeout expires.
+const EXIT_TIMEOUT: i32 = 2;
+
+#[derive(StructOpt)]
+pub struct WaitOptions {
+	/// The pin to wait for.
+	#[structopt(value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	/// The edge to wait for: rise, fall or both [default: both].
+	#[structopt(long = "edge", value_name = "EDGE", conflicts_with = "level")]
+	edge: Option<EdgeList>,
+
+	/// Wait until the pin reads a level instead of waiting for an edge: high or low.
+	///
+	/// This returns immediately if the pin already has the level.
+	#[structopt(long = "level", value_name = "LEVEL")]
+	level: Option<Level>,
+
+	/// Give up after this long, for example 5s or 500ms.
+	#[structopt(long = "timeout", value_name = "DURATION", parse(try_from_str = "crate::parse_duration"))]
+	timeout: Option<Duration>,
+
+	/// The polling interval in microseconds.
+	#[structopt(long = "interval", value_name = "MICROSECONDS", default_value = "100")]
+	interval: u64,
+}
+
+/// Wait for an edge or a level on a pin.
+///
+/// Exits with status 0 when the edge or level is detected, or 2 when the timeout expires.
+pub fn run(options: &Options, command: &WaitOptions) {
+	let gpio = crate::open_gpio(options);
+
+	if let Some(level) = command.level {
+		match gpio.wait_for_level(command.pin, level, command.timeout) {
+			Ok(()) => return,
+			Err(error) if error.kind() == ErrorKind::Timeout => {
+				if options.verbose {
+					eprintln!("timeout expired while waiting for pin {} to become {}", command.pin, level);
+				}
+				std::process::exit(EXIT_TIMEOUT);
+			},
+			Err(error) => crate::exit_with_error(error),
+		}
+	}
+
+	let mut listener = EventListener::new();
+	let edges = command.edge.clone().unwrap_or_default();
+	for edge in edges.iter() {
+		if let Err(error) = listener.watch(command.pin, edge) {
+			crate::exit_with_error(error);
+		}
+	}
+
+	listener.poll(&gpio);
+	let interval = Duration::from_micros(command.interval);
+	if listener.wait(&gpio, interval, command.timeout).is_none() {
+		if options.verbose {
+			eprintln!("timeout expired while waiting for an edge on pin {}", command.pin);
+		}
+		std::process::exit(EXIT_TIMEOUT);
+	}
+}
@@ -0,0 +1,97 @@
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use yansi::Paint;
+
+use bcm283x_linux_gpio::{GpioState, PinChange};
+
+use crate::{interrupt, Options};
+
+#[derive(StructOpt)]
+pub struct WatchOptions {
+	/// Print a timestamped line for every change of a pin, instead of redrawing the pin table.
+	///
+	/// Colors are disabled when the output is not a terminal, so the lines can be piped into a log.
+	#[structopt(long = "changes")]
+	changes: bool,
+
+	/// The time between reads of the GPIO state, for example 100ms.
+	#[structopt(long = "interval", value_name = "DURATION", default_value = "100ms", parse(try_from_str = "crate::parse_duration"))]
+	interval: Duration,
+}
+
+/// Show the pin table until interrupted, redrawing it after every read, or print the changes only.
+pub fn run(options: &Options, command: &WatchOptions) {
+	let gpio = crate::open_gpio(options);
+
+	if command.changes && !std::io::stdout().is_terminal() {
+		Paint::disable();
+	}
+
+	interrupt::install_handler();
+
+	let start = Instant::now();
+	let mut previous: Option<GpioState> = None;
+	for state in gpio.snapshots_blocking(command.interval) {
+		if interrupt::interrupted() {
+			break;
+		}
+
+		if !command.changes {
+			// Clear the screen and move the cursor to the top left.
+			print!("\x1b[2J\x1b[H");
+			for (index, pin) in gpio.pins_of(&state).iter().enumerate() {
+				crate::print_pin(index, pin, None, options.verbose);
+			}
+			continue;
+		}
+
+		if let Some(previous) = &previous {
+			let time = start.elapsed();
+			for change in state.changes(previous) {
+				print_change(time, previous, &change);
+			}
+		}
+		previous = Some(state);
+	}
+}
+
+/// Print a single line with the old and new values of the changed fields of a pin.
+fn print_change(time: Duration, previous: &GpioState, change: &PinChange) {
+	print!("[{:>6}.{:06}]   pin={:<2}", time.as_secs(), time.subsec_micros(), Paint::yellow(change.pin));
+
+	if let Some(new) = change.function {
+		let old = match previous.pin_function(change.pin) {
+			Ok(x) => format!("{:?}", x),
+			Err(error) => crate::exit_with_error(error),
+		};
+		print!("   function={}->{}", Paint::red(old), Paint::green(format!("{:?}", new)));
+	}
+	if let Some(new) = change.level {
+		print!("   level={}->{}", Paint::red(!new), Paint::green(new));
+	}
+
+	let flags = [
+		("event",             change.event),
+		("detect-rise",       change.detect_rise),
+		("detect-fall",       change.detect_fall),
+		("detect-high",       change.detect_high),
+		("detect-low",        change.detect_low),
+		("detect-async-rise", change.detect_async_rise),
+		("detect-async-fall", change.detect_async_fall),
+	];
+	for (name, new) in flags.iter() {
+		if let Some(new) = *new {
+			print!("   {}={}->{}", name, Paint::red(on_off(!new)), Paint::green(on_off(new)));
+		}
+	}
+
+	println!();
+}
+
+fn on_off(value: bool) -> &'static str {
+	match value {
+		true  => "on",
+		false => "off",
+	}
+}
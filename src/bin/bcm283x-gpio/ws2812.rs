@@ -0,0 +1,79 @@
+use structopt::StructOpt;
+
+use bcm283x_linux_gpio::Ws2812;
+
+use crate::Options;
+
+#[derive(StructOpt)]
+pub struct Ws2812Options {
+	/// The pin the data line of the LED strip is connected to.
+	#[structopt(value_name = "PIN", parse(try_from_str = "crate::parse_pin_index"))]
+	pin: usize,
+
+	/// The number of LEDs on the strip.
+	#[structopt(long = "count", value_name = "N", default_value = "30")]
+	count: usize,
+
+	/// The pattern to show: rainbow, off, or solid:RRGGBB.
+	#[structopt(long = "pattern", value_name = "PATTERN", default_value = "rainbow")]
+	pattern: Pattern,
+}
+
+enum Pattern {
+	Rainbow,
+	Solid([u8; 3]),
+}
+
+impl std::str::FromStr for Pattern {
+	type Err = String;
+
+	fn from_str(input: &str) -> Result<Self, String> {
+		match input {
+			"rainbow" => return Ok(Pattern::Rainbow),
+			"off"     => return Ok(Pattern::Solid([0, 0, 0])),
+			_ => (),
+		}
+
+		let color = match input.strip_prefix("solid:") {
+			Some(x) => x,
+			None => return Err(format!("invalid pattern: {}, expected rainbow, off or solid:RRGGBB", input)),
+		};
+		let color = color.strip_prefix('#').unwrap_or(color);
+		if color.len() != 6 || !color.bytes().all(|c| c.is_ascii_hexdigit()) {
+			return Err(format!("invalid color: {}, expected six hexadecimal digits", color));
+		}
+
+		let component = |i: usize| u8::from_str_radix(&color[i..i + 2], 16).unwrap();
+		Ok(Pattern::Solid([component(0), component(2), component(4)]))
+	}
+}
+
+/// Show a test pattern on a WS2812 LED strip.
+///
+/// The pin is left as output to keep the data line from floating.
+pub fn run(options: &Options, command: &Ws2812Options) {
+	let mut gpio = crate::open_gpio(options);
+
+	let colors: Vec<_> = (0..command.count)
+		.map(|i| match command.pattern {
+			Pattern::Rainbow      => wheel(i * 256 / command.count),
+			Pattern::Solid(color) => color,
+		})
+		.collect();
+
+	match Ws2812::new(&mut gpio, command.pin) {
+		Ok(mut strip) => strip.write(&colors),
+		Err(error) => crate::exit_with_error(error),
+	}
+}
+
+/// Get a fully saturated color on the color wheel, for a position from 0 to 255.
+fn wheel(position: usize) -> [u8; 3] {
+	let position = (position % 256) as u8;
+	let step = position % 85 * 3;
+	match position / 85 {
+		0 => [255 - step, step, 0],
+		1 => [0, 255 - step, step],
+		_ => [step, 0, 255 - step],
+	}
+}
@@ -0,0 +1,279 @@
+//! A flat C API for the library, enabled with the `capi` feature.
+//!
+//! All functions return `RPI_GPIO_OK` on success or a negative error code on failure.
+//! A description of the last error on the calling thread is available from [`rpi_gpio_last_error`].
+//! The header is generated with cbindgen from this module, see `cbindgen.toml`.
+//! Build the shared library with `cargo rustc --release --lib --crate-type cdylib --features capi`.
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_uint};
+
+use crate::{Error, ErrorKind, Gpio, GpioConfig, GpioPullConfig, PinFunction, PullMode};
+
+pub const RPI_GPIO_OK                          : c_int = 0;
+pub const RPI_GPIO_ERROR_PERMISSION_DENIED     : c_int = -1;
+pub const RPI_GPIO_ERROR_PERIPHERAL_NOT_FOUND  : c_int = -2;
+pub const RPI_GPIO_ERROR_UNSUPPORTED_SOC       : c_int = -3;
+pub const RPI_GPIO_ERROR_INVALID_PIN_INDEX     : c_int = -4;
+pub const RPI_GPIO_ERROR_IOMEM_PARSE           : c_int = -5;
+pub const RPI_GPIO_ERROR_DEVICE_TREE           : c_int = -6;
+pub const RPI_GPIO_ERROR_MMAP                  : c_int = -7;
+pub const RPI_GPIO_ERROR_UNSUPPORTED           : c_int = -8;
+pub const RPI_GPIO_ERROR_IO                    : c_int = -9;
+pub const RPI_GPIO_ERROR_INVALID_ARGUMENT      : c_int = -10;
+pub const RPI_GPIO_ERROR_UNSAFE_REGISTER       : c_int = -11;
+pub const RPI_GPIO_ERROR_NOT_ALLOWED           : c_int = -12;
+pub const RPI_GPIO_ERROR_VERIFICATION_FAILED   : c_int = -13;
+pub const RPI_GPIO_ERROR_TIMEOUT               : c_int = -14;
+pub const RPI_GPIO_ERROR_NO_RESPONSE           : c_int = -15;
+pub const RPI_GPIO_ERROR_PROTOCOL              : c_int = -16;
+pub const RPI_GPIO_ERROR_OTHER                 : c_int = -17;
+
+pub const RPI_GPIO_PULL_FLOAT : c_int = 0;
+pub const RPI_GPIO_PULL_DOWN  : c_int = 1;
+pub const RPI_GPIO_PULL_UP    : c_int = 2;
+
+/// The number of register words filled in by [`rpi_gpio_read_all`].
+pub const RPI_GPIO_STATE_WORDS : usize = 0x100;
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Open the GPIO peripheral through /dev/mem.
+///
+/// # Safety
+/// `gpio` must be a valid pointer to write the handle to.
+/// The handle must be closed with [`rpi_gpio_close`].
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_open(gpio: *mut *mut Gpio) -> c_int {
+	open_with(gpio, Gpio::new)
+}
+
+/// Open the GPIO peripheral through /dev/gpiomem, which usually does not require root permission.
+///
+/// # Safety
+/// See [`rpi_gpio_open`].
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_open_gpiomem(gpio: *mut *mut Gpio) -> c_int {
+	open_with(gpio, Gpio::new_gpiomem)
+}
+
+/// Open the GPIO peripheral through the GPIO character device.
+///
+/// Alternate functions are not supported by the handle.
+///
+/// # Safety
+/// See [`rpi_gpio_open`].
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_open_cdev(gpio: *mut *mut Gpio) -> c_int {
+	open_with(gpio, Gpio::new_cdev)
+}
+
+/// Close a handle and release its resources.
+///
+/// Passing a null pointer does nothing.
+///
+/// # Safety
+/// `gpio` must be null or a handle returned by one of the open functions, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_close(gpio: *mut Gpio) {
+	if !gpio.is_null() {
+		drop(Box::from_raw(gpio));
+	}
+}
+
+/// Read the level of a pin.
+///
+/// # Safety
+/// `gpio` must be a valid handle and `level` a valid pointer to write the level to.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_read_level(gpio: *const Gpio, pin: c_uint, level: *mut bool) -> c_int {
+	let (gpio, level) = match (gpio.as_ref(), level.as_mut()) {
+		(Some(gpio), Some(level)) => (gpio, level),
+		_ => return null_argument(),
+	};
+	result(gpio.read_level(pin as usize).map(|x| *level = x.is_high()))
+}
+
+/// Read the levels of all pins, with bit N holding the level of pin N.
+///
+/// # Safety
+/// `gpio` must be a valid handle and `levels` a valid pointer to write the levels to.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_read_levels(gpio: *const Gpio, levels: *mut u64) -> c_int {
+	let (gpio, levels) = match (gpio.as_ref(), levels.as_mut()) {
+		(Some(gpio), Some(levels)) => (gpio, levels),
+		_ => return null_argument(),
+	};
+	*levels = gpio.read_levels();
+	RPI_GPIO_OK
+}
+
+/// Set the level of a pin.
+///
+/// # Safety
+/// `gpio` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_set_level(gpio: *mut Gpio, pin: c_uint, level: bool) -> c_int {
+	match gpio.as_mut() {
+		Some(gpio) => result(gpio.set_level(pin as usize, level)),
+		None => null_argument(),
+	}
+}
+
+/// Read the function of a pin, as the three function select bits: 0 for input, 1 for output, 4 for alt0, and so on.
+///
+/// # Safety
+/// `gpio` must be a valid handle and `function` a valid pointer to write the function to.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_get_function(gpio: *const Gpio, pin: c_uint, function: *mut u8) -> c_int {
+	let (gpio, function) = match (gpio.as_ref(), function.as_mut()) {
+		(Some(gpio), Some(function)) => (gpio, function),
+		_ => return null_argument(),
+	};
+	result(crate::check_pin_index(pin as usize).map(|()| *function = gpio.read_functions()[pin as usize].to_bits()))
+}
+
+/// Set the function of a pin, as the three function select bits: 0 for input, 1 for output, 4 for alt0, and so on.
+///
+/// # Safety
+/// `gpio` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_set_function(gpio: *mut Gpio, pin: c_uint, function: u8) -> c_int {
+	let gpio = match gpio.as_mut() {
+		Some(x) => x,
+		None => return null_argument(),
+	};
+	let mut config = GpioConfig::new();
+	result(config.set_function(pin as usize, PinFunction::from_bits(function)).and_then(|()| config.apply(gpio)))
+}
+
+/// Set the pull up/down mode of a pin to one of the `RPI_GPIO_PULL_*` values.
+///
+/// # Safety
+/// `gpio` must be a valid handle.
+/// Changing the pull up/down mode is not atomic, see [`GpioPullConfig::apply`].
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_set_pull_mode(gpio: *mut Gpio, pin: c_uint, mode: c_int) -> c_int {
+	let gpio = match gpio.as_mut() {
+		Some(x) => x,
+		None => return null_argument(),
+	};
+	let mode = match mode {
+		RPI_GPIO_PULL_FLOAT => PullMode::Float,
+		RPI_GPIO_PULL_DOWN  => PullMode::PullDown,
+		RPI_GPIO_PULL_UP    => PullMode::PullUp,
+		_ => return error(Error::new(ErrorKind::InvalidArgument, format!("invalid pull up/down mode: {}", mode))),
+	};
+	let mut config = GpioPullConfig::new();
+	result(config.set_pull_mode(pin as usize, mode).and_then(|()| config.apply(gpio)))
+}
+
+/// Read all GPIO registers at once.
+///
+/// `registers` must have room for `RPI_GPIO_STATE_WORDS` values.
+///
+/// # Safety
+/// `gpio` must be a valid handle and `registers` must be valid for writes of `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn rpi_gpio_read_all(gpio: *const Gpio, registers: *mut u32, len: usize) -> c_int {
+	let gpio = match gpio.as_ref() {
+		Some(x) if !registers.is_null() => x,
+		_ => return null_argument(),
+	};
+	if len < RPI_GPIO_STATE_WORDS {
+		return error(Error::new(ErrorKind::InvalidArgument, format!("register buffer too small: {} < {}", len, RPI_GPIO_STATE_WORDS)));
+	}
+	let registers = std::slice::from_raw_parts_mut(registers, RPI_GPIO_STATE_WORDS);
+	registers.copy_from_slice(gpio.read_all().data());
+	RPI_GPIO_OK
+}
+
+/// Get a description of the last error on the calling thread, or null if there was none.
+///
+/// The string remains valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn rpi_gpio_last_error() -> *const c_char {
+	LAST_ERROR.with(|x| x.borrow().as_ref().map_or(std::ptr::null(), |x| x.as_ptr()))
+}
+
+/// Get a static description of an error code.
+#[no_mangle]
+pub extern "C" fn rpi_gpio_strerror(code: c_int) -> *const c_char {
+	let message : &'static [u8] = match code {
+		RPI_GPIO_OK                         => b"success\0",
+		RPI_GPIO_ERROR_PERMISSION_DENIED    => b"permission denied\0",
+		RPI_GPIO_ERROR_PERIPHERAL_NOT_FOUND => b"peripheral not found\0",
+		RPI_GPIO_ERROR_UNSUPPORTED_SOC      => b"unsupported SoC\0",
+		RPI_GPIO_ERROR_INVALID_PIN_INDEX    => b"invalid pin index\0",
+		RPI_GPIO_ERROR_IOMEM_PARSE          => b"failed to parse /proc/iomem\0",
+		RPI_GPIO_ERROR_DEVICE_TREE          => b"invalid device tree\0",
+		RPI_GPIO_ERROR_MMAP                 => b"failed to map peripheral memory\0",
+		RPI_GPIO_ERROR_UNSUPPORTED          => b"operation not supported\0",
+		RPI_GPIO_ERROR_IO                   => b"I/O error\0",
+		RPI_GPIO_ERROR_INVALID_ARGUMENT     => b"invalid argument\0",
+		RPI_GPIO_ERROR_UNSAFE_REGISTER      => b"register can not be written safely\0",
+		RPI_GPIO_ERROR_NOT_ALLOWED          => b"operation not allowed\0",
+		RPI_GPIO_ERROR_VERIFICATION_FAILED  => b"verification failed\0",
+		RPI_GPIO_ERROR_TIMEOUT              => b"timeout\0",
+		RPI_GPIO_ERROR_NO_RESPONSE          => b"no response\0",
+		RPI_GPIO_ERROR_PROTOCOL             => b"protocol error\0",
+		_                                   => b"unknown error\0",
+	};
+	message.as_ptr() as *const c_char
+}
+
+unsafe fn open_with(gpio: *mut *mut Gpio, open: fn() -> Result<Gpio, Error>) -> c_int {
+	if gpio.is_null() {
+		return null_argument();
+	}
+	match open() {
+		Ok(x) => {
+			*gpio = Box::into_raw(Box::new(x));
+			RPI_GPIO_OK
+		},
+		Err(e) => error(e),
+	}
+}
+
+fn result(result: Result<(), Error>) -> c_int {
+	match result {
+		Ok(()) => RPI_GPIO_OK,
+		Err(e) => error(e),
+	}
+}
+
+fn null_argument() -> c_int {
+	error(Error::new(ErrorKind::InvalidArgument, "unexpected null pointer"))
+}
+
+/// Record an error as the last error of the thread and get its error code.
+fn error(error: Error) -> c_int {
+	let message = CString::new(error.to_string()).unwrap_or_default();
+	LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+	error_code(error.kind())
+}
+
+fn error_code(kind: ErrorKind) -> c_int {
+	match kind {
+		ErrorKind::PermissionDenied   => RPI_GPIO_ERROR_PERMISSION_DENIED,
+		ErrorKind::PeripheralNotFound => RPI_GPIO_ERROR_PERIPHERAL_NOT_FOUND,
+		ErrorKind::UnsupportedSoC     => RPI_GPIO_ERROR_UNSUPPORTED_SOC,
+		ErrorKind::InvalidPinIndex    => RPI_GPIO_ERROR_INVALID_PIN_INDEX,
+		ErrorKind::IoMemParse         => RPI_GPIO_ERROR_IOMEM_PARSE,
+		ErrorKind::DeviceTree         => RPI_GPIO_ERROR_DEVICE_TREE,
+		ErrorKind::Mmap               => RPI_GPIO_ERROR_MMAP,
+		ErrorKind::Unsupported        => RPI_GPIO_ERROR_UNSUPPORTED,
+		ErrorKind::Io                 => RPI_GPIO_ERROR_IO,
+		ErrorKind::InvalidArgument    => RPI_GPIO_ERROR_INVALID_ARGUMENT,
+		ErrorKind::UnsafeRegister     => RPI_GPIO_ERROR_UNSAFE_REGISTER,
+		ErrorKind::NotAllowed         => RPI_GPIO_ERROR_NOT_ALLOWED,
+		ErrorKind::VerificationFailed => RPI_GPIO_ERROR_VERIFICATION_FAILED,
+		ErrorKind::Timeout            => RPI_GPIO_ERROR_TIMEOUT,
+		ErrorKind::NoResponse         => RPI_GPIO_ERROR_NO_RESPONSE,
+		ErrorKind::Protocol           => RPI_GPIO_ERROR_PROTOCOL,
+		ErrorKind::Other              => RPI_GPIO_ERROR_OTHER,
+	}
+}
@@ -0,0 +1,479 @@
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::libc;
+use std::cell::Cell;
+use std::fs::File;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+use crate::{Capabilities, Error, ErrorKind, GpioBackend, PinFunction, Register};
+
+/// The consumer name of the lines requested by the backend.
+const CONSUMER : &[u8] = b"bcm283x-gpio";
+
+const GPIO_MAX_NAME_SIZE         : usize = 32;
+const GPIO_V2_LINES_MAX          : usize = 64;
+const GPIO_V2_LINE_NUM_ATTRS_MAX : usize = 10;
+
+const GPIO_V2_LINE_FLAG_USED           : u64 = 1 << 0;
+const GPIO_V2_LINE_FLAG_INPUT          : u64 = 1 << 2;
+const GPIO_V2_LINE_FLAG_OUTPUT         : u64 = 1 << 3;
+const GPIO_V2_LINE_FLAG_EDGE_RISING    : u64 = 1 << 4;
+const GPIO_V2_LINE_FLAG_EDGE_FALLING   : u64 = 1 << 5;
+const GPIO_V2_LINE_FLAG_BIAS_PULL_UP   : u64 = 1 << 8;
+const GPIO_V2_LINE_FLAG_BIAS_PULL_DOWN : u64 = 1 << 9;
+const GPIO_V2_LINE_FLAG_BIAS_DISABLED  : u64 = 1 << 10;
+
+const DIRECTION_FLAGS : u64 = GPIO_V2_LINE_FLAG_INPUT | GPIO_V2_LINE_FLAG_OUTPUT;
+const EDGE_FLAGS      : u64 = GPIO_V2_LINE_FLAG_EDGE_RISING | GPIO_V2_LINE_FLAG_EDGE_FALLING;
+const BIAS_FLAGS      : u64 = GPIO_V2_LINE_FLAG_BIAS_PULL_UP | GPIO_V2_LINE_FLAG_BIAS_PULL_DOWN | GPIO_V2_LINE_FLAG_BIAS_DISABLED;
+
+const GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES : u32 = 2;
+const GPIO_V2_LINE_EVENT_RISING_EDGE     : u32 = 1;
+
+const GPIO_GET_CHIPINFO_IOCTL       : u64 = ior(0x01, std::mem::size_of::<GpioChipInfo>());
+const GPIO_V2_GET_LINEINFO_IOCTL    : u64 = iowr(0x05, std::mem::size_of::<GpioLineInfo>());
+const GPIO_V2_GET_LINE_IOCTL        : u64 = iowr(0x07, std::mem::size_of::<GpioLineRequest>());
+const GPIO_V2_LINE_SET_CONFIG_IOCTL : u64 = iowr(0x0D, std::mem::size_of::<GpioLineConfig>());
+const GPIO_V2_LINE_GET_VALUES_IOCTL : u64 = iowr(0x0E, std::mem::size_of::<GpioLineValues>());
+const GPIO_V2_LINE_SET_VALUES_IOCTL : u64 = iowr(0x0F, std::mem::size_of::<GpioLineValues>());
+
+/// The ioctl request code for reading a structure from the GPIO character device.
+const fn ior(nr: u64, size: usize) -> u64 {
+	2 << 30 | (size as u64) << 16 | 0xB4 << 8 | nr
+}
+
+/// The ioctl request code for passing a structure to the GPIO character device and back.
+const fn iowr(nr: u64, size: usize) -> u64 {
+	3 << 30 | (size as u64) << 16 | 0xB4 << 8 | nr
+}
+
+// The structures below mirror the v2 uAPI in linux/gpio.h.
+
+#[repr(C)]
+struct GpioChipInfo {
+	name  : [u8; GPIO_MAX_NAME_SIZE],
+	label : [u8; GPIO_MAX_NAME_SIZE],
+	lines : u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct GpioLineAttribute {
+	id      : u32,
+	padding : u32,
+	value   : u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct GpioLineConfigAttribute {
+	attr : GpioLineAttribute,
+	mask : u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct GpioLineConfig {
+	flags     : u64,
+	num_attrs : u32,
+	padding   : [u32; 5],
+	attrs     : [GpioLineConfigAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+}
+
+#[repr(C)]
+struct GpioLineRequest {
+	offsets           : [u32; GPIO_V2_LINES_MAX],
+	consumer          : [u8; GPIO_MAX_NAME_SIZE],
+	config            : GpioLineConfig,
+	num_lines         : u32,
+	event_buffer_size : u32,
+	padding           : [u32; 5],
+	fd                : i32,
+}
+
+#[repr(C)]
+struct GpioLineInfo {
+	name      : [u8; GPIO_MAX_NAME_SIZE],
+	consumer  : [u8; GPIO_MAX_NAME_SIZE],
+	offset    : u32,
+	num_attrs : u32,
+	flags     : u64,
+	attrs     : [GpioLineAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+	padding   : [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GpioLineValues {
+	bits : u64,
+	mask : u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GpioLineEvent {
+	timestamp_ns : u64,
+	id           : u32,
+	offset       : u32,
+	seqno        : u32,
+	line_seqno   : u32,
+	padding      : [u32; 6],
+}
+
+/// A backend using the Linux GPIO character device, for systems where the GPIO registers can not be mapped.
+///
+/// The backend emulates the registers through line requests:
+///  - the function select registers switch lines between input and output;
+///  - the set and clear registers change the value of output lines;
+///  - the edge detect registers request edge events, which are reported in the event status registers;
+///  - the pull up/down clock registers set the bias of the selected lines.
+///
+/// Alternate functions and level detection can not be emulated.
+/// Lines are requested when they are first changed and held until the backend is dropped,
+/// so they can not be used by other processes in the mean time.
+pub struct CdevBackend {
+	chip: File,
+	name: String,
+	lines: Vec<Option<Line>>,
+	registers: [u32; crate::CONTROL_BLOCK_SIZE / 4],
+	latch: u64,
+	events: Cell<u64>,
+}
+
+/// A single requested line.
+struct Line {
+	file: File,
+	flags: u64,
+}
+
+impl CdevBackend {
+	/// Open the GPIO character device of the BCM283x pin controller.
+	///
+	/// The device is found by the label of the chip, which is `pinctrl-bcm2835` or `pinctrl-bcm2711`.
+	pub fn new() -> Result<Self, Error> {
+		let mut paths = std::fs::read_dir("/dev")
+			.map_err(|e| Error::from_io(ErrorKind::Io, "failed to list /dev", e))?
+			.filter_map(Result::ok)
+			.map(|x| x.path())
+			.filter(|x| x.file_name().is_some_and(|x| x.to_string_lossy().starts_with("gpiochip")))
+			.collect::<Vec<_>>();
+		paths.sort();
+
+		for path in paths {
+			let file = match crate::open_rw(&path) {
+				Ok(x) => x.file,
+				Err(_) => continue,
+			};
+			let mut info = GpioChipInfo { name: [0; GPIO_MAX_NAME_SIZE], label: [0; GPIO_MAX_NAME_SIZE], lines: 0 };
+			if ioctl(file.as_raw_fd(), GPIO_GET_CHIPINFO_IOCTL, &mut info).is_err() {
+				continue;
+			}
+			if info.label.starts_with(b"pinctrl-bcm2") {
+				let lines = (info.lines as usize).min(54);
+				return Ok(Self {
+					chip: file,
+					name: path.display().to_string(),
+					lines: (0..lines).map(|_| None).collect(),
+					registers: [0; crate::CONTROL_BLOCK_SIZE / 4],
+					latch: 0,
+					events: Cell::new(0),
+				});
+			}
+		}
+
+		Err(Error::new(ErrorKind::PeripheralNotFound, "no GPIO character device found for the BCM283x pin controller"))
+	}
+
+	/// Get the flags of a line: from our own request if we have one, or from the kernel otherwise.
+	fn line_flags(&self, pin: usize) -> u64 {
+		if let Some(line) = &self.lines[pin] {
+			return line.flags;
+		}
+		let mut info = GpioLineInfo {
+			name: [0; GPIO_MAX_NAME_SIZE],
+			consumer: [0; GPIO_MAX_NAME_SIZE],
+			offset: pin as u32,
+			num_attrs: 0,
+			flags: 0,
+			attrs: Default::default(),
+			padding: [0; 4],
+		};
+		match ioctl(self.chip.as_raw_fd(), GPIO_V2_GET_LINEINFO_IOCTL, &mut info) {
+			Ok(()) => info.flags,
+			Err(_) => 0,
+		}
+	}
+
+	/// Request a line or change the configuration of a requested line.
+	///
+	/// Output lines are driven with the level of the output latch.
+	fn configure(&mut self, pin: usize, flags: u64) -> nix::Result<()> {
+		let mut config = GpioLineConfig { flags, ..Default::default() };
+		if flags & GPIO_V2_LINE_FLAG_OUTPUT != 0 {
+			config.num_attrs = 1;
+			config.attrs[0] = GpioLineConfigAttribute {
+				attr: GpioLineAttribute { id: GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES, padding: 0, value: self.latch >> pin & 1 },
+				mask: 1,
+			};
+		}
+
+		if let Some(line) = &mut self.lines[pin] {
+			ioctl(line.file.as_raw_fd(), GPIO_V2_LINE_SET_CONFIG_IOCTL, &mut config)?;
+			line.flags = flags;
+			return Ok(());
+		}
+
+		let file = request(self.chip.as_raw_fd(), &[pin as u32], config)?;
+		// Events are read without blocking when the event status registers are read.
+		fcntl(file.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+		self.lines[pin] = Some(Line { file, flags });
+		Ok(())
+	}
+
+	/// Change some flags of a line, keeping its current direction if it is not part of the change.
+	fn update_flags(&mut self, pin: usize, mask: u64, flags: u64) {
+		let current = self.line_flags(pin) & (DIRECTION_FLAGS | EDGE_FLAGS | BIAS_FLAGS);
+		let mut new = current & !mask | flags & mask;
+
+		// Edge detection and bias require an explicit direction.
+		if new & DIRECTION_FLAGS == 0 && new & (EDGE_FLAGS | BIAS_FLAGS) != 0 {
+			new |= GPIO_V2_LINE_FLAG_INPUT;
+		}
+		// Edge detection is only supported on inputs.
+		if new & GPIO_V2_LINE_FLAG_OUTPUT != 0 {
+			new &= !EDGE_FLAGS;
+		}
+
+		if new != current || self.lines[pin].is_none() {
+			// The register interface has no way to report errors, so lines that are in use elsewhere are skipped.
+			let _ = self.configure(pin, new);
+		}
+	}
+
+	/// Read the levels of all lines.
+	fn read_line_levels(&self) -> u64 {
+		let mut levels = 0;
+		let mut unrequested = Vec::new();
+		for pin in 0..self.lines.len() {
+			match &self.lines[pin] {
+				Some(line) => levels |= read_value(&line.file) << pin,
+				None if self.line_flags(pin) & GPIO_V2_LINE_FLAG_USED == 0 => unrequested.push(pin as u32),
+				None => (),
+			}
+		}
+
+		// Lines that are not requested yet are requested as-is for the duration of the read.
+		if !unrequested.is_empty() {
+			if let Ok(file) = request(self.chip.as_raw_fd(), &unrequested, GpioLineConfig::default()) {
+				let mut values = GpioLineValues { bits: 0, mask: (1 << unrequested.len()) - 1 };
+				if ioctl(file.as_raw_fd(), GPIO_V2_LINE_GET_VALUES_IOCTL, &mut values).is_ok() {
+					for (i, pin) in unrequested.iter().enumerate() {
+						levels |= (values.bits >> i & 1) << pin;
+					}
+				}
+			}
+		}
+
+		levels
+	}
+
+	/// Read the pending edge events of all lines into the event status.
+	fn read_line_events(&self) -> u64 {
+		let mut events = self.events.get();
+		for (pin, line) in self.lines.iter().enumerate() {
+			let line = match line {
+				Some(x) if x.flags & EDGE_FLAGS != 0 => x,
+				_ => continue,
+			};
+			let mut event = GpioLineEvent::default();
+			while read_struct(&line.file, &mut event) {
+				let edge = match event.id {
+					GPIO_V2_LINE_EVENT_RISING_EDGE => GPIO_V2_LINE_FLAG_EDGE_RISING,
+					_ => GPIO_V2_LINE_FLAG_EDGE_FALLING,
+				};
+				if line.flags & edge != 0 {
+					events |= 1 << pin;
+				}
+			}
+		}
+		self.events.set(events);
+		events
+	}
+
+	/// Write the function select register for ten pins.
+	///
+	/// Lines can only be inputs or outputs, so alternate functions are left out.
+	/// [`Gpio`](crate::Gpio) rejects them before they get here, since the capabilities don't include them.
+	fn write_functions(&mut self, first_pin: usize, value: u32) {
+		for pin in first_pin..(first_pin + 10).min(self.lines.len()) {
+			let flags = match PinFunction::from_bits((value >> ((pin - first_pin) * 3) & 0b111) as u8) {
+				PinFunction::Input  => GPIO_V2_LINE_FLAG_INPUT,
+				PinFunction::Output => GPIO_V2_LINE_FLAG_OUTPUT,
+				_ => continue,
+			};
+			if self.line_flags(pin) & DIRECTION_FLAGS != flags {
+				self.update_flags(pin, DIRECTION_FLAGS, flags);
+			}
+		}
+	}
+
+	/// Change the output latch, and drive the new level on output lines.
+	fn write_latch(&mut self, bits: u64, value: bool) {
+		match value {
+			true  => self.latch |= bits,
+			false => self.latch &= !bits,
+		}
+		for pin in (0..self.lines.len()).filter(|pin| bits >> pin & 1 != 0) {
+			match &self.lines[pin] {
+				Some(line) if line.flags & GPIO_V2_LINE_FLAG_OUTPUT != 0 => {
+					let mut values = GpioLineValues { bits: u64::from(value), mask: 1 };
+					let _ = ioctl(line.file.as_raw_fd(), GPIO_V2_LINE_SET_VALUES_IOCTL, &mut values);
+				},
+				Some(_) => (),
+				// Outputs configured by others are requested, so the level can be changed.
+				None => if self.line_flags(pin) & GPIO_V2_LINE_FLAG_OUTPUT != 0 {
+					let _ = self.configure(pin, GPIO_V2_LINE_FLAG_OUTPUT);
+				},
+			}
+		}
+	}
+
+	/// Update the edge detection of all lines from the edge detect registers.
+	fn update_edges(&mut self) {
+		let rise = self.pair(Register::GPREN0, Register::GPREN1) | self.pair(Register::GPAREN0, Register::GPAREN1);
+		let fall = self.pair(Register::GPFEN0, Register::GPFEN1) | self.pair(Register::GPAFEN0, Register::GPAFEN1);
+		for pin in 0..self.lines.len() {
+			let mut flags = 0;
+			if rise >> pin & 1 != 0 {
+				flags |= GPIO_V2_LINE_FLAG_EDGE_RISING;
+			}
+			if fall >> pin & 1 != 0 {
+				flags |= GPIO_V2_LINE_FLAG_EDGE_FALLING;
+			}
+			if self.line_flags(pin) & EDGE_FLAGS != flags {
+				self.update_flags(pin, EDGE_FLAGS, flags);
+			}
+		}
+	}
+
+	/// Apply the pull up/down mode of GPPUD to the lines selected by a pull up/down clock register.
+	fn write_pull_clock(&mut self, bits: u64) {
+		let bias = match self.registers[Register::GPPUD as usize / 4] & 0b11 {
+			0b00 => GPIO_V2_LINE_FLAG_BIAS_DISABLED,
+			0b01 => GPIO_V2_LINE_FLAG_BIAS_PULL_DOWN,
+			0b10 => GPIO_V2_LINE_FLAG_BIAS_PULL_UP,
+			_ => return,
+		};
+		for pin in (0..self.lines.len()).filter(|pin| bits >> pin & 1 != 0) {
+			self.update_flags(pin, BIAS_FLAGS, bias);
+		}
+	}
+
+	fn pair(&self, low: Register, high: Register) -> u64 {
+		u64::from(self.registers[low as usize / 4]) | u64::from(self.registers[high as usize / 4]) << 32
+	}
+}
+
+impl GpioBackend for CdevBackend {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities {
+			registers:     false,
+			alt_functions: false,
+			pull:          true,
+			events:        true,
+			read_only:     false,
+		}
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		match reg {
+			Register::GPFSEL0 | Register::GPFSEL1 | Register::GPFSEL2 | Register::GPFSEL3 | Register::GPFSEL4 | Register::GPFSEL5 => {
+				let first_pin = (reg as usize - Register::GPFSEL0 as usize) / 4 * 10;
+				let mut value = 0;
+				for pin in first_pin..(first_pin + 10).min(self.lines.len()) {
+					if self.line_flags(pin) & GPIO_V2_LINE_FLAG_OUTPUT != 0 {
+						value |= u32::from(PinFunction::Output.to_bits()) << ((pin - first_pin) * 3);
+					}
+				}
+				value
+			},
+			Register::GPSET0 | Register::GPSET1 | Register::GPCLR0 | Register::GPCLR1 => 0,
+			Register::GPLEV0 => self.read_line_levels() as u32,
+			Register::GPLEV1 => (self.read_line_levels() >> 32) as u32,
+			Register::GPEDS0 => self.read_line_events() as u32,
+			Register::GPEDS1 => (self.read_line_events() >> 32) as u32,
+			_ => self.registers[reg as usize / 4],
+		}
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		match reg {
+			Register::GPFSEL0 | Register::GPFSEL1 | Register::GPFSEL2 | Register::GPFSEL3 | Register::GPFSEL4 | Register::GPFSEL5 => {
+				self.write_functions((reg as usize - Register::GPFSEL0 as usize) / 4 * 10, value);
+			},
+			Register::GPSET0 => self.write_latch(u64::from(value), true),
+			Register::GPSET1 => self.write_latch(u64::from(value) << 32, true),
+			Register::GPCLR0 => self.write_latch(u64::from(value), false),
+			Register::GPCLR1 => self.write_latch(u64::from(value) << 32, false),
+			Register::GPLEV0 | Register::GPLEV1 => (),
+			Register::GPEDS0 => self.events.set(self.read_line_events() & !u64::from(value)),
+			Register::GPEDS1 => self.events.set(self.read_line_events() & !(u64::from(value) << 32)),
+			Register::GPPUDCLK0 => self.write_pull_clock(u64::from(value)),
+			Register::GPPUDCLK1 => self.write_pull_clock(u64::from(value) << 32),
+			_ => {
+				self.registers[reg as usize / 4] = value;
+				match reg {
+					Register::GPREN0 | Register::GPREN1 | Register::GPFEN0 | Register::GPFEN1 => self.update_edges(),
+					Register::GPAREN0 | Register::GPAREN1 | Register::GPAFEN0 | Register::GPAFEN1 => self.update_edges(),
+					_ => (),
+				}
+			},
+		}
+	}
+
+	fn read_levels(&self) -> u64 {
+		self.read_line_levels()
+	}
+}
+
+/// Request lines from the GPIO chip.
+fn request(chip: RawFd, offsets: &[u32], config: GpioLineConfig) -> nix::Result<File> {
+	let mut request = GpioLineRequest {
+		offsets: [0; GPIO_V2_LINES_MAX],
+		consumer: [0; GPIO_MAX_NAME_SIZE],
+		config,
+		num_lines: offsets.len() as u32,
+		event_buffer_size: 0,
+		padding: [0; 5],
+		fd: -1,
+	};
+	request.offsets[..offsets.len()].copy_from_slice(offsets);
+	request.consumer[..CONSUMER.len()].copy_from_slice(CONSUMER);
+	ioctl(chip, GPIO_V2_GET_LINE_IOCTL, &mut request)?;
+	Ok(unsafe { File::from_raw_fd(request.fd) })
+}
+
+/// Read the value of a single requested line.
+fn read_value(file: &File) -> u64 {
+	let mut values = GpioLineValues { bits: 0, mask: 1 };
+	match ioctl(file.as_raw_fd(), GPIO_V2_LINE_GET_VALUES_IOCTL, &mut values) {
+		Ok(()) => values.bits & 1,
+		Err(_) => 0,
+	}
+}
+
+/// Read a structure from a non-blocking file, returning false if nothing could be read.
+fn read_struct<T>(file: &File, data: &mut T) -> bool {
+	let size = std::mem::size_of::<T>();
+	let read = unsafe { libc::read(file.as_raw_fd(), data as *mut T as *mut libc::c_void, size) };
+	read == size as isize
+}
+
+fn ioctl<T>(fd: RawFd, request: u64, data: &mut T) -> nix::Result<()> {
+	let result = unsafe { libc::ioctl(fd, request as _, data as *mut T) };
+	Errno::result(result).map(drop)
+}
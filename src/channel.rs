@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, EventListener, Gpio, GpioEvent};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+/// What an [`EventReceiver`] does with new events when its queue is full.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum OverflowPolicy {
+	/// Drop the oldest queued event, so the queue always holds the most recent events.
+	///
+	/// The number of dropped events is reported by [`EventReceiver::dropped`].
+	#[default]
+	DropOldest,
+	/// Stop polling until the receiver makes room, so no detected event is lost.
+	///
+	/// Edges that occur while polling is stopped are missed,
+	/// except that a changed level is detected as a single edge when polling resumes.
+	Block,
+}
+
+struct Queue {
+	events: VecDeque<GpioEvent>,
+	capacity: usize,
+	dropped: u64,
+
+	/// Set when the receiver is dropped, to stop the listener thread.
+	closed: bool,
+}
+
+struct Shared {
+	queue: Mutex<Queue>,
+	not_empty: Condvar,
+	not_full: Condvar,
+}
+
+impl Shared {
+	fn queue(&self) -> MutexGuard<'_, Queue> {
+		self.queue.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// The receiving end of a bounded queue of events, filled by an [`EventListener`] on a background thread.
+///
+/// Created with [`EventListener::into_channel`].
+/// The listener thread stops when the receiver is dropped.
+pub struct EventReceiver {
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EventListener {
+	/// Move the listener to a background thread that polls the pins at an interval,
+	/// and delivers the events through a bounded queue.
+	///
+	/// Events that were already queued in the listener are delivered first.
+	/// When the queue holds `capacity` events, new events are handled according to the overflow policy.
+	pub fn into_channel(self, interval: Duration, capacity: usize, policy: OverflowPolicy) -> Result<EventReceiver, Error> {
+		self.into_channel_with_options(interval, capacity, policy, ThreadOptions::default())
+	}
+
+	/// Move the listener to a background thread, with options for the thread, see [`into_channel`](Self::into_channel).
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn into_channel_with_options(self, interval: Duration, capacity: usize, policy: OverflowPolicy, options: ThreadOptions) -> Result<EventReceiver, Error> {
+		self.into_channel_with_gpio(Gpio::new()?, interval, capacity, policy, options)
+	}
+
+	/// Move the listener and an existing handle to a background thread, see [`into_channel`](Self::into_channel).
+	pub fn into_channel_with_gpio(self, gpio: Gpio, interval: Duration, capacity: usize, policy: OverflowPolicy, options: ThreadOptions) -> Result<EventReceiver, Error> {
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "event channel polling interval must not be zero"));
+		}
+		if capacity == 0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, "event channel capacity must not be zero"));
+		}
+
+		let shared = Arc::new(Shared {
+			queue: Mutex::new(Queue {
+				events:   VecDeque::with_capacity(capacity),
+				capacity,
+				dropped:  0,
+				closed:   false,
+			}),
+			not_empty: Condvar::new(),
+			not_full:  Condvar::new(),
+		});
+
+		let thread = spawn_worker("event channel", options, gpio, {
+			let shared = shared.clone();
+			move |gpio| run(self, &gpio, interval, policy, &shared)
+		})?;
+
+		Ok(EventReceiver {
+			shared,
+			thread: Some(thread),
+		})
+	}
+}
+
+impl EventReceiver {
+	/// Wait for the next event.
+	pub fn recv(&self) -> GpioEvent {
+		let mut queue = self.shared.queue();
+		loop {
+			if let Some(event) = self.pop(&mut queue) {
+				return event;
+			}
+			queue = self.shared.not_empty.wait(queue).unwrap_or_else(|e| e.into_inner());
+		}
+	}
+
+	/// Wait for the next event, up to a timeout.
+	///
+	/// Returns `None` if the timeout expires before an event is received.
+	pub fn recv_timeout(&self, timeout: Duration) -> Option<GpioEvent> {
+		let deadline = Instant::now() + timeout;
+		let mut queue = self.shared.queue();
+		loop {
+			if let Some(event) = self.pop(&mut queue) {
+				return Some(event);
+			}
+			let now = Instant::now();
+			if now >= deadline {
+				return None;
+			}
+			queue = self.shared.not_empty.wait_timeout(queue, deadline - now).unwrap_or_else(|e| e.into_inner()).0;
+		}
+	}
+
+	/// Take the oldest queued event without waiting, if any.
+	pub fn try_recv(&self) -> Option<GpioEvent> {
+		self.pop(&mut self.shared.queue())
+	}
+
+	/// Get the number of events dropped because the queue was full, see [`OverflowPolicy::DropOldest`].
+	pub fn dropped(&self) -> u64 {
+		self.shared.queue().dropped
+	}
+
+	fn pop(&self, queue: &mut Queue) -> Option<GpioEvent> {
+		let event = queue.events.pop_front()?;
+		self.shared.not_full.notify_one();
+		Some(event)
+	}
+}
+
+impl Iterator for EventReceiver {
+	type Item = GpioEvent;
+
+	/// Wait for the next event, see [`recv`](EventReceiver::recv).
+	fn next(&mut self) -> Option<GpioEvent> {
+		Some(self.recv())
+	}
+}
+
+impl Drop for EventReceiver {
+	fn drop(&mut self) {
+		self.shared.queue().closed = true;
+		self.shared.not_full.notify_one();
+		if let Some(thread) = self.thread.take() {
+			thread.thread().unpark();
+			let _ = thread.join();
+		}
+	}
+}
+
+fn run(mut listener: EventListener, gpio: &Gpio, interval: Duration, policy: OverflowPolicy, shared: &Shared) {
+	loop {
+		listener.poll(gpio);
+		while let Some(event) = listener.pop() {
+			let mut queue = shared.queue();
+			while queue.events.len() >= queue.capacity && !queue.closed {
+				match policy {
+					OverflowPolicy::DropOldest => {
+						queue.events.pop_front();
+						queue.dropped += 1;
+					},
+					OverflowPolicy::Block => {
+						queue = shared.not_full.wait(queue).unwrap_or_else(|e| e.into_inner());
+					},
+				}
+			}
+			if queue.closed {
+				return;
+			}
+			queue.events.push_back(event);
+			shared.not_empty.notify_one();
+		}
+
+		if shared.queue().closed {
+			return;
+		}
+		std::thread::park_timeout(interval);
+	}
+}
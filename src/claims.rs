@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use crate::{Error, PinInfo};
+
+const PINCTRL_DIR : &str = "/sys/kernel/debug/pinctrl";
+const SYSFS_GPIO_DIR : &str = "/sys/class/gpio";
+
+/// The GPIO pins claimed by kernel drivers.
+///
+/// Reconfiguring a pin that is in use by a kernel driver (such as I2C, SPI or UART)
+/// will silently break that driver, so check the claims before changing pin functions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KernelClaims {
+	owners: Vec<Option<String>>,
+}
+
+impl KernelClaims {
+	/// Read the pin claims from the kernel.
+	///
+	/// Pin multiplexing claims are read from `pinmux-pins` in debugfs,
+	/// which is only available to root and only if debugfs is mounted.
+	/// Pins exported through `/sys/class/gpio` are reported as claimed by `sysfs`.
+	///
+	/// Sources that are not available are skipped, so an empty result does not guarantee that no pins are claimed.
+	pub fn read() -> Result<Self, Error> {
+		let mut claims = Self { owners: vec![None; 54] };
+		claims.read_pinctrl()?;
+		claims.read_sysfs()?;
+		Ok(claims)
+	}
+
+	/// Get the kernel driver that claimed a pin, if any.
+	///
+	/// Pins that are out of range are never claimed.
+	pub fn owner(&self, pin: usize) -> Option<&str> {
+		self.owners.get(pin)?.as_deref()
+	}
+
+	/// Iterate over all claimed pins and their owners.
+	pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> + '_ {
+		self.owners.iter().enumerate().filter_map(|(pin, owner)| Some((pin, owner.as_deref()?)))
+	}
+
+	/// Fill in the kernel owner of a list of pins, indexed by pin number.
+	pub fn annotate(&self, pins: &mut [PinInfo]) {
+		for (pin, info) in pins.iter_mut().enumerate().take(54) {
+			info.kernel_owner = self.owners[pin].clone();
+		}
+	}
+
+	fn read_pinctrl(&mut self) -> Result<(), Error> {
+		let entries = match std::fs::read_dir(PINCTRL_DIR) {
+			Ok(x) => x,
+			Err(_) => return Ok(()),
+		};
+
+		// The GPIO pin controller is named after its address, like fe200000.gpio or 3f200000.gpio-pinctrl-bcm2835.
+		for entry in entries.filter_map(Result::ok) {
+			if !entry.file_name().to_string_lossy().contains(".gpio") {
+				continue;
+			}
+			let path = entry.path().join("pinmux-pins");
+			if !path.exists() {
+				continue;
+			}
+
+			let data = crate::read_all(crate::open(path)?)?;
+			for line in String::from_utf8_lossy(&data).lines() {
+				if let Some((pin, owner)) = parse_pinmux_line(line) {
+					if pin < 54 {
+						self.owners[pin] = Some(owner);
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn read_sysfs(&mut self) -> Result<(), Error> {
+		let entries = match std::fs::read_dir(SYSFS_GPIO_DIR) {
+			Ok(x) => x,
+			Err(_) => return Ok(()),
+		};
+
+		for entry in entries.filter_map(Result::ok) {
+			if !entry.file_name().to_string_lossy().starts_with("gpiochip") {
+				continue;
+			}
+
+			let label = read_trimmed(&entry.path().join("label"))?;
+			if !label.starts_with("pinctrl-bcm2") {
+				continue;
+			}
+
+			let base = read_trimmed(&entry.path().join("base"))?;
+			let base = match base.parse::<usize>() {
+				Ok(x) => x,
+				Err(_) => continue,
+			};
+
+			for pin in 0..54 {
+				if self.owners[pin].is_none() && Path::new(SYSFS_GPIO_DIR).join(format!("gpio{}", base + pin)).exists() {
+					self.owners[pin] = Some(String::from("sysfs"));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Parse a line from pinmux-pins.
+///
+/// Lines look like `pin 2 (gpio2): 3f804000.i2c (GPIO UNCLAIMED) function alt0 group gpio2`,
+/// where the first owner is the mux owner and the second the GPIO owner.
+fn parse_pinmux_line(line: &str) -> Option<(usize, String)> {
+	let line = line.strip_prefix("pin ")?;
+	let (pin, rest) = line.split_at(line.find(' ')?);
+	let pin = pin.parse().ok()?;
+	let rest = &rest[rest.find("): ")? + 3..];
+
+	let (mux_owner, rest) = match rest.strip_prefix("(MUX UNCLAIMED)") {
+		Some(rest) => (None, rest.trim_start()),
+		None => {
+			let end = rest.find(' ').unwrap_or(rest.len());
+			(Some(&rest[..end]), rest[end..].trim_start())
+		},
+	};
+
+	let gpio_owner = match rest.starts_with("(GPIO UNCLAIMED)") {
+		true  => None,
+		false => rest.split_whitespace().next(),
+	};
+
+	mux_owner.or(gpio_owner).map(|owner| (pin, owner.to_string()))
+}
+
+fn read_trimmed(path: &Path) -> Result<String, Error> {
+	let data = crate::read_all(crate::open(path)?)?;
+	Ok(String::from_utf8_lossy(crate::trim(&data)).into_owned())
+}
@@ -0,0 +1,190 @@
+use crate::{Error, ErrorKind, Gpio, PinFunction, Soc};
+
+/// Offset of the clock manager control block from the GPIO control block.
+const CLOCK_OFFSET : i64 = 0x101000 - 0x200000;
+const CLOCK_SIZE : usize = 0x00000100;
+
+/// Password that must be present in the top byte of every clock manager write.
+const PASSWORD : u32 = 0x5A << 24;
+
+/// The register offsets of the control and divisor registers of GPCLK0, GPCLK1 and GPCLK2.
+const CLOCK_REGISTERS : [(usize, usize); 3] = [(0x70, 0x74), (0x78, 0x7C), (0x80, 0x84)];
+
+/// The register offsets of the control and divisor registers of the PWM clock.
+const PWM_CLOCK_REGISTERS : (usize, usize) = (0xA0, 0xA4);
+
+/// The divisor of the oscillator for the PWM clock.
+const PWM_CLOCK_DIVISOR : u32 = 2;
+
+const CTL_ENABLE : u32 = 1 << 4;
+const CTL_BUSY   : u32 = 1 << 7;
+
+/// The clock sources we use, with their frequency on BCM2835/6/7.
+///
+/// The other sources are either not stable (PLLC follows the core clock) or used by the firmware.
+const BCM2835_SOURCES : [(u32, f64); 2] = [
+	(1, 19.2e6),  // oscillator
+	(6, 500.0e6), // PLLD
+];
+
+/// The clock sources we use, with their frequency on BCM2711.
+const BCM2711_SOURCES : [(u32, f64); 2] = [
+	(1, 54.0e6),  // oscillator
+	(6, 750.0e6), // PLLD
+];
+
+/// The smallest and largest integer divisor we use, the largest one the divisor register can hold.
+const MIN_DIVISOR : f64 = 2.0;
+const MAX_DIVISOR : f64 = 4095.0;
+
+/// The pins that can output a general purpose clock, with the clock index and pin function.
+const CLOCK_PINS : [(usize, usize, PinFunction); 10] = [
+	(4,  0, PinFunction::Alt0),
+	(20, 0, PinFunction::Alt5),
+	(32, 0, PinFunction::Alt0),
+	(34, 0, PinFunction::Alt0),
+	(5,  1, PinFunction::Alt0),
+	(21, 1, PinFunction::Alt5),
+	(42, 1, PinFunction::Alt0),
+	(44, 1, PinFunction::Alt0),
+	(6,  2, PinFunction::Alt0),
+	(43, 2, PinFunction::Alt0),
+];
+
+/// A handle to the general purpose clocks of the clock manager peripheral.
+///
+/// The general purpose clocks (GPCLK0-2) can output square waves up to tens of MHz
+/// on a few pins, without any CPU load.
+/// Note that the firmware or kernel may use GPCLK0 or GPCLK2 for other purposes on some boards.
+pub struct Clock {
+	control_block: *mut std::ffi::c_void,
+}
+
+// The mapping is owned by the handle, and all writes go through a mutable reference.
+unsafe impl Send for Clock {}
+
+impl Clock {
+	/// Create a new handle to the clock manager peripheral.
+	///
+	/// This maps a portion of /dev/mem just like [`Gpio::new`](crate::Gpio::new),
+	/// and may fail for the same reasons.
+	pub fn new() -> Result<Self, Error> {
+		let address       = crate::read_gpio_address()? + CLOCK_OFFSET;
+		let control_block = crate::map_dev_mem("clock manager", address, CLOCK_SIZE)?;
+		Ok(Self { control_block })
+	}
+
+	/// Output a square wave on a pin, using the general purpose clock connected to it.
+	///
+	/// The clock source and integer divisor closest to the requested frequency are picked,
+	/// to get a 50% duty cycle without jitter.
+	/// The pin is switched to the clock function.
+	///
+	/// Returns the actual frequency of the square wave.
+	pub fn square_wave(&mut self, gpio: &mut Gpio, pin: usize, frequency: f64) -> Result<f64, Error> {
+		let (clock, function) = clock_for_pin(pin)?;
+		gpio.check_function(function)?;
+		if !frequency.is_finite() || frequency <= 0.0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid clock frequency: {}", frequency)));
+		}
+
+		let sources = sources(gpio.soc());
+		let (source, divisor, actual) = sources.iter()
+			.filter_map(|&(source, source_frequency)| {
+				let divisor = (source_frequency / frequency).round();
+				if (MIN_DIVISOR..=MAX_DIVISOR).contains(&divisor) {
+					Some((source, divisor as u32, source_frequency / divisor))
+				} else {
+					None
+				}
+			})
+			.min_by(|a, b| (a.2 - frequency).abs().total_cmp(&(b.2 - frequency).abs()))
+			.ok_or_else(|| {
+				let min = sources.iter().map(|x| x.1).fold(f64::INFINITY, f64::min) / MAX_DIVISOR;
+				let max = sources.iter().map(|x| x.1).fold(0.0, f64::max) / MIN_DIVISOR;
+				Error::new(ErrorKind::InvalidArgument, format!("clock frequency out of range: {} Hz, expected {:.0} Hz to {:.0} Hz", frequency, min, max))
+			})?;
+
+		self.configure(CLOCK_REGISTERS[clock], source, divisor);
+
+		gpio.write_function(pin, function);
+		Ok(actual)
+	}
+
+	/// Run the clock of the PWM peripheral from the oscillator, and return its frequency.
+	///
+	/// The clock is shared by both PWM channels.
+	pub(crate) fn start_pwm_clock(&mut self, soc: Soc) -> f64 {
+		let (source, frequency) = sources(soc)[0];
+		self.configure(PWM_CLOCK_REGISTERS, source, PWM_CLOCK_DIVISOR);
+		frequency / f64::from(PWM_CLOCK_DIVISOR)
+	}
+
+	/// Stop a clock, then start it with a new source and integer divisor.
+	fn configure(&mut self, registers: (usize, usize), source: u32, divisor: u32) {
+		self.stop_clock(registers);
+		let (control, divider) = registers;
+		self.write(divider, PASSWORD | divisor << 12);
+		self.write(control, PASSWORD | source);
+		self.write(control, PASSWORD | source | CTL_ENABLE);
+	}
+
+	/// Stop the general purpose clock connected to a pin.
+	///
+	/// The pin function is left unchanged.
+	pub fn stop(&mut self, pin: usize) -> Result<(), Error> {
+		let (clock, _) = clock_for_pin(pin)?;
+		self.stop_clock(CLOCK_REGISTERS[clock]);
+		Ok(())
+	}
+
+	fn stop_clock(&mut self, registers: (usize, usize)) {
+		let (control, _) = registers;
+		let source = self.read(control) & 0xF;
+		self.write(control, PASSWORD | source);
+
+		// The clock must not be changed while it is busy, so wait for it to stop.
+		for _ in 0..1000 {
+			if self.read(control) & CTL_BUSY == 0 {
+				break;
+			}
+			std::thread::sleep(std::time::Duration::from_micros(10));
+		}
+	}
+
+	fn read(&self, offset: usize) -> u32 {
+		let value = unsafe { (self.control_block.wrapping_add(offset) as *const u32).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	fn write(&mut self, offset: usize, value: u32) {
+		crate::peripheral_barrier();
+		unsafe { (self.control_block.wrapping_add(offset) as *mut u32).write_volatile(value) }
+	}
+}
+
+impl Drop for Clock {
+	fn drop(&mut self) {
+		unsafe {
+			crate::procfs::unmap_memory(self.control_block, CLOCK_SIZE);
+		}
+	}
+}
+
+/// Get the clock sources we use with their frequency, for a SoC.
+fn sources(soc: Soc) -> &'static [(u32, f64)] {
+	match soc {
+		Soc::Bcm2835 => &BCM2835_SOURCES,
+		Soc::Bcm2711 => &BCM2711_SOURCES,
+	}
+}
+
+/// Get the general purpose clock index and pin function for a pin.
+fn clock_for_pin(pin: usize) -> Result<(usize, PinFunction), Error> {
+	crate::check_pin_index(pin)?;
+	CLOCK_PINS.iter()
+		.find(|x| x.0 == pin)
+		.map(|&(_, clock, function)| (clock, function))
+		.ok_or_else(|| Error::new(ErrorKind::InvalidArgument, format!("pin {} can not output a general purpose clock, use pin 4, 5, 6, 20, 21, 32, 34, 42, 43 or 44", pin)))
+}
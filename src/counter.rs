@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{Edge, Error, ErrorKind, Gpio};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+/// The number of level reads between updates of the shared counters.
+const BATCH_SIZE : usize = 256;
+
+/// The number of samples kept for the rolling rate.
+const RATE_SAMPLES : u32 = 16;
+
+struct Shared {
+	/// The total number of counted edges since the counter was started.
+	total: AtomicU64,
+
+	/// The value of `total` at the last reset.
+	offset: AtomicU64,
+
+	/// Samples of `total` over the rate window, oldest first.
+	samples: Mutex<VecDeque<(Instant, u64)>>,
+
+	/// Set to stop the counter thread.
+	stop: AtomicBool,
+}
+
+impl Shared {
+	fn samples(&self) -> MutexGuard<'_, VecDeque<(Instant, u64)>> {
+		self.samples.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Counts edges on a single GPIO pin at high rates, for flow meters, tachometers and other pulse outputs.
+///
+/// The edges are counted by a background thread that reads the level register of the pin in a tight loop,
+/// so it keeps a CPU core busy, but it sustains pulse rates far above what an [`EventListener`](crate::EventListener) can handle.
+/// Like the event listener, it does not touch the event detect registers, since those are normally in use by the kernel.
+/// Pulses that are shorter than a single register read can be missed.
+///
+/// The count and rate are reported in prescaled units,
+/// so a flow meter with 450 pulses per liter reports liters and liters per second with a prescaler of 450.
+pub struct PulseCounter {
+	pin: usize,
+	prescaler: u32,
+	window: Duration,
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl PulseCounter {
+	/// Start counting edges on a pin.
+	///
+	/// Every `prescaler` edges count as one pulse.
+	/// The rate is averaged over the given window.
+	pub fn start(pin: usize, edge: Edge, prescaler: u32, window: Duration) -> Result<Self, Error> {
+		Self::start_with_options(pin, edge, prescaler, window, ThreadOptions::default())
+	}
+
+	/// Start counting edges on a pin, with options for the background thread.
+	///
+	/// Use a real-time priority or a dedicated CPU core to miss fewer short pulses, see [`realtime`](crate::realtime).
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn start_with_options(pin: usize, edge: Edge, prescaler: u32, window: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?, pin, edge, prescaler, window, options)
+	}
+
+	/// Start counting edges on a pin, moving an existing handle to the background thread.
+	pub fn start_with_gpio(gpio: Gpio, pin: usize, edge: Edge, prescaler: u32, window: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+		if prescaler == 0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, "pulse counter prescaler must not be zero"));
+		}
+		if window.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "pulse counter rate window must not be zero"));
+		}
+
+		let shared = Arc::new(Shared {
+			total:   AtomicU64::new(0),
+			offset:  AtomicU64::new(0),
+			samples: Mutex::new(VecDeque::new()),
+			stop:    AtomicBool::new(false),
+		});
+
+		let thread = spawn_worker("pulse counter", options, gpio, {
+			let shared = shared.clone();
+			move |gpio| run(&gpio, pin, edge, window, &shared)
+		})?;
+
+		Ok(Self {
+			pin,
+			prescaler,
+			window,
+			shared,
+			thread: Some(thread),
+		})
+	}
+
+	/// Get the pin the edges are counted on.
+	pub fn pin(&self) -> usize {
+		self.pin
+	}
+
+	/// Get the number of edges counted since the counter was started or reset, without prescaling.
+	pub fn edges(&self) -> u64 {
+		let total  = self.shared.total.load(Ordering::Relaxed);
+		let offset = self.shared.offset.load(Ordering::Relaxed);
+		total.wrapping_sub(offset)
+	}
+
+	/// Get the number of pulses counted since the counter was started or reset.
+	///
+	/// This is the number of edges divided by the prescaler, rounded down.
+	pub fn count(&self) -> u64 {
+		self.edges() / u64::from(self.prescaler)
+	}
+
+	/// Reset the count to zero.
+	///
+	/// This does not affect the rate.
+	pub fn reset(&self) {
+		self.shared.offset.store(self.shared.total.load(Ordering::Relaxed), Ordering::Relaxed);
+	}
+
+	/// Get the average number of pulses per second over the rate window.
+	///
+	/// Right after starting, the rate is averaged over the time since the start.
+	pub fn rate(&self) -> f64 {
+		let total = self.shared.total.load(Ordering::Relaxed);
+		let now   = Instant::now();
+		let (time, oldest) = match self.shared.samples().front() {
+			Some(x) => *x,
+			None => return 0.0,
+		};
+
+		let elapsed = now.saturating_duration_since(time).as_secs_f64();
+		if elapsed <= 0.0 {
+			return 0.0;
+		}
+		total.wrapping_sub(oldest) as f64 / elapsed / f64::from(self.prescaler)
+	}
+
+	/// Get the window the rate is averaged over.
+	pub fn window(&self) -> Duration {
+		self.window
+	}
+
+	/// Stop counting and return the final count.
+	pub fn stop(mut self) -> u64 {
+		self.stop_thread();
+		self.count()
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for PulseCounter {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+fn run(gpio: &Gpio, pin: usize, edge: Edge, window: Duration, shared: &Shared) {
+	let counted_level = edge == Edge::Rise;
+	let sample_interval = window / RATE_SAMPLES;
+	let mut level = gpio.level(pin);
+	let mut next_sample = Instant::now();
+
+	while !shared.stop.load(Ordering::Relaxed) {
+		// Only touch the shared state once per batch, to keep the loop tight.
+		let mut edges = 0;
+		for _ in 0..BATCH_SIZE {
+			let new_level = gpio.level(pin);
+			if new_level != level {
+				level = new_level;
+				edges += u64::from(level == counted_level);
+			}
+		}
+		let total = shared.total.fetch_add(edges, Ordering::Relaxed) + edges;
+
+		let now = Instant::now();
+		if now >= next_sample {
+			let mut samples = shared.samples();
+			samples.push_back((now, total));
+			while samples.len() > 1 && samples.front().is_some_and(|(time, _)| now - *time > window) {
+				samples.pop_front();
+			}
+			next_sample = now + sample_interval;
+		}
+	}
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Delays shorter than this use a calibrated spin loop instead of reading the clock.
+const SHORT_DELAY: Duration = Duration::from_micros(10);
+
+/// The number of spin loop iterations per millisecond, or 0 if not calibrated yet.
+static SPINS_PER_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Sleep until a deadline, busy-waiting for the last part for better accuracy.
+pub(crate) fn sleep_until(deadline: Instant) {
+	const SPIN_TIME: Duration = Duration::from_micros(100);
+
+	let now = Instant::now();
+	if deadline <= now {
+		return;
+	}
+
+	let remaining = deadline - now;
+	if remaining > SPIN_TIME {
+		std::thread::sleep(remaining - SPIN_TIME);
+	}
+
+	while Instant::now() < deadline {
+		std::hint::spin_loop();
+	}
+}
+
+/// Sleep for a duration, busy-waiting for the last part for better accuracy.
+pub(crate) fn sleep(duration: Duration) {
+	sleep_until(Instant::now() + duration)
+}
+
+/// Wait for a precise amount of time.
+///
+/// Delays of at least 10 microseconds busy-wait on CLOCK_MONOTONIC (sleeping first for long delays).
+/// Shorter delays use a spin loop calibrated against CLOCK_MONOTONIC on first use,
+/// since reading the clock itself takes a significant part of such a delay.
+///
+/// The calibrated loop assumes the CPU frequency does not change after calibration,
+/// so short delays may be too long when the CPU is clocked up later, or too short when it is clocked down.
+pub fn precise_delay(duration: Duration) {
+	if duration >= SHORT_DELAY {
+		return sleep(duration);
+	}
+
+	let spins_per_ms = match SPINS_PER_MS.load(Ordering::Relaxed) {
+		0 => calibrate(),
+		x => x,
+	};
+	spin(duration.as_nanos() as u64 * spins_per_ms / 1_000_000);
+}
+
+/// Measure the number of spin loop iterations per millisecond.
+///
+/// Takes the fastest of a few runs, to reduce the effect of preemption.
+fn calibrate() -> u64 {
+	const SPINS: u64 = 100_000;
+
+	let fastest = (0..5)
+		.map(|_| {
+			let start = Instant::now();
+			spin(SPINS);
+			start.elapsed()
+		})
+		.min()
+		.unwrap_or_default();
+
+	let spins_per_ms = (SPINS as f64 / fastest.as_secs_f64() / 1e3).max(1.0) as u64;
+	SPINS_PER_MS.store(spins_per_ms, Ordering::Relaxed);
+	spins_per_ms
+}
+
+fn spin(count: u64) {
+	for i in 0..count {
+		std::hint::black_box(i);
+		std::hint::spin_loop();
+	}
+}
@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use crate::platform::{bcm283x_gpio_lines, join_cells, parse_cell_count, parse_cells, parse_string_list, read_gpio_node, GpioController};
+use crate::{Error, ErrorKind};
+
+const DEVICE_TREE : &str = "/proc/device-tree";
+pub(crate) const SOC_NODE  : &str = "/proc/device-tree/soc";
+pub(crate) const GPIO_NODE : &str = "/proc/device-tree/soc/gpio@7e200000";
+
+/// The size of the GPIO registers we access, up to and including GPPUDCLK1.
+const MIN_GPIO_SIZE : u64 = 0xA0;
+
+/// Cross-check the GPIO address from /proc/iomem with the device tree.
+///
+/// The `reg` property of the GPIO node holds the bus address and size of the peripheral,
+/// which is translated to a physical address with the `ranges` property of the `soc` node.
+///
+/// Systems without a GPIO node in the device tree are not checked.
+pub(crate) fn verify_gpio_address(address: i64) -> Result<(), Error> {
+	let node = match read_gpio_node() {
+		Ok(x) => x,
+		Err(e) if e.is_not_found() => return Ok(()),
+		Err(e) => return Err(e),
+	};
+
+	let root_address_cells = read_cell_count("/proc/device-tree/#address-cells", 2)?;
+	let soc_address_cells  = read_cell_count(&format!("{}/#address-cells", SOC_NODE), 2)?;
+	let soc_size_cells     = read_cell_count(&format!("{}/#size-cells", SOC_NODE), 1)?;
+	let bus_address        = node.address;
+	let size               = node.size;
+
+	if size < MIN_GPIO_SIZE {
+		return Err(Error::new(ErrorKind::DeviceTree, format!(
+			"GPIO peripheral in device tree is too small: expected at least 0x{:X} bytes, got 0x{:X}",
+			MIN_GPIO_SIZE,
+			size,
+		)));
+	}
+
+	let ranges = read_cells(&format!("{}/ranges", SOC_NODE))?;
+	let physical = translate(&ranges, bus_address, soc_address_cells, root_address_cells, soc_size_cells)
+		.ok_or_else(|| Error::new(ErrorKind::DeviceTree, format!("GPIO bus address 0x{:08X} is not in the ranges of {}", bus_address, SOC_NODE)))?;
+
+	if physical != address as u64 {
+		return Err(Error::new(ErrorKind::DeviceTree, format!(
+			"GPIO address mismatch: /proc/iomem reports 0x{:08X}, but the device tree reports 0x{:08X}",
+			address,
+			physical,
+		)));
+	}
+
+	Ok(())
+}
+
+/// Translate a bus address to a parent address using a `ranges` property.
+///
+/// An empty `ranges` property means the address spaces are identical.
+fn translate(ranges: &[u32], address: u64, child_cells: usize, parent_cells: usize, size_cells: usize) -> Option<u64> {
+	if ranges.is_empty() {
+		return Some(address);
+	}
+	if child_cells + parent_cells + size_cells == 0 {
+		return None;
+	}
+
+	for entry in ranges.chunks_exact(child_cells + parent_cells + size_cells) {
+		let child  = join_cells(&entry[..child_cells]);
+		let parent = join_cells(&entry[child_cells..child_cells + parent_cells]);
+		let size   = join_cells(&entry[child_cells + parent_cells..]);
+		if address >= child && address - child < size {
+			return parent.checked_add(address - child);
+		}
+	}
+
+	None
+}
+
+/// Read the raw contents of a device tree property.
+pub(crate) fn read_property(path: &str) -> Result<Vec<u8>, Error> {
+	crate::read_all(crate::open(path)?)
+}
+
+/// Read a device tree property as a list of big-endian 32 bit cells.
+fn read_cells(path: &str) -> Result<Vec<u32>, Error> {
+	parse_cells(&read_property(path)?).map_err(|e| Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+/// Read an #address-cells or #size-cells property, with a default if it is missing.
+pub(crate) fn read_cell_count(path: &str, default: usize) -> Result<usize, Error> {
+	match read_property(path) {
+		Ok(data) => parse_cell_count(&data).map_err(|e| Error::new(e.kind(), format!("{}: {}", path, e))),
+		Err(e) if e.is_not_found() => Ok(default),
+		Err(e) => Err(e),
+	}
+}
+
+/// The address translation of a node to the address space of its parent.
+struct Translation {
+	/// The `ranges` property, or `None` if the node has none and its children are not memory mapped.
+	ranges: Option<Vec<u32>>,
+	child_cells: usize,
+	parent_cells: usize,
+	size_cells: usize,
+}
+
+/// Find all enabled GPIO controllers in the device tree.
+pub(crate) fn gpio_controllers() -> Result<Vec<GpioController>, Error> {
+	let root = Path::new(DEVICE_TREE);
+	if !root.is_dir() {
+		return Ok(Vec::new());
+	}
+
+	let address_cells = read_cell_count(&format!("{}/#address-cells", DEVICE_TREE), 2)?;
+	let size_cells    = read_cell_count(&format!("{}/#size-cells", DEVICE_TREE), 1)?;
+	let mut controllers = Vec::new();
+	find_gpio_controllers(root, "", address_cells, size_cells, &mut Vec::new(), &mut controllers)?;
+	Ok(controllers)
+}
+
+/// Recursively find the GPIO controllers below a node.
+///
+/// The cell counts are those of the node itself, which apply to the `reg` properties of its children.
+fn find_gpio_controllers(
+	dir: &Path,
+	path: &str,
+	address_cells: usize,
+	size_cells: usize,
+	translations: &mut Vec<Translation>,
+	controllers: &mut Vec<GpioController>,
+) -> Result<(), Error> {
+	let entries = std::fs::read_dir(dir)
+		.map_err(|e| Error::from_io(ErrorKind::DeviceTree, format!("failed to list {}", dir.display()), e))?;
+	let mut children = entries
+		.filter_map(Result::ok)
+		.filter(|x| x.file_type().is_ok_and(|x| x.is_dir()))
+		.map(|x| x.file_name().to_string_lossy().into_owned())
+		.collect::<Vec<_>>();
+	children.sort();
+
+	for name in children {
+		let node = dir.join(&name);
+		let path = format!("{}/{}", path, name);
+		match read_optional_property(&node.join("status"))? {
+			Some(status) if !matches!(parse_string_list(&status).first().map(String::as_str), Some("okay" | "ok")) => continue,
+			_ => (),
+		}
+
+		if read_optional_property(&node.join("gpio-controller"))?.is_some() {
+			controllers.push(read_gpio_controller(&node, path.clone(), address_cells, size_cells, translations)?);
+		}
+
+		let child_address_cells = read_cell_count(&format!("{}/#address-cells", node.display()), 2)?;
+		let child_size_cells    = read_cell_count(&format!("{}/#size-cells", node.display()), 1)?;
+		let ranges = match read_optional_property(&node.join("ranges"))? {
+			Some(data) => Some(parse_cells(&data).map_err(|e| Error::new(e.kind(), format!("{}/ranges: {}", node.display(), e)))?),
+			None => None,
+		};
+		translations.push(Translation {
+			ranges,
+			child_cells: child_address_cells,
+			parent_cells: address_cells,
+			size_cells: child_size_cells,
+		});
+		let result = find_gpio_controllers(&node, &path, child_address_cells, child_size_cells, translations, controllers);
+		translations.pop();
+		result?;
+	}
+
+	Ok(())
+}
+
+/// Read a GPIO controller node.
+///
+/// The `reg` property is in the address space of the parent, described by the cell counts and translations.
+fn read_gpio_controller(node: &Path, path: String, address_cells: usize, size_cells: usize, translations: &[Translation]) -> Result<GpioController, Error> {
+	let compatible = parse_string_list(&read_optional_property(&node.join("compatible"))?.unwrap_or_default());
+	let reg = match read_optional_property(&node.join("reg"))? {
+		Some(data) => parse_cells(&data).map_err(|e| Error::new(e.kind(), format!("{}/reg: {}", node.display(), e)))?,
+		None => Vec::new(),
+	};
+	let ngpios = match read_optional_property(&node.join("ngpios"))? {
+		Some(data) => match parse_cells(&data).as_deref() {
+			Ok([count]) => Some(*count),
+			_ => return Err(Error::new(ErrorKind::DeviceTree, format!("invalid ngpios property in {}", node.display()))),
+		},
+		None => bcm283x_gpio_lines(&compatible),
+	};
+
+	let (address, size) = match reg.get(..address_cells + size_cells) {
+		Some(reg) if address_cells > 0 => {
+			let address = translations.iter().rev().try_fold(join_cells(&reg[..address_cells]), |address, x| {
+				translate(x.ranges.as_ref()?, address, x.child_cells, x.parent_cells, x.size_cells)
+			});
+			(address, address.map(|_| join_cells(&reg[address_cells..])))
+		},
+		_ => (None, None),
+	};
+
+	Ok(GpioController { path, compatible, address, size, ngpios })
+}
+
+/// Read the raw contents of a device tree property, or `None` if it doesn't exist.
+fn read_optional_property(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+	match crate::open(path) {
+		Ok(file) => Ok(Some(crate::read_all(file)?)),
+		Err(e) if e.is_not_found() => Ok(None),
+		Err(e) => Err(e),
+	}
+}
@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+#[cfg(not(feature = "stub"))]
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use crate::{Error, Gpio, Level};
+#[cfg(not(feature = "stub"))]
+use crate::ErrorKind;
+
+/// A signal edge on a GPIO pin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Edge {
+	Rise,
+	Fall,
+}
+
+/// An edge detected on a GPIO pin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GpioEvent {
+	pub pin: usize,
+	pub edge: Edge,
+	pub time: Instant,
+}
+
+impl Edge {
+	/// Get the level of a pin right after the edge.
+	pub fn level(self) -> Level {
+		match self {
+			Edge::Rise => Level::High,
+			Edge::Fall => Level::Low,
+		}
+	}
+}
+
+impl GpioEvent {
+	/// Get the level of the pin right after the event.
+	pub fn level(&self) -> Level {
+		self.edge.level()
+	}
+}
+
+/// Edge detection for GPIO pins.
+///
+/// The listener detects edges by polling the pin levels.
+/// It does not touch the event detect registers,
+/// since those are normally in use by the kernel.
+///
+/// Pulses shorter than the polling interval can be missed.
+///
+/// Use a [`PollableEventListener`] to register the listener in an epoll, mio or select loop when another thread polls it.
+#[derive(Clone, Debug, Default)]
+pub struct EventListener {
+	rise: u64,
+	fall: u64,
+	levels: Option<u64>,
+	pending: VecDeque<GpioEvent>,
+}
+
+/// An [`EventListener`] with an eventfd that is readable while events are queued.
+///
+/// The listener implements [`AsRawFd`] with the eventfd,
+/// so it can be registered in an epoll, mio or select loop when another thread polls it.
+#[cfg(not(feature = "stub"))]
+#[derive(Debug)]
+pub struct PollableEventListener {
+	listener: EventListener,
+	ready: OwnedFd,
+	readable: bool,
+}
+
+impl EventListener {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add an eventfd that is readable while events are queued, so the listener can be registered in an event loop.
+	#[cfg(not(feature = "stub"))]
+	pub fn into_pollable(self) -> Result<PollableEventListener, Error> {
+		use nix::sys::eventfd::{eventfd, EfdFlags};
+
+		let readable = !self.pending.is_empty();
+		let fd = eventfd(u32::from(readable), EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK)
+			.map_err(|e| Error::from_nix(ErrorKind::Io, "failed to create eventfd for event listener", e))?;
+		// Safety: the file descriptor was just created and is not owned by anything else.
+		let ready = unsafe { OwnedFd::from_raw_fd(fd) };
+		Ok(PollableEventListener { listener: self, ready, readable })
+	}
+
+	/// Start watching a pin for an edge.
+	pub fn watch(&mut self, pin: usize, edge: Edge) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		match edge {
+			Edge::Rise => self.rise |= 1 << pin,
+			Edge::Fall => self.fall |= 1 << pin,
+		}
+		Ok(())
+	}
+
+	/// Stop watching a pin for an edge.
+	pub fn unwatch(&mut self, pin: usize, edge: Edge) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		match edge {
+			Edge::Rise => self.rise &= !(1 << pin),
+			Edge::Fall => self.fall &= !(1 << pin),
+		}
+		Ok(())
+	}
+
+	/// Poll the pin levels once and queue an event for every watched edge since the last poll.
+	///
+	/// The first poll only records the initial levels.
+	pub fn poll(&mut self, gpio: &Gpio) {
+		let levels = gpio.read_levels();
+		let time   = Instant::now();
+
+		let previous = match self.levels.replace(levels) {
+			Some(x) => x,
+			None    => return,
+		};
+
+		let changed = levels ^ previous;
+		let rise    = changed &  levels & self.rise;
+		let fall    = changed & !levels & self.fall;
+
+		for pin in 0..54 {
+			if rise & (1 << pin) != 0 {
+				self.pending.push_back(GpioEvent { pin, edge: Edge::Rise, time });
+			}
+			if fall & (1 << pin) != 0 {
+				self.pending.push_back(GpioEvent { pin, edge: Edge::Fall, time });
+			}
+		}
+	}
+
+	/// Take the oldest queued event, if any.
+	pub fn pop(&mut self) -> Option<GpioEvent> {
+		self.pending.pop_front()
+	}
+
+	/// Get the number of queued events.
+	pub fn pending(&self) -> usize {
+		self.pending.len()
+	}
+
+	/// Wait for the next event, polling the pin levels at the given interval.
+	///
+	/// Returns `None` if the timeout expires before an event is detected.
+	/// If no timeout is given, this function waits forever.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, gpio), ret))]
+	pub fn wait(&mut self, gpio: &Gpio, interval: Duration, timeout: Option<Duration>) -> Option<GpioEvent> {
+		let deadline = timeout.map(|x| Instant::now() + x);
+		loop {
+			if let Some(event) = self.pop() {
+				return Some(event);
+			}
+
+			self.poll(gpio);
+			if !self.pending.is_empty() {
+				continue;
+			}
+
+			if deadline.is_some_and(|x| Instant::now() >= x) {
+				return None;
+			}
+
+			std::thread::sleep(interval);
+		}
+	}
+}
+
+#[cfg(not(feature = "stub"))]
+impl PollableEventListener {
+	/// Create a listener with an eventfd that is readable while events are queued.
+	pub fn new() -> Result<Self, Error> {
+		EventListener::new().into_pollable()
+	}
+
+	/// Start watching a pin for an edge.
+	pub fn watch(&mut self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.listener.watch(pin, edge)
+	}
+
+	/// Stop watching a pin for an edge.
+	pub fn unwatch(&mut self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.listener.unwatch(pin, edge)
+	}
+
+	/// Poll the pin levels once and queue an event for every watched edge since the last poll.
+	///
+	/// See [`EventListener::poll`].
+	pub fn poll(&mut self, gpio: &Gpio) {
+		self.listener.poll(gpio);
+		self.update_ready();
+	}
+
+	/// Take the oldest queued event, if any.
+	pub fn pop(&mut self) -> Option<GpioEvent> {
+		let event = self.listener.pop();
+		self.update_ready();
+		event
+	}
+
+	/// Get the number of queued events.
+	pub fn pending(&self) -> usize {
+		self.listener.pending()
+	}
+
+	/// Wait for the next event, polling the pin levels at the given interval.
+	///
+	/// See [`EventListener::wait`].
+	pub fn wait(&mut self, gpio: &Gpio, interval: Duration, timeout: Option<Duration>) -> Option<GpioEvent> {
+		let event = self.listener.wait(gpio, interval, timeout);
+		self.update_ready();
+		event
+	}
+
+	/// Remove the eventfd and get back the listener.
+	pub fn into_inner(self) -> EventListener {
+		self.listener
+	}
+
+	/// Make the eventfd readable while events are queued.
+	fn update_ready(&mut self) {
+		let readable = self.listener.pending() > 0;
+		if readable == self.readable {
+			return;
+		}
+
+		// The eventfd is non-blocking, so a read of an already cleared counter fails with EAGAIN.
+		let fd = self.ready.as_raw_fd();
+		let result = if readable {
+			nix::unistd::write(fd, &1u64.to_ne_bytes()).map(drop)
+		} else {
+			nix::unistd::read(fd, &mut [0; 8]).map(drop)
+		};
+		match result {
+			Ok(()) => self.readable = readable,
+			Err(_e) => {
+				#[cfg(feature = "log")]
+				log::debug!(target: "bcm283x_linux_gpio::event", "failed to update the eventfd of an event listener: {}", _e);
+			},
+		}
+	}
+}
+
+#[cfg(not(feature = "stub"))]
+impl AsRawFd for PollableEventListener {
+	/// Get the eventfd that is readable while events are queued.
+	fn as_raw_fd(&self) -> RawFd {
+		self.ready.as_raw_fd()
+	}
+}
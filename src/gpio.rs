@@ -0,0 +1,759 @@
+use crate::{Capabilities, CdevBackend, Error, ErrorKind, GpioBackend, GpioState, Level, MappedBackend, PinFunction, PinInfo, PinMask, PreadBackend, PullMode, Register, Soc, SysfsBackend, SystemTimer};
+use crate::platform::GpioController;
+use crate::{check_pin_index, lock, peripheral_barrier, precise_delay, restore, trace_modify, trace_write, CONTROL_BLOCK_SIZE};
+#[cfg(feature = "grpc")]
+use crate::GrpcBackend;
+
+/// How long [`Gpio::wait_for_level`] busy-polls before it starts sleeping between reads.
+const WAIT_SPIN_TIME : std::time::Duration = std::time::Duration::from_micros(50);
+
+/// The longest sleep between reads in [`Gpio::wait_for_level`].
+const WAIT_MAX_SLEEP : std::time::Duration = std::time::Duration::from_millis(1);
+
+/// Serializes read-modify-write cycles on GPIO registers within the process.
+static REGISTER_LOCK : std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// A handle to the memory mapped GPIO peripheral.
+///
+/// The handle can be moved to another thread, but not shared between threads,
+/// since most operations go through unsynchronized state of the handle.
+/// Use [`Gpio::into_sync`] for a handle that can be shared,
+/// or [`Gpio::split_banks`] for one handle per bank.
+pub struct Gpio {
+	pub(crate) backend: Box<dyn GpioBackend>,
+	pub(crate) control_block: *mut std::ffi::c_void,
+	timer: Option<SystemTimer>,
+	lock: Option<lock::GpioLock>,
+	restore: Option<restore::RestoreLog>,
+	soc: Soc,
+	/// The pull up/down modes applied through this handle, for SoCs that can't read them back.
+	pub(crate) pull_modes: [Option<PullMode>; 54],
+	#[cfg(feature = "bench")]
+	accesses: std::cell::Cell<RegisterAccesses>,
+}
+
+/// The number of register accesses performed through a [`Gpio`] handle.
+#[cfg(feature = "bench")]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RegisterAccesses {
+	pub reads: u64,
+	pub writes: u64,
+}
+
+// The control block is owned by the backend, which is Send itself.
+// The handle is not Sync: the raw pointer already opts out, and the access counters use a Cell.
+unsafe impl Send for Gpio {}
+
+impl Gpio {
+	/// Create a new handle to the GPIO peripheral.
+	///
+	/// This will attempt to map a portion of /dev/mem,
+	/// in order to access the memory mapped GPIO peripheral.
+	/// See [`MappedBackend::dev_mem`] for the reasons this may fail.
+	///
+	/// The system timer is mapped too if possible, to use for delays.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new() -> Result<Self, Error> {
+		let mut gpio = Self::with_backend(MappedBackend::dev_mem()?);
+		gpio.timer = SystemTimer::new().ok();
+		Ok(gpio)
+	}
+
+	/// Create a new handle to a specific GPIO controller.
+	///
+	/// The controllers of the system are listed by [`platform::gpio_controllers`](crate::platform::gpio_controllers).
+	/// Only controllers compatible with the BCM283x GPIO peripheral are supported, see [`MappedBackend::open`].
+	///
+	/// The system timer is mapped too if possible, to use for delays.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn open(controller: &GpioController) -> Result<Self, Error> {
+		let mut gpio = Self::with_backend(MappedBackend::open(controller)?);
+		gpio.timer = SystemTimer::new().ok();
+		Ok(gpio)
+	}
+
+	/// Create a new handle to the GPIO peripheral through /dev/gpiomem.
+	///
+	/// This does not require root permission, see [`MappedBackend::gpiomem`].
+	/// The system timer is only used for delays if /dev/mem can be mapped too.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new_gpiomem() -> Result<Self, Error> {
+		let mut gpio = Self::with_backend(MappedBackend::gpiomem()?);
+		gpio.timer = SystemTimer::new().ok();
+		Ok(gpio)
+	}
+
+	/// Create a new handle to the GPIO peripheral through the GPIO character device.
+	///
+	/// This works without any access to the peripheral memory, see [`CdevBackend`].
+	/// Alternate functions are not supported.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new_cdev() -> Result<Self, Error> {
+		Ok(Self::with_backend(CdevBackend::new()?))
+	}
+
+	/// Create a new handle to the GPIO peripheral through the legacy sysfs GPIO interface.
+	///
+	/// This is a last resort for systems without the GPIO character device, see [`SysfsBackend`].
+	/// Alternate functions and pull up/down modes are not supported.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new_sysfs() -> Result<Self, Error> {
+		Ok(Self::with_backend(SysfsBackend::new()?))
+	}
+
+	/// Create a new read-only handle to the GPIO peripheral, reading /dev/mem without mapping it.
+	///
+	/// This is a fallback for kernels that refuse to map /dev/mem, see [`PreadBackend`].
+	/// All writes are ignored.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new_pread() -> Result<Self, Error> {
+		Ok(Self::with_backend(PreadBackend::dev_mem()?))
+	}
+
+	/// Create a new handle to the GPIO peripheral of a remote machine running a [`GrpcServer`].
+	///
+	/// The address is a URI like `http://raspberrypi:50051`, see [`GrpcBackend`].
+	#[cfg(feature = "grpc")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn new_grpc(address: &str) -> Result<Self, Error> {
+		Ok(Self::with_backend(GrpcBackend::connect(address)?))
+	}
+
+	/// Create a handle that accesses the GPIO registers through a custom backend.
+	pub fn with_backend(backend: impl GpioBackend + 'static) -> Self {
+		let backend = Box::new(backend);
+		Self {
+			control_block: backend.control_block().unwrap_or(std::ptr::null_mut()),
+			soc: backend.soc(),
+			backend,
+			timer: None,
+			lock: None,
+			restore: None,
+			pull_modes: [None; 54],
+			#[cfg(feature = "bench")]
+			accesses: Default::default(),
+		}
+	}
+
+	/// Create a new handle to the GPIO peripheral while holding an exclusive lock.
+	///
+	/// The lock is an advisory `flock` on [`LOCK_FILE`], held until the handle is dropped.
+	/// It only protects against other processes that also take the lock,
+	/// such as when changing the non-atomic function select, pull up/down or event detect registers.
+	///
+	/// This blocks until all other shared and exclusive locks are released.
+	pub fn new_exclusive() -> Result<Self, Error> {
+		Self::new_locked(true)
+	}
+
+	/// Create a new handle to the GPIO peripheral while holding a shared lock.
+	///
+	/// Any number of processes can hold a shared lock at the same time,
+	/// but not while another process holds an exclusive lock.
+	/// See [`new_exclusive`](Self::new_exclusive) for more details.
+	pub fn new_shared() -> Result<Self, Error> {
+		Self::new_locked(false)
+	}
+
+	/// Create a new handle to the GPIO peripheral that restores the original state when dropped.
+	///
+	/// The original value of every register is recorded before it is first modified through this handle,
+	/// and written back when the handle is dropped.
+	/// Pin levels changed through the set and clear registers are restored too.
+	///
+	/// Pull up/down settings and event status bits can not be read back, so they are not restored.
+	pub fn with_restore() -> Result<Self, Error> {
+		let mut gpio = Self::new()?;
+		gpio.restore = Some(Default::default());
+		Ok(gpio)
+	}
+
+	fn new_locked(exclusive: bool) -> Result<Self, Error> {
+		let lock = lock::GpioLock::acquire(exclusive)?;
+		let mut gpio = Self::new()?;
+		gpio.lock = Some(lock);
+		Ok(gpio)
+	}
+
+	/// Take the lock on [`LOCK_FILE`] for an existing handle, regardless of its backend.
+	///
+	/// The lock is held until the handle is dropped.
+	/// See [`new_exclusive`](Self::new_exclusive) and [`new_shared`](Self::new_shared) for the meaning of the lock.
+	pub fn acquire_lock(&mut self, exclusive: bool) -> Result<(), Error> {
+		self.lock = Some(lock::GpioLock::acquire(exclusive)?);
+		Ok(())
+	}
+
+	/// Create a GPIO handle that operates on ordinary memory instead of the peripheral.
+	///
+	/// This is meant for benchmarking the register access code without hardware.
+	///
+	/// # Safety
+	/// The memory must be valid for reads and writes of 0x100 bytes, suitably aligned for `u32`,
+	/// and must outlive the returned handle.
+	#[cfg(feature = "bench")]
+	pub unsafe fn from_memory(memory: *mut std::ffi::c_void) -> Self {
+		Self::with_backend(MappedBackend::from_memory(memory))
+	}
+
+	/// Get the number of register accesses performed through this handle.
+	#[cfg(feature = "bench")]
+	pub fn register_accesses(&self) -> RegisterAccesses {
+		self.accesses.get()
+	}
+
+	/// Reset the register access counters to zero.
+	#[cfg(feature = "bench")]
+	pub fn reset_register_accesses(&self) {
+		self.accesses.set(RegisterAccesses::default());
+	}
+
+	#[inline]
+	fn count_accesses(&self, reads: usize, writes: usize) {
+		#[cfg(feature = "bench")]
+		{
+			let mut accesses = self.accesses.get();
+			accesses.reads  += reads as u64;
+			accesses.writes += writes as u64;
+			self.accesses.set(accesses);
+		}
+		#[cfg(not(feature = "bench"))]
+		let _ = (reads, writes);
+	}
+
+	/// Get the backend used to access the registers.
+	pub fn backend(&self) -> &dyn GpioBackend {
+		&*self.backend
+	}
+
+	/// Get the features supported by the backend.
+	pub fn capabilities(&self) -> Capabilities {
+		self.backend.capabilities()
+	}
+
+	/// Get the pointer to the mapped control block, if the backend maps the GPIO peripheral.
+	pub fn control_block(&self) -> Option<*mut std::ffi::c_void> {
+		self.mapping()
+	}
+
+	/// Get the mapped control block, or `None` if registers are accessed through the backend.
+	#[inline]
+	pub(crate) fn mapping(&self) -> Option<*mut std::ffi::c_void> {
+		match self.control_block.is_null() {
+			true  => None,
+			false => Some(self.control_block),
+		}
+	}
+
+	/// Busy-wait for at least the given duration.
+	///
+	/// This uses the system timer if it could be mapped, and falls back to [`precise_delay`] otherwise.
+	pub fn delay(&self, duration: std::time::Duration) {
+		match &self.timer {
+			Some(timer) => timer.wait(duration),
+			None        => precise_delay(duration),
+		}
+	}
+
+	/// Lock the mapped GPIO and system timer registers in memory, and touch them.
+	///
+	/// After this, the first register access in a time-critical path does not cause a page fault.
+	/// The registers are only read, not written.
+	/// Use [`realtime::lock_memory`](crate::realtime::lock_memory) for the rest of the process.
+	///
+	/// Backends that do not map the peripheral are left alone,
+	/// but their registers are still read once.
+	pub fn lock_mapping(&self) -> Result<(), Error> {
+		if let Some(mapping) = self.mapping() {
+			crate::procfs::lock_memory("GPIO", mapping, CONTROL_BLOCK_SIZE)?;
+		}
+		self.read_all();
+		if let Some(timer) = &self.timer {
+			timer.lock_mapping()?;
+		}
+		Ok(())
+	}
+
+	/// Read the entire current GPIO state.
+	pub fn read_all(&self) -> GpioState {
+		let mut state = GpioState::new();
+		self.read_all_into(&mut state);
+		state
+	}
+
+	/// Read the state of all pins, including the fields that depend on the SoC.
+	///
+	/// On SoCs that can't read back the pull up/down modes,
+	/// the modes applied through this handle with [`GpioPullConfig::apply`](crate::GpioPullConfig::apply) are reported.
+	pub fn read_pins(&self) -> Vec<PinInfo> {
+		self.pins_of(&self.read_all())
+	}
+
+	/// Get the state of all pins from a state read earlier, like [`read_pins`](Self::read_pins) does.
+	///
+	/// This reports the pull up/down modes applied through this handle the same way, without reading the registers again.
+	pub fn pins_of(&self, state: &GpioState) -> Vec<PinInfo> {
+		let mut pins = state.pins_for(self.soc);
+		if !self.soc.has_pull_readback() {
+			for (info, mode) in pins.iter_mut().zip(self.pull_modes.iter()) {
+				info.pull_mode = *mode;
+			}
+		}
+		pins
+	}
+
+	/// Read the state of a single pin.
+	///
+	/// Unlike [`read_pins`](Self::read_pins), this only reads the registers that hold bits of the pin:
+	/// one function select register, the level and event registers of its bank and the six detect registers.
+	pub fn read_pin(&self, index: usize) -> Result<PinInfo, Error> {
+		check_pin_index(index)?;
+
+		let bank = index / 32;
+		let registers = [
+			Register::fsel(index / 10),
+			Register::lev(bank),
+			Register::eds(bank),
+			Register::ren(bank),
+			Register::fen(bank),
+			Register::hen(bank),
+			Register::len(bank),
+			Register::aren(bank),
+			Register::afen(bank),
+		];
+
+		let mut state = GpioState::new();
+		for &reg in registers.iter() {
+			state.data_mut()[reg as usize / 4] = self.read_register(reg);
+		}
+		if self.soc.has_pull_readback() {
+			let reg = Register::puppdn(index / 16);
+			state.data_mut()[reg as usize / 4] = self.read_register(reg);
+		}
+
+		let mut info = state.pin_for(index, self.soc)?;
+		if !self.soc.has_pull_readback() {
+			info.pull_mode = self.pull_modes[index];
+		}
+		Ok(info)
+	}
+
+	/// Read the entire current GPIO state into an existing state object.
+	///
+	/// This avoids copying the state around, for programs that poll the state at a high rate.
+	/// Only the mapped part of the control block is read, the rest of the state is left as-is.
+	pub fn read_all_into(&self, state: &mut GpioState) {
+		self.read_words(0..CONTROL_BLOCK_SIZE / 4, state.data_mut());
+	}
+
+	/// Read a range of registers into an existing state object.
+	///
+	/// Registers outside of the range are left as-is.
+	/// For example, `gpio.read_block(Register::GPLEV0..=Register::GPEDS1, &mut state)`
+	/// only updates the level and event registers.
+	pub fn read_block(&self, registers: std::ops::RangeInclusive<Register>, state: &mut GpioState) {
+		let start = *registers.start() as usize / 4;
+		let end   = *registers.end() as usize / 4;
+		self.read_words(start..end + 1, state.data_mut());
+	}
+
+	fn read_words(&self, words: std::ops::Range<usize>, data: &mut [u32; 0x100]) {
+		let base = match self.mapping() {
+			Some(x) => x as *const u32,
+			None => {
+				// Without a mapping, only the known registers can be read.
+				for &reg in Register::ALL.iter().filter(|x| words.contains(&(**x as usize / 4))) {
+					data[reg as usize / 4] = self.read_register(reg);
+				}
+				return;
+			},
+		};
+
+		self.count_accesses(words.len(), 0);
+		for i in words {
+			data[i] = unsafe { base.wrapping_add(i).read_volatile() };
+		}
+		peripheral_barrier();
+	}
+
+	/// Read a value from a register.
+	pub fn read_register(&self, reg: Register) -> u32 {
+		self.count_accesses(1, 0);
+		if self.mapping().is_none() {
+			return self.backend.read_register(reg);
+		}
+		let value = unsafe { self.register_address(reg).read_volatile() };
+		peripheral_barrier();
+		value
+	}
+
+	/// Write a value to a register.
+	///
+	/// # Safety
+	/// The value is written to the hardware as-is, without any validation.
+	/// Writing the wrong value can damage connected hardware or interfere with the kernel.
+	pub unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		trace_write(reg, value);
+		self.record_restore(reg, value);
+		self.count_accesses(0, 1);
+		peripheral_barrier();
+		self.write_raw(reg, value)
+	}
+
+	/// Write a value to a register that is safe to write.
+	///
+	/// Only the set, clear and event status registers are accepted,
+	/// see [`Register::is_write_safe`].
+	/// Use [`write_register`](Self::write_register) for other registers.
+	pub fn write_register_checked(&mut self, reg: Register, value: u32) -> Result<(), Error> {
+		if !reg.is_write_safe() {
+			return Err(Error::new(ErrorKind::UnsafeRegister, format!("writing to {} is not safe", reg)));
+		}
+		self.check_writable()?;
+		unsafe { self.write_register(reg, value) };
+		Ok(())
+	}
+
+	/// Perform a bitwise AND on the contents of a register.
+	///
+	/// This is atomic with respect to other read-modify-write operations in the same process.
+	///
+	/// # Safety
+	/// This is a read-modify-write cycle, which is not atomic with respect to other processes or the kernel.
+	/// The same precautions as for [`write_register`](Self::write_register) apply.
+	pub unsafe fn and_register(&mut self, reg: Register, value: u32) {
+		self.modify_register(reg, |x| x & value)
+	}
+
+	/// Perform a bitwise OR on the contents of a register.
+	///
+	/// This is atomic with respect to other read-modify-write operations in the same process.
+	///
+	/// # Safety
+	/// This is a read-modify-write cycle, which is not atomic with respect to other processes or the kernel.
+	/// The same precautions as for [`write_register`](Self::write_register) apply.
+	pub unsafe fn or_register(&mut self, reg: Register, value: u32) {
+		self.modify_register(reg, |x| x | value)
+	}
+
+	/// Perform a bitwise XOR on the contents of a register.
+	///
+	/// This is atomic with respect to other read-modify-write operations in the same process.
+	///
+	/// # Safety
+	/// This is a read-modify-write cycle, which is not atomic with respect to other processes or the kernel.
+	/// The same precautions as for [`write_register`](Self::write_register) apply.
+	pub unsafe fn xor_register(&mut self, reg: Register, value: u32) {
+		self.modify_register(reg, |x| x ^ value)
+	}
+
+	/// Perform a read-modify-write cycle on a register.
+	///
+	/// The cycle holds a process wide lock, so concurrent cycles from other handles or threads can't lose updates.
+	/// The exclusive load/store instructions can not be used for this, since they are not supported on device memory.
+	pub(crate) unsafe fn modify_register(&mut self, reg: Register, modify: impl FnOnce(u32) -> u32) {
+		let _guard  = REGISTER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+		let old   = self.read_register(reg);
+		let value = modify(old);
+		trace_modify(reg, old, value);
+		self.record_restore(reg, value);
+		self.count_accesses(0, 1);
+		peripheral_barrier();
+		self.write_raw(reg, value);
+		peripheral_barrier();
+	}
+
+	/// Write a value to a register directly, or through the backend if the registers are not mapped.
+	#[inline]
+	unsafe fn write_raw(&mut self, reg: Register, value: u32) {
+		match self.mapping() {
+			Some(_) => self.register_address_mut(reg).write_volatile(value),
+			None    => self.backend.write_register(reg, value),
+		}
+	}
+
+	/// Record the original state of a register before it is written, if restore-on-drop is enabled.
+	fn record_restore(&mut self, reg: Register, value: u32) {
+		if let Some(mut log) = self.restore.take() {
+			log.record(reg, value, |reg| self.read_register(reg));
+			self.restore = Some(log);
+		}
+	}
+
+	/// Get the SoC family of the GPIO peripheral.
+	///
+	/// If the SoC could not be detected, the BCM2835 is assumed.
+	pub fn soc(&self) -> Soc {
+		self.soc
+	}
+
+	/// Read the pull up/down mode of a GPIO pin.
+	///
+	/// Returns `None` if the SoC has no readable pull up/down registers,
+	/// see [`Soc::has_pull_readback`].
+	pub fn read_pull_mode(&self, index: usize) -> Result<Option<PullMode>, Error> {
+		check_pin_index(index)?;
+		if !self.soc.has_pull_readback() {
+			return Ok(None);
+		}
+		let value = self.read_register(Register::puppdn(index / 16));
+		Ok(PullMode::from_bcm2711_bits(value >> (index % 16 * 2)))
+	}
+
+	/// Read the current level of a GPIO pin.
+	pub fn read_level(&self, index: usize) -> Result<Level, Error> {
+		check_pin_index(index)?;
+		Ok(self.level(index).into())
+	}
+
+	/// Wait until a GPIO pin reads a level.
+	///
+	/// This returns immediately if the pin is already at the level.
+	/// Unlike waiting for an edge with an [`EventListener`](crate::EventListener), this can not miss a level that was reached before the call.
+	///
+	/// The pin is polled in a busy loop at first, for fast handshakes.
+	/// After that the time between reads doubles up to a millisecond, so long waits hardly use the CPU.
+	/// If no timeout is given, this waits forever.
+	/// Returns a [`Timeout`](crate::ErrorKind::Timeout) error if the timeout expires first.
+	pub fn wait_for_level(&self, index: usize, level: impl Into<Level>, timeout: Option<std::time::Duration>) -> Result<(), Error> {
+		check_pin_index(index)?;
+		let level = level.into();
+		let start = std::time::Instant::now();
+		let deadline = timeout.map(|x| start + x);
+		let mut sleep = std::time::Duration::from_micros(1);
+
+		loop {
+			if self.level(index) == level.is_high() {
+				return Ok(());
+			}
+
+			let now = std::time::Instant::now();
+			if deadline.is_some_and(|x| now >= x) {
+				return Err(Error::new(ErrorKind::Timeout, format!("timed out waiting for pin {} to become {}", index, level)));
+			}
+			if now - start < WAIT_SPIN_TIME {
+				std::hint::spin_loop();
+				continue;
+			}
+
+			let remaining = deadline.map_or(sleep, |x| x - now);
+			std::thread::sleep(sleep.min(remaining));
+			sleep = (sleep * 2).min(WAIT_MAX_SLEEP);
+		}
+	}
+
+	/// Read the current level of a GPIO pin that is known to be valid.
+	pub(crate) fn level(&self, index: usize) -> bool {
+		let value = self.read_register(Register::lev(index / 32));
+		let value = value >> (index % 32);
+		value & 1 == 1
+	}
+
+	/// Read the current level of all GPIO pins.
+	///
+	/// Bit N of the returned value holds the level of pin N.
+	pub fn read_levels(&self) -> u64 {
+		match self.mapping() {
+			Some(_) => self.read_pin_bits(Register::GPLEV0, Register::GPLEV1),
+			None    => {
+				self.count_accesses(2, 0);
+				self.backend.read_levels()
+			},
+		}
+	}
+
+	/// Read the event detect status of all GPIO pins.
+	///
+	/// Bit N of the returned value is set if an event was detected on pin N.
+	pub fn read_events(&self) -> u64 {
+		self.read_pin_bits(Register::GPEDS0, Register::GPEDS1)
+	}
+
+	/// Read the levels and event detect status of all GPIO pins.
+	///
+	/// This only reads the four registers involved, which is much cheaper than [`read_all`](Self::read_all).
+	pub fn read_levels_and_events(&self) -> (u64, u64) {
+		(self.read_levels(), self.read_events())
+	}
+
+	/// Read the functions of all GPIO pins.
+	///
+	/// This only reads the six function select registers.
+	pub fn read_functions(&self) -> [PinFunction; 54] {
+		let mut registers = [0u32; 6];
+		for (i, value) in registers.iter_mut().enumerate() {
+			*value = self.read_register(Register::fsel(i));
+		}
+
+		let mut functions = [PinFunction::Input; 54];
+		for (pin, function) in functions.iter_mut().enumerate() {
+			let bits = registers[pin / 10] >> (pin % 10 * 3) & 0b111;
+			*function = PinFunction::from_bits(bits as u8);
+		}
+		functions
+	}
+
+	/// Read a pair of registers with one bit per pin.
+	fn read_pin_bits(&self, low: Register, high: Register) -> u64 {
+		let low  = u64::from(self.read_register(low));
+		let high = u64::from(self.read_register(high));
+		(low | high << 32) & ((1 << 54) - 1)
+	}
+
+	/// Read the current level of all GPIO pins as a pin mask of the high pins.
+	pub fn read_level_mask(&self) -> PinMask {
+		PinMask::from_bits(self.read_levels())
+	}
+
+	/// Set the function of a single GPIO pin.
+	///
+	/// This performs a read-modify-write of the function select register.
+	/// It is atomic with respect to other read-modify-write operations in the same process,
+	/// but not with respect to other processes changing pin functions.
+	///
+	/// The pin goes directly from the old function to the new one, without passing through input.
+	/// To switch to output without driving the old level of the output latch, use [`set_output`](Self::set_output).
+	///
+	/// Alternate functions are rejected if the backend does not support them, see [`Capabilities::alt_functions`].
+	pub fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error> {
+		check_pin_index(index)?;
+		self.check_function(function)?;
+		self.write_function(index, function);
+		Ok(())
+	}
+
+	/// Configure a GPIO pin as output with an initial level.
+	///
+	/// The level is set before the function, so the pin never drives the wrong level,
+	/// not even for the time between two register writes.
+	pub fn set_output(&mut self, index: usize, level: impl Into<Level>) -> Result<(), Error> {
+		check_pin_index(index)?;
+		self.check_writable()?;
+		self.write_level(index, level.into().is_high());
+		self.write_function(index, PinFunction::Output);
+		Ok(())
+	}
+
+	/// Configure a GPIO pin as output that starts low, see [`set_output`](Self::set_output).
+	pub fn set_output_low(&mut self, index: usize) -> Result<(), Error> {
+		self.set_output(index, Level::Low)
+	}
+
+	/// Configure a GPIO pin as output that starts high, see [`set_output`](Self::set_output).
+	pub fn set_output_high(&mut self, index: usize) -> Result<(), Error> {
+		self.set_output(index, Level::High)
+	}
+
+	/// Configure a GPIO pin as input with a pull up/down mode.
+	///
+	/// The pull mode is applied before the function, so a pin that was an output never floats in between.
+	///
+	/// # Safety
+	/// Changing the pull mode is not atomic, see [`GpioPullConfig::apply`](crate::GpioPullConfig::apply).
+	pub unsafe fn set_input(&mut self, index: usize, mode: PullMode) -> Result<(), Error> {
+		let mut config = crate::GpioPullConfig::new();
+		config.set_pull_mode(index, mode)?;
+		config.apply(self)?;
+		self.write_function(index, PinFunction::Input);
+		Ok(())
+	}
+
+	/// Configure a GPIO pin as input with the pull up enabled, see [`set_input`](Self::set_input).
+	///
+	/// # Safety
+	/// Changing the pull mode is not atomic, see [`GpioPullConfig::apply`](crate::GpioPullConfig::apply).
+	pub unsafe fn set_input_pullup(&mut self, index: usize) -> Result<(), Error> {
+		self.set_input(index, PullMode::PullUp)
+	}
+
+	/// Configure a GPIO pin as input with the pull down enabled, see [`set_input`](Self::set_input).
+	///
+	/// # Safety
+	/// Changing the pull mode is not atomic, see [`GpioPullConfig::apply`](crate::GpioPullConfig::apply).
+	pub unsafe fn set_input_pulldown(&mut self, index: usize) -> Result<(), Error> {
+		self.set_input(index, PullMode::PullDown)
+	}
+
+	/// Check that the backend does not ignore writes, see [`Capabilities::read_only`].
+	pub(crate) fn check_writable(&self) -> Result<(), Error> {
+		match self.capabilities().read_only {
+			true  => Err(Error::new(ErrorKind::Unsupported, format!("the {} backend is read-only", self.backend.name()))),
+			false => Ok(()),
+		}
+	}
+
+	/// Check that the backend can set a pin function, see [`Capabilities::alt_functions`].
+	pub(crate) fn check_function(&self, function: PinFunction) -> Result<(), Error> {
+		self.check_writable()?;
+		match function {
+			PinFunction::Input | PinFunction::Output => Ok(()),
+			_ if self.capabilities().alt_functions => Ok(()),
+			_ => Err(Error::new(ErrorKind::Unsupported, format!("the {} backend does not support alternate functions", self.backend.name()))),
+		}
+	}
+
+	/// Set the function of a GPIO pin that is known to be valid.
+	pub(crate) fn write_function(&mut self, index: usize, function: PinFunction) {
+		let register = Register::fsel(index / 10);
+		let shift    = (index % 10) * 3;
+		let bits     = u32::from(function.to_bits()) << shift;
+		unsafe { self.modify_register(register, |x| x & !(0b111 << shift) | bits) }
+	}
+
+	/// Atomically set the level of a single GPIO pin.
+	///
+	/// The level can be given as a [`Level`] or a `bool`, where `true` is high.
+	pub fn set_level(&mut self, index: usize, value: impl Into<Level>) -> Result<(), Error> {
+		check_pin_index(index)?;
+		self.check_writable()?;
+		self.write_level(index, value.into().is_high());
+		Ok(())
+	}
+
+	/// Drive a pin to a level for a duration, and then restore its previous level.
+	///
+	/// The pin must already be configured as output.
+	/// If it is already at the given level, it simply stays there for the duration.
+	///
+	/// The pulse is timed with [`delay`](Self::delay), so it is never shorter than `width`.
+	/// With the system timer it is at most about a microsecond longer on an idle system,
+	/// but preemption during the pulse makes it longer by up to a scheduler time slice,
+	/// unless the thread runs with a real-time priority.
+	pub fn pulse(&mut self, index: usize, level: impl Into<Level>, width: std::time::Duration) -> Result<(), Error> {
+		check_pin_index(index)?;
+		self.check_writable()?;
+		let previous = self.level(index);
+		self.write_level(index, level.into().is_high());
+		self.delay(width);
+		self.write_level(index, previous);
+		Ok(())
+	}
+
+	/// Atomically set the level of a GPIO pin that is known to be valid.
+	///
+	/// This skips the index check, for use in timing sensitive loops.
+	pub(crate) fn write_level(&mut self, index: usize, value: bool) {
+		let bits = 1 << (index % 32);
+		let register = match value {
+			true  => Register::set(index / 32),
+			false => Register::clr(index / 32),
+		};
+		unsafe { self.write_register(register, bits) }
+	}
+
+	pub(crate) fn register_address(&self, reg: Register) -> *const u32 {
+		self.control_block.wrapping_add(reg as usize) as *const u32
+	}
+
+	pub(crate) fn register_address_mut(&self, reg: Register) -> *mut u32 {
+		self.control_block.wrapping_add(reg as usize) as *mut u32
+	}
+}
+
+impl Drop for Gpio {
+	fn drop(&mut self) {
+		if let Some(log) = self.restore.take() {
+			log.restore(self);
+		}
+	}
+}
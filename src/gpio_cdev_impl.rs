@@ -0,0 +1,49 @@
+//! Conversions between the types of this crate and the types of gpio-cdev.
+//!
+//! The character device only knows the direction of a line,
+//! so alternate functions can not be converted.
+
+use gpio_cdev::{EventType, LineDirection};
+use std::convert::TryFrom;
+
+use crate::{Edge, Error, ErrorKind, PinFunction};
+
+impl From<LineDirection> for PinFunction {
+	fn from(direction: LineDirection) -> Self {
+		match direction {
+			LineDirection::In  => PinFunction::Input,
+			LineDirection::Out => PinFunction::Output,
+		}
+	}
+}
+
+/// The conversion fails for alternate functions.
+impl TryFrom<PinFunction> for LineDirection {
+	type Error = Error;
+
+	fn try_from(function: PinFunction) -> Result<Self, Self::Error> {
+		match function {
+			PinFunction::Input  => Ok(LineDirection::In),
+			PinFunction::Output => Ok(LineDirection::Out),
+			_ => Err(Error::new(ErrorKind::Unsupported, format!("pin function {:?} has no line direction", function))),
+		}
+	}
+}
+
+impl From<EventType> for Edge {
+	fn from(event: EventType) -> Self {
+		match event {
+			EventType::RisingEdge  => Edge::Rise,
+			EventType::FallingEdge => Edge::Fall,
+		}
+	}
+}
+
+impl From<Edge> for EventType {
+	fn from(edge: Edge) -> Self {
+		match edge {
+			Edge::Rise => EventType::RisingEdge,
+			Edge::Fall => EventType::FallingEdge,
+		}
+	}
+}
@@ -0,0 +1,206 @@
+use std::net::SocketAddr;
+use std::sync::{Mutex, MutexGuard};
+use tonic::{Request, Response, Status};
+
+use crate::{Capabilities, Error, ErrorKind, Gpio, GpioBackend, Register, Soc};
+
+#[allow(clippy::all)]
+mod proto {
+	tonic::include_proto!("bcm283x_gpio");
+}
+
+use proto::gpio_client::GpioClient;
+use proto::gpio_server::GpioServer;
+
+/// A backend that drives the GPIO of a remote machine through a [`GrpcServer`].
+///
+/// Every register access is a request to the server, so this is much slower than a local backend.
+/// The backend runs its own single threaded runtime, so it can not be used from within an async runtime.
+///
+/// The register functions of [`GpioBackend`] can not report errors.
+/// Failed reads return zero and failed writes are lost.
+/// With the `log` feature, failed requests are logged as warnings.
+pub struct GrpcBackend {
+	name: String,
+	soc: Soc,
+	capabilities: Capabilities,
+	runtime: tokio::runtime::Runtime,
+	client: GpioClient<tonic::transport::Channel>,
+}
+
+impl GrpcBackend {
+	/// Connect to a server, like `http://raspberrypi:50051`.
+	pub fn connect(address: &str) -> Result<Self, Error> {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.map_err(|e| Error::from_io(ErrorKind::Io, "failed to start the gRPC runtime", e))?;
+		let endpoint = tonic::transport::Endpoint::from_shared(address.to_string())
+			.map_err(|e| Error::new(ErrorKind::InvalidArgument, format!("invalid gRPC address: {}: {}", address, e)))?;
+		let channel = runtime.block_on(endpoint.connect())
+			.map_err(|e| Error::new(ErrorKind::PeripheralNotFound, format!("failed to connect to {}: {}", address, e)))?;
+		let mut client = GpioClient::new(channel);
+		let info = runtime.block_on(client.get_info(proto::Empty {}))
+			.map_err(|e| Error::new(ErrorKind::Io, format!("failed to get the GPIO info from {}: {}", address, e.message())))?
+			.into_inner();
+
+		Ok(Self {
+			name: format!("{} on {}", info.name, address),
+			soc: match info.soc.as_str() {
+				"bcm2711" => Soc::Bcm2711,
+				_         => Soc::Bcm2835,
+			},
+			capabilities: Capabilities {
+				registers:     info.registers,
+				alt_functions: info.alt_functions,
+				pull:          info.pull,
+				events:        info.events,
+				read_only:     info.read_only,
+			},
+			runtime,
+			client,
+		})
+	}
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn warn_failed(message: std::fmt::Arguments, status: &Status) {
+	#[cfg(feature = "log")]
+	log::warn!(target: "bcm283x_linux_gpio::grpc", "{}: {}", message, status.message());
+}
+
+impl GpioBackend for GrpcBackend {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		self.capabilities
+	}
+
+	fn soc(&self) -> Soc {
+		self.soc
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		let request = proto::ReadRegisterRequest { register: reg.name().to_string() };
+		match self.runtime.block_on(self.client.clone().read_register(request)) {
+			Ok(x) => x.into_inner().value,
+			Err(e) => {
+				warn_failed(format_args!("failed to read {}", reg), &e);
+				0
+			},
+		}
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		let request = proto::WriteRegisterRequest { register: reg.name().to_string(), value };
+		if let Err(e) = self.runtime.block_on(self.client.write_register(request)) {
+			warn_failed(format_args!("failed to write {}", reg), &e);
+		}
+	}
+
+	fn read_levels(&self) -> u64 {
+		match self.runtime.block_on(self.client.clone().read_levels(proto::Empty {})) {
+			Ok(x) => x.into_inner().levels,
+			Err(e) => {
+				warn_failed(format_args!("failed to read the pin levels"), &e);
+				0
+			},
+		}
+	}
+
+	fn write_levels(&mut self, high: u64, low: u64) {
+		let request = proto::WriteLevelsRequest { high, low };
+		if let Err(e) = self.runtime.block_on(self.client.write_levels(request)) {
+			warn_failed(format_args!("failed to write the pin levels"), &e);
+		}
+	}
+}
+
+/// A gRPC server giving remote [`GrpcBackend`] clients access to the GPIO registers.
+///
+/// The service is described by `proto/gpio.proto` in the crate source.
+/// Clients can write any register, so only serve on trusted networks.
+pub struct GrpcServer {
+	gpio: Mutex<Gpio>,
+}
+
+impl GrpcServer {
+	/// Create a server for a GPIO peripheral.
+	pub fn new(gpio: Gpio) -> Self {
+		Self { gpio: Mutex::new(gpio) }
+	}
+
+	/// Serve requests on an address until an error occurs.
+	///
+	/// This blocks the current thread, and can not be used from within an async runtime.
+	pub fn serve(self, address: SocketAddr) -> Result<(), Error> {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.map_err(|e| Error::from_io(ErrorKind::Io, "failed to start the gRPC runtime", e))?;
+		let server = tonic::transport::Server::builder()
+			.add_service(GpioServer::new(self))
+			.serve(address);
+		runtime.block_on(server)
+			.map_err(|e| Error::new(ErrorKind::Io, format!("failed to serve gRPC on {}: {}", address, e)))
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Gpio> {
+		self.gpio.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+fn invalid_argument(error: Error) -> Status {
+	Status::invalid_argument(error.to_string())
+}
+
+#[tonic::async_trait]
+impl proto::gpio_server::Gpio for GrpcServer {
+	async fn get_info(&self, _request: Request<proto::Empty>) -> Result<Response<proto::Info>, Status> {
+		let gpio = self.lock();
+		let capabilities = gpio.capabilities();
+		Ok(Response::new(proto::Info {
+			name:          gpio.backend().name().to_string(),
+			soc:           format!("{:?}", gpio.soc()).to_lowercase(),
+			registers:     capabilities.registers,
+			alt_functions: capabilities.alt_functions,
+			pull:          capabilities.pull,
+			events:        capabilities.events,
+			read_only:     capabilities.read_only,
+		}))
+	}
+
+	async fn read_register(&self, request: Request<proto::ReadRegisterRequest>) -> Result<Response<proto::RegisterValue>, Status> {
+		let reg: Register = request.get_ref().register.parse().map_err(invalid_argument)?;
+		Ok(Response::new(proto::RegisterValue { value: self.lock().read_register(reg) }))
+	}
+
+	async fn write_register(&self, request: Request<proto::WriteRegisterRequest>) -> Result<Response<proto::Empty>, Status> {
+		let reg: Register = request.get_ref().register.parse().map_err(invalid_argument)?;
+		// The client takes the same responsibility as for a local Gpio::write_register.
+		unsafe { self.lock().write_register(reg, request.get_ref().value) };
+		Ok(Response::new(proto::Empty {}))
+	}
+
+	async fn read_levels(&self, _request: Request<proto::Empty>) -> Result<Response<proto::Levels>, Status> {
+		Ok(Response::new(proto::Levels { levels: self.lock().read_levels() }))
+	}
+
+	async fn write_levels(&self, request: Request<proto::WriteLevelsRequest>) -> Result<Response<proto::Empty>, Status> {
+		let proto::WriteLevelsRequest { high, low } = request.into_inner();
+		let mut gpio = self.lock();
+		for (reg, bits) in [
+			(Register::GPSET0, high as u32),
+			(Register::GPSET1, (high >> 32) as u32),
+			(Register::GPCLR0, low as u32),
+			(Register::GPCLR1, (low >> 32) as u32),
+		] {
+			if bits != 0 {
+				gpio.write_register_checked(reg, bits).map_err(|e| Status::internal(e.to_string()))?;
+			}
+		}
+		Ok(Response::new(proto::Empty {}))
+	}
+}
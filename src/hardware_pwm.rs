@@ -0,0 +1,156 @@
+use crate::{Clock, Error, ErrorKind, Gpio, PinFunction};
+
+/// Offset of the PWM control block from the GPIO control block.
+const PWM_OFFSET : i64 = 0x20C000 - 0x200000;
+const PWM_SIZE : usize = 0x00000028;
+
+/// The control register, shared by both channels.
+const CTL : usize = 0x00;
+
+/// The register offsets of the range and data registers of channel 1 and 2.
+const CHANNEL_REGISTERS : [(usize, usize); 2] = [(0x10, 0x14), (0x20, 0x24)];
+
+/// The enable and mark-space mode bits of channel 1 and 2 in the control register.
+const CHANNEL_BITS : [(u32, u32); 2] = [(1 << 0, 1 << 7), (1 << 8, 1 << 15)];
+
+/// The pins that can output a PWM channel, with the channel index and pin function.
+const PWM_PINS : [(usize, usize, PinFunction); 7] = [
+	(12, 0, PinFunction::Alt0),
+	(18, 0, PinFunction::Alt5),
+	(40, 0, PinFunction::Alt0),
+	(13, 1, PinFunction::Alt0),
+	(19, 1, PinFunction::Alt5),
+	(41, 1, PinFunction::Alt0),
+	(45, 1, PinFunction::Alt0),
+];
+
+/// PWM on a single pin, generated by the PWM peripheral.
+///
+/// Unlike [`SoftPwm`](crate::SoftPwm), the signal has no jitter and costs no CPU time,
+/// but it is only available on a few pins, see [`is_capable`](Self::is_capable).
+/// Both channels share the PWM clock, which is reconfigured when a channel is started.
+/// Note that the firmware or kernel may use the PWM peripheral for analog audio on some boards.
+pub struct HardwarePwm {
+	control_block: *mut std::ffi::c_void,
+	pin: usize,
+	channel: usize,
+	clock_frequency: f64,
+	range: u32,
+}
+
+// The mapping is owned by the handle, and all writes go through a mutable reference.
+unsafe impl Send for HardwarePwm {}
+
+impl HardwarePwm {
+	/// Check if a pin can output a PWM channel.
+	pub fn is_capable(pin: usize) -> bool {
+		PWM_PINS.iter().any(|x| x.0 == pin)
+	}
+
+	/// Get the pin function that connects a pin to its PWM channel, if it has one.
+	pub fn function(pin: usize) -> Option<PinFunction> {
+		PWM_PINS.iter().find(|x| x.0 == pin).map(|x| x.2)
+	}
+
+	/// Start generating a PWM signal on a pin.
+	///
+	/// The duty cycle is given as a fraction in the range [0, 1].
+	/// The pin is switched to the PWM function.
+	///
+	/// This maps a portion of /dev/mem just like [`Gpio::new`](crate::Gpio::new),
+	/// and may fail for the same reasons.
+	pub fn start(gpio: &mut Gpio, pin: usize, frequency: f64, duty: f64) -> Result<Self, Error> {
+		let (channel, function) = channel_for_pin(pin)?;
+		gpio.check_function(function)?;
+		let address       = crate::read_gpio_address()? + PWM_OFFSET;
+		let control_block = crate::map_dev_mem("PWM", address, PWM_SIZE)?;
+
+		let mut pwm = Self {
+			control_block,
+			pin,
+			channel,
+			clock_frequency: 0.0,
+			range: 0,
+		};
+
+		let (enable, mark_space) = CHANNEL_BITS[channel];
+		let control = pwm.read(CTL);
+		pwm.write(CTL, control & !enable);
+		pwm.clock_frequency = Clock::new()?.start_pwm_clock(gpio.soc());
+		pwm.set(frequency, duty)?;
+		pwm.write(CTL, pwm.read(CTL) | enable | mark_space);
+
+		gpio.write_function(pin, function);
+		Ok(pwm)
+	}
+
+	/// Get the pin the PWM signal is generated on.
+	pub fn pin(&self) -> usize {
+		self.pin
+	}
+
+	/// Get the actual frequency of the PWM signal.
+	///
+	/// This differs slightly from the requested frequency, since the period is a whole number of PWM clock cycles.
+	pub fn frequency(&self) -> f64 {
+		self.clock_frequency / f64::from(self.range)
+	}
+
+	/// Change the frequency and duty cycle of the PWM signal.
+	pub fn set(&mut self, frequency: f64, duty: f64) -> Result<(), Error> {
+		if !frequency.is_finite() || frequency <= 0.0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid PWM frequency: {}", frequency)));
+		}
+		if !(0.0..=1.0).contains(&duty) {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("PWM duty cycle must be in the range [0, 1], got {}", duty)));
+		}
+
+		let range = (self.clock_frequency / frequency).round();
+		if !(2.0..=f64::from(u32::MAX)).contains(&range) {
+			let min = self.clock_frequency / f64::from(u32::MAX);
+			let max = self.clock_frequency / 2.0;
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("PWM frequency out of range: {} Hz, expected {:.3} Hz to {:.0} Hz", frequency, min, max)));
+		}
+
+		self.range = range as u32;
+		let (range_register, data_register) = CHANNEL_REGISTERS[self.channel];
+		self.write(range_register, self.range);
+		self.write(data_register, (range * duty).round() as u32);
+		Ok(())
+	}
+
+	/// Stop the PWM signal.
+	///
+	/// The pin function is left unchanged.
+	pub fn stop(self) {}
+
+	fn read(&self, offset: usize) -> u32 {
+		let value = unsafe { (self.control_block.wrapping_add(offset) as *const u32).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	fn write(&mut self, offset: usize, value: u32) {
+		crate::peripheral_barrier();
+		unsafe { (self.control_block.wrapping_add(offset) as *mut u32).write_volatile(value) }
+	}
+}
+
+impl Drop for HardwarePwm {
+	fn drop(&mut self) {
+		let (enable, _) = CHANNEL_BITS[self.channel];
+		self.write(CTL, self.read(CTL) & !enable);
+		unsafe {
+			crate::procfs::unmap_memory(self.control_block, PWM_SIZE);
+		}
+	}
+}
+
+/// Get the PWM channel index and pin function for a pin.
+fn channel_for_pin(pin: usize) -> Result<(usize, PinFunction), Error> {
+	crate::check_pin_index(pin)?;
+	PWM_PINS.iter()
+		.find(|x| x.0 == pin)
+		.map(|&(_, channel, function)| (channel, function))
+		.ok_or_else(|| Error::new(ErrorKind::InvalidArgument, format!("pin {} can not output a PWM channel, use pin 12, 13, 18, 19, 40, 41 or 45", pin)))
+}
@@ -0,0 +1,361 @@
+use std::path::Path;
+
+use crate::{Error, ErrorKind, Gpio, GpioConfig, GpioPullConfig, PinFunction, PinMask, PullMode, SoftI2c};
+
+/// The pins of the ID EEPROM bus: ID_SD and ID_SC.
+const ID_SD : usize = 0;
+const ID_SC : usize = 1;
+
+/// The I2C address of the ID EEPROM.
+const EEPROM_ADDRESS : u8 = 0x50;
+
+/// The clock frequency used to read the ID EEPROM.
+const EEPROM_FREQUENCY : f64 = 100e3;
+
+/// The largest EEPROM that is read, a 24C512.
+const EEPROM_MAX_SIZE : usize = 0x10000;
+
+const HEADER_SIZE      : usize = 12;
+const ATOM_HEADER_SIZE : usize = 8;
+
+const ATOM_VENDOR_INFO : u16 = 0x0001;
+const ATOM_GPIO_MAP    : u16 = 0x0002;
+
+/// The node in the device tree where the firmware publishes the HAT information.
+const HAT_NODE : &str = "/proc/device-tree/hat";
+
+/// The number of pins on the 40 pin header that a HAT can configure: GPIO 0 to 27.
+pub const HAT_PINS : usize = 28;
+
+/// Information about a HAT (Hardware Attached on Top).
+///
+/// A HAT carries an ID EEPROM on the ID_SD and ID_SC pins (GPIO 0 and 1),
+/// which describes the board and the GPIO pins it uses.
+/// The firmware reads it at boot and publishes the vendor information in the device tree,
+/// but the GPIO map is only available from the EEPROM itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Hat {
+	/// The UUID of the board, formatted like `01234567-89ab-cdef-0123-456789abcdef`.
+	pub uuid: String,
+	pub product_id: u16,
+	pub product_version: u16,
+	pub vendor: String,
+	pub product: String,
+	/// The GPIO map of the HAT, if it was read from the EEPROM and it has one.
+	pub gpio_map: Option<HatGpioMap>,
+}
+
+/// The GPIO pins a HAT uses, from the GPIO map atom of the EEPROM.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HatGpioMap {
+	/// The drive strength of the pins: 0 to leave it as-is, or 1-8 for 2-16 mA.
+	pub drive: u8,
+	/// The slew rate limiting: 0 to leave it as-is, 1 for limited and 2 for unlimited.
+	pub slew: u8,
+	/// The input hysteresis: 0 to leave it as-is, 1 for disabled and 2 for enabled.
+	pub hysteresis: u8,
+	/// Whether the HAT back-powers the Raspberry Pi: 0 for no, 1 for 1.3 A and 2 for 2 A.
+	pub back_power: u8,
+	/// The pins used by the HAT, indexed by GPIO number.
+	pub pins: [Option<HatPin>; HAT_PINS],
+}
+
+/// The configuration of a single pin in a [`HatGpioMap`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct HatPin {
+	pub function: PinFunction,
+	/// The pull mode of the pin, or `None` to leave it as-is.
+	pub pull: Option<PullMode>,
+}
+
+impl Hat {
+	/// Read the vendor information of the HAT from the device tree.
+	///
+	/// Returns `None` if no HAT was detected by the firmware at boot.
+	/// The device tree does not contain the GPIO map, see [`read_eeprom`](Self::read_eeprom).
+	pub fn read_device_tree() -> Result<Option<Self>, Error> {
+		let node = Path::new(HAT_NODE);
+		if !node.is_dir() {
+			return Ok(None);
+		}
+
+		let read_string = |name: &str| -> Result<String, Error> {
+			let data = crate::read_all(crate::open(node.join(name))?)?;
+			let data = data.strip_suffix(&[0]).unwrap_or(&data);
+			Ok(String::from_utf8_lossy(data).into_owned())
+		};
+		let read_number = |name: &str| -> Result<u16, Error> {
+			let value = read_string(name)?;
+			let parsed = match value.strip_prefix("0x") {
+				Some(hex) => u16::from_str_radix(hex, 16),
+				None      => value.parse(),
+			};
+			parsed.map_err(|_| Error::new(ErrorKind::DeviceTree, format!("invalid {} in {}: {:?}", name, HAT_NODE, value)))
+		};
+
+		Ok(Some(Self {
+			uuid: read_string("uuid")?,
+			product_id: read_number("product_id")?,
+			product_version: read_number("product_ver")?,
+			vendor: read_string("vendor")?,
+			product: read_string("product")?,
+			gpio_map: None,
+		}))
+	}
+
+	/// Read and parse the ID EEPROM of the HAT over the ID_SD and ID_SC pins.
+	///
+	/// The pins are used as a bit-banged I2C bus, and left as inputs afterwards.
+	/// This fails with a [`NoResponse`](ErrorKind::NoResponse) error if no HAT is mounted.
+	pub fn read_eeprom(gpio: &mut Gpio) -> Result<Self, Error> {
+		let mut bus = SoftI2c::new(gpio, ID_SD, ID_SC, EEPROM_FREQUENCY)?;
+		let mut header = [0; HEADER_SIZE];
+		read_eeprom_at(&mut bus, 0, &mut header)?;
+		let length = parse_header(&header)?;
+
+		let mut data = vec![0; length];
+		read_eeprom_at(&mut bus, 0, &mut data)?;
+		Self::parse_eeprom(&data)
+	}
+
+	/// Parse the contents of a HAT ID EEPROM.
+	///
+	/// The EEPROM must contain a vendor info atom.
+	/// The CRC of every atom is checked, and unknown atoms are ignored.
+	pub fn parse_eeprom(data: &[u8]) -> Result<Self, Error> {
+		let length = parse_header(data)?;
+		let atom_count = u16::from_le_bytes([data[6], data[7]]);
+		let data = data.get(..length).ok_or_else(|| invalid(format!("EEPROM is truncated: expected {} bytes, got {}", length, data.len())))?;
+
+		let mut hat = None;
+		let mut gpio_map = None;
+		let mut offset = HEADER_SIZE;
+		for i in 0..atom_count {
+			let header = data.get(offset..offset + ATOM_HEADER_SIZE).ok_or_else(|| invalid(format!("atom {} is truncated", i)))?;
+			let kind   = u16::from_le_bytes([header[0], header[1]]);
+			let size   = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+			if size < 2 {
+				return Err(invalid(format!("atom {} is too small", i)));
+			}
+			let end  = offset.checked_add(ATOM_HEADER_SIZE + size).filter(|&x| x <= data.len()).ok_or_else(|| invalid(format!("atom {} is truncated", i)))?;
+			let crc  = u16::from_le_bytes([data[end - 2], data[end - 1]]);
+			if crc16(&data[offset..end - 2]) != crc {
+				return Err(invalid(format!("atom {} has an invalid CRC", i)));
+			}
+
+			let body = &data[offset + ATOM_HEADER_SIZE..end - 2];
+			match kind {
+				ATOM_VENDOR_INFO => hat = Some(parse_vendor_info(body)?),
+				ATOM_GPIO_MAP    => gpio_map = Some(parse_gpio_map(body)?),
+				_ => (),
+			}
+			offset = end;
+		}
+
+		let mut hat = hat.ok_or_else(|| invalid("EEPROM has no vendor info atom"))?;
+		hat.gpio_map = gpio_map;
+		Ok(hat)
+	}
+}
+
+impl HatGpioMap {
+	/// Get the pins used by the HAT.
+	///
+	/// Tools can refuse to reconfigure these pins while the HAT is mounted.
+	pub fn used_pins(&self) -> PinMask {
+		let pins = self.pins.iter().enumerate().filter(|(_, pin)| pin.is_some()).map(|(index, _)| index);
+		PinMask::try_from_pins(pins).unwrap_or_else(|e| unreachable!("{}", e))
+	}
+
+	/// Get a configuration that sets the functions declared by the HAT.
+	pub fn gpio_config(&self) -> GpioConfig {
+		let mut config = GpioConfig::new();
+		for (index, pin) in self.pins.iter().enumerate() {
+			if let Some(pin) = pin {
+				config.set_function(index, pin.function).unwrap_or_else(|e| unreachable!("{}", e));
+			}
+		}
+		config
+	}
+
+	/// Get a configuration that sets the pull modes declared by the HAT.
+	pub fn pull_config(&self) -> GpioPullConfig {
+		let mut config = GpioPullConfig::new();
+		for (index, pin) in self.pins.iter().enumerate() {
+			if let Some(mode) = pin.and_then(|x| x.pull) {
+				config.set_pull_mode(index, mode).unwrap_or_else(|e| unreachable!("{}", e));
+			}
+		}
+		config
+	}
+
+	/// Apply the functions and pull modes declared by the HAT.
+	///
+	/// # Safety
+	/// Changing the pull modes is not atomic, see [`GpioPullConfig::apply`].
+	pub unsafe fn apply(&self, gpio: &mut Gpio) -> Result<(), Error> {
+		self.gpio_config().apply(gpio)?;
+		self.pull_config().apply(gpio)
+	}
+}
+
+/// Read from the EEPROM, which uses 16 bit addresses.
+fn read_eeprom_at(bus: &mut SoftI2c, address: u16, buffer: &mut [u8]) -> Result<(), Error> {
+	bus.write(EEPROM_ADDRESS, &address.to_be_bytes())?;
+	bus.read(EEPROM_ADDRESS, buffer)
+}
+
+/// Check the EEPROM header and get the total length of the EEPROM data.
+fn parse_header(data: &[u8]) -> Result<usize, Error> {
+	let header = data.get(..HEADER_SIZE).ok_or_else(|| invalid("EEPROM header is truncated"))?;
+	if &header[0..4] != b"R-Pi" {
+		return Err(invalid("EEPROM has no HAT signature"));
+	}
+	if header[4] != 1 {
+		return Err(invalid(format!("unsupported EEPROM format version: {}", header[4])));
+	}
+	let length = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+	if !(HEADER_SIZE..=EEPROM_MAX_SIZE).contains(&length) {
+		return Err(invalid(format!("invalid EEPROM length: {}", length)));
+	}
+	Ok(length)
+}
+
+fn parse_vendor_info(data: &[u8]) -> Result<Hat, Error> {
+	if data.len() < 22 {
+		return Err(invalid("vendor info atom is truncated"));
+	}
+	let vendor_len  = usize::from(data[20]);
+	let product_len = usize::from(data[21]);
+	let strings = data.get(22..22 + vendor_len + product_len).ok_or_else(|| invalid("vendor info atom is truncated"))?;
+
+	// The UUID is stored as little-endian 32 bit words, starting with the least significant word.
+	let mut uuid = [0; 16];
+	for (i, byte) in uuid.iter_mut().enumerate() {
+		*byte = data[15 - i];
+	}
+	let uuid = format!(
+		"{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+		u32::from_be_bytes([uuid[0], uuid[1], uuid[2], uuid[3]]),
+		u16::from_be_bytes([uuid[4], uuid[5]]),
+		u16::from_be_bytes([uuid[6], uuid[7]]),
+		u16::from_be_bytes([uuid[8], uuid[9]]),
+		uuid[10..].iter().fold(0u64, |acc, &x| acc << 8 | u64::from(x)),
+	);
+
+	Ok(Hat {
+		uuid,
+		product_id: u16::from_le_bytes([data[16], data[17]]),
+		product_version: u16::from_le_bytes([data[18], data[19]]),
+		vendor: String::from_utf8_lossy(&strings[..vendor_len]).into_owned(),
+		product: String::from_utf8_lossy(&strings[vendor_len..]).into_owned(),
+		gpio_map: None,
+	})
+}
+
+fn parse_gpio_map(data: &[u8]) -> Result<HatGpioMap, Error> {
+	if data.len() < 2 + HAT_PINS {
+		return Err(invalid("GPIO map atom is truncated"));
+	}
+
+	let mut pins = [None; HAT_PINS];
+	for (pin, &value) in pins.iter_mut().zip(&data[2..]) {
+		if value & 0x80 == 0 {
+			continue;
+		}
+		*pin = Some(HatPin {
+			function: PinFunction::from_bits(value),
+			pull: match value >> 5 & 0b11 {
+				1 => Some(PullMode::PullUp),
+				2 => Some(PullMode::PullDown),
+				3 => Some(PullMode::Float),
+				_ => None,
+			},
+		});
+	}
+
+	Ok(HatGpioMap {
+		drive: data[0] & 0x0F,
+		slew: data[0] >> 4 & 0b11,
+		hysteresis: data[0] >> 6 & 0b11,
+		back_power: data[1] & 0b11,
+		pins,
+	})
+}
+
+/// The CRC-16 of the EEPROM atoms, with polynomial 0x8005 and reflected input and output.
+fn crc16(data: &[u8]) -> u16 {
+	let mut crc = 0u16;
+	for &byte in data {
+		crc ^= u16::from(byte);
+		for _ in 0..8 {
+			crc = match crc & 1 {
+				1 => crc >> 1 ^ 0xA001,
+				_ => crc >> 1,
+			};
+		}
+	}
+	crc
+}
+
+fn invalid(message: impl std::fmt::Display) -> Error {
+	Error::new(ErrorKind::Protocol, format!("invalid HAT EEPROM: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// An EEPROM with a vendor info atom and a GPIO map atom.
+	///
+	/// The GPIO map uses pin 4 as ALT0 with the pull down, pin 17 as output and pin 22 as input with the pull up.
+	const EEPROM : [u8; 97] = [
+		0x52, 0x2D, 0x50, 0x69, 0x01, 0x00, 0x02, 0x00, 0x61, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+		0x25, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B,
+		0x0C, 0x0D, 0x0E, 0x0F, 0x34, 0x12, 0x02, 0x00, 0x04, 0x09, 0x41, 0x43, 0x4D, 0x45, 0x52, 0x65,
+		0x6C, 0x61, 0x79, 0x20, 0x48, 0x41, 0x54, 0x63, 0x35, 0x02, 0x00, 0x01, 0x00, 0x20, 0x00, 0x00,
+		0x00, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x00, 0x81, 0x00, 0x00, 0x00, 0x00, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x61,
+		0xBC,
+	];
+
+	#[test]
+	fn crc() {
+		// The check value of CRC-16/ARC.
+		assert_eq!(crc16(b"123456789"), 0xBB3D);
+		assert_eq!(crc16(b""), 0);
+	}
+
+	#[test]
+	fn parse() {
+		let hat = Hat::parse_eeprom(&EEPROM).unwrap();
+		assert_eq!(hat.uuid, "0f0e0d0c-0b0a-0908-0706-050403020100");
+		assert_eq!(hat.product_id, 0x1234);
+		assert_eq!(hat.product_version, 2);
+		assert_eq!(hat.vendor, "ACME");
+		assert_eq!(hat.product, "Relay HAT");
+
+		let map = hat.gpio_map.unwrap();
+		assert_eq!((map.drive, map.slew, map.hysteresis, map.back_power), (2, 1, 0, 0));
+		assert_eq!(map.pins[4], Some(HatPin { function: PinFunction::Alt0, pull: Some(PullMode::PullDown) }));
+		assert_eq!(map.pins[17], Some(HatPin { function: PinFunction::Output, pull: None }));
+		assert_eq!(map.pins[22], Some(HatPin { function: PinFunction::Input, pull: Some(PullMode::PullUp) }));
+		assert_eq!(map.used_pins(), PinMask::try_from_pins([4, 17, 22].iter().copied()).unwrap());
+		assert_eq!(map.gpio_config().function[17], Some(PinFunction::Output));
+		assert_eq!(map.pull_config().pull_mode[22], Some(PullMode::PullUp));
+	}
+
+	#[test]
+	fn parse_invalid() {
+		let mut data = EEPROM;
+		data[0] = b'X';
+		assert_eq!(Hat::parse_eeprom(&data).unwrap_err().kind(), ErrorKind::Protocol);
+
+		// A corrupted byte in the vendor string fails the CRC check of the atom.
+		let mut data = EEPROM;
+		data[45] ^= 0x20;
+		assert!(Hat::parse_eeprom(&data).unwrap_err().to_string().contains("atom 0 has an invalid CRC"));
+
+		assert!(Hat::parse_eeprom(&EEPROM[..90]).unwrap_err().to_string().contains("truncated"));
+	}
+}
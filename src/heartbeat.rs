@@ -0,0 +1,150 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, Gpio};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+struct Shared {
+	/// The time of the last feed, in nanoseconds since `epoch`.
+	fed: AtomicU64,
+
+	/// The reference time for `fed`.
+	epoch: Instant,
+
+	/// Set to stop the heartbeat thread.
+	stop: AtomicBool,
+}
+
+/// A heartbeat signal on a single GPIO pin.
+///
+/// A background thread toggles the pin following a repeating pattern,
+/// for feeding an external hardware watchdog or blinking an "alive" LED.
+/// The pin must already be configured as output.
+///
+/// With a timeout, the pin stops toggling when [`feed`](Self::feed) is not called within the timeout,
+/// so the watchdog fires when the application hangs even though the heartbeat thread keeps running.
+/// Toggling resumes on the next feed.
+pub struct Heartbeat {
+	pin: usize,
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Heartbeat {
+	/// Start a heartbeat on a pin.
+	///
+	/// The pattern holds the durations of the high and low phases, alternating and starting with a high phase.
+	/// It must have an even, non-zero length, so it repeats with the same phase.
+	/// For example, `[100ms, 900ms]` blinks once per second, and `[100ms, 100ms, 100ms, 700ms]` blinks twice per second.
+	///
+	/// If a timeout is given, the heartbeat stops toggling when it is not fed within the timeout.
+	/// The heartbeat is fed when it starts.
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn start(pin: usize, pattern: &[Duration], timeout: Option<Duration>) -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?, pin, pattern, timeout)
+	}
+
+	/// Start a heartbeat on a pin, moving an existing handle to the background thread, see [`start`](Self::start).
+	pub fn start_with_gpio(gpio: Gpio, pin: usize, pattern: &[Duration], timeout: Option<Duration>) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+		if pattern.is_empty() || !pattern.len().is_multiple_of(2) {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("heartbeat pattern must have an even, non-zero length, got {}", pattern.len())));
+		}
+		if pattern.iter().any(|x| x.is_zero()) {
+			return Err(Error::new(ErrorKind::InvalidArgument, "heartbeat pattern must not contain zero durations"));
+		}
+
+		let shared = Arc::new(Shared {
+			fed:   AtomicU64::new(0),
+			epoch: Instant::now(),
+			stop:  AtomicBool::new(false),
+		});
+
+		let pattern = pattern.to_vec();
+		let thread = spawn_worker("heartbeat", ThreadOptions::default(), gpio, {
+			let shared = shared.clone();
+			move |mut gpio| run(&mut gpio, pin, &pattern, timeout, &shared)
+		})?;
+
+		Ok(Self {
+			pin,
+			shared,
+			thread: Some(thread),
+		})
+	}
+
+	/// Get the pin the heartbeat is generated on.
+	pub fn pin(&self) -> usize {
+		self.pin
+	}
+
+	/// Feed the heartbeat, so it keeps toggling for another timeout.
+	///
+	/// This does nothing if the heartbeat has no timeout.
+	pub fn feed(&self) {
+		self.shared.fed.store(nanos_since(self.shared.epoch), Ordering::Relaxed);
+	}
+
+	/// Stop the heartbeat.
+	///
+	/// The pin is left low.
+	pub fn stop(mut self) {
+		self.stop_thread();
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			thread.thread().unpark();
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for Heartbeat {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+fn nanos_since(epoch: Instant) -> u64 {
+	u64::try_from(epoch.elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn run(gpio: &mut Gpio, pin: usize, pattern: &[Duration], timeout: Option<Duration>, shared: &Shared) {
+	let mut deadline = Instant::now();
+	for (step, duration) in pattern.iter().enumerate().cycle() {
+		let starving = timeout.is_some_and(|timeout| {
+			let fed = Duration::from_nanos(shared.fed.load(Ordering::Relaxed));
+			shared.epoch.elapsed().saturating_sub(fed) > timeout
+		});
+		if !starving {
+			gpio.write_level(pin, step.is_multiple_of(2));
+		}
+
+		deadline += *duration;
+
+		// Don't try to catch up if we fell behind more than a full step.
+		let now = Instant::now();
+		if now > deadline + *duration {
+			deadline = now;
+		}
+
+		// Park instead of sleeping, so stopping doesn't wait for the end of a long step.
+		loop {
+			if shared.stop.load(Ordering::Relaxed) {
+				gpio.write_level(pin, false);
+				return;
+			}
+			let now = Instant::now();
+			if now >= deadline {
+				break;
+			}
+			std::thread::park_timeout(deadline - now);
+		}
+	}
+}
@@ -0,0 +1,198 @@
+use std::time::{Duration, Instant};
+
+use crate::{delay, Error, ErrorKind, Gpio, PinFunction};
+
+/// How long a device may stretch the clock before we give up.
+const CLOCK_STRETCH_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Bit-banged I2C master on two GPIO pins.
+///
+/// The open-drain outputs are emulated by switching the pins between input (released)
+/// and output with a low level (pulled low).
+/// Both lines need a pull-up resistor, either external or the internal one.
+///
+/// Clock stretching by devices is supported.
+/// The pins are left as inputs when the bus is dropped.
+pub struct SoftI2c<'a> {
+	gpio: &'a mut Gpio,
+	sda: usize,
+	scl: usize,
+	half_period: Duration,
+}
+
+impl<'a> SoftI2c<'a> {
+	/// Create a new I2C bus on the given pins with the given clock frequency.
+	pub fn new(gpio: &'a mut Gpio, sda: usize, scl: usize, frequency: f64) -> Result<Self, Error> {
+		crate::check_pin_index(sda)?;
+		crate::check_pin_index(scl)?;
+		if sda == scl {
+			return Err(Error::new(ErrorKind::InvalidArgument, "SDA and SCL must be different pins"));
+		}
+		if !frequency.is_finite() || frequency <= 0.0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid I2C frequency: {}", frequency)));
+		}
+
+		// Latch a low output level, so switching to output pulls the line low.
+		gpio.write_level(sda, false);
+		gpio.write_level(scl, false);
+
+		let mut bus = Self {
+			gpio,
+			sda,
+			scl,
+			half_period: Duration::from_secs_f64(0.5 / frequency),
+		};
+		bus.release(sda);
+		bus.release(scl);
+		Ok(bus)
+	}
+
+	/// Check if a device acknowledges its address.
+	pub fn probe(&mut self, address: u8) -> Result<bool, Error> {
+		self.start()?;
+		let ack = self.write_byte(address << 1);
+		self.stop()?;
+		ack
+	}
+
+	/// Write data to a device.
+	pub fn write(&mut self, address: u8, data: &[u8]) -> Result<(), Error> {
+		self.start()?;
+		let result = self.write_bytes(address, data);
+		self.stop()?;
+		result
+	}
+
+	/// Read data from a device.
+	pub fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+		self.start()?;
+		let result = self.read_bytes(address, buffer);
+		self.stop()?;
+		result
+	}
+
+	/// Get the GPIO handle of the bus, to use other pins while the bus exists.
+	pub(crate) fn gpio(&mut self) -> &mut Gpio {
+		self.gpio
+	}
+
+	fn write_bytes(&mut self, address: u8, data: &[u8]) -> Result<(), Error> {
+		self.address(address, false)?;
+		for (i, &byte) in data.iter().enumerate() {
+			if !self.write_byte(byte)? {
+				return Err(Error::new(ErrorKind::NoResponse, format!("I2C device 0x{:02X} did not acknowledge byte {}", address, i)));
+			}
+		}
+		Ok(())
+	}
+
+	fn read_bytes(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+		self.address(address, true)?;
+		let len = buffer.len();
+		for (i, byte) in buffer.iter_mut().enumerate() {
+			*byte = self.read_byte(i + 1 < len)?;
+		}
+		Ok(())
+	}
+
+	fn address(&mut self, address: u8, read: bool) -> Result<(), Error> {
+		if self.write_byte(address << 1 | u8::from(read))? {
+			Ok(())
+		} else {
+			Err(Error::new(ErrorKind::NoResponse, format!("no I2C device acknowledged address 0x{:02X}", address)))
+		}
+	}
+
+	fn release(&mut self, pin: usize) {
+		self.gpio.write_function(pin, PinFunction::Input);
+	}
+
+	fn pull_low(&mut self, pin: usize) {
+		self.gpio.write_function(pin, PinFunction::Output);
+	}
+
+	fn delay(&self) {
+		delay::sleep(self.half_period);
+	}
+
+	/// Release SCL and wait for devices to stop stretching the clock.
+	fn scl_high(&mut self) -> Result<(), Error> {
+		self.release(self.scl);
+		let start = Instant::now();
+		while !self.gpio.level(self.scl) {
+			if start.elapsed() > CLOCK_STRETCH_TIMEOUT {
+				return Err(Error::new(ErrorKind::Timeout, "timeout while waiting for I2C clock line to go high"));
+			}
+		}
+		self.delay();
+		Ok(())
+	}
+
+	fn start(&mut self) -> Result<(), Error> {
+		self.release(self.sda);
+		self.scl_high()?;
+		if !self.gpio.level(self.sda) {
+			return Err(Error::new(ErrorKind::Protocol, "I2C data line is held low"));
+		}
+		self.pull_low(self.sda);
+		self.delay();
+		self.pull_low(self.scl);
+		self.delay();
+		Ok(())
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.pull_low(self.sda);
+		self.delay();
+		self.scl_high()?;
+		self.release(self.sda);
+		self.delay();
+		Ok(())
+	}
+
+	fn write_bit(&mut self, bit: bool) -> Result<(), Error> {
+		if bit {
+			self.release(self.sda);
+		} else {
+			self.pull_low(self.sda);
+		}
+		self.delay();
+		self.scl_high()?;
+		self.pull_low(self.scl);
+		Ok(())
+	}
+
+	fn read_bit(&mut self) -> Result<bool, Error> {
+		self.release(self.sda);
+		self.delay();
+		self.scl_high()?;
+		let bit = self.gpio.level(self.sda);
+		self.pull_low(self.scl);
+		Ok(bit)
+	}
+
+	/// Write a byte and return true if it was acknowledged.
+	fn write_byte(&mut self, byte: u8) -> Result<bool, Error> {
+		for i in (0..8).rev() {
+			self.write_bit(byte >> i & 1 == 1)?;
+		}
+		Ok(!self.read_bit()?)
+	}
+
+	/// Read a byte, and acknowledge it if more bytes are to be read.
+	fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+		let mut byte = 0;
+		for _ in 0..8 {
+			byte = byte << 1 | u8::from(self.read_bit()?);
+		}
+		self.write_bit(!ack)?;
+		Ok(byte)
+	}
+}
+
+impl Drop for SoftI2c<'_> {
+	fn drop(&mut self) {
+		self.release(self.sda);
+		self.release(self.scl);
+	}
+}
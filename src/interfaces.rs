@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use crate::{Error, PinInfo};
+
+const DEVICE_TREE : &str = "/proc/device-tree";
+const GPIO_NODE   : &str = "/proc/device-tree/soc/gpio@7e200000";
+
+/// A hardware interface that is enabled in the device tree, such as I2C, SPI or UART.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Interface {
+	/// The alias of the interface, like `i2c1` or `serial0`.
+	///
+	/// For nodes without an alias found by [`read_all`](Self::read_all), this is the node name without the unit address, like `dpi`.
+	pub name: String,
+
+	/// The path of the device tree node, like `/soc/i2c@7e804000`.
+	pub path: String,
+
+	/// The GPIO pins the interface uses.
+	pub pins: Vec<usize>,
+}
+
+impl Interface {
+	/// Read the interfaces that are enabled in the device tree and use GPIO pins.
+	///
+	/// The device tree is built by the firmware from `/boot/config.txt` at boot,
+	/// so it reflects the `dtparam` and `dtoverlay` lines that are in effect.
+	///
+	/// Interfaces are found through the aliases of the device tree,
+	/// and their pins through the pin control group of the interface node.
+	/// If the system has no device tree, the result is empty.
+	pub fn read_enabled() -> Result<Vec<Interface>, Error> {
+		let aliases = match std::fs::read_dir(Path::new(DEVICE_TREE).join("aliases")) {
+			Ok(x) => x,
+			Err(_) => return Ok(Vec::new()),
+		};
+
+		let mut aliases = aliases.filter_map(Result::ok).map(|x| x.file_name().to_string_lossy().into_owned()).collect::<Vec<_>>();
+		aliases.sort();
+
+		let groups = read_pin_groups()?;
+		let mut interfaces = Vec::<Interface>::new();
+		for name in aliases {
+			let path = read_string(&Path::new(DEVICE_TREE).join("aliases").join(&name))?;
+			// Skip properties that are not a path, like the name of the node on older kernels.
+			let path = match path {
+				Some(x) if x.starts_with('/') => x,
+				_ => continue,
+			};
+
+			// Several aliases may refer to the same node, like serial0 and uart1.
+			if interfaces.iter().any(|x| x.path == path) {
+				continue;
+			}
+
+			let node = Path::new(DEVICE_TREE).join(path.trim_start_matches('/'));
+			match read_string(&node.join("status"))? {
+				Some(status) if status != "okay" && status != "ok" => continue,
+				_ => (),
+			}
+
+			let pins = read_node_pins(&node, &groups)?;
+			if !pins.is_empty() {
+				interfaces.push(Interface { name, path, pins });
+			}
+		}
+
+		Ok(interfaces)
+	}
+
+	/// Read all enabled device tree nodes that use GPIO pins, including nodes without an alias.
+	///
+	/// This finds the users of pins added by overlays that have no alias, like `dpi` or `gpio-fan`.
+	/// Nodes with an alias are named after it, and other nodes after their node name without the unit address.
+	/// If the system has no device tree, the result is empty.
+	pub fn read_all() -> Result<Vec<Interface>, Error> {
+		let root = Path::new(DEVICE_TREE);
+		if !root.is_dir() {
+			return Ok(Vec::new());
+		}
+
+		let groups  = read_pin_groups()?;
+		let aliases = read_aliases()?;
+		let mut interfaces = Vec::new();
+		find_pin_users(root, "", &groups, &aliases, &mut interfaces)?;
+		Ok(interfaces)
+	}
+
+	/// Fill in the interface that uses each pin of a list, indexed by pin number.
+	///
+	/// If multiple interfaces use the same pin, the first one is used.
+	pub fn annotate(interfaces: &[Interface], pins: &mut [PinInfo]) {
+		for (pin, info) in pins.iter_mut().enumerate() {
+			info.interface = interfaces.iter().find(|x| x.pins.contains(&pin)).map(|x| x.name.clone());
+		}
+	}
+}
+
+/// Recursively find the enabled nodes below a node that use GPIO pins.
+fn find_pin_users(dir: &Path, path: &str, groups: &[(u32, Vec<usize>)], aliases: &[(String, String)], interfaces: &mut Vec<Interface>) -> Result<(), Error> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(x) => x,
+		Err(_) => return Ok(()),
+	};
+	let mut children = entries
+		.filter_map(Result::ok)
+		.filter(|x| x.file_type().is_ok_and(|x| x.is_dir()))
+		.map(|x| x.file_name().to_string_lossy().into_owned())
+		.collect::<Vec<_>>();
+	children.sort();
+
+	for name in children {
+		let node = dir.join(&name);
+		let path = format!("{}/{}", path, name);
+		match read_string(&node.join("status"))? {
+			Some(status) if status != "okay" && status != "ok" => continue,
+			_ => (),
+		}
+
+		// The pinctrl-0 of the GPIO controller itself holds the boot-time defaults of older device trees,
+		// like the pins of SPI0 as plain outputs, so it is not a user of those pins.
+		let pins = match node.join("gpio-controller").exists() {
+			true  => Vec::new(),
+			false => read_node_pins(&node, groups)?,
+		};
+		if !pins.is_empty() {
+			let name = match aliases.iter().find(|(_, target)| *target == path) {
+				Some((alias, _)) => alias.clone(),
+				None => name.split('@').next().unwrap_or(&name).to_string(),
+			};
+			interfaces.push(Interface { name, path: path.clone(), pins });
+		}
+
+		find_pin_users(&node, &path, groups, aliases, interfaces)?;
+	}
+
+	Ok(())
+}
+
+/// Read the GPIO pins of a node from the pin groups referenced by its `pinctrl-0` property.
+fn read_node_pins(node: &Path, groups: &[(u32, Vec<usize>)]) -> Result<Vec<usize>, Error> {
+	let mut pins = Vec::new();
+	for phandle in read_cells(&node.join("pinctrl-0"))?.unwrap_or_default() {
+		if let Some((_, group)) = groups.iter().find(|(x, _)| *x == phandle) {
+			pins.extend(group.iter().cloned().filter(|pin| *pin < 54));
+		}
+	}
+	pins.sort_unstable();
+	pins.dedup();
+	Ok(pins)
+}
+
+/// Read the aliases of the device tree, with the path they refer to, sorted by name.
+fn read_aliases() -> Result<Vec<(String, String)>, Error> {
+	let entries = match std::fs::read_dir(Path::new(DEVICE_TREE).join("aliases")) {
+		Ok(x) => x,
+		Err(_) => return Ok(Vec::new()),
+	};
+
+	let mut aliases = Vec::new();
+	for entry in entries.filter_map(Result::ok) {
+		let name = entry.file_name().to_string_lossy().into_owned();
+		// Skip properties that are not a path, like the name of the node on older kernels.
+		if let Some(path) = read_string(&entry.path())?.filter(|x| x.starts_with('/')) {
+			aliases.push((name, path));
+		}
+	}
+	aliases.sort();
+	Ok(aliases)
+}
+
+/// Read the pin groups of the GPIO node, with their phandle.
+fn read_pin_groups() -> Result<Vec<(u32, Vec<usize>)>, Error> {
+	let entries = match std::fs::read_dir(GPIO_NODE) {
+		Ok(x) => x,
+		Err(_) => return Ok(Vec::new()),
+	};
+
+	let mut groups = Vec::new();
+	for entry in entries.filter_map(Result::ok) {
+		let path = entry.path();
+		if !path.is_dir() {
+			continue;
+		}
+
+		let phandle = read_cells(&path.join("phandle"))?;
+		let pins    = read_cells(&path.join("brcm,pins"))?;
+		if let (Some(&[phandle]), Some(pins)) = (phandle.as_deref(), pins) {
+			groups.push((phandle, pins.into_iter().map(|x| x as usize).collect()));
+		}
+	}
+
+	Ok(groups)
+}
+
+/// Read a device tree property as a string, or `None` if it doesn't exist.
+fn read_string(path: &Path) -> Result<Option<String>, Error> {
+	let mut data = match read_property(path)? {
+		Some(x) => x,
+		None => return Ok(None),
+	};
+	if data.last() == Some(&0) {
+		data.pop();
+	}
+	Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+}
+
+/// Read a device tree property as big-endian 32 bit cells, or `None` if it doesn't exist.
+fn read_cells(path: &Path) -> Result<Option<Vec<u32>>, Error> {
+	let data = match read_property(path)? {
+		Some(x) => x,
+		None => return Ok(None),
+	};
+	Ok(Some(data.chunks_exact(4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])).collect()))
+}
+
+fn read_property(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+	match crate::open(path) {
+		Ok(file) => Ok(Some(crate::read_all(file)?)),
+		Err(e) if e.is_not_found() => Ok(None),
+		Err(e) => Err(e),
+	}
+}
@@ -0,0 +1,221 @@
+//! Just enough JSON to read back our own state files.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The maximum nesting depth of arrays and objects.
+///
+/// Our state files only nest two levels deep, this just keeps malicious input from overflowing the stack.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Debug)]
+pub enum Value {
+	Null,
+	Bool(#[allow(dead_code)] bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Value>),
+	Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(x) => Some(x),
+			_ => None,
+		}
+	}
+
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			// Integers up to 2^53 are exactly representable.
+			Value::Number(x) if *x >= 0.0 && *x <= 9_007_199_254_740_992.0 && *x as u64 as f64 == *x => Some(*x as u64),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&Vec<Value>> {
+		match self {
+			Value::Array(x) => Some(x),
+			_ => None,
+		}
+	}
+
+	pub fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+		match self {
+			Value::Object(x) => Some(x),
+			_ => None,
+		}
+	}
+}
+
+pub struct Parser<'a> {
+	data: &'a str,
+	pos: usize,
+	depth: usize,
+}
+
+impl<'a> Parser<'a> {
+	pub fn new(data: &'a str) -> Self {
+		Self { data, pos: 0, depth: 0 }
+	}
+
+	pub fn parse_document(&mut self) -> Result<Value, String> {
+		let value = self.parse_value()?;
+		self.skip_whitespace();
+		if self.pos != self.data.len() {
+			return Err(self.error("trailing data"));
+		}
+		Ok(value)
+	}
+
+	fn parse_value(&mut self) -> Result<Value, String> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some('{') => self.parse_nested(Self::parse_object),
+			Some('[') => self.parse_nested(Self::parse_array),
+			Some('"') => Ok(Value::String(self.parse_string()?)),
+			Some('t') => self.parse_literal("true", Value::Bool(true)),
+			Some('f') => self.parse_literal("false", Value::Bool(false)),
+			Some('n') => self.parse_literal("null", Value::Null),
+			Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+			Some(_) => Err(self.error("unexpected character")),
+			None => Err(self.error("unexpected end of data")),
+		}
+	}
+
+	fn parse_nested(&mut self, parse: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+		if self.depth == MAX_DEPTH {
+			return Err(self.error("nesting too deep"));
+		}
+		self.depth += 1;
+		let value = parse(self);
+		self.depth -= 1;
+		value
+	}
+
+	fn parse_object(&mut self) -> Result<Value, String> {
+		let mut object = BTreeMap::new();
+		self.expect('{')?;
+		self.skip_whitespace();
+		if self.consume('}') {
+			return Ok(Value::Object(object));
+		}
+		loop {
+			self.skip_whitespace();
+			let key = self.parse_string()?;
+			self.skip_whitespace();
+			self.expect(':')?;
+			let value = self.parse_value()?;
+			object.insert(key, value);
+			self.skip_whitespace();
+			if self.consume('}') {
+				return Ok(Value::Object(object));
+			}
+			self.expect(',')?;
+		}
+	}
+
+	fn parse_array(&mut self) -> Result<Value, String> {
+		let mut array = Vec::new();
+		self.expect('[')?;
+		self.skip_whitespace();
+		if self.consume(']') {
+			return Ok(Value::Array(array));
+		}
+		loop {
+			array.push(self.parse_value()?);
+			self.skip_whitespace();
+			if self.consume(']') {
+				return Ok(Value::Array(array));
+			}
+			self.expect(',')?;
+		}
+	}
+
+	fn parse_string(&mut self) -> Result<String, String> {
+		self.expect('"')?;
+		let mut result = String::new();
+		loop {
+			match self.next() {
+				Some('"') => return Ok(result),
+				Some('\\') => match self.next() {
+					Some('"')  => result.push('"'),
+					Some('\\') => result.push('\\'),
+					Some('/')  => result.push('/'),
+					Some('n')  => result.push('\n'),
+					Some('t')  => result.push('\t'),
+					Some('r')  => result.push('\r'),
+					_ => return Err(self.error("unsupported escape sequence")),
+				},
+				Some(c) => result.push(c),
+				None => return Err(self.error("unterminated string")),
+			}
+		}
+	}
+
+	fn parse_number(&mut self) -> Result<Value, String> {
+		let start = self.pos;
+		while let Some(c) = self.peek() {
+			if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+				self.pos += 1;
+			} else {
+				break;
+			}
+		}
+		let number = &self.data[start..self.pos];
+		number.parse().map(Value::Number).map_err(|_| self.error("invalid number"))
+	}
+
+	fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+		if self.data[self.pos..].starts_with(literal) {
+			self.pos += literal.len();
+			Ok(value)
+		} else {
+			Err(self.error("unexpected character"))
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while let Some(c) = self.peek() {
+			if c.is_ascii_whitespace() {
+				self.pos += 1;
+			} else {
+				break;
+			}
+		}
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.data[self.pos..].chars().next()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.pos += c.len_utf8();
+		Some(c)
+	}
+
+	fn consume(&mut self, expected: char) -> bool {
+		if self.peek() == Some(expected) {
+			self.pos += expected.len_utf8();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), String> {
+		if self.consume(expected) {
+			Ok(())
+		} else {
+			Err(self.error(&format!("expected `{}`", expected)))
+		}
+	}
+
+	fn error(&self, message: &str) -> String {
+		format!("{} at byte {}", message, self.pos)
+	}
+}
@@ -1,57 +1,450 @@
-#![feature(asm)]
-#![feature(core_intrinsics)]
+//! Without the default `std` feature, only the `no_std` core is available:
+//! the register model, [`GpioState`], the planning of [`GpioConfig`] and [`GpioPullConfig`],
+//! and [`MmioBackend`] to access the registers at a caller-provided address.
+//! The [`Gpio`] handle and everything that needs Linux is part of the `std` layer.
+//!
+//! With the `stub` feature, the `std` layer also builds on other platforms,
+//! but everything that accesses the hardware fails with an [`ErrorKind::Unsupported`] error.
+//! Only the [`MockBackend`] works.
 
-use nix::sys::mman;
-use std::fmt::Display;
-use std::io::Read;
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+
+#[cfg(all(feature = "std", not(feature = "stub"), not(target_os = "linux")))]
+compile_error!("the std feature requires Linux, enable the stub feature to build on other platforms");
+
+#[cfg(feature = "std")]
 const CONTROL_BLOCK_SIZE : usize = 0x00000100;
 
+#[cfg(all(feature = "async", not(feature = "stub")))]
+mod async_events;
+mod backend;
+#[cfg(feature = "std")]
+mod bank;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+mod cdev;
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(feature = "std")]
+mod claims;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+mod counter;
+#[cfg(feature = "std")]
+mod delay;
+#[cfg(feature = "std")]
+mod devicetree;
+#[cfg(feature = "std")]
+mod event;
+#[cfg(feature = "std")]
+mod gpio;
+#[cfg(feature = "gpio-cdev")]
+mod gpio_cdev_impl;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "std")]
+mod hardware_pwm;
+#[cfg(feature = "std")]
+mod hat;
+#[cfg(feature = "std")]
+mod heartbeat;
+#[cfg(feature = "std")]
+mod i2c;
+#[cfg(feature = "std")]
+mod interfaces;
+mod json;
+#[cfg(feature = "std")]
+mod lock;
+mod mask;
+#[cfg(feature = "std")]
+mod mcp23017;
+#[cfg(feature = "prometheus")]
+mod metrics;
+#[cfg(feature = "std")]
+mod mock;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+mod notify;
+#[cfg(feature = "std")]
+mod onewire;
+#[cfg(feature = "std")]
+mod pads;
+pub mod platform;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+mod pread;
+#[cfg(feature = "std")]
+mod procfs;
+#[cfg(feature = "std")]
+mod provider;
+#[cfg(feature = "std")]
+mod pwm;
+#[cfg(feature = "python")]
+mod python;
 mod read;
+#[cfg(feature = "std")]
+pub mod realtime;
+#[cfg(feature = "std")]
+mod record;
 mod register;
+#[cfg(feature = "std")]
+mod restore;
+#[cfg(feature = "rppal")]
+mod rppal_impl;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod soc;
+#[cfg(feature = "std")]
+mod sbus;
+#[cfg(feature = "std")]
+mod scheduler;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+mod signal;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod spi;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "stub")]
+mod stub;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+mod sysfs;
+pub mod testing;
+#[cfg(feature = "std")]
+mod timer;
+#[cfg(feature = "std")]
+pub mod timing;
+#[cfg(feature = "tokio")]
+mod watch;
+#[cfg(feature = "std")]
+mod worker;
 mod write;
+#[cfg(feature = "std")]
+mod ws2812;
 
+#[cfg(all(feature = "std", not(feature = "stub")))]
 use nix::errno::Errno;
-
+#[cfg(feature = "std")]
+use procfs::{map_dev_mem, map_memory, open, read_all, read_gpio_address, read_gpio_compatible, trim};
+#[cfg(all(feature = "std", not(feature = "stub")))]
+use procfs::open_rw;
+
+#[cfg(all(feature = "async", not(feature = "stub")))]
+pub use async_events::AsyncEvents;
+#[cfg(all(feature = "async", not(feature = "stub")))]
+pub use async_events::AsyncFd;
+#[cfg(all(feature = "async-io", not(feature = "stub")))]
+pub use async_events::AsyncIoFd;
+#[cfg(all(feature = "tokio", not(feature = "stub")))]
+pub use async_events::TokioFd;
+pub use backend::Capabilities;
+pub use backend::GpioBackend;
+#[cfg(feature = "std")]
+pub use backend::MappedBackend;
+pub use backend::MmioBackend;
+#[cfg(feature = "std")]
+pub use bank::GpioBank;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use cdev::CdevBackend;
+#[cfg(feature = "std")]
+pub use channel::EventReceiver;
+#[cfg(feature = "std")]
+pub use channel::OverflowPolicy;
+#[cfg(feature = "std")]
+pub use claims::KernelClaims;
+#[cfg(feature = "std")]
+pub use clock::Clock;
+#[cfg(feature = "std")]
+pub use counter::PulseCounter;
+#[cfg(feature = "std")]
+pub use delay::precise_delay;
+#[cfg(feature = "std")]
+pub use event::Edge;
+#[cfg(feature = "std")]
+pub use event::EventListener;
+#[cfg(feature = "std")]
+pub use event::GpioEvent;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use event::PollableEventListener;
+#[cfg(feature = "std")]
+pub use gpio::Gpio;
+#[cfg(all(feature = "std", feature = "bench"))]
+pub use gpio::RegisterAccesses;
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcBackend;
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcServer;
+#[cfg(feature = "std")]
+pub use hardware_pwm::HardwarePwm;
+#[cfg(feature = "std")]
+pub use hat::Hat;
+#[cfg(feature = "std")]
+pub use hat::HatGpioMap;
+#[cfg(feature = "std")]
+pub use hat::HatPin;
+#[cfg(feature = "std")]
+pub use hat::HAT_PINS;
+#[cfg(feature = "std")]
+pub use heartbeat::Heartbeat;
+#[cfg(feature = "std")]
+pub use i2c::SoftI2c;
+#[cfg(feature = "std")]
+pub use interfaces::Interface;
+#[cfg(feature = "std")]
+pub use lock::LOCK_FILE;
+pub use mask::PinMask;
+#[cfg(feature = "std")]
+pub use mcp23017::ExpanderInterrupt;
+#[cfg(feature = "std")]
+pub use mcp23017::Mcp23017;
+#[cfg(feature = "std")]
+pub use mcp23017::MCP23017_PINS;
+#[cfg(feature = "prometheus")]
+pub use metrics::GpioMetrics;
+#[cfg(feature = "std")]
+pub use mock::MockBackend;
+#[cfg(feature = "std")]
+pub use mock::MockGpio;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use notify::EventNotifier;
+#[cfg(feature = "std")]
+pub use onewire::DS18B20_FAMILY;
+#[cfg(feature = "std")]
+pub use onewire::OneWire;
+#[cfg(feature = "std")]
+pub use onewire::format_rom;
+#[cfg(feature = "std")]
+pub use pads::DriveStrength;
+#[cfg(feature = "std")]
+pub use pads::PadConfig;
+#[cfg(feature = "std")]
+pub use pads::Pads;
+#[cfg(feature = "std")]
+pub use pads::pad_bank;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use pread::PreadBackend;
+#[cfg(feature = "std")]
+pub use procfs::check_bcm283x_gpio;
+#[cfg(feature = "std")]
+pub use provider::ActiveLow;
+#[cfg(feature = "std")]
+pub use provider::GpioProvider;
+#[cfg(feature = "std")]
+pub use pwm::SoftPwm;
 pub use read::GpioState;
+pub use read::PinChange;
 pub use read::PinInfo;
+pub use read::GPIO_STATE_SIZE;
+#[cfg(feature = "std")]
+pub use record::AccessKind;
+#[cfg(feature = "std")]
+pub use record::RecordedAccess;
+#[cfg(feature = "std")]
+pub use record::Recording;
+#[cfg(feature = "std")]
+pub use record::RecordingBackend;
+pub use register::InvalidRegisterIndex;
 pub use register::Register;
+pub use register::RegisterKind;
+#[cfg(feature = "std")]
+pub use sbus::SbusFrame;
+#[cfg(feature = "std")]
+pub use sbus::SbusReceiver;
+#[cfg(feature = "std")]
+pub use sbus::SBUS_FRAME_SIZE;
+#[cfg(feature = "std")]
+pub use scheduler::PinAction;
+#[cfg(feature = "std")]
+pub use scheduler::ScheduledTask;
+#[cfg(feature = "std")]
+pub use scheduler::Scheduler;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use signal::SignalBridge;
+#[cfg(all(feature = "async", not(feature = "stub")))]
+pub use snapshot::SnapshotStream;
+#[cfg(feature = "std")]
+pub use snapshot::Snapshots;
+pub use soc::Soc;
+pub use soc::SpecialPin;
+#[cfg(feature = "std")]
+pub use spi::SoftSpi;
+#[cfg(feature = "std")]
+pub use sync::SyncGpio;
+#[cfg(feature = "stub")]
+pub use stub::CdevBackend;
+#[cfg(feature = "stub")]
+pub use stub::PreadBackend;
+#[cfg(feature = "stub")]
+pub use stub::SysfsBackend;
+#[cfg(all(feature = "std", not(feature = "stub")))]
+pub use sysfs::SysfsBackend;
+#[cfg(feature = "std")]
+pub use timer::SystemTimer;
+pub use write::ConflictPolicy;
 pub use write::GpioConfig;
 pub use write::GpioPullConfig;
+pub use write::Permissions;
+pub use write::RegisterOp;
+#[cfg(feature = "std")]
+pub use ws2812::Ws2812;
+
+/// The cause of an [`Error`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+	/// Access to a device file or memory region was denied.
+	PermissionDenied,
+
+	/// The peripheral could not be found on this system.
+	PeripheralNotFound,
+
+	/// The system has a peripheral that is not compatible with the BCM283x.
+	UnsupportedSoC,
+
+	/// A pin index was out of range.
+	InvalidPinIndex,
+
+	/// The contents of /proc/iomem could not be parsed.
+	IoMemParse,
+
+	/// The device tree is invalid or does not match the peripheral found in /proc/iomem.
+	DeviceTree,
+
+	/// Mapping peripheral memory failed.
+	Mmap,
+
+	/// The operation is not supported by the backend or the system.
+	Unsupported,
+
+	/// Some other I/O operation failed.
+	Io,
+
+	/// An argument was out of range or otherwise invalid.
+	InvalidArgument,
+
+	/// A register can not be written safely, see [`Register::is_write_safe`].
+	UnsafeRegister,
+
+	/// A configuration changes settings that are not allowed by the given [`Permissions`].
+	NotAllowed,
+
+	/// The hardware did not reflect a configuration after it was applied.
+	VerificationFailed,
+
+	/// A device did not respond in time.
+	Timeout,
+
+	/// A device did not acknowledge or did not respond at all.
+	NoResponse,
+
+	/// A device responded with invalid data or left the bus in an invalid state.
+	Protocol,
+
+	/// Any other error.
+	Other,
+}
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Error {
+	kind: ErrorKind,
 	message: String,
+	#[cfg(all(feature = "std", not(feature = "stub")))]
 	errno: Option<Errno>,
 }
 
 impl Error {
-	fn new(message: impl std::string::ToString, errno: Option<Errno>) -> Self {
-		Self { message: message.to_string(), errno }
+	fn new(kind: ErrorKind, message: impl ToString) -> Self {
+		Self {
+			kind,
+			message: message.to_string(),
+			#[cfg(all(feature = "std", not(feature = "stub")))]
+			errno: None,
+		}
+	}
+
+	#[cfg(all(feature = "std", not(feature = "stub")))]
+	fn from_errno(kind: ErrorKind, message: impl ToString, errno: Option<Errno>) -> Self {
+		// Permission problems are reported the same way, whatever operation failed.
+		let kind = match errno {
+			Some(Errno::EACCES) | Some(Errno::EPERM) => ErrorKind::PermissionDenied,
+			_ => kind,
+		};
+		Self { kind, message: message.to_string(), errno }
 	}
 
-	fn from_nix(message: impl std::string::ToString, error: nix::Error) -> Self {
-		Self::new(message, error.as_errno())
+	#[cfg(all(feature = "std", not(feature = "stub")))]
+	fn from_nix(kind: ErrorKind, message: impl ToString, error: nix::Error) -> Self {
+		Self::from_errno(kind, message, error.as_errno())
 	}
 
-	fn from_io(message: impl std::string::ToString, error: std::io::Error) -> Self {
+	#[cfg(all(feature = "std", not(feature = "stub")))]
+	fn from_io(kind: ErrorKind, message: impl ToString, error: std::io::Error) -> Self {
 		let errno = error.raw_os_error().map(Errno::from_i32);
-		Self::new(message, errno)
+		Self::from_errno(kind, message, errno)
+	}
+
+	/// Without nix, the OS error is kept as part of the message.
+	#[cfg(feature = "stub")]
+	fn from_io(kind: ErrorKind, message: impl ToString, error: std::io::Error) -> Self {
+		let kind = match error.kind() {
+			std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+			_ => kind,
+		};
+		Self::new(kind, format!("{}: {}", message.to_string(), error))
+	}
+
+	/// Check if the error was caused by a missing file.
+	#[cfg(feature = "std")]
+	fn is_not_found(&self) -> bool {
+		#[cfg(not(feature = "stub"))]
+		return self.errno == Some(Errno::ENOENT);
+		// With the stub feature, files are never opened.
+		#[cfg(feature = "stub")]
+		return false;
+	}
+
+	/// Get the kind of error.
+	pub fn kind(&self) -> ErrorKind {
+		self.kind
+	}
+
+	/// Get the error message, without the OS error.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// Get the OS error number, if the error was caused by a failing system call.
+	///
+	/// This is not available with the `stub` feature, which does not depend on nix.
+	#[cfg(all(feature = "std", not(feature = "stub")))]
+	pub fn errno(&self) -> Option<Errno> {
+		self.errno
 	}
 }
 
 impl Display for Error {
-	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		match self.errno {
-			None => write!(f, "{}", self.message),
-			Some(errno) => write!(f, "{}: {}", self.message, errno),
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		#[cfg(all(feature = "std", not(feature = "stub")))]
+		if let Some(errno) = self.errno {
+			return write!(f, "{}: {}", self.message, errno);
 		}
+		write!(f, "{}", self.message)
 	}
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum PinFunction {
 	Input,
 	Output,
@@ -65,240 +458,209 @@ pub enum PinFunction {
 
 /// A pull up/down mode for a GPIO pin.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum PullMode {
 	Float,
 	PullDown,
 	PullUp,
 }
 
-impl PinFunction {
-	pub fn try_from_bits(bits: u8) -> Result<Self, ()> {
-		match bits {
-			0b000 => Ok(PinFunction::Input),
-			0b001 => Ok(PinFunction::Output),
-			0b100 => Ok(PinFunction::Alt0),
-			0b101 => Ok(PinFunction::Alt1),
-			0b110 => Ok(PinFunction::Alt2),
-			0b111 => Ok(PinFunction::Alt3),
-			0b011 => Ok(PinFunction::Alt4),
-			0b010 => Ok(PinFunction::Alt5),
-			_     => Err(())
+impl PullMode {
+	/// Get the pull up/down mode of a pin after reset, according to the BCM2835 datasheet.
+	pub fn power_on_default(index: usize) -> Result<Self, Error> {
+		check_pin_index(index)?;
+		Ok(match index {
+			0..=8   => PullMode::PullUp,
+			9..=27  => PullMode::PullDown,
+			28..=29 => PullMode::Float,
+			30..=33 => PullMode::PullDown,
+			34..=36 => PullMode::PullUp,
+			37..=43 => PullMode::PullDown,
+			44..=45 => PullMode::Float,
+			_       => PullMode::PullUp,
+		})
+	}
+}
+
+impl PullMode {
+	/// Parse the pull up/down bits of a GPPUPPDN register on the BCM2711.
+	pub(crate) fn from_bcm2711_bits(bits: u32) -> Option<Self> {
+		match bits & 0b11 {
+			0b00 => Some(PullMode::Float),
+			0b01 => Some(PullMode::PullUp),
+			0b10 => Some(PullMode::PullDown),
+			_    => None,
 		}
 	}
 
-	pub fn to_bits(self) -> u8 {
+	/// Get the pull up/down bits for a GPPUPPDN register on the BCM2711.
+	pub(crate) fn to_bcm2711_bits(self) -> u32 {
 		match self {
-			PinFunction::Input  => 0b000,
-			PinFunction::Output => 0b001,
-			PinFunction::Alt0   => 0b100,
-			PinFunction::Alt1   => 0b101,
-			PinFunction::Alt2   => 0b110,
-			PinFunction::Alt3   => 0b111,
-			PinFunction::Alt4   => 0b011,
-			PinFunction::Alt5   => 0b010,
+			PullMode::Float    => 0b00,
+			PullMode::PullUp   => 0b01,
+			PullMode::PullDown => 0b10,
 		}
 	}
 }
 
-pub struct Gpio {
-	control_block: *mut std::ffi::c_void,
+/// The logic level of a GPIO pin.
+///
+/// This is the electrical level of the pin, regardless of what it means for the connected hardware.
+/// See [`ActiveLow`] for pins that are active when they are low.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Level {
+	Low,
+	High,
 }
 
-impl Gpio {
-	/// Create a new handle to the GPIO peripheral.
-	///
-	/// This will attempt to map a portion of /dev/mem,
-	/// in order to access the memory mapped GPIO peripheral.
-	///
-	/// This may fail if:
-	///  - we don't have root permission.
-	///  - the kernel was compiled with CONFIG_IO_STRICT_DEVMEM.
-	///  - the kernel was compiled with CONFIG_STRICT_DEVMEM,
-	///    and not started with `iomem=relaxed` on the kernel command line.
-	pub fn new() -> Result<Self, Error> {
-		use std::os::unix::io::AsRawFd;
-
-		let gpio_address = read_gpio_address()?;
-
-		let file = open_rw("/dev/mem")?;
-		let fd   = file.file.as_raw_fd();
-		let control_block = unsafe {
-			mman::mmap(std::ptr::null_mut(), CONTROL_BLOCK_SIZE, mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE, mman::MapFlags::MAP_SHARED, fd, gpio_address)
-				.map_err(|e| Error::from_nix(format!("failed to map GPIO memory (0x{:08X}) from /dev/mem", gpio_address), e))?
-		};
-
-		Ok(Self { control_block })
-	}
-
-	/// Get the pointer to the mapped control block.
-	pub fn control_block(&self) -> *mut std::ffi::c_void {
-		self.control_block
-	}
-
-	/// Read the entire current GPIO state.
-	pub fn read_all(&self) -> GpioState {
-		let address = self.control_block as *const [u32; 0x100];
-		GpioState::from_data(unsafe { address.read_volatile() })
+impl Level {
+	/// Check if the level is high.
+	pub fn is_high(self) -> bool {
+		self == Level::High
 	}
 
-	/// Read a value from a register.
-	pub fn read_register(&self, reg: Register) -> u32 {
-		unsafe { self.register_address(reg).read_volatile() }
+	/// Check if the level is low.
+	pub fn is_low(self) -> bool {
+		self == Level::Low
 	}
+}
 
-	/// Write a value to a register.
-	pub unsafe fn write_register(&mut self, reg: Register, value: u32) {
-		self.register_address_mut(reg).write_volatile(value)
+impl From<bool> for Level {
+	/// Convert `true` to [`Level::High`] and `false` to [`Level::Low`].
+	fn from(value: bool) -> Self {
+		match value {
+			true  => Level::High,
+			false => Level::Low,
+		}
 	}
+}
 
-	/// Perform an atomic bitwise AND on the contents of a register.
-	pub unsafe fn and_register(&mut self, reg: Register, value: u32) {
-		*self.register_address_mut(reg) &= value;
+impl From<Level> for bool {
+	fn from(level: Level) -> Self {
+		level.is_high()
 	}
+}
 
-	/// Perform an atomic bitwise OR on the contents of a register.
-	pub unsafe fn or_register(&mut self, reg: Register, value: u32) {
-		*self.register_address_mut(reg) |= value;
-	}
+impl core::ops::Not for Level {
+	type Output = Level;
 
-	/// Perform an atomic bitwise XOR on the contents of a register.
-	pub unsafe fn xor_register(&mut self, reg: Register, value: u32) {
-		*self.register_address_mut(reg) ^= value;
+	fn not(self) -> Level {
+		match self {
+			Level::Low  => Level::High,
+			Level::High => Level::Low,
+		}
 	}
+}
 
-	/// Read the current level of a GPIO pin.
-	pub fn read_level(&self, index: usize) -> bool {
-		assert_pin_index(index);
-		let value = self.read_register(Register::lev(index / 32));
-		let value = value >> (index % 32);
-		value & 1 == 1
+impl core::str::FromStr for Level {
+	type Err = Error;
+
+	/// Parse `high` or `low`, ignoring case.
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		if data.eq_ignore_ascii_case("high") {
+			Ok(Level::High)
+		} else if data.eq_ignore_ascii_case("low") {
+			Ok(Level::Low)
+		} else {
+			Err(Error::new(ErrorKind::InvalidArgument, format!("invalid level: {}, expected high or low", data)))
+		}
 	}
+}
 
-	/// Atomically set the level of a single GPIO pin.
-	pub fn set_level(&mut self, index: usize, value: bool) {
-		let bits = 1 << (index % 32);
-		let register = match value {
-			true  => Register::set(index / 32),
-			false => Register::clr(index / 32),
-		};
-		unsafe { self.write_register(register, bits) }
+impl Display for Level {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			Level::Low  => write!(f, "low"),
+			Level::High => write!(f, "high"),
+		}
 	}
+}
 
-	fn register_address(&self, reg: Register) -> *const u32 {
-		self.control_block.wrapping_add(reg as usize) as *const u32
+impl PinFunction {
+	/// Get the function for the three function select bits of a pin.
+	///
+	/// Every combination of three bits is a valid function, so this can not fail.
+	pub fn from_bits(bits: u8) -> Self {
+		match bits & 0b111 {
+			0b000 => PinFunction::Input,
+			0b001 => PinFunction::Output,
+			0b100 => PinFunction::Alt0,
+			0b101 => PinFunction::Alt1,
+			0b110 => PinFunction::Alt2,
+			0b111 => PinFunction::Alt3,
+			0b011 => PinFunction::Alt4,
+			_     => PinFunction::Alt5,
+		}
 	}
 
-	fn register_address_mut(&self, reg: Register) -> *mut u32 {
-		self.control_block.wrapping_add(reg as usize) as *mut u32
+	#[allow(clippy::result_unit_err)]
+	pub fn try_from_bits(bits: u8) -> Result<Self, ()> {
+		match bits {
+			0b000 => Ok(PinFunction::Input),
+			0b001 => Ok(PinFunction::Output),
+			0b100 => Ok(PinFunction::Alt0),
+			0b101 => Ok(PinFunction::Alt1),
+			0b110 => Ok(PinFunction::Alt2),
+			0b111 => Ok(PinFunction::Alt3),
+			0b011 => Ok(PinFunction::Alt4),
+			0b010 => Ok(PinFunction::Alt5),
+			_     => Err(())
+		}
 	}
-}
 
-impl Drop for Gpio {
-	fn drop(&mut self) {
-		unsafe {
-			drop(mman::munmap(self.control_block, CONTROL_BLOCK_SIZE))
+	pub fn to_bits(self) -> u8 {
+		match self {
+			PinFunction::Input  => 0b000,
+			PinFunction::Output => 0b001,
+			PinFunction::Alt0   => 0b100,
+			PinFunction::Alt1   => 0b101,
+			PinFunction::Alt2   => 0b110,
+			PinFunction::Alt3   => 0b111,
+			PinFunction::Alt4   => 0b011,
+			PinFunction::Alt5   => 0b010,
 		}
 	}
 }
 
-fn assert_pin_index(index: usize) {
-	assert!(index <= 53, "gpio pin index out of range, expected a value in the range [0-53], got {}", index);
-}
-
-fn partition(data: &[u8], split_on: u8) -> Result<(&[u8], &[u8]), ()> {
-	let mut iterator = data.splitn(2, |c| *c == split_on);
-	Ok((
-		iterator.next().ok_or(())?,
-		iterator.next().ok_or(())?,
-	))
-}
-
-fn is_whitespace(c: u8) -> bool {
-	c == b' ' || c == b'\t' || c == b'\n' || c == b'\r'
-}
-
-fn trim(data: &[u8]) -> &[u8] {
-	let first = match data.iter().position(|x| !is_whitespace(*x)) {
-		None => return &data[0..0],
-		Some(x) => x,
-	};
-
-	let last = match data.iter().rposition(|x| !is_whitespace(*x)) {
-		None => return &data[0..0],
-		Some(x) => x,
-	};
-
-	&data[first..last+1]
-}
-
-struct FileWithPath {
-	pub path: std::path::PathBuf,
-	pub file: std::fs::File,
-}
-
-fn open(path: impl Into<std::path::PathBuf>) -> Result<FileWithPath, Error> {
-	let path = path.into();
-	let file = std::fs::File::open(&path).map_err(|e| Error::from_io(format!("failed to open {}", path.display()), e))?;
-	Ok(FileWithPath {
-		path,
-		file,
-	})
+/// Order memory accesses around peripheral register access.
+///
+/// The BCM2835 ARM peripherals manual (section 1.3) warns that reads from different peripherals
+/// may return data out of order, and requires a memory barrier before the first write to a peripheral
+/// and after the last read from a peripheral.
+/// We place a barrier after every read and before every write, which covers both.
+#[inline]
+pub(crate) fn peripheral_barrier() {
+	core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
 }
 
-fn open_rw(path: impl Into<std::path::PathBuf>) -> Result<FileWithPath, Error> {
-	let path = path.into();
-	let file = std::fs::OpenOptions::new().create(false).read(true).write(true).open(&path)
-		.map_err(|e| Error::from_io(format!("failed to open {}", path.display()), e))?;
+/// The log target of the register write records.
+#[cfg(feature = "log")]
+const LOG_TARGET : &str = "bcm283x_linux_gpio::registers";
 
-	Ok(FileWithPath {
-		path,
-		file,
-	})
+/// Emit a trace record for a register write.
+#[inline]
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn trace_write(reg: Register, value: u32) {
+	#[cfg(feature = "log")]
+	log::trace!(target: LOG_TARGET, "{} = 0x{:08X}", reg, value);
 }
 
-fn read_all(file: FileWithPath) -> Result<Vec<u8>, Error> {
-	let mut file = file;
-	let mut data = Vec::new();
-	file.file.read_to_end(&mut data).map_err(|e| Error::from_io(format!("failed to read from {}", file.path.display()), e))?;
-	Ok(data)
+/// Emit a trace record for a read-modify-write cycle on a register.
+#[inline]
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn trace_modify(reg: Register, old: u32, new: u32) {
+	#[cfg(feature = "log")]
+	log::trace!(target: LOG_TARGET, "{} = 0x{:08X} (was 0x{:08X}, changed mask 0x{:08X})", reg, new, old, old ^ new);
 }
 
-/// Check whether the current platform has a bcm2835-gpio peripheral at the expected bus address.
-pub fn check_bcm283x_gpio() -> Result<(), Error> {
-	const EXPECTED: &str = "brcm,bcm2835-gpio";
-
-	let file = open("/proc/device-tree/soc/gpio@7e200000/compatible")?;
-	let mut data = read_all(file)?;
-	if data.last() == Some(&0) {
-		data.pop();
-	}
-
-	if data == EXPECTED.as_bytes() {
+fn check_pin_index(index: usize) -> Result<(), Error> {
+	if index <= 53 {
 		Ok(())
 	} else {
-		Err(Error::new(format!("invalid gpio peripheral type, expected {}, got {:?}", EXPECTED, String::from_utf8_lossy(&data)), None))
+		Err(Error::new(ErrorKind::InvalidPinIndex, format!("gpio pin index out of range, expected a value in the range [0-53], got {}", index)))
 	}
 }
 
-/// Read the GPIO peripheral base address from /proc/iomem.
-fn read_gpio_address() -> Result<i64, Error> {
-	let file = open("/proc/iomem")?;
-	let data = read_all(file)?;
-
-	// Loop over lines.
-	for (i, line) in data.split(|c| *c == b'\n').enumerate().filter(|(_, line)| !line.is_empty()) {
-		// Split kernel range from peripheral name.
-		let (range, peripheral) = partition(line, b':').map_err(|_| Error::new(format!("malformed entry in /proc/iomem on line {}", i), None))?;
-		let range = trim(range);
-		let peripheral = trim(peripheral);
-
-		if peripheral.ends_with(b".gpio") || peripheral.ends_with(b".gpio gpio@7e200000") {
-			let (start, _end) = partition(range, b'-').map_err(|_| Error::new(format!("malformed entry in /proc/iomem on line {}", i), None))?;
-			let start = std::str::from_utf8(start).map_err(|_| Error::new(format!("malformed entry in /proc/iomem on line {}", i), None))?;
-			let start = i64::from_str_radix(start, 16).map_err(|_| Error::new(format!("invalid start address in /proc/iomem on line {}: {}", i, start), None))?;
-			return Ok(start);
-		}
-	}
-
-	Err(Error::new(&"failed to find GPIO peripheral in /proc/iomem", None))
-}
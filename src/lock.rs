@@ -0,0 +1,38 @@
+#[cfg(not(feature = "stub"))]
+use nix::fcntl::{flock, FlockArg};
+#[cfg(not(feature = "stub"))]
+use std::os::unix::io::AsRawFd;
+
+use crate::Error;
+#[cfg(not(feature = "stub"))]
+use crate::ErrorKind;
+
+/// The lock file used to coordinate access to the GPIO peripheral between processes.
+pub const LOCK_FILE: &str = "/run/lock/bcm283x-gpio.lock";
+
+/// An advisory lock on [`LOCK_FILE`].
+///
+/// The lock is released when the file is closed.
+pub(crate) struct GpioLock {
+	_file: std::fs::File,
+}
+
+impl GpioLock {
+	/// Acquire the lock, blocking until it is available.
+	#[cfg(not(feature = "stub"))]
+	pub(crate) fn acquire(exclusive: bool) -> Result<Self, Error> {
+		let file = std::fs::OpenOptions::new().create(true).truncate(false).read(true).write(true).open(LOCK_FILE)
+			.map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to open {}", LOCK_FILE), e))?;
+
+		let arg = if exclusive { FlockArg::LockExclusive } else { FlockArg::LockShared };
+		flock(file.as_raw_fd(), arg)
+			.map_err(|e| Error::from_nix(ErrorKind::Io, format!("failed to lock {}", LOCK_FILE), e))?;
+
+		Ok(Self { _file: file })
+	}
+
+	#[cfg(feature = "stub")]
+	pub(crate) fn acquire(_exclusive: bool) -> Result<Self, Error> {
+		Err(crate::procfs::unsupported(format_args!("locking {}", LOCK_FILE)))
+	}
+}
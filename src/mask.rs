@@ -0,0 +1,206 @@
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign};
+
+use crate::Error;
+#[cfg(feature = "std")]
+use crate::{Gpio, Register};
+
+/// The valid bits of the second register word: only pins 32 to 53 exist.
+const HIGH_WORD_MASK: u32 = (1 << 22) - 1;
+
+/// A set of GPIO pins.
+///
+/// The set is stored as the two 32 bit words used by the GPSET and GPCLR registers,
+/// so setting or clearing all pins in the set needs no bit manipulation at all.
+/// This makes it suitable for tight loops, such as generating waveforms on multiple pins.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PinMask {
+	words: [u32; 2],
+}
+
+impl PinMask {
+	/// Create an empty pin mask.
+	pub const fn empty() -> Self {
+		Self { words: [0, 0] }
+	}
+
+	/// Create a pin mask with all 54 pins.
+	pub const fn all() -> Self {
+		Self { words: [u32::MAX, HIGH_WORD_MASK] }
+	}
+
+	/// Create a pin mask with a single pin.
+	pub fn pin(index: usize) -> Result<Self, Error> {
+		let mut mask = Self::empty();
+		mask.insert(index)?;
+		Ok(mask)
+	}
+
+	/// Create a pin mask from a list of pins.
+	///
+	/// Unlike collecting into a mask, this fails if any of the pins is out of range.
+	pub fn try_from_pins(pins: impl IntoIterator<Item = usize>) -> Result<Self, Error> {
+		let mut mask = Self::empty();
+		for index in pins {
+			mask.insert(index)?;
+		}
+		Ok(mask)
+	}
+
+	/// Create a pin mask from a bitmask where bit N represents pin N.
+	///
+	/// Bits above pin 53 are ignored.
+	pub const fn from_bits(bits: u64) -> Self {
+		Self { words: [bits as u32, (bits >> 32) as u32 & HIGH_WORD_MASK] }
+	}
+
+	/// Get the mask as bitmask where bit N represents pin N.
+	pub const fn to_bits(self) -> u64 {
+		self.words[0] as u64 | (self.words[1] as u64) << 32
+	}
+
+	/// Get the register words of the mask, for pins 0-31 and pins 32-53.
+	pub const fn words(self) -> [u32; 2] {
+		self.words
+	}
+
+	/// Add a pin to the mask.
+	pub fn insert(&mut self, index: usize) -> Result<(), Error> {
+		crate::check_pin_index(index)?;
+		self.words[index / 32] |= 1 << (index % 32);
+		Ok(())
+	}
+
+	/// Remove a pin from the mask.
+	pub fn remove(&mut self, index: usize) -> Result<(), Error> {
+		crate::check_pin_index(index)?;
+		self.words[index / 32] &= !(1 << (index % 32));
+		Ok(())
+	}
+
+	/// Check if the mask contains a pin.
+	pub fn contains(self, index: usize) -> bool {
+		index <= 53 && self.words[index / 32] & 1 << (index % 32) != 0
+	}
+
+	/// Check if the mask is empty.
+	pub const fn is_empty(self) -> bool {
+		self.words[0] == 0 && self.words[1] == 0
+	}
+
+	/// Get the number of pins in the mask.
+	pub const fn len(self) -> usize {
+		(self.words[0].count_ones() + self.words[1].count_ones()) as usize
+	}
+
+	/// Iterate over the pins in the mask, in ascending order.
+	pub fn iter(self) -> impl Iterator<Item = usize> {
+		(0..54).filter(move |&index| self.contains(index))
+	}
+
+	/// Set all pins in the mask high, using one write per non-empty register word.
+	#[cfg(feature = "std")]
+	pub fn set_all(self, gpio: &mut Gpio) {
+		self.write_words(gpio, Register::GPSET0, Register::GPSET1);
+	}
+
+	/// Set all pins in the mask low, using one write per non-empty register word.
+	#[cfg(feature = "std")]
+	pub fn clear_all(self, gpio: &mut Gpio) {
+		self.write_words(gpio, Register::GPCLR0, Register::GPCLR1);
+	}
+
+	/// Set the pins in the mask to the levels given by another mask.
+	///
+	/// Pins in both masks are set high, pins only in this mask are set low.
+	/// The high pins are set before the low pins are cleared.
+	#[cfg(feature = "std")]
+	pub fn write_levels(self, gpio: &mut Gpio, high: PinMask) {
+		(self & high).set_all(gpio);
+		(self - high).clear_all(gpio);
+	}
+
+	#[cfg(feature = "std")]
+	fn write_words(self, gpio: &mut Gpio, low: Register, high: Register) {
+		// Writing zero has no effect on GPSET and GPCLR, so skip empty words.
+		unsafe {
+			if self.words[0] != 0 {
+				gpio.write_register(low, self.words[0]);
+			}
+			if self.words[1] != 0 {
+				gpio.write_register(high, self.words[1]);
+			}
+		}
+	}
+}
+
+/// Collect pins into a mask, ignoring pins that are out of range.
+///
+/// Use [`PinMask::try_from_pins`] to detect invalid pins instead.
+impl core::iter::FromIterator<usize> for PinMask {
+	fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+		let mut mask = Self::empty();
+		for index in iter {
+			let _ = mask.insert(index);
+		}
+		mask
+	}
+}
+
+impl BitOr for PinMask {
+	type Output = Self;
+	fn bitor(self, other: Self) -> Self {
+		Self { words: [self.words[0] | other.words[0], self.words[1] | other.words[1]] }
+	}
+}
+
+impl BitAnd for PinMask {
+	type Output = Self;
+	fn bitand(self, other: Self) -> Self {
+		Self { words: [self.words[0] & other.words[0], self.words[1] & other.words[1]] }
+	}
+}
+
+impl BitXor for PinMask {
+	type Output = Self;
+	fn bitxor(self, other: Self) -> Self {
+		Self { words: [self.words[0] ^ other.words[0], self.words[1] ^ other.words[1]] }
+	}
+}
+
+impl Sub for PinMask {
+	type Output = Self;
+	fn sub(self, other: Self) -> Self {
+		self & !other
+	}
+}
+
+impl Not for PinMask {
+	type Output = Self;
+	fn not(self) -> Self {
+		Self { words: [!self.words[0], !self.words[1] & HIGH_WORD_MASK] }
+	}
+}
+
+impl BitOrAssign for PinMask {
+	fn bitor_assign(&mut self, other: Self) {
+		*self = *self | other;
+	}
+}
+
+impl BitAndAssign for PinMask {
+	fn bitand_assign(&mut self, other: Self) {
+		*self = *self & other;
+	}
+}
+
+impl BitXorAssign for PinMask {
+	fn bitxor_assign(&mut self, other: Self) {
+		*self = *self ^ other;
+	}
+}
+
+impl SubAssign for PinMask {
+	fn sub_assign(&mut self, other: Self) {
+		*self = *self - other;
+	}
+}
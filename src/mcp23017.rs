@@ -0,0 +1,257 @@
+use crate::{Edge, Error, ErrorKind, Gpio, Level, PinFunction, PullMode, SoftI2c, SoftSpi};
+
+const IODIR   : u8 = 0x00;
+const GPINTEN : u8 = 0x04;
+const INTCON  : u8 = 0x08;
+const IOCON   : u8 = 0x0A;
+const GPPU    : u8 = 0x0C;
+const INTF    : u8 = 0x0E;
+const INTCAP  : u8 = 0x10;
+const GPIO    : u8 = 0x12;
+const OLAT    : u8 = 0x14;
+
+/// Connect the INTA and INTB outputs internally.
+///
+/// IOCON is mapped at both addresses of the register pair, so its bits are set in both bytes.
+const IOCON_MIRROR : u16 = 0x4040;
+
+/// Enable the hardware address pins of the MCP23S17.
+const IOCON_HAEN : u16 = 0x0808;
+
+/// The number of pins of the expander.
+pub const MCP23017_PINS : usize = 16;
+
+/// The bus an expander is connected to.
+enum Bus<'a> {
+	I2c(SoftI2c<'a>, u8),
+	Spi(SoftSpi<'a>, u8),
+}
+
+/// A pending interrupt of an [`Mcp23017`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ExpanderInterrupt {
+	/// The pins that caused the interrupt, bit N for pin N.
+	pub pins: u16,
+	/// The levels of all pins at the time of the interrupt.
+	pub levels: u16,
+}
+
+/// Driver for the MCP23017 (I2C) and MCP23S17 (SPI) 16 pin GPIO expanders.
+///
+/// Pins 0-7 are GPA0-GPA7 and pins 8-15 are GPB0-GPB7.
+/// The pins are used with the same functions as the pins of the SoC through [`Gpio`],
+/// but only support the input and output functions and the pull-up resistor.
+///
+/// Interrupts on change can be mirrored onto a single SoC pin with [`mirror_interrupts`](Self::mirror_interrupts),
+/// which connects both interrupt outputs of the expander internally.
+/// The registers are accessed with read-modify-write cycles over the bus, so nothing is cached.
+pub struct Mcp23017<'a> {
+	bus: Bus<'a>,
+	interrupt_pin: Option<usize>,
+	detect_rise: u16,
+	detect_fall: u16,
+}
+
+impl<'a> Mcp23017<'a> {
+	/// Use an MCP23017 on a bit-banged I2C bus, with an address in the range 0x20-0x27.
+	pub fn new_i2c(bus: SoftI2c<'a>, address: u8) -> Result<Self, Error> {
+		if !(0x20..=0x27).contains(&address) {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid MCP23017 address: 0x{:02X}, expected 0x20-0x27", address)));
+		}
+		let mut expander = Self { bus: Bus::I2c(bus, address), interrupt_pin: None, detect_rise: 0, detect_fall: 0 };
+		expander.read_register(IOCON)?;
+		Ok(expander)
+	}
+
+	/// Use an MCP23S17 on a bit-banged SPI bus, with a hardware address in the range 0-7.
+	///
+	/// The SPI bus must use mode 0 or 3.
+	/// The hardware address pins are enabled, so all expanders on the same chip select must be configured before use.
+	pub fn new_spi(bus: SoftSpi<'a>, address: u8) -> Result<Self, Error> {
+		if address > 7 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid MCP23S17 hardware address: {}, expected 0-7", address)));
+		}
+		let mut expander = Self { bus: Bus::Spi(bus, address), interrupt_pin: None, detect_rise: 0, detect_fall: 0 };
+		expander.modify_register(IOCON, |x| x | IOCON_HAEN)?;
+		Ok(expander)
+	}
+
+	/// Set the function of a pin, which must be input or output.
+	pub fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error> {
+		let bit = check_pin_index(index)?;
+		match function {
+			PinFunction::Input  => self.modify_register(IODIR, |x| x | bit),
+			PinFunction::Output => self.modify_register(IODIR, |x| x & !bit),
+			_ => Err(Error::new(ErrorKind::Unsupported, format!("the MCP23017 does not support the {:?} function", function))),
+		}
+	}
+
+	/// Read the functions of all pins.
+	pub fn read_functions(&mut self) -> Result<[PinFunction; MCP23017_PINS], Error> {
+		let inputs = self.read_register(IODIR)?;
+		let mut functions = [PinFunction::Output; MCP23017_PINS];
+		for (pin, function) in functions.iter_mut().enumerate() {
+			if inputs >> pin & 1 == 1 {
+				*function = PinFunction::Input;
+			}
+		}
+		Ok(functions)
+	}
+
+	/// Set the pull mode of a pin, which must be floating or pull-up.
+	pub fn set_pull_mode(&mut self, index: usize, mode: PullMode) -> Result<(), Error> {
+		let bit = check_pin_index(index)?;
+		match mode {
+			PullMode::Float    => self.modify_register(GPPU, |x| x & !bit),
+			PullMode::PullUp   => self.modify_register(GPPU, |x| x | bit),
+			PullMode::PullDown => Err(Error::new(ErrorKind::Unsupported, "the MCP23017 has no pull-down resistors")),
+		}
+	}
+
+	/// Read the pull mode of a pin.
+	pub fn read_pull_mode(&mut self, index: usize) -> Result<PullMode, Error> {
+		let bit = check_pin_index(index)?;
+		match self.read_register(GPPU)? & bit {
+			0 => Ok(PullMode::Float),
+			_ => Ok(PullMode::PullUp),
+		}
+	}
+
+	/// Read the current level of a pin.
+	pub fn read_level(&mut self, index: usize) -> Result<Level, Error> {
+		let bit = check_pin_index(index)?;
+		Ok((self.read_levels()? & bit != 0).into())
+	}
+
+	/// Read the current level of all pins.
+	///
+	/// Bit N of the returned value holds the level of pin N.
+	pub fn read_levels(&mut self) -> Result<u16, Error> {
+		self.read_register(GPIO)
+	}
+
+	/// Set the level of a single pin.
+	///
+	/// The level is latched even if the pin is an input, and applied when it becomes an output.
+	pub fn set_level(&mut self, index: usize, value: impl Into<Level>) -> Result<(), Error> {
+		let bit = check_pin_index(index)?;
+		match value.into() {
+			Level::High => self.modify_register(OLAT, |x| x | bit),
+			Level::Low  => self.modify_register(OLAT, |x| x & !bit),
+		}
+	}
+
+	/// Set the levels of all pins in a mask at once.
+	///
+	/// Bit N of `mask` selects pin N, and bit N of `levels` holds its new level.
+	pub fn set_levels(&mut self, mask: u16, levels: u16) -> Result<(), Error> {
+		self.modify_register(OLAT, |x| x & !mask | levels & mask)
+	}
+
+	/// Enable or disable the detection of an edge on a pin.
+	///
+	/// The expander raises an interrupt on every change of a pin with any edge detection enabled,
+	/// so [`read_interrupt`](Self::read_interrupt) may report changes with the other edge too.
+	pub fn set_edge_detect(&mut self, index: usize, edge: Edge, enabled: bool) -> Result<(), Error> {
+		let bit = check_pin_index(index)?;
+		let mask = match edge {
+			Edge::Rise => &mut self.detect_rise,
+			Edge::Fall => &mut self.detect_fall,
+		};
+		match enabled {
+			true  => *mask |= bit,
+			false => *mask &= !bit,
+		}
+		let enabled = self.detect_rise | self.detect_fall;
+		// Compare against the previous value, so every change triggers an interrupt.
+		self.modify_register(INTCON, |x| x & !bit)?;
+		self.modify_register(GPINTEN, |x| x & !bit | enabled & bit)
+	}
+
+	/// Get the pins with rising and falling edge detection enabled.
+	pub(crate) fn edge_detect(&self) -> (u16, u16) {
+		(self.detect_rise, self.detect_fall)
+	}
+
+	/// Mirror the interrupts of all pins onto a single SoC pin.
+	///
+	/// The INTA and INTB outputs are connected internally, so only one of them needs to be wired to the SoC pin.
+	/// The outputs are active low and push-pull, so the SoC pin is configured as input without pull resistor.
+	pub fn mirror_interrupts(&mut self, soc_pin: usize) -> Result<(), Error> {
+		crate::check_pin_index(soc_pin)?;
+		self.modify_register(IOCON, |x| x | IOCON_MIRROR)?;
+		self.gpio().write_function(soc_pin, PinFunction::Input);
+		self.interrupt_pin = Some(soc_pin);
+		Ok(())
+	}
+
+	/// Check if an interrupt is signaled on the SoC pin given to [`mirror_interrupts`](Self::mirror_interrupts).
+	///
+	/// This only reads the SoC pin, so it is cheap enough to poll.
+	pub fn interrupt_pending(&mut self) -> Result<bool, Error> {
+		let pin = self.interrupt_pin.ok_or_else(|| Error::new(ErrorKind::InvalidArgument, "interrupts are not mirrored onto a SoC pin"))?;
+		Ok(!self.gpio().level(pin))
+	}
+
+	/// Read and clear the pending interrupt, if any.
+	///
+	/// This reads the interrupt flags and the captured levels from the expander,
+	/// which releases the interrupt output.
+	pub fn read_interrupt(&mut self) -> Result<Option<ExpanderInterrupt>, Error> {
+		let pins = self.read_register(INTF)?;
+		if pins == 0 {
+			return Ok(None);
+		}
+		let levels = self.read_register(INTCAP)?;
+		Ok(Some(ExpanderInterrupt { pins, levels }))
+	}
+
+	/// Read a pair of A and B registers as one 16 bit value.
+	fn read_register(&mut self, register: u8) -> Result<u16, Error> {
+		let mut data = [0; 2];
+		match &mut self.bus {
+			Bus::I2c(bus, address) => {
+				bus.write(*address, &[register])?;
+				bus.read(*address, &mut data)?;
+			},
+			Bus::Spi(bus, address) => {
+				let mut buffer = [0x41 | *address << 1, register, 0, 0];
+				bus.transfer(&mut buffer);
+				data.copy_from_slice(&buffer[2..]);
+			},
+		}
+		Ok(u16::from_le_bytes(data))
+	}
+
+	/// Write a pair of A and B registers as one 16 bit value.
+	fn write_register(&mut self, register: u8, value: u16) -> Result<(), Error> {
+		let [low, high] = value.to_le_bytes();
+		match &mut self.bus {
+			Bus::I2c(bus, address) => bus.write(*address, &[register, low, high]),
+			Bus::Spi(bus, address) => {
+				bus.transfer(&mut [0x40 | *address << 1, register, low, high]);
+				Ok(())
+			},
+		}
+	}
+
+	fn modify_register(&mut self, register: u8, f: impl FnOnce(u16) -> u16) -> Result<(), Error> {
+		let value = self.read_register(register)?;
+		self.write_register(register, f(value))
+	}
+
+	fn gpio(&mut self) -> &mut Gpio {
+		match &mut self.bus {
+			Bus::I2c(bus, _) => bus.gpio(),
+			Bus::Spi(bus, _) => bus.gpio(),
+		}
+	}
+}
+
+fn check_pin_index(index: usize) -> Result<u16, Error> {
+	if index < MCP23017_PINS {
+		Ok(1 << index)
+	} else {
+		Err(Error::new(ErrorKind::InvalidPinIndex, format!("MCP23017 pin index out of range, expected a value in the range [0-15], got {}", index)))
+	}
+}
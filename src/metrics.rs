@@ -0,0 +1,89 @@
+use std::fmt::Write;
+
+use crate::{Edge, Error, GpioState};
+
+/// Pin metrics in the Prometheus text exposition format.
+///
+/// The levels and functions are taken from a [`GpioState`] when rendering.
+/// Edges are counted by sampling the pin levels with [`update`](Self::update),
+/// so pulses shorter than the sampling interval are missed.
+#[derive(Clone, Debug)]
+pub struct GpioMetrics {
+	levels: Option<u64>,
+	rising: [u64; 54],
+	falling: [u64; 54],
+}
+
+impl Default for GpioMetrics {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl GpioMetrics {
+	/// Create metrics with all edge counters at zero.
+	pub fn new() -> Self {
+		Self {
+			levels: None,
+			rising: [0; 54],
+			falling: [0; 54],
+		}
+	}
+
+	/// Count the edges since the previous update.
+	///
+	/// Bit N of `levels` holds the level of pin N, as returned by [`Gpio::read_levels`](crate::Gpio::read_levels).
+	/// The first update only records the levels.
+	pub fn update(&mut self, levels: u64) {
+		let previous = match self.levels.replace(levels) {
+			Some(x) => x,
+			None    => return,
+		};
+
+		let changed = levels ^ previous;
+		for pin in (0..54).filter(|pin| changed >> pin & 1 != 0) {
+			match levels >> pin & 1 != 0 {
+				true  => self.rising[pin] += 1,
+				false => self.falling[pin] += 1,
+			}
+		}
+	}
+
+	/// Get the number of edges counted on a pin.
+	pub fn edges(&self, pin: usize, edge: Edge) -> Result<u64, Error> {
+		crate::check_pin_index(pin)?;
+		Ok(match edge {
+			Edge::Rise => self.rising[pin],
+			Edge::Fall => self.falling[pin],
+		})
+	}
+
+	/// Render the metrics for all pins.
+	pub fn render(&self, state: &GpioState) -> String {
+		let pins = (0..54).filter_map(|pin| state.pin(pin).ok().map(|info| (pin, info))).collect::<Vec<_>>();
+		let mut output = String::new();
+
+		// Writing to a String can not fail.
+		let _ = writeln!(output, "# HELP bcm283x_gpio_level The current level of the pin.");
+		let _ = writeln!(output, "# TYPE bcm283x_gpio_level gauge");
+		for (pin, info) in &pins {
+			let _ = writeln!(output, "bcm283x_gpio_level{{pin=\"{}\"}} {}", pin, u8::from(info.level.is_high()));
+		}
+
+		let _ = writeln!(output, "# HELP bcm283x_gpio_function The current function of the pin, as a label.");
+		let _ = writeln!(output, "# TYPE bcm283x_gpio_function gauge");
+		for (pin, info) in &pins {
+			let function = format!("{:?}", info.function).to_lowercase();
+			let _ = writeln!(output, "bcm283x_gpio_function{{pin=\"{}\",function=\"{}\"}} 1", pin, function);
+		}
+
+		let _ = writeln!(output, "# HELP bcm283x_gpio_edges_total The number of edges seen on the pin.");
+		let _ = writeln!(output, "# TYPE bcm283x_gpio_edges_total counter");
+		for (pin, _) in &pins {
+			let _ = writeln!(output, "bcm283x_gpio_edges_total{{pin=\"{}\",edge=\"rise\"}} {}", pin, self.rising[*pin]);
+			let _ = writeln!(output, "bcm283x_gpio_edges_total{{pin=\"{}\",edge=\"fall\"}} {}", pin, self.falling[*pin]);
+		}
+
+		output
+	}
+}
@@ -0,0 +1,303 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{Capabilities, Error, Gpio, GpioBackend, GpioState, PinFunction, Register};
+
+/// The bits of all 54 pins in a pair of registers.
+const ALL_PINS : u64 = (1 << 54) - 1;
+
+/// A backend with simulated registers in memory, for development and tests without hardware.
+///
+/// The registers start out zeroed, like after a reset: all pins are inputs and read low.
+/// Writes are simulated as follows:
+///  - the set and clear registers change the output latch, which drives the level of output pins;
+///  - the level of input pins is controlled with [`set_input_level`](Self::set_input_level);
+///  - level changes set the event status bits of pins with edge or level detection enabled;
+///  - writing ones to the event status registers clears those bits;
+///  - the level registers are read-only.
+///
+/// The pull up/down registers simply hold the last written value and do not affect the input levels.
+///
+/// Clones of a backend share the same simulated registers,
+/// so a clone can be kept to control the inputs of a [`Gpio`] handle using the backend.
+#[derive(Clone, Default)]
+pub struct MockBackend {
+	state: Arc<Mutex<MockState>>,
+}
+
+/// A GPIO handle with a [`MockBackend`].
+///
+/// The mock dereferences to a [`Gpio`], so it can be used wherever a `&Gpio` or `&mut Gpio` is expected,
+/// including [`GpioConfig::apply`](crate::GpioConfig::apply) and the software protocols.
+pub struct MockGpio {
+	gpio: Gpio,
+	backend: MockBackend,
+}
+
+struct MockState {
+	registers: [u32; crate::CONTROL_BLOCK_SIZE / 4],
+
+	/// The output latch, as changed by the set and clear registers.
+	latch: u64,
+
+	/// The levels driven onto the pins from outside.
+	inputs: u64,
+}
+
+impl Default for MockState {
+	fn default() -> Self {
+		Self { registers: [0; crate::CONTROL_BLOCK_SIZE / 4], latch: 0, inputs: 0 }
+	}
+}
+
+impl MockBackend {
+	/// Create a new backend with all registers zeroed.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a new backend with the registers of a saved state, to replay it.
+	///
+	/// The output latch and the input levels are taken from the level registers,
+	/// so the pins keep their levels until they are changed.
+	pub fn from_state(state: &GpioState) -> Self {
+		let mut mock = MockState::default();
+		mock.registers.copy_from_slice(&state.data()[..crate::CONTROL_BLOCK_SIZE / 4]);
+		let levels = mock.read_pair(Register::GPLEV0, Register::GPLEV1) & ALL_PINS;
+		mock.latch  = levels;
+		mock.inputs = levels;
+		Self { state: Arc::new(Mutex::new(mock)) }
+	}
+
+	/// Set the level driven onto a pin from outside.
+	///
+	/// The level is only visible while the pin is not configured as output.
+	pub fn set_input_level(&self, index: usize, value: bool) -> Result<(), Error> {
+		crate::check_pin_index(index)?;
+		let mut state = self.lock();
+		match value {
+			true  => state.inputs |= 1 << index,
+			false => state.inputs &= !(1 << index),
+		}
+		state.update();
+		Ok(())
+	}
+
+	/// Get the levels driven onto the pins from outside.
+	///
+	/// Bit N of the returned value holds the input level of pin N.
+	pub fn input_levels(&self) -> u64 {
+		self.lock().inputs
+	}
+
+	fn lock(&self) -> MutexGuard<'_, MockState> {
+		self.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl GpioBackend for MockBackend {
+	fn name(&self) -> &str {
+		"mock"
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities::all()
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		self.lock().registers[reg as usize / 4]
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		self.lock().write(reg, value)
+	}
+}
+
+impl MockGpio {
+	/// Create a new mock with all registers zeroed.
+	pub fn new() -> Self {
+		let backend = MockBackend::new();
+		let gpio    = Gpio::with_backend(backend.clone());
+		Self { gpio, backend }
+	}
+
+	/// Create a new mock with the registers of a saved state, to replay it.
+	///
+	/// See [`MockBackend::from_state`].
+	pub fn from_state(state: &GpioState) -> Self {
+		let backend = MockBackend::from_state(state);
+		let gpio    = Gpio::with_backend(backend.clone());
+		Self { gpio, backend }
+	}
+
+	/// Get the backend of the mock, to control the simulated inputs.
+	pub fn backend(&self) -> &MockBackend {
+		&self.backend
+	}
+
+	/// Set the level driven onto a pin from outside.
+	///
+	/// See [`MockBackend::set_input_level`].
+	pub fn set_input_level(&self, index: usize, value: bool) -> Result<(), Error> {
+		self.backend.set_input_level(index, value)
+	}
+
+	/// Get the levels driven onto the pins from outside.
+	pub fn input_levels(&self) -> u64 {
+		self.backend.input_levels()
+	}
+}
+
+impl Default for MockGpio {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl std::ops::Deref for MockGpio {
+	type Target = Gpio;
+
+	fn deref(&self) -> &Gpio {
+		&self.gpio
+	}
+}
+
+impl std::ops::DerefMut for MockGpio {
+	fn deref_mut(&mut self) -> &mut Gpio {
+		&mut self.gpio
+	}
+}
+
+impl MockState {
+	/// Simulate a write to a register.
+	fn write(&mut self, reg: Register, value: u32) {
+		let word = &mut self.registers[reg as usize / 4];
+		match reg {
+			Register::GPSET0 => self.latch |= u64::from(value),
+			Register::GPSET1 => self.latch |= u64::from(value) << 32,
+			Register::GPCLR0 => self.latch &= !u64::from(value),
+			Register::GPCLR1 => self.latch &= !(u64::from(value) << 32),
+			Register::GPEDS0 | Register::GPEDS1 => *word &= !value,
+			Register::GPLEV0 | Register::GPLEV1 => (),
+			_ => *word = value,
+		}
+		self.update();
+	}
+
+	/// Update the level and event status registers after a change.
+	fn update(&mut self) {
+		let outputs = self.output_pins();
+		let old = self.read_pair(Register::GPLEV0, Register::GPLEV1);
+		let new = (self.latch & outputs | self.inputs & !outputs) & ALL_PINS;
+		self.write_pair(Register::GPLEV0, Register::GPLEV1, new);
+
+		let rise  = self.read_pair(Register::GPREN0, Register::GPREN1) | self.read_pair(Register::GPAREN0, Register::GPAREN1);
+		let fall  = self.read_pair(Register::GPFEN0, Register::GPFEN1) | self.read_pair(Register::GPAFEN0, Register::GPAFEN1);
+		let high  = self.read_pair(Register::GPHEN0, Register::GPHEN1);
+		let low   = self.read_pair(Register::GPLEN0, Register::GPLEN1);
+		let events = (!old & new & rise) | (old & !new & fall) | (new & high) | (!new & low);
+
+		let status = self.read_pair(Register::GPEDS0, Register::GPEDS1);
+		self.write_pair(Register::GPEDS0, Register::GPEDS1, (status | events) & ALL_PINS);
+	}
+
+	/// Get the pins configured as output from the function select registers.
+	fn output_pins(&self) -> u64 {
+		let mut outputs = 0;
+		for pin in 0..54 {
+			let register = self.registers[Register::GPFSEL0 as usize / 4 + pin / 10];
+			if PinFunction::from_bits((register >> (pin % 10 * 3) & 0b111) as u8) == PinFunction::Output {
+				outputs |= 1 << pin;
+			}
+		}
+		outputs
+	}
+
+	fn read_pair(&self, low: Register, high: Register) -> u64 {
+		u64::from(self.registers[low as usize / 4]) | u64::from(self.registers[high as usize / 4]) << 32
+	}
+
+	fn write_pair(&mut self, low: Register, high: Register, value: u64) {
+		self.registers[low as usize / 4]  = value as u32;
+		self.registers[high as usize / 4] = (value >> 32) as u32;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GpioConfig, Level};
+
+	#[test]
+	fn outputs_follow_the_latch() {
+		let mut gpio = MockGpio::new();
+		gpio.set_level(17, Level::High).unwrap();
+		assert_eq!(gpio.read_pin(17).unwrap().level, Level::Low);
+
+		// The latch drives the pin as soon as it becomes an output.
+		gpio.set_function(17, PinFunction::Output).unwrap();
+		assert_eq!(gpio.read_pin(17).unwrap().level, Level::High);
+
+		gpio.set_level(17, Level::Low).unwrap();
+		assert_eq!(gpio.read_pin(17).unwrap().level, Level::Low);
+
+		// Writes to the level registers are ignored.
+		unsafe { gpio.write_register(Register::GPLEV0, u32::MAX) };
+		assert_eq!(gpio.read_register(Register::GPLEV0), 0);
+	}
+
+	#[test]
+	fn inputs_follow_the_simulated_levels() {
+		let gpio = MockGpio::new();
+		gpio.set_input_level(40, true).unwrap();
+		assert_eq!(gpio.read_pin(40).unwrap().level, Level::High);
+		assert_eq!(gpio.input_levels(), 1 << 40);
+		assert!(gpio.set_input_level(54, true).is_err());
+	}
+
+	#[test]
+	fn edges_set_the_event_status() {
+		let mut gpio = MockGpio::new();
+		unsafe { gpio.write_register(Register::GPREN0, 1 << 4) };
+		gpio.set_input_level(4, true).unwrap();
+		gpio.set_input_level(4, false).unwrap();
+		assert!(gpio.read_pin(4).unwrap().event);
+
+		// Writing a one clears the status bit.
+		unsafe { gpio.write_register(Register::GPEDS0, 1 << 4) };
+		assert!(!gpio.read_pin(4).unwrap().event);
+	}
+
+	#[test]
+	fn replay_a_saved_state() {
+		let mut gpio = MockGpio::new();
+		gpio.set_output(17, Level::High).unwrap();
+		gpio.set_input_level(4, true).unwrap();
+
+		let replay = MockGpio::from_state(&gpio.read_all());
+		assert_eq!(replay.read_pin(17).unwrap().function, PinFunction::Output);
+		assert_eq!(replay.read_pin(17).unwrap().level, Level::High);
+		assert_eq!(replay.read_pin(4).unwrap().level, Level::High);
+	}
+
+	#[test]
+	fn apply_config() {
+		let mut gpio = MockGpio::new();
+		gpio.set_input_level(4, true).unwrap();
+
+		let mut config = GpioConfig::new();
+		config.set_function(17, PinFunction::Output).unwrap();
+		config.set_level(17, Level::High).unwrap();
+		config.set_function(4, PinFunction::Input).unwrap();
+		config.set_detect_rise(4, true).unwrap();
+		config.apply(&mut gpio).unwrap();
+
+		let pin = gpio.read_pin(17).unwrap();
+		assert_eq!(pin.function, PinFunction::Output);
+		assert_eq!(pin.level, Level::High);
+
+		let pin = gpio.read_pin(4).unwrap();
+		assert_eq!(pin.function, PinFunction::Input);
+		assert_eq!(pin.level, Level::High);
+		assert!(pin.detect_rise);
+	}
+}
@@ -0,0 +1,138 @@
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{Edge, Error, ErrorKind, EventListener, Gpio, GpioEvent};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+struct Shared {
+	listener: Mutex<EventListener>,
+
+	/// Set to stop the notifier thread.
+	stop: AtomicBool,
+}
+
+impl Shared {
+	fn listener(&self) -> MutexGuard<'_, EventListener> {
+		self.listener.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Signals GPIO edges on an eventfd, for applications with their own poll or epoll loop.
+///
+/// A background thread polls the pins with an [`EventListener`] at a fixed interval.
+/// When edges are detected, they are queued and the number of new events is added to the eventfd counter.
+/// The application waits for the eventfd to become readable in its own loop,
+/// reads the counter to reset it, and takes the events with [`pop`](Self::pop).
+///
+/// The eventfd is supplied by the application and is never closed by the notifier.
+/// It must stay open until the notifier is stopped.
+pub struct EventNotifier {
+	eventfd: RawFd,
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EventNotifier {
+	/// Start polling the pins at an interval, notifying the given eventfd of new events.
+	///
+	/// No events are detected until edges are added with [`watch`](Self::watch).
+	pub fn start(eventfd: RawFd, interval: Duration) -> Result<Self, Error> {
+		Self::start_with_options(eventfd, interval, ThreadOptions::default())
+	}
+
+	/// Start notifying an eventfd of new events, with options for the background thread.
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn start_with_options(eventfd: RawFd, interval: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?, eventfd, interval, options)
+	}
+
+	/// Start notifying an eventfd of new events, moving an existing handle to the background thread.
+	pub fn start_with_gpio(gpio: Gpio, eventfd: RawFd, interval: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "event notifier polling interval must not be zero"));
+		}
+
+		let shared = Arc::new(Shared {
+			listener: Mutex::new(EventListener::new()),
+			stop:     AtomicBool::new(false),
+		});
+
+		let thread = spawn_worker("event notifier", options, gpio, {
+			let shared = shared.clone();
+			move |gpio| run(&gpio, eventfd, interval, &shared)
+		})?;
+
+		Ok(Self {
+			eventfd,
+			shared,
+			thread: Some(thread),
+		})
+	}
+
+	/// Get the eventfd that is notified of new events.
+	pub fn eventfd(&self) -> RawFd {
+		self.eventfd
+	}
+
+	/// Start watching a pin for an edge.
+	pub fn watch(&self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.shared.listener().watch(pin, edge)
+	}
+
+	/// Stop watching a pin for an edge.
+	///
+	/// Events that were already queued for the edge are kept.
+	pub fn unwatch(&self, pin: usize, edge: Edge) -> Result<(), Error> {
+		self.shared.listener().unwatch(pin, edge)
+	}
+
+	/// Take the oldest queued event, if any.
+	pub fn pop(&self) -> Option<GpioEvent> {
+		self.shared.listener().pop()
+	}
+
+	/// Stop the notifier thread.
+	pub fn stop(mut self) {
+		self.stop_thread();
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			thread.thread().unpark();
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for EventNotifier {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+fn run(gpio: &Gpio, eventfd: RawFd, interval: Duration, shared: &Shared) {
+	while !shared.stop.load(Ordering::Relaxed) {
+		let new_events = {
+			let mut listener = shared.listener();
+			let pending = listener.pending();
+			listener.poll(gpio);
+			listener.pending() - pending
+		};
+
+		if new_events > 0 {
+			// Writing to an eventfd adds the written value to its counter.
+			let count = new_events as u64;
+			if let Err(_e) = nix::unistd::write(eventfd, &count.to_ne_bytes()) {
+				#[cfg(feature = "log")]
+				log::warn!(target: "bcm283x_linux_gpio::notify", "failed to write to eventfd {}: {}", eventfd, _e);
+			}
+		}
+
+		std::thread::park_timeout(interval);
+	}
+}
@@ -0,0 +1,216 @@
+use std::time::{Duration, Instant};
+
+use crate::{delay, Error, ErrorKind, Gpio, PinFunction};
+
+const SEARCH_ROM: u8 = 0xF0;
+const MATCH_ROM: u8 = 0x55;
+const DS18B20_CONVERT: u8 = 0x44;
+const DS18B20_READ_SCRATCHPAD: u8 = 0xBE;
+
+/// The family code of DS18B20 temperature sensors.
+pub const DS18B20_FAMILY: u8 = 0x28;
+
+/// Maximum time a DS18B20 needs for a 12 bit temperature conversion.
+const DS18B20_CONVERT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Bit-banged 1-Wire master on a single GPIO pin.
+///
+/// The open-drain output is emulated by switching the pin between input (released)
+/// and output with a low level (pulled low).
+/// The line needs a pull-up resistor, typically 4.7 kΩ.
+///
+/// The 1-Wire timing is tight, so transfers may fail if the process is preempted.
+/// All received ROM IDs and data are checked with the 1-Wire CRC.
+/// The pin is left as input when the bus is dropped.
+pub struct OneWire<'a> {
+	gpio: &'a mut Gpio,
+	pin: usize,
+}
+
+impl<'a> OneWire<'a> {
+	/// Create a new 1-Wire bus on the given pin.
+	pub fn new(gpio: &'a mut Gpio, pin: usize) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+
+		// Latch a low output level, so switching to output pulls the line low.
+		gpio.write_level(pin, false);
+		gpio.write_function(pin, PinFunction::Input);
+		Ok(Self { gpio, pin })
+	}
+
+	/// Send a reset pulse and return true if any device signaled its presence.
+	pub fn reset(&mut self) -> bool {
+		self.pull_low();
+		delay::sleep(Duration::from_micros(480));
+		self.release();
+		delay::sleep(Duration::from_micros(70));
+		let presence = !self.gpio.level(self.pin);
+		delay::sleep(Duration::from_micros(410));
+		presence
+	}
+
+	/// Write a byte, least significant bit first.
+	pub fn write_byte(&mut self, byte: u8) {
+		for i in 0..8 {
+			self.write_bit(byte >> i & 1 == 1);
+		}
+	}
+
+	/// Read a byte, least significant bit first.
+	pub fn read_byte(&mut self) -> u8 {
+		let mut byte = 0;
+		for i in 0..8 {
+			byte |= u8::from(self.read_bit()) << i;
+		}
+		byte
+	}
+
+	/// Reset the bus and address a single device.
+	pub fn select(&mut self, rom: u64) -> Result<(), Error> {
+		if !self.reset() {
+			return Err(Error::new(ErrorKind::NoResponse, "no 1-Wire device responded to the reset pulse"));
+		}
+		self.write_byte(MATCH_ROM);
+		for byte in rom.to_le_bytes().iter() {
+			self.write_byte(*byte);
+		}
+		Ok(())
+	}
+
+	/// Find the ROM IDs of all devices on the bus.
+	pub fn search(&mut self) -> Result<Vec<u64>, Error> {
+		let mut roms = Vec::new();
+		let mut previous: u64 = 0;
+		let mut last_discrepancy = 0;
+
+		loop {
+			if !self.reset() {
+				return Ok(roms);
+			}
+			self.write_byte(SEARCH_ROM);
+
+			let mut rom: u64 = 0;
+			let mut last_zero = 0;
+			for bit in 1..=64 {
+				let direction = match (self.read_bit(), self.read_bit()) {
+					(true, true)   => return Err(Error::new(ErrorKind::NoResponse, "no 1-Wire device responded during ROM search")),
+					(true, false)  => true,
+					(false, true)  => false,
+					(false, false) => {
+						// Devices disagree: pick the branch we haven't explored yet.
+						let direction = if bit < last_discrepancy {
+							previous >> (bit - 1) & 1 == 1
+						} else {
+							bit == last_discrepancy
+						};
+						if !direction {
+							last_zero = bit;
+						}
+						direction
+					},
+				};
+				rom |= u64::from(direction) << (bit - 1);
+				self.write_bit(direction);
+			}
+
+			if crc8(&rom.to_le_bytes()) != 0 {
+				return Err(Error::new(ErrorKind::Protocol, format!("CRC error in 1-Wire ROM ID {}", format_rom(rom))));
+			}
+
+			roms.push(rom);
+			previous = rom;
+			last_discrepancy = last_zero;
+			if last_discrepancy == 0 {
+				return Ok(roms);
+			}
+		}
+	}
+
+	/// Measure the temperature with a DS18B20 sensor, in degrees Celsius.
+	pub fn read_ds18b20(&mut self, rom: u64) -> Result<f64, Error> {
+		if rom as u8 != DS18B20_FAMILY {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("1-Wire device {} is not a DS18B20", format_rom(rom))));
+		}
+
+		self.select(rom)?;
+		self.write_byte(DS18B20_CONVERT);
+
+		// The sensor answers read slots with 0 until the conversion is done.
+		let start = Instant::now();
+		while !self.read_bit() {
+			if start.elapsed() > DS18B20_CONVERT_TIMEOUT {
+				return Err(Error::new(ErrorKind::Timeout, format!("timeout waiting for temperature conversion of {}", format_rom(rom))));
+			}
+			delay::sleep(Duration::from_millis(1));
+		}
+
+		self.select(rom)?;
+		self.write_byte(DS18B20_READ_SCRATCHPAD);
+		let mut scratchpad = [0u8; 9];
+		for byte in scratchpad.iter_mut() {
+			*byte = self.read_byte();
+		}
+		if crc8(&scratchpad) != 0 {
+			return Err(Error::new(ErrorKind::Protocol, format!("CRC error in scratchpad of {}", format_rom(rom))));
+		}
+
+		let raw = i16::from_le_bytes([scratchpad[0], scratchpad[1]]);
+		Ok(f64::from(raw) / 16.0)
+	}
+
+	fn release(&mut self) {
+		self.gpio.write_function(self.pin, PinFunction::Input);
+	}
+
+	fn pull_low(&mut self) {
+		self.gpio.write_function(self.pin, PinFunction::Output);
+	}
+
+	fn write_bit(&mut self, bit: bool) {
+		let low = if bit { 6 } else { 60 };
+		self.pull_low();
+		delay::sleep(Duration::from_micros(low));
+		self.release();
+		delay::sleep(Duration::from_micros(70 - low));
+	}
+
+	fn read_bit(&mut self) -> bool {
+		self.pull_low();
+		delay::sleep(Duration::from_micros(6));
+		self.release();
+		delay::sleep(Duration::from_micros(9));
+		let bit = self.gpio.level(self.pin);
+		delay::sleep(Duration::from_micros(55));
+		bit
+	}
+}
+
+impl Drop for OneWire<'_> {
+	fn drop(&mut self) {
+		self.release();
+	}
+}
+
+/// Format a 1-Wire ROM ID like the Linux w1 subsystem does, for example `28-0000056a3b2c`.
+pub fn format_rom(rom: u64) -> String {
+	format!("{:02x}-{:012x}", rom as u8, rom >> 8 & 0xFFFF_FFFF_FFFF)
+}
+
+/// Compute the Dallas/Maxim CRC-8 of some data.
+///
+/// The CRC of data that includes its own trailing CRC byte is zero.
+fn crc8(data: &[u8]) -> u8 {
+	let mut crc = 0u8;
+	for &byte in data {
+		let mut byte = byte;
+		for _ in 0..8 {
+			let mix = (crc ^ byte) & 1;
+			crc >>= 1;
+			if mix != 0 {
+				crc ^= 0x8C;
+			}
+			byte >>= 1;
+		}
+	}
+	crc
+}
@@ -0,0 +1,162 @@
+use crate::{Error, ErrorKind};
+
+/// Offset of the pad control block from the GPIO control block.
+const PADS_OFFSET : i64 = 0x100000 - 0x200000;
+const PADS_SIZE : usize = 0x00000100;
+
+/// Password that must be present in the top byte of every pad control write.
+const PASSWORD : u32 = 0x5A << 24;
+
+/// The register offsets of the pad control registers for each bank.
+const BANK_REGISTERS : [usize; 3] = [0x2C, 0x30, 0x34];
+
+/// The output drive strength of a pad.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum DriveStrength {
+	Ma2,
+	Ma4,
+	Ma6,
+	Ma8,
+	Ma10,
+	Ma12,
+	Ma14,
+	Ma16,
+}
+
+impl DriveStrength {
+	pub fn from_bits(bits: u8) -> Self {
+		match bits & 0b111 {
+			0 => DriveStrength::Ma2,
+			1 => DriveStrength::Ma4,
+			2 => DriveStrength::Ma6,
+			3 => DriveStrength::Ma8,
+			4 => DriveStrength::Ma10,
+			5 => DriveStrength::Ma12,
+			6 => DriveStrength::Ma14,
+			_ => DriveStrength::Ma16,
+		}
+	}
+
+	pub fn to_bits(self) -> u8 {
+		self as u8
+	}
+
+	/// Get the drive strength in milliamps.
+	pub fn milliamps(self) -> u8 {
+		2 + 2 * self.to_bits()
+	}
+
+	/// Get the drive strength for a number of milliamps.
+	///
+	/// Only even values from 2 up to 16 are valid.
+	pub fn from_milliamps(milliamps: u8) -> Option<Self> {
+		match milliamps {
+			2 | 4 | 6 | 8 | 10 | 12 | 14 | 16 => Some(Self::from_bits(milliamps / 2 - 1)),
+			_ => None,
+		}
+	}
+}
+
+/// The configuration of a bank of pads.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PadConfig {
+	pub drive: DriveStrength,
+	pub slew_limited: bool,
+	pub hysteresis: bool,
+}
+
+impl PadConfig {
+	pub fn from_bits(bits: u32) -> Self {
+		Self {
+			drive:        DriveStrength::from_bits(bits as u8),
+			slew_limited: bits & 0b1_0000 == 0,
+			hysteresis:   bits & 0b0_1000 != 0,
+		}
+	}
+
+	pub fn to_bits(self) -> u32 {
+		let mut bits = u32::from(self.drive.to_bits());
+		if !self.slew_limited {
+			bits |= 0b1_0000;
+		}
+		if self.hysteresis {
+			bits |= 0b0_1000;
+		}
+		bits
+	}
+}
+
+/// Get the pad bank of a GPIO pin.
+///
+/// Bank 0 holds pins 0-27, bank 1 holds pins 28-45 and bank 2 holds pins 46-53.
+pub fn pad_bank(pin: usize) -> Result<usize, Error> {
+	crate::check_pin_index(pin)?;
+	Ok(match pin {
+		0..=27  => 0,
+		28..=45 => 1,
+		_       => 2,
+	})
+}
+
+/// A handle to the pad control peripheral.
+///
+/// The pad control registers configure the drive strength,
+/// slew rate and input hysteresis of the GPIO pins.
+/// They apply to an entire bank of pins at once.
+pub struct Pads {
+	control_block: *mut std::ffi::c_void,
+}
+
+// The mapping is owned by the handle, and all writes go through a mutable reference.
+unsafe impl Send for Pads {}
+
+impl Pads {
+	/// Create a new handle to the pad control peripheral.
+	///
+	/// This maps a portion of /dev/mem just like [`Gpio::new`](crate::Gpio::new),
+	/// and may fail for the same reasons.
+	pub fn new() -> Result<Self, Error> {
+		let address       = crate::read_gpio_address()? + PADS_OFFSET;
+		let control_block = crate::map_dev_mem("pad control", address, PADS_SIZE)?;
+		Ok(Self { control_block })
+	}
+
+	/// Read the configuration of a bank of pads.
+	pub fn read_bank(&self, bank: usize) -> Result<PadConfig, Error> {
+		let value = unsafe { self.register_address(bank)?.read_volatile() };
+		crate::peripheral_barrier();
+		Ok(PadConfig::from_bits(value))
+	}
+
+	/// Read the configuration of the pad bank of a GPIO pin.
+	pub fn read_pin(&self, pin: usize) -> Result<PadConfig, Error> {
+		self.read_bank(pad_bank(pin)?)
+	}
+
+	/// Write the configuration of a bank of pads.
+	///
+	/// # Safety
+	/// This changes the electrical properties of all pins in the bank,
+	/// including pins that may be in use by other processes or the kernel.
+	pub unsafe fn write_bank(&mut self, bank: usize, config: PadConfig) -> Result<(), Error> {
+		let address = self.register_address(bank)?;
+		crate::peripheral_barrier();
+		address.write_volatile(PASSWORD | config.to_bits());
+		Ok(())
+	}
+
+	fn register_address(&self, bank: usize) -> Result<*mut u32, Error> {
+		match BANK_REGISTERS.get(bank) {
+			Some(offset) => Ok(self.control_block.wrapping_add(*offset) as *mut u32),
+			None => Err(Error::new(ErrorKind::InvalidArgument, format!("pad bank index must be in the range [0..3), got {}", bank))),
+		}
+	}
+}
+
+impl Drop for Pads {
+	fn drop(&mut self) {
+		unsafe {
+			crate::procfs::unmap_memory(self.control_block, PADS_SIZE);
+		}
+	}
+}
@@ -0,0 +1,351 @@
+//! Parsers for the platform information used to find the GPIO peripheral.
+//!
+//! The parsers work on the raw contents of /proc/iomem and device tree properties,
+//! so they can be used without the `std` feature and with data from other systems.
+//! They never panic on malformed input.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{Error, ErrorKind, Soc};
+
+/// The bus address of the BCM283x GPIO peripheral.
+const GPIO_BUS_ADDRESS : u64 = 0x7E20_0000;
+
+/// A single entry of /proc/iomem.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IomemEntry {
+	/// The first physical address of the range.
+	pub start: u64,
+	/// The last physical address of the range, inclusive.
+	pub end: u64,
+	/// The nesting level of the entry, zero for top-level entries.
+	pub depth: usize,
+	/// The name of the range, like `fe200000.gpio gpio@7e200000`.
+	pub name: String,
+}
+
+impl IomemEntry {
+	/// Get the size of the range in bytes.
+	pub fn size(&self) -> u64 {
+		(self.end - self.start).saturating_add(1)
+	}
+
+	/// Check if the entry is a GPIO controller, named like `fe200000.gpio`.
+	pub fn is_gpio(&self) -> bool {
+		self.name.split_whitespace().next().is_some_and(|x| x.ends_with(".gpio"))
+	}
+
+	/// Check if the entry is named after the bus address of the BCM283x GPIO peripheral.
+	fn is_bcm283x_gpio_node(&self) -> bool {
+		self.name.split_whitespace().any(|x| x == "gpio@7e200000")
+	}
+}
+
+/// Parse the contents of /proc/iomem.
+///
+/// Empty lines are ignored.
+/// Nested entries are indented by two spaces per level, which is reported as their [`depth`](IomemEntry::depth).
+pub fn parse_iomem(data: &[u8]) -> Result<Vec<IomemEntry>, Error> {
+	let mut entries = Vec::new();
+	for (i, line) in data.split(|c| *c == b'\n').enumerate() {
+		let line = String::from_utf8_lossy(line);
+		if line.trim().is_empty() {
+			continue;
+		}
+		let invalid = |what: &str| Error::new(ErrorKind::IoMemParse, format!("{} in /proc/iomem on line {}", what, i + 1));
+
+		let indent = line.len() - line.trim_start_matches(' ').len();
+		let (range, name) = line.split_once(':').ok_or_else(|| invalid("malformed entry"))?;
+		let (start, end)  = range.trim().split_once('-').ok_or_else(|| invalid("malformed range"))?;
+		let start = u64::from_str_radix(start, 16).map_err(|_| invalid("invalid start address"))?;
+		let end   = u64::from_str_radix(end, 16).map_err(|_| invalid("invalid end address"))?;
+		if end < start {
+			return Err(invalid("range ends before it starts"));
+		}
+
+		entries.push(IomemEntry {
+			start,
+			end,
+			depth: indent / 2,
+			name: name.trim().to_string(),
+		});
+	}
+	Ok(entries)
+}
+
+/// Find the BCM283x GPIO peripheral in the entries of /proc/iomem.
+///
+/// SoCs with multiple GPIO controllers have multiple `.gpio` entries.
+/// The entry named after the bus address of the peripheral (`gpio@7e200000`) is preferred,
+/// then the only entry at the usual offset of the peripheral in the peripheral block.
+/// A single `.gpio` entry is always used.
+pub fn find_gpio(entries: &[IomemEntry]) -> Result<&IomemEntry, Error> {
+	let gpio: Vec<_> = entries.iter().filter(|x| x.is_gpio()).collect();
+	if let [entry] = gpio.as_slice() {
+		return Ok(entry);
+	}
+	if gpio.is_empty() {
+		return Err(Error::new(ErrorKind::PeripheralNotFound, "failed to find GPIO peripheral in /proc/iomem"));
+	}
+
+	if let Some(entry) = gpio.iter().find(|x| x.is_bcm283x_gpio_node()) {
+		return Ok(entry);
+	}
+	let mut at_offset = gpio.iter().filter(|x| x.start & 0xFF_FFFF == GPIO_BUS_ADDRESS & 0xFF_FFFF);
+	match (at_offset.next(), at_offset.next()) {
+		(Some(entry), None) => Ok(entry),
+		_ => Err(Error::new(ErrorKind::PeripheralNotFound, format!(
+			"found {} GPIO controllers in /proc/iomem, but none is recognizable as the BCM283x GPIO peripheral",
+			gpio.len(),
+		))),
+	}
+}
+
+/// The GPIO node of the device tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GpioNode {
+	/// The compatible strings of the node, from most to least specific.
+	pub compatible: Vec<String>,
+	/// The bus address of the peripheral.
+	pub address: u64,
+	/// The size of the peripheral in bytes.
+	pub size: u64,
+}
+
+impl GpioNode {
+	/// Parse the `compatible` and `reg` properties of the GPIO node.
+	///
+	/// The cell counts are the `#address-cells` and `#size-cells` properties of the parent node,
+	/// see [`parse_cell_count`].
+	pub fn parse(compatible: &[u8], reg: &[u8], address_cells: usize, size_cells: usize) -> Result<Self, Error> {
+		if address_cells > 2 || size_cells > 2 {
+			return Err(Error::new(ErrorKind::DeviceTree, "invalid cell count for the GPIO node"));
+		}
+		let reg = parse_cells(reg)?;
+		if reg.len() < address_cells + size_cells {
+			return Err(Error::new(ErrorKind::DeviceTree, "invalid reg property: too few cells"));
+		}
+		Ok(Self {
+			compatible: parse_string_list(compatible),
+			address: join_cells(&reg[..address_cells]),
+			size: join_cells(&reg[address_cells..address_cells + size_cells]),
+		})
+	}
+
+	/// Check if the node is compatible with a device, like `brcm,bcm2835-gpio`.
+	pub fn is_compatible(&self, device: &str) -> bool {
+		self.compatible.iter().any(|x| x == device)
+	}
+
+	/// Get the SoC of the peripheral from the most specific compatible string, if it is supported.
+	pub fn soc(&self) -> Option<Soc> {
+		soc_from_compatible(&self.compatible)
+	}
+}
+
+/// A GPIO controller in the device tree.
+///
+/// SoCs like the BCM2711 have more than one GPIO controller,
+/// but only the ones compatible with the BCM283x GPIO peripheral can be opened with [`Gpio::open`](crate::Gpio::open).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GpioController {
+	/// The path of the device tree node, like `/soc/gpio@7e200000`.
+	pub path: String,
+	/// The compatible strings of the node, from most to least specific.
+	pub compatible: Vec<String>,
+	/// The physical base address of the controller, if it is memory mapped.
+	///
+	/// This is `None` for controllers that are not memory mapped, like the GPIO expander of the firmware.
+	pub address: Option<u64>,
+	/// The size of the memory mapped registers in bytes, if the controller is memory mapped.
+	pub size: Option<u64>,
+	/// The number of GPIO lines of the controller, if known.
+	///
+	/// This is the `ngpios` property of the node,
+	/// or the number of lines of the BCM283x GPIO peripheral if the node has none.
+	pub ngpios: Option<u32>,
+}
+
+impl GpioController {
+	/// Check if the controller is compatible with a device, like `brcm,bcm2835-gpio`.
+	pub fn is_compatible(&self, device: &str) -> bool {
+		self.compatible.iter().any(|x| x == device)
+	}
+
+	/// Get the SoC of the controller from the most specific compatible string, if it is supported.
+	pub fn soc(&self) -> Option<Soc> {
+		soc_from_compatible(&self.compatible)
+	}
+}
+
+/// Get the number of GPIO lines of a BCM283x GPIO peripheral, as exposed by the kernel.
+#[cfg(feature = "std")]
+pub(crate) fn bcm283x_gpio_lines(compatible: &[String]) -> Option<u32> {
+	match soc_from_compatible(compatible)? {
+		Soc::Bcm2835 => Some(54),
+		Soc::Bcm2711 => Some(58),
+	}
+}
+
+fn soc_from_compatible(compatible: &[String]) -> Option<Soc> {
+	match compatible.first()?.as_str() {
+		"brcm,bcm2835-gpio" => Some(Soc::Bcm2835),
+		"brcm,bcm2711-gpio" => Some(Soc::Bcm2711),
+		_ => None,
+	}
+}
+
+/// Parse a device tree property as a list of big-endian 32 bit cells.
+pub fn parse_cells(data: &[u8]) -> Result<Vec<u32>, Error> {
+	if !data.len().is_multiple_of(4) {
+		return Err(Error::new(ErrorKind::DeviceTree, "invalid property: length is not a multiple of 4"));
+	}
+	Ok(data.chunks_exact(4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])).collect())
+}
+
+/// Parse an `#address-cells` or `#size-cells` property.
+///
+/// Only counts of up to two cells are supported, since addresses and sizes are 64 bit.
+pub fn parse_cell_count(data: &[u8]) -> Result<usize, Error> {
+	match parse_cells(data)?.as_slice() {
+		[count] if *count <= 2 => Ok(*count as usize),
+		_ => Err(Error::new(ErrorKind::DeviceTree, "invalid cell count")),
+	}
+}
+
+/// Parse a device tree property holding a list of null-terminated strings.
+pub fn parse_string_list(data: &[u8]) -> Vec<String> {
+	let data = data.strip_suffix(&[0]).unwrap_or(data);
+	if data.is_empty() {
+		return Vec::new();
+	}
+	data.split(|c| *c == 0).map(|x| String::from_utf8_lossy(x).into_owned()).collect()
+}
+
+/// Read and parse /proc/iomem.
+#[cfg(feature = "std")]
+pub fn read_iomem() -> Result<Vec<IomemEntry>, Error> {
+	parse_iomem(&crate::read_all(crate::open("/proc/iomem")?)?)
+}
+
+/// Read and parse the GPIO node from the device tree.
+#[cfg(feature = "std")]
+pub fn read_gpio_node() -> Result<GpioNode, Error> {
+	use crate::devicetree::{read_cell_count, read_property, GPIO_NODE, SOC_NODE};
+
+	let compatible    = read_property(&format!("{}/compatible", GPIO_NODE))?;
+	let reg           = read_property(&format!("{}/reg", GPIO_NODE))?;
+	let address_cells = read_cell_count(&format!("{}/#address-cells", SOC_NODE), 2)?;
+	let size_cells    = read_cell_count(&format!("{}/#size-cells", SOC_NODE), 1)?;
+	GpioNode::parse(&compatible, &reg, address_cells, size_cells)
+		.map_err(|e| Error::new(e.kind(), format!("{}: {}", GPIO_NODE, e)))
+}
+
+/// Find all enabled GPIO controllers in the device tree.
+///
+/// Every node with a `gpio-controller` property is a GPIO controller.
+/// Their `reg` property is translated to a physical address through the `ranges` of their parent nodes.
+/// If the system has no device tree, the result is empty.
+#[cfg(feature = "std")]
+pub fn gpio_controllers() -> Result<Vec<GpioController>, Error> {
+	crate::devicetree::gpio_controllers()
+}
+
+/// Combine big-endian cells into a single number.
+pub(crate) fn join_cells(cells: &[u32]) -> u64 {
+	cells.iter().fold(0, |acc, &cell| acc << 32 | u64::from(cell))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const IOMEM : &[u8] = b"\
+00000000-3b3fffff : System RAM
+  00000000-00000fff : reserved
+  00210000-0125ffff : Kernel code
+
+fd500000-fd50930f : fd500000.pcie pcie@7d500000
+fe200000-fe2000b3 : fe200000.gpio gpio@7e200000
+fe201000-fe2011ff : serial@7e201000
+  fe201000-fe2011ff : fe201000.serial serial@7e201000
+";
+
+	#[test]
+	fn iomem() {
+		let entries = parse_iomem(IOMEM).unwrap();
+		assert_eq!(entries.len(), 7);
+		assert_eq!(entries[1], IomemEntry { start: 0, end: 0xFFF, depth: 1, name: "reserved".into() });
+		assert_eq!(entries[0].size(), 0x3B40_0000);
+
+		let gpio = find_gpio(&entries).unwrap();
+		assert_eq!(gpio.start, 0xFE20_0000);
+		assert_eq!(gpio.size(), 0xB4);
+	}
+
+	#[test]
+	fn invalid_iomem() {
+		let invalid: [&[u8]; 4] = [
+			b"00000000-3b3fffff System RAM",
+			b"00000000 : System RAM",
+			b"0000zzzz-3b3fffff : System RAM",
+			b"3b3fffff-00000000 : System RAM",
+		];
+		for data in invalid.iter() {
+			assert_eq!(parse_iomem(data).unwrap_err().kind(), ErrorKind::IoMemParse);
+		}
+
+		let entries = parse_iomem(b"00000000-3b3fffff : System RAM\n").unwrap();
+		assert_eq!(find_gpio(&entries).unwrap_err().kind(), ErrorKind::PeripheralNotFound);
+	}
+
+	#[test]
+	fn find_gpio_among_multiple_controllers() {
+		let entries = parse_iomem(b"\
+fe200000-fe2000b3 : fe200000.gpio
+fe215000-fe2150ff : fe215000.gpio
+").unwrap();
+		assert_eq!(find_gpio(&entries).unwrap().start, 0xFE20_0000);
+
+		let entries = parse_iomem(b"\
+107d508500-107d50853f : 107d508500.gpio gpio@7d508500
+1f000d0000-1f000d00bf : 1f000d0000.gpio gpio@d0000
+").unwrap();
+		assert_eq!(find_gpio(&entries).unwrap_err().kind(), ErrorKind::PeripheralNotFound);
+	}
+
+	#[test]
+	fn gpio_node() {
+		let compatible = b"brcm,bcm2711-gpio\0brcm,bcm2835-gpio\0";
+		let reg = [0x7E, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB4];
+		let node = GpioNode::parse(compatible, &reg, 1, 1).unwrap();
+		assert_eq!(node.compatible, ["brcm,bcm2711-gpio", "brcm,bcm2835-gpio"]);
+		assert_eq!(node.address, 0x7E20_0000);
+		assert_eq!(node.size, 0xB4);
+		assert!(node.is_compatible("brcm,bcm2835-gpio"));
+		assert_eq!(node.soc(), Some(Soc::Bcm2711));
+
+		let reg = [0x00, 0x00, 0x00, 0x10, 0x7E, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB4];
+		assert_eq!(GpioNode::parse(compatible, &reg, 2, 1).unwrap().address, 0x10_7E20_0000);
+
+		assert_eq!(GpioNode::parse(compatible, &reg[..4], 1, 1).unwrap_err().kind(), ErrorKind::DeviceTree);
+		assert_eq!(GpioNode::parse(compatible, &reg, 3, 1).unwrap_err().kind(), ErrorKind::DeviceTree);
+	}
+
+	#[test]
+	fn device_tree_properties() {
+		assert_eq!(parse_cells(&[0, 0, 0, 1, 0xFF, 0, 0, 0]).unwrap(), [1, 0xFF00_0000]);
+		assert_eq!(parse_cells(&[0, 0, 1]).unwrap_err().kind(), ErrorKind::DeviceTree);
+
+		assert_eq!(parse_cell_count(&[0, 0, 0, 2]).unwrap(), 2);
+		assert_eq!(parse_cell_count(&[0, 0, 0, 3]).unwrap_err().kind(), ErrorKind::DeviceTree);
+		assert_eq!(parse_cell_count(&[0, 0, 0, 1, 0, 0, 0, 1]).unwrap_err().kind(), ErrorKind::DeviceTree);
+
+		assert_eq!(parse_string_list(b"a\0bc\0"), ["a", "bc"]);
+		assert_eq!(parse_string_list(b"a\0bc"), ["a", "bc"]);
+		assert!(parse_string_list(b"").is_empty());
+		assert!(parse_string_list(b"\0").is_empty());
+	}
+}
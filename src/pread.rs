@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+use crate::{Capabilities, Error, ErrorKind, GpioBackend, Register, Soc};
+
+/// A read-only backend that reads the GPIO registers with `pread` on /dev/mem.
+///
+/// Some hardened kernels allow reading ranges of /dev/mem, but refuse to map them with `MAP_SHARED`.
+/// This backend still allows inspecting the state of the pins on such systems.
+///
+/// Every register read is a separate system call, so reading is slow compared to a mapping.
+/// All writes are ignored, as reported by [`Capabilities::read_only`],
+/// so the setters of [`Gpio`](crate::Gpio) return an error instead of doing nothing.
+pub struct PreadBackend {
+	file: File,
+	address: u64,
+	soc: Soc,
+}
+
+impl PreadBackend {
+	/// Open /dev/mem for reading the GPIO peripheral.
+	///
+	/// The address of the peripheral is found the same way as for [`MappedBackend::dev_mem`](crate::MappedBackend::dev_mem).
+	/// A register is read once to check that the kernel allows reading the peripheral.
+	pub fn dev_mem() -> Result<Self, Error> {
+		let address = crate::read_gpio_address()?;
+		crate::devicetree::verify_gpio_address(address)?;
+		let file = crate::procfs::open("/dev/mem")?.file;
+
+		let backend = Self {
+			file,
+			address: address as u64,
+			soc: Soc::detect().unwrap_or(Soc::Bcm2835),
+		};
+		backend.try_read_register(Register::GPLEV0)
+			.map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to read GPIO memory (0x{:08X}) from /dev/mem", address), e))?;
+		Ok(backend)
+	}
+
+	fn try_read_register(&self, reg: Register) -> std::io::Result<u32> {
+		let mut data = [0; 4];
+		self.file.read_exact_at(&mut data, self.address + reg as u64)?;
+		Ok(u32::from_ne_bytes(data))
+	}
+}
+
+impl GpioBackend for PreadBackend {
+	fn name(&self) -> &str {
+		"/dev/mem (read-only)"
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities {
+			registers:     true,
+			alt_functions: true,
+			pull:          false,
+			events:        true,
+			read_only:     true,
+		}
+	}
+
+	fn soc(&self) -> Soc {
+		self.soc
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		let result = self.try_read_register(reg);
+		#[cfg(feature = "log")]
+		if let Err(e) = &result {
+			log::warn!(target: "bcm283x_linux_gpio::pread", "failed to read {}: {}", reg, e);
+		}
+		result.unwrap_or(0)
+	}
+
+	unsafe fn write_register(&mut self, _reg: Register, _value: u32) {}
+}
@@ -0,0 +1,153 @@
+//! Access to /dev/mem and the information about the peripherals in procfs.
+
+#[cfg(not(feature = "stub"))]
+use nix::sys::mman;
+use std::convert::TryFrom;
+use std::io::Read;
+
+use crate::{Error, ErrorKind};
+
+/// Map a peripheral from /dev/mem.
+pub(crate) fn map_dev_mem(name: &str, address: i64, size: usize) -> Result<*mut std::ffi::c_void, Error> {
+	map_memory("/dev/mem", name, address, size)
+}
+
+/// Map a part of a memory device file, like /dev/mem or /dev/gpiomem.
+#[cfg(not(feature = "stub"))]
+pub(crate) fn map_memory(path: &str, name: &str, address: i64, size: usize) -> Result<*mut std::ffi::c_void, Error> {
+	use std::os::unix::io::AsRawFd;
+
+	let file = open_rw(path)?;
+	let fd   = file.file.as_raw_fd();
+	unsafe {
+		mman::mmap(std::ptr::null_mut(), size, mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE, mman::MapFlags::MAP_SHARED, fd, address)
+			.map_err(|e| Error::from_nix(ErrorKind::Mmap, format!("failed to map {} memory (0x{:08X}) from {}", name, address, path), e))
+	}
+}
+
+#[cfg(feature = "stub")]
+pub(crate) fn map_memory(path: &str, name: &str, address: i64, _size: usize) -> Result<*mut std::ffi::c_void, Error> {
+	Err(unsupported(format!("mapping {} memory (0x{:08X}) from {}", name, address, path)))
+}
+
+/// Unmap memory mapped by [`map_memory`], ignoring errors.
+pub(crate) unsafe fn unmap_memory(address: *mut std::ffi::c_void, size: usize) {
+	#[cfg(not(feature = "stub"))]
+	let _ = mman::munmap(address, size);
+	#[cfg(feature = "stub")]
+	let _ = (address, size);
+}
+
+/// Lock memory mapped by [`map_memory`] in RAM, so accessing it never causes a page fault.
+#[cfg(not(feature = "stub"))]
+pub(crate) fn lock_memory(name: &str, address: *mut std::ffi::c_void, size: usize) -> Result<(), Error> {
+	unsafe {
+		mman::mlock(address, size)
+			.map_err(|e| Error::from_nix(ErrorKind::Other, format!("failed to lock {} memory", name), e))
+	}
+}
+
+#[cfg(feature = "stub")]
+pub(crate) fn lock_memory(name: &str, _address: *mut std::ffi::c_void, _size: usize) -> Result<(), Error> {
+	Err(unsupported(format!("locking {} memory", name)))
+}
+
+/// The error for everything that needs Linux when built with the `stub` feature.
+#[cfg(feature = "stub")]
+pub(crate) fn unsupported(what: impl std::fmt::Display) -> Error {
+	Error::new(ErrorKind::Unsupported, format!("{} requires Linux, but the crate was built with the stub feature", what))
+}
+
+fn is_whitespace(c: u8) -> bool {
+	c == b' ' || c == b'\t' || c == b'\n' || c == b'\r'
+}
+
+pub(crate) fn trim(data: &[u8]) -> &[u8] {
+	let first = match data.iter().position(|x| !is_whitespace(*x)) {
+		None => return &data[0..0],
+		Some(x) => x,
+	};
+
+	let last = match data.iter().rposition(|x| !is_whitespace(*x)) {
+		None => return &data[0..0],
+		Some(x) => x,
+	};
+
+	&data[first..last+1]
+}
+
+pub(crate) struct FileWithPath {
+	pub path: std::path::PathBuf,
+	pub file: std::fs::File,
+}
+
+#[cfg(not(feature = "stub"))]
+pub(crate) fn open(path: impl Into<std::path::PathBuf>) -> Result<FileWithPath, Error> {
+	let path = path.into();
+	let file = std::fs::File::open(&path).map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to open {}", path.display()), e))?;
+	Ok(FileWithPath {
+		path,
+		file,
+	})
+}
+
+#[cfg(not(feature = "stub"))]
+pub(crate) fn open_rw(path: impl Into<std::path::PathBuf>) -> Result<FileWithPath, Error> {
+	let path = path.into();
+	let file = std::fs::OpenOptions::new().create(false).read(true).write(true).open(&path)
+		.map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to open {}", path.display()), e))?;
+
+	Ok(FileWithPath {
+		path,
+		file,
+	})
+}
+
+#[cfg(feature = "stub")]
+pub(crate) fn open(path: impl Into<std::path::PathBuf>) -> Result<FileWithPath, Error> {
+	Err(unsupported(format_args!("opening {}", path.into().display())))
+}
+
+pub(crate) fn read_all(file: FileWithPath) -> Result<Vec<u8>, Error> {
+	let mut file = file;
+	let mut data = Vec::new();
+	file.file.read_to_end(&mut data).map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to read from {}", file.path.display()), e))?;
+	Ok(data)
+}
+
+/// Check whether the current platform has a bcm2835-gpio peripheral at the expected bus address.
+pub fn check_bcm283x_gpio() -> Result<(), Error> {
+	const EXPECTED: &str = "brcm,bcm2835-gpio";
+
+	let data = read_gpio_compatible()?;
+	if data == EXPECTED.as_bytes() {
+		Ok(())
+	} else {
+		Err(Error::new(ErrorKind::UnsupportedSoC, format!("invalid gpio peripheral type, expected {}, got {:?}", EXPECTED, String::from_utf8_lossy(&data))))
+	}
+}
+
+/// Read the compatible property of the GPIO node in the device tree, without the trailing null byte.
+pub(crate) fn read_gpio_compatible() -> Result<Vec<u8>, Error> {
+	// A missing device tree node means there is no GPIO peripheral at the expected address.
+	let file = open("/proc/device-tree/soc/gpio@7e200000/compatible").map_err(|e| match e.is_not_found() {
+		true  => Error { kind: ErrorKind::PeripheralNotFound, ..e },
+		false => e,
+	})?;
+	let mut data = read_all(file)?;
+	if data.last() == Some(&0) {
+		data.pop();
+	}
+	Ok(data)
+}
+
+/// Read the GPIO peripheral base address from /proc/iomem.
+///
+/// See [`find_gpio`](crate::platform::find_gpio) for how the peripheral is found.
+pub(crate) fn read_gpio_address() -> Result<i64, Error> {
+	let entries = crate::platform::read_iomem()?;
+	let gpio    = crate::platform::find_gpio(&entries)?;
+	// The address is used as offset in /dev/mem, which is signed.
+	i64::try_from(gpio.start)
+		.map_err(|_| Error::new(ErrorKind::IoMemParse, format!("GPIO peripheral address 0x{:X} in /proc/iomem is out of range", gpio.start)))
+}
@@ -0,0 +1,197 @@
+use std::time::Instant;
+
+use crate::{Edge, Error, ErrorKind, Gpio, GpioEvent, GpioPullConfig, Level, Mcp23017, MockGpio, PinFunction, PullMode, MCP23017_PINS};
+
+/// A source of GPIO pins, like the SoC or a GPIO expander.
+///
+/// Drivers for devices like displays, keypads or shift registers can take a `&mut dyn GpioProvider`
+/// to work with the pins of the SoC and expanders alike.
+/// Pins are numbered from zero up to [`pin_count`](Self::pin_count).
+///
+/// Providers only support the functions and pull modes of their hardware,
+/// and return an [`Unsupported`](ErrorKind::Unsupported) error for the others.
+pub trait GpioProvider {
+	/// Get the number of pins of the provider.
+	fn pin_count(&self) -> usize;
+
+	/// Set the function of a pin.
+	fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error>;
+
+	/// Read the current level of a pin.
+	fn read_level(&mut self, index: usize) -> Result<Level, Error>;
+
+	/// Read the current level of all pins.
+	///
+	/// Bit N of the returned value holds the level of pin N.
+	fn read_levels(&mut self) -> Result<u64, Error>;
+
+	/// Set the level of a pin.
+	fn set_level(&mut self, index: usize, value: Level) -> Result<(), Error>;
+
+	/// Set the pull mode of a pin.
+	///
+	/// # Safety
+	/// For the SoC this is not atomic, see [`GpioPullConfig::apply`].
+	unsafe fn set_pull_mode(&mut self, index: usize, mode: PullMode) -> Result<(), Error>;
+
+	/// Enable or disable the detection of an edge on a pin in hardware.
+	///
+	/// Detected edges are reported by [`take_events`](Self::take_events).
+	/// Providers without hardware edge detection return an [`Unsupported`](ErrorKind::Unsupported) error.
+	fn set_edge_detect(&mut self, _index: usize, _edge: Edge, _enabled: bool) -> Result<(), Error> {
+		Err(Error::new(ErrorKind::Unsupported, "this GPIO provider does not support edge detection"))
+	}
+
+	/// Take the edges detected since the last call.
+	fn take_events(&mut self) -> Result<Vec<GpioEvent>, Error> {
+		Err(Error::new(ErrorKind::Unsupported, "this GPIO provider does not support edge detection"))
+	}
+}
+
+/// The pins of the SoC.
+///
+/// Edge detection is not supported, since the event detect registers are normally in use by the kernel.
+/// Use an [`EventListener`](crate::EventListener) to poll for edges instead.
+impl GpioProvider for Gpio {
+	fn pin_count(&self) -> usize {
+		54
+	}
+
+	fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error> {
+		Gpio::set_function(self, index, function)
+	}
+
+	fn read_level(&mut self, index: usize) -> Result<Level, Error> {
+		Gpio::read_level(self, index)
+	}
+
+	fn read_levels(&mut self) -> Result<u64, Error> {
+		Ok(Gpio::read_levels(self))
+	}
+
+	fn set_level(&mut self, index: usize, value: Level) -> Result<(), Error> {
+		Gpio::set_level(self, index, value)
+	}
+
+	unsafe fn set_pull_mode(&mut self, index: usize, mode: PullMode) -> Result<(), Error> {
+		let mut config = GpioPullConfig::new();
+		config.set_pull_mode(index, mode)?;
+		config.apply(self)
+	}
+}
+
+/// The pins of a [`Gpio`] handle with a mock backend.
+impl GpioProvider for MockGpio {
+	fn pin_count(&self) -> usize {
+		GpioProvider::pin_count(&**self)
+	}
+
+	fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error> {
+		GpioProvider::set_function(&mut **self, index, function)
+	}
+
+	fn read_level(&mut self, index: usize) -> Result<Level, Error> {
+		GpioProvider::read_level(&mut **self, index)
+	}
+
+	fn read_levels(&mut self) -> Result<u64, Error> {
+		GpioProvider::read_levels(&mut **self)
+	}
+
+	fn set_level(&mut self, index: usize, value: Level) -> Result<(), Error> {
+		GpioProvider::set_level(&mut **self, index, value)
+	}
+
+	unsafe fn set_pull_mode(&mut self, index: usize, mode: PullMode) -> Result<(), Error> {
+		GpioProvider::set_pull_mode(&mut **self, index, mode)
+	}
+}
+
+/// The pins of an expander.
+///
+/// Edges are detected with the interrupt on change of the expander,
+/// and reported with the level that was captured when the interrupt was raised.
+/// Changes after the first one are lost until the events are taken.
+impl GpioProvider for Mcp23017<'_> {
+	fn pin_count(&self) -> usize {
+		MCP23017_PINS
+	}
+
+	fn set_function(&mut self, index: usize, function: PinFunction) -> Result<(), Error> {
+		Mcp23017::set_function(self, index, function)
+	}
+
+	fn read_level(&mut self, index: usize) -> Result<Level, Error> {
+		Mcp23017::read_level(self, index)
+	}
+
+	fn read_levels(&mut self) -> Result<u64, Error> {
+		Ok(Mcp23017::read_levels(self)?.into())
+	}
+
+	fn set_level(&mut self, index: usize, value: Level) -> Result<(), Error> {
+		Mcp23017::set_level(self, index, value)
+	}
+
+	unsafe fn set_pull_mode(&mut self, index: usize, mode: PullMode) -> Result<(), Error> {
+		Mcp23017::set_pull_mode(self, index, mode)
+	}
+
+	fn set_edge_detect(&mut self, index: usize, edge: Edge, enabled: bool) -> Result<(), Error> {
+		Mcp23017::set_edge_detect(self, index, edge, enabled)
+	}
+
+	fn take_events(&mut self) -> Result<Vec<GpioEvent>, Error> {
+		let time = Instant::now();
+		let interrupt = match self.read_interrupt()? {
+			Some(x) => x,
+			None => return Ok(Vec::new()),
+		};
+		let (rise, fall) = self.edge_detect();
+		let mut events = Vec::new();
+		for pin in (0..MCP23017_PINS).filter(|pin| interrupt.pins >> pin & 1 == 1) {
+			let (detect, edge) = match interrupt.levels >> pin & 1 == 1 {
+				true  => (rise, Edge::Rise),
+				false => (fall, Edge::Fall),
+			};
+			if detect >> pin & 1 == 1 {
+				events.push(GpioEvent { pin, edge, time });
+			}
+		}
+		Ok(events)
+	}
+}
+
+/// A pin that is active when it is low, like an LED connected to 3.3V or a button connected to ground.
+///
+/// The wrapper translates between the active state of the hardware and the level of the pin,
+/// so call sites can say `led.set_active(&mut gpio, true)` instead of setting the pin low.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ActiveLow(pub usize);
+
+impl ActiveLow {
+	/// Get the index of the pin.
+	pub fn pin(self) -> usize {
+		self.0
+	}
+
+	/// Get the level of the pin for an active state.
+	pub fn level(self, active: bool) -> Level {
+		(!active).into()
+	}
+
+	/// Check if a level of the pin means it is active.
+	pub fn is_active(self, level: Level) -> bool {
+		level.is_low()
+	}
+
+	/// Activate or deactivate the pin.
+	pub fn set_active<P: GpioProvider + ?Sized>(self, provider: &mut P, active: bool) -> Result<(), Error> {
+		provider.set_level(self.0, self.level(active))
+	}
+
+	/// Check if the pin is active.
+	pub fn read_active<P: GpioProvider + ?Sized>(self, provider: &mut P) -> Result<bool, Error> {
+		Ok(self.is_active(provider.read_level(self.0)?))
+	}
+}
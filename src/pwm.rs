@@ -0,0 +1,265 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, Gpio};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+use crate::delay::sleep_until;
+
+struct Shared {
+	/// The period in nanoseconds.
+	period: AtomicU64,
+
+	/// The high time in nanoseconds.
+	high: AtomicU64,
+
+	/// Set to stop the PWM thread.
+	stop: AtomicBool,
+
+	/// The effect that is changing the duty cycle.
+	///
+	/// The PWM thread updates `high` while holding the lock, so setting the duty cycle can cancel the effect without a race.
+	effect: Mutex<Effect>,
+}
+
+impl Shared {
+	fn effect(&self) -> std::sync::MutexGuard<'_, Effect> {
+		self.effect.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// An effect that changes the duty cycle over time.
+#[derive(Copy, Clone, Debug)]
+enum Effect {
+	None,
+	Fade { from: f64, to: f64, start: Instant, duration: Duration },
+	Breathe { start: Instant, period: Duration },
+}
+
+impl Effect {
+	/// Get the duty cycle of the effect at a point in time, and if the effect is still running.
+	fn duty(&self, now: Instant) -> Option<(f64, bool)> {
+		match *self {
+			Effect::None => None,
+			Effect::Fade { from, to, start, duration } => {
+				let progress = now.saturating_duration_since(start).as_secs_f64() / duration.as_secs_f64();
+				match progress < 1.0 {
+					true  => Some((from + (to - from) * progress, true)),
+					false => Some((to, false)),
+				}
+			},
+			Effect::Breathe { start, period } => {
+				let phase = now.saturating_duration_since(start).as_secs_f64() / period.as_secs_f64();
+				Some(((1.0 - (phase * std::f64::consts::TAU).cos()) / 2.0, true))
+			},
+		}
+	}
+}
+
+/// Software PWM on a single GPIO pin.
+///
+/// The PWM signal is generated by a background thread toggling the pin level.
+/// The pin must already be configured as output.
+///
+/// The accuracy of the signal depends on the scheduling of the background thread,
+/// so expect jitter in the order of tens of microseconds on a busy system.
+pub struct SoftPwm {
+	pin: usize,
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+
+	/// Receives the GPIO handle back when the thread stops.
+	gpio: mpsc::Receiver<Gpio>,
+}
+
+impl SoftPwm {
+	/// Start generating a PWM signal on a pin.
+	///
+	/// The duty cycle is given as a fraction in the range [0, 1].
+	pub fn start(pin: usize, frequency: f64, duty: f64) -> Result<Self, Error> {
+		Self::start_with_options(pin, frequency, duty, ThreadOptions::default())
+	}
+
+	/// Start generating a PWM signal on a pin, with options for the background thread.
+	///
+	/// Use a real-time priority or a dedicated CPU core to reduce the jitter of the signal, see [`realtime`](crate::realtime).
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn start_with_options(pin: usize, frequency: f64, duty: f64, options: ThreadOptions) -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?, pin, frequency, duty, options)
+	}
+
+	/// Start generating a PWM signal on a pin, moving an existing handle to the background thread.
+	pub fn start_with_gpio(gpio: Gpio, pin: usize, frequency: f64, duty: f64, options: ThreadOptions) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+		let (period, high) = timing(frequency, duty)?;
+
+		let shared = Arc::new(Shared {
+			period: AtomicU64::new(period),
+			high:   AtomicU64::new(high),
+			stop:   AtomicBool::new(false),
+			effect: Mutex::new(Effect::None),
+		});
+
+		let (gpio_tx, gpio_rx) = mpsc::channel();
+		let thread = spawn_worker("software PWM", options, gpio, {
+			let shared = shared.clone();
+			move |mut gpio| {
+				run(&mut gpio, pin, &shared);
+				let _ = gpio_tx.send(gpio);
+			}
+		})?;
+
+		Ok(Self {
+			pin,
+			shared,
+			thread: Some(thread),
+			gpio: gpio_rx,
+		})
+	}
+
+	/// Get the pin the PWM signal is generated on.
+	pub fn pin(&self) -> usize {
+		self.pin
+	}
+
+	/// Change the frequency and duty cycle of the PWM signal.
+	///
+	/// This stops a running fade or breathe effect.
+	pub fn set(&self, frequency: f64, duty: f64) -> Result<(), Error> {
+		let (period, high) = timing(frequency, duty)?;
+		let mut effect = self.shared.effect();
+		*effect = Effect::None;
+		self.shared.period.store(period, Ordering::Relaxed);
+		self.shared.high.store(high, Ordering::Relaxed);
+		Ok(())
+	}
+
+	/// Change the duty cycle of the PWM signal.
+	///
+	/// This stops a running fade or breathe effect.
+	pub fn set_duty(&self, duty: f64) -> Result<(), Error> {
+		let period = self.shared.period.load(Ordering::Relaxed);
+		self.set(1e9 / period as f64, duty)
+	}
+
+	/// Get the current duty cycle of the PWM signal.
+	pub fn duty(&self) -> f64 {
+		let period = self.shared.period.load(Ordering::Relaxed);
+		let high   = self.shared.high.load(Ordering::Relaxed);
+		high as f64 / period as f64
+	}
+
+	/// Fade the duty cycle linearly from the current value to a new value.
+	///
+	/// The duty cycle is updated every PWM period by the background thread, so this returns immediately.
+	/// The fade stops at the new duty cycle, or when the duty cycle is changed otherwise.
+	pub fn fade_to(&self, duty: f64, duration: Duration) -> Result<(), Error> {
+		if !(0.0..=1.0).contains(&duty) {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("PWM duty cycle must be in the range [0, 1], got {}", duty)));
+		}
+		if duration.is_zero() {
+			return self.set_duty(duty);
+		}
+		let mut effect = self.shared.effect();
+		*effect = Effect::Fade { from: self.duty(), to: duty, start: Instant::now(), duration };
+		Ok(())
+	}
+
+	/// Let the duty cycle breathe between 0 and 1 with a sine wave, starting at 0.
+	///
+	/// The duty cycle is updated every PWM period by the background thread, so this returns immediately.
+	/// The effect continues until the duty cycle is changed otherwise.
+	pub fn breathe(&self, period: Duration) -> Result<(), Error> {
+		if period.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "breathe period must not be zero"));
+		}
+		let mut effect = self.shared.effect();
+		*effect = Effect::Breathe { start: Instant::now(), period };
+		Ok(())
+	}
+
+	/// Stop the PWM signal.
+	///
+	/// The pin is left low.
+	pub fn stop(mut self) {
+		self.stop_thread();
+	}
+
+	/// Stop the PWM signal and get back the GPIO handle used by the background thread.
+	///
+	/// The pin is left low.
+	pub fn into_gpio(mut self) -> Result<Gpio, Error> {
+		self.stop_thread();
+		self.gpio.try_recv().map_err(|_| Error::new(ErrorKind::Other, "software PWM thread panicked"))
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for SoftPwm {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+/// Compute the period and high time in nanoseconds.
+fn timing(frequency: f64, duty: f64) -> Result<(u64, u64), Error> {
+	if !frequency.is_finite() || frequency <= 0.0 {
+		return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid PWM frequency: {}", frequency)));
+	}
+	if !(0.0..=1.0).contains(&duty) {
+		return Err(Error::new(ErrorKind::InvalidArgument, format!("PWM duty cycle must be in the range [0, 1], got {}", duty)));
+	}
+
+	let period = (1e9 / frequency).round().max(1.0) as u64;
+	let high   = (period as f64 * duty).round() as u64;
+	Ok((period, high))
+}
+
+/// Update the high time from the running effect, if any, and return it.
+fn apply_effect(shared: &Shared, period: u64, now: Instant) -> u64 {
+	let mut effect = shared.effect();
+	if let Some((duty, running)) = effect.duty(now) {
+		let high = (period as f64 * duty.clamp(0.0, 1.0)).round() as u64;
+		shared.high.store(high, Ordering::Relaxed);
+		if !running {
+			*effect = Effect::None;
+		}
+	}
+	shared.high.load(Ordering::Relaxed)
+}
+
+fn run(gpio: &mut Gpio, pin: usize, shared: &Shared) {
+	let mut start = Instant::now();
+	while !shared.stop.load(Ordering::Relaxed) {
+		let period = shared.period.load(Ordering::Relaxed);
+		let high   = apply_effect(shared, period, start);
+
+		if high > 0 {
+			gpio.write_level(pin, true);
+			sleep_until(start + Duration::from_nanos(high));
+		}
+		if high < period {
+			gpio.write_level(pin, false);
+		}
+
+		start += Duration::from_nanos(period);
+
+		// Don't try to catch up if we fell behind more than a full period.
+		let now = Instant::now();
+		if now > start + Duration::from_nanos(period) {
+			start = now;
+		}
+
+		sleep_until(start);
+	}
+
+	gpio.write_level(pin, false);
+}
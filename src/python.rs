@@ -0,0 +1,277 @@
+//! Python bindings for the library, enabled with the `python` feature.
+//!
+//! Pin functions and pull up/down modes are passed as the same strings used by the serde support,
+//! like `"output"`, `"alt0"` or `"pull-up"`.
+
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{Error, Gpio, GpioConfig, GpioPullConfig, Level, MockBackend, PinFunction, PullMode, SpecialPin};
+
+pyo3::create_exception!(bcm283x_gpio, GpioError, PyException, "An error reported by the GPIO library.");
+
+/// A handle to the GPIO peripheral.
+///
+/// Python objects may be shared between threads, so the handle is guarded by a mutex.
+#[pyclass(name = "Gpio")]
+struct PyGpio {
+	gpio: Mutex<Gpio>,
+}
+
+/// The state of a single pin.
+#[pyclass(name = "PinInfo", get_all)]
+#[derive(Clone)]
+struct PyPinInfo {
+	index: usize,
+	function: &'static str,
+	level: bool,
+	event: bool,
+	detect_rise: bool,
+	detect_fall: bool,
+	detect_high: bool,
+	detect_low: bool,
+	detect_async_rise: bool,
+	detect_async_fall: bool,
+	function_name: Option<String>,
+	pull_mode: Option<&'static str>,
+	special: Option<&'static str>,
+	kernel_owner: Option<String>,
+	interface: Option<String>,
+}
+
+/// A GPIO config that can be applied at once.
+#[pyclass(name = "GpioConfig")]
+struct PyGpioConfig {
+	config: GpioConfig,
+}
+
+/// A pull up/down configuration that can be applied at once.
+#[pyclass(name = "GpioPullConfig")]
+struct PyGpioPullConfig {
+	config: GpioPullConfig,
+}
+
+#[pymethods]
+impl PyGpio {
+	/// Open the GPIO peripheral with a backend: mem, gpiomem, cdev, sysfs, pread or mock.
+	#[new]
+	#[pyo3(signature = (backend = "mem"))]
+	fn new(backend: &str) -> PyResult<Self> {
+		let gpio = match backend {
+			"mem"     => Gpio::new(),
+			"gpiomem" => Gpio::new_gpiomem(),
+			"cdev"    => Gpio::new_cdev(),
+			"sysfs"   => Gpio::new_sysfs(),
+			"pread"   => Gpio::new_pread(),
+			"mock"    => Ok(Gpio::with_backend(MockBackend::new())),
+			_ => return Err(GpioError::new_err(format!("unknown backend: {}, expected mem, gpiomem, cdev, sysfs, pread or mock", backend))),
+		};
+		Ok(Self { gpio: Mutex::new(gpio.map_err(to_py)?) })
+	}
+
+	/// The name of the backend used by the handle.
+	#[getter]
+	fn backend(&self) -> String {
+		self.lock().backend().name().to_string()
+	}
+
+	/// Read the current level of a pin.
+	fn read_level(&self, index: usize) -> PyResult<bool> {
+		self.lock().read_level(index).map(Level::is_high).map_err(to_py)
+	}
+
+	/// Read the current level of all pins, with bit N holding the level of pin N.
+	fn read_levels(&self) -> u64 {
+		self.lock().read_levels()
+	}
+
+	/// Set the level of a pin.
+	fn set_level(&self, index: usize, value: bool) -> PyResult<()> {
+		self.lock().set_level(index, value).map_err(to_py)
+	}
+
+	/// Read the state of a single pin.
+	fn pin(&self, index: usize) -> PyResult<PyPinInfo> {
+		let info = self.lock().read_pin(index).map_err(to_py)?;
+		Ok(PyPinInfo::new(index, info))
+	}
+
+	/// Read the state of all pins at once.
+	fn pins(&self) -> Vec<PyPinInfo> {
+		self.lock().read_pins().into_iter().enumerate().map(|(i, x)| PyPinInfo::new(i, x)).collect()
+	}
+}
+
+impl PyGpio {
+	fn lock(&self) -> MutexGuard<'_, Gpio> {
+		self.gpio.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl PyPinInfo {
+	fn new(index: usize, info: crate::PinInfo) -> Self {
+		Self {
+			index,
+			function:          function_name(info.function),
+			level:             info.level.is_high(),
+			event:             info.event,
+			detect_rise:       info.detect_rise,
+			detect_fall:       info.detect_fall,
+			detect_high:       info.detect_high,
+			detect_low:        info.detect_low,
+			detect_async_rise: info.detect_async_rise,
+			detect_async_fall: info.detect_async_fall,
+			function_name:     info.function_name,
+			pull_mode:         info.pull_mode.map(pull_mode_name),
+			special:           info.special.map(special_pin_name),
+			kernel_owner:      info.kernel_owner,
+			interface:         info.interface,
+		}
+	}
+}
+
+#[pymethods]
+impl PyPinInfo {
+	fn __repr__(&self) -> String {
+		format!("PinInfo(index={}, function='{}', level={})", self.index, self.function, self.level as u8)
+	}
+}
+
+#[pymethods]
+impl PyGpioConfig {
+	/// Create an empty configuration that changes nothing.
+	#[new]
+	fn new() -> Self {
+		Self { config: GpioConfig::new() }
+	}
+
+	/// Set the function of a pin: input, output or alt0 to alt5.
+	fn set_function(&mut self, pin: usize, function: &str) -> PyResult<()> {
+		self.config.set_function(pin, parse_function(function)?).map_err(to_py)
+	}
+
+	fn set_level(&mut self, pin: usize, level: bool) -> PyResult<()> {
+		self.config.set_level(pin, level).map_err(to_py)
+	}
+
+	fn set_detect_rise(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_rise(pin, detect).map_err(to_py)
+	}
+
+	fn set_detect_fall(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_fall(pin, detect).map_err(to_py)
+	}
+
+	fn set_detect_high(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_high(pin, detect).map_err(to_py)
+	}
+
+	fn set_detect_low(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_low(pin, detect).map_err(to_py)
+	}
+
+	fn set_detect_async_rise(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_async_rise(pin, detect).map_err(to_py)
+	}
+
+	fn set_detect_async_fall(&mut self, pin: usize, detect: bool) -> PyResult<()> {
+		self.config.set_detect_async_fall(pin, detect).map_err(to_py)
+	}
+
+	/// Check the configuration for invalid combinations of settings.
+	fn validate(&self) -> PyResult<()> {
+		self.config.validate().map_err(to_py)
+	}
+
+	/// Check the configuration like validate, and check that the backend and SoC of a handle can apply it.
+	fn validate_for(&self, gpio: &PyGpio) -> PyResult<()> {
+		let gpio = gpio.lock();
+		self.config.validate_for(gpio.capabilities(), gpio.soc()).map_err(to_py)
+	}
+
+	/// Validate and apply the configuration.
+	fn apply(&self, gpio: &PyGpio) -> PyResult<()> {
+		self.config.apply(&mut gpio.lock()).map_err(to_py)
+	}
+}
+
+#[pymethods]
+impl PyGpioPullConfig {
+	/// Create an empty configuration that changes nothing.
+	#[new]
+	fn new() -> Self {
+		Self { config: GpioPullConfig::new() }
+	}
+
+	/// Set the pull up/down mode of a pin: float, pull-down or pull-up.
+	fn set_pull_mode(&mut self, pin: usize, mode: &str) -> PyResult<()> {
+		self.config.set_pull_mode(pin, parse_pull_mode(mode)?).map_err(to_py)
+	}
+
+	/// Apply the configuration.
+	///
+	/// This is not atomic: other processes or the kernel must not change pull up/down modes at the same time.
+	fn apply(&self, gpio: &PyGpio) -> PyResult<()> {
+		unsafe { self.config.apply(&mut gpio.lock()) }.map_err(to_py)
+	}
+}
+
+/// The bcm283x_gpio Python module.
+#[pymodule]
+fn bcm283x_gpio(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<PyGpio>()?;
+	module.add_class::<PyPinInfo>()?;
+	module.add_class::<PyGpioConfig>()?;
+	module.add_class::<PyGpioPullConfig>()?;
+	module.add("GpioError", module.py().get_type::<GpioError>())?;
+	Ok(())
+}
+
+fn to_py(error: Error) -> PyErr {
+	GpioError::new_err(error.to_string())
+}
+
+fn function_name(function: PinFunction) -> &'static str {
+	match function {
+		PinFunction::Input  => "input",
+		PinFunction::Output => "output",
+		PinFunction::Alt0   => "alt0",
+		PinFunction::Alt1   => "alt1",
+		PinFunction::Alt2   => "alt2",
+		PinFunction::Alt3   => "alt3",
+		PinFunction::Alt4   => "alt4",
+		PinFunction::Alt5   => "alt5",
+	}
+}
+
+fn parse_function(name: &str) -> PyResult<PinFunction> {
+	(0..8)
+		.map(PinFunction::from_bits)
+		.find(|x| function_name(*x) == name)
+		.ok_or_else(|| GpioError::new_err(format!("unknown pin function: {}, expected input, output or alt0 to alt5", name)))
+}
+
+fn pull_mode_name(mode: PullMode) -> &'static str {
+	match mode {
+		PullMode::Float    => "float",
+		PullMode::PullDown => "pull-down",
+		PullMode::PullUp   => "pull-up",
+	}
+}
+
+fn special_pin_name(special: SpecialPin) -> &'static str {
+	match special {
+		SpecialPin::IdEeprom => "id-eeprom",
+		SpecialPin::SdCard   => "sd-card",
+	}
+}
+
+fn parse_pull_mode(name: &str) -> PyResult<PullMode> {
+	match name {
+		"float"     => Ok(PullMode::Float),
+		"pull-down" => Ok(PullMode::PullDown),
+		"pull-up"   => Ok(PullMode::PullUp),
+		_ => Err(GpioError::new_err(format!("unknown pull up/down mode: {}, expected float, pull-down or pull-up", name))),
+	}
+}
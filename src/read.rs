@@ -1,9 +1,23 @@
-use crate::{PinFunction, Register};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{json, Error, ErrorKind, GpioBackend, Level, PinFunction, PullMode, Register, Soc, SpecialPin};
+
+/// The format name of a GPIO state in JSON.
+const JSON_FORMAT : &str = "bcm283x-gpio-state";
+
+/// The version of the JSON format of a GPIO state.
+const JSON_VERSION : u64 = 1;
+
+/// The size of a GPIO state in bytes, see [`GpioState::to_bytes`].
+pub const GPIO_STATE_SIZE : usize = 0x400;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PinInfo {
 	pub function: PinFunction,
-	pub level: bool,
+	pub level: Level,
 	pub event: bool,
 	pub detect_rise: bool,
 	pub detect_fall: bool,
@@ -11,14 +25,97 @@ pub struct PinInfo {
 	pub detect_low: bool,
 	pub detect_async_rise: bool,
 	pub detect_async_fall: bool,
+
+	/// The name of the current alternate function of the pin, like `SDA1`, if known.
+	///
+	/// This depends on the SoC, see [`GpioState::pin_for`] and [`Soc::alt_function_name`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub function_name: Option<String>,
+
+	/// The pull up/down mode of the pin, if known.
+	///
+	/// This can only be read back on the BCM2711.
+	/// On other SoCs, [`Gpio::read_pins`](crate::Gpio::read_pins) reports the modes set through the same handle.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub pull_mode: Option<PullMode>,
+
+	/// The special purpose of the pin on Raspberry Pi boards, if any.
+	///
+	/// This depends on the SoC, see [`GpioState::pin_for`] and [`Soc::special_pin`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub special: Option<SpecialPin>,
+
+	/// The kernel driver that claimed the pin, if known.
+	///
+	/// This is not part of the register state, see [`KernelClaims::annotate`](crate::KernelClaims::annotate).
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub kernel_owner: Option<String>,
+
+	/// The device tree node that uses the pin through its pin control settings, like `i2c1`, if known.
+	///
+	/// This is not part of the register state, see [`Interface::annotate`](crate::Interface::annotate).
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub interface: Option<String>,
 }
 
+/// The differences of a single pin between two [`GpioState`]s.
+///
+/// Every field holds the new value if it changed, or `None` if it didn't.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PinChange {
+	pub pin: usize,
+	pub function: Option<PinFunction>,
+	pub level: Option<Level>,
+	pub event: Option<bool>,
+	pub detect_rise: Option<bool>,
+	pub detect_fall: Option<bool>,
+	pub detect_high: Option<bool>,
+	pub detect_low: Option<bool>,
+	pub detect_async_rise: Option<bool>,
+	pub detect_async_fall: Option<bool>,
+}
+
+/// The registers with a single bit per pin, in the order of the fields of [`PinChange`].
+const BIT_REGISTERS : [Register; 8] = [
+	Register::GPLEV0,
+	Register::GPEDS0,
+	Register::GPREN0,
+	Register::GPFEN0,
+	Register::GPHEN0,
+	Register::GPLEN0,
+	Register::GPAREN0,
+	Register::GPAFEN0,
+];
+
 #[derive(Clone)]
 pub struct GpioState {
 	data: [u32; 0x100],
 }
 
+impl Default for GpioState {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl GpioState {
+	/// Create a state with all registers set to zero.
+	///
+	/// Use [`Gpio::read_all_into`](crate::Gpio::read_all_into) to fill it.
+	pub fn new() -> Self {
+		Self { data: [0; 0x100] }
+	}
+
+	/// Read the known registers directly from a backend, without a [`Gpio`](crate::Gpio) handle.
+	pub fn from_backend(backend: &dyn GpioBackend) -> Self {
+		let mut state = Self::new();
+		for &reg in Register::ALL.iter() {
+			state.data[reg as usize / 4] = backend.read_register(reg);
+		}
+		state
+	}
+
 	pub fn from_data(data: [u32; 0x100]) -> Self {
 		Self { data }
 	}
@@ -27,66 +124,229 @@ impl GpioState {
 		&self.data
 	}
 
+	pub fn data_mut(&mut self) -> &mut [u32; 0x100] {
+		&mut self.data
+	}
+
 	pub fn into_data(self) -> [u32; 0x100] {
 		self.data
 	}
 
-	pub fn pin_function(&self, index: usize) -> PinFunction {
-		PinFunction::try_from_bits(self.read_pin_bits(index, Register::GPFSEL0, 10, 3) as u8).unwrap()
+	/// Encode the registers as bytes, in little-endian order.
+	///
+	/// Register N is stored at byte offset N * 4, like in the control block of the peripheral.
+	pub fn to_bytes(&self) -> [u8; GPIO_STATE_SIZE] {
+		let mut bytes = [0; GPIO_STATE_SIZE];
+		for (chunk, value) in bytes.chunks_exact_mut(4).zip(self.data.iter()) {
+			chunk.copy_from_slice(&value.to_le_bytes());
+		}
+		bytes
+	}
+
+	/// Decode the registers from bytes encoded by [`to_bytes`](Self::to_bytes).
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		if bytes.len() != GPIO_STATE_SIZE {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid GPIO state: expected {} bytes, got {}", GPIO_STATE_SIZE, bytes.len())));
+		}
+		let mut state = Self::new();
+		for (value, chunk) in state.data.iter_mut().zip(bytes.chunks_exact(4)) {
+			*value = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+		}
+		Ok(state)
 	}
 
-	pub fn pin_level(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPLEV0, 32, 1) != 0
+	/// Encode the registers as JSON, in the format of the `save` command of the CLI.
+	pub fn to_json(&self) -> String {
+		let mut output = String::new();
+		output += "{\n";
+		output += &format!("\t\"format\": \"{}\",\n", JSON_FORMAT);
+		output += &format!("\t\"version\": {},\n", JSON_VERSION);
+		output += "\t\"registers\": [";
+		for (i, value) in self.data.iter().enumerate() {
+			if i % 8 == 0 {
+				output += "\n\t\t";
+			} else {
+				output += " ";
+			}
+			output += &value.to_string();
+			if i + 1 < self.data.len() {
+				output += ",";
+			}
+		}
+		output += "\n\t]\n}\n";
+		output
 	}
 
-	pub fn pin_event(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPEDS0, 32, 1) != 0
+	/// Decode the registers from JSON encoded by [`to_json`](Self::to_json).
+	pub fn from_json(data: &str) -> Result<Self, Error> {
+		Self::parse_json(data).map_err(|e| Error::new(ErrorKind::InvalidArgument, format!("invalid GPIO state: {}", e)))
 	}
 
-	pub fn pin_detect_rise(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPREN0, 32, 1) != 0
+	fn parse_json(data: &str) -> Result<Self, String> {
+		let mut parser = json::Parser::new(data);
+		let value = parser.parse_document()?;
+		let object = value.as_object().ok_or("expected a JSON object")?;
+
+		match object.get("format").and_then(json::Value::as_str) {
+			Some(JSON_FORMAT) => (),
+			_ => return Err(format!("missing or invalid format, expected \"{}\"", JSON_FORMAT)),
+		}
+
+		match object.get("version").and_then(json::Value::as_u64) {
+			Some(JSON_VERSION) => (),
+			_ => return Err(format!("missing or unsupported version, expected {}", JSON_VERSION)),
+		}
+
+		let registers = object.get("registers")
+			.and_then(json::Value::as_array)
+			.ok_or("missing registers array")?;
+
+		let mut state = Self::new();
+		if registers.len() != state.data.len() {
+			return Err(format!("expected {} registers, got {}", state.data.len(), registers.len()));
+		}
+
+		for (i, (value, register)) in state.data.iter_mut().zip(registers.iter()).enumerate() {
+			*value = register.as_u64()
+				.filter(|x| *x <= u64::from(u32::MAX))
+				.ok_or_else(|| format!("invalid value for register {}", i))? as u32;
+		}
+
+		Ok(state)
 	}
 
-	pub fn pin_detect_fall(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPFEN0, 32, 1) != 0
+	pub fn pin_function(&self, index: usize) -> Result<PinFunction, Error> {
+		Ok(PinFunction::from_bits(self.read_pin_bits(index, Register::GPFSEL0, 10, 3)? as u8))
 	}
 
-	pub fn pin_detect_high(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPHEN0, 32, 1) != 0
+	pub fn pin_level(&self, index: usize) -> Result<Level, Error> {
+		Ok((self.read_pin_bits(index, Register::GPLEV0, 32, 1)? != 0).into())
 	}
 
-	pub fn pin_detect_low(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPLEN0, 32, 1) != 0
+	pub fn pin_event(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPEDS0, 32, 1)? != 0)
 	}
 
-	pub fn pin_detect_async_rise(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPAREN0, 32, 1) != 0
+	pub fn pin_detect_rise(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPREN0, 32, 1)? != 0)
 	}
 
-	pub fn pin_detect_async_fall(&self, index: usize) -> bool {
-		self.read_pin_bits(index, Register::GPAFEN0, 32, 1) != 0
+	pub fn pin_detect_fall(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPFEN0, 32, 1)? != 0)
 	}
 
-	pub fn pin(&self, index: usize) -> PinInfo {
-		PinInfo {
-			function:          self.pin_function(index),
-			level:             self.pin_level(index),
-			event:             self.pin_event(index),
-			detect_rise:       self.pin_detect_rise(index),
-			detect_fall:       self.pin_detect_fall(index),
-			detect_high:       self.pin_detect_high(index),
-			detect_low:        self.pin_detect_low(index),
-			detect_async_rise: self.pin_detect_async_rise(index),
-			detect_async_fall: self.pin_detect_async_fall(index),
+	pub fn pin_detect_high(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPHEN0, 32, 1)? != 0)
+	}
+
+	pub fn pin_detect_low(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPLEN0, 32, 1)? != 0)
+	}
+
+	pub fn pin_detect_async_rise(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPAREN0, 32, 1)? != 0)
+	}
+
+	pub fn pin_detect_async_fall(&self, index: usize) -> Result<bool, Error> {
+		Ok(self.read_pin_bits(index, Register::GPAFEN0, 32, 1)? != 0)
+	}
+
+	/// Get the state of a single pin.
+	///
+	/// The fields that depend on the SoC are left empty, see [`pin_for`](Self::pin_for).
+	pub fn pin(&self, index: usize) -> Result<PinInfo, Error> {
+		Ok(PinInfo {
+			function:          self.pin_function(index)?,
+			level:             self.pin_level(index)?,
+			event:             self.pin_event(index)?,
+			detect_rise:       self.pin_detect_rise(index)?,
+			detect_fall:       self.pin_detect_fall(index)?,
+			detect_high:       self.pin_detect_high(index)?,
+			detect_low:        self.pin_detect_low(index)?,
+			detect_async_rise: self.pin_detect_async_rise(index)?,
+			detect_async_fall: self.pin_detect_async_fall(index)?,
+			function_name:     None,
+			pull_mode:         None,
+			special:           None,
+			kernel_owner:      None,
+			interface:         None,
+		})
+	}
+
+	/// Get the pins that differ from an older state, with their new values.
+	///
+	/// Only the registers are compared, so this is cheap enough to run after every poll.
+	/// Pins are reported in ascending order, and pins without differences are skipped.
+	pub fn changes<'a>(&'a self, previous: &'a GpioState) -> impl Iterator<Item = PinChange> + 'a {
+		let mut changed = 0u64;
+		for pin in 0..54 {
+			let function = self.function_bits(pin) != previous.function_bits(pin);
+			changed |= u64::from(function) << pin;
 		}
+		for &reg in BIT_REGISTERS.iter() {
+			changed |= self.bit_register(reg) ^ previous.bit_register(reg);
+		}
+
+		(0..54).filter(move |pin| changed >> pin & 1 == 1).map(move |pin| {
+			let bit = |reg: Register| {
+				let new = self.bit_register(reg) >> pin & 1 == 1;
+				let old = previous.bit_register(reg) >> pin & 1 == 1;
+				Some(new).filter(|_| new != old)
+			};
+			let function = self.function_bits(pin);
+			PinChange {
+				pin,
+				function:          Some(PinFunction::from_bits(function as u8)).filter(|_| function != previous.function_bits(pin)),
+				level:             bit(Register::GPLEV0).map(Level::from),
+				event:             bit(Register::GPEDS0),
+				detect_rise:       bit(Register::GPREN0),
+				detect_fall:       bit(Register::GPFEN0),
+				detect_high:       bit(Register::GPHEN0),
+				detect_low:        bit(Register::GPLEN0),
+				detect_async_rise: bit(Register::GPAREN0),
+				detect_async_fall: bit(Register::GPAFEN0),
+			}
+		})
+	}
+
+	/// Get the state of a single pin, including the fields that depend on the SoC.
+	///
+	/// The pull up/down mode is only filled in for SoCs that can read it back.
+	pub fn pin_for(&self, index: usize, soc: Soc) -> Result<PinInfo, Error> {
+		let mut info = self.pin(index)?;
+		info.function_name = soc.alt_function_name(index, info.function).map(String::from);
+		info.special       = soc.special_pin(index);
+		if soc.has_pull_readback() {
+			info.pull_mode = PullMode::from_bcm2711_bits(self.read_pin_bits(index, Register::GPPUPPDN0, 16, 2)?);
+		}
+		Ok(info)
 	}
 
+	/// Get the state of all pins.
+	///
+	/// The fields that depend on the SoC are left empty, see [`pins_for`](Self::pins_for).
 	pub fn pins(&self) -> Vec<PinInfo> {
-		(0..53).map(|i| self.pin(i)).collect()
+		(0..54).filter_map(|i| self.pin(i).ok()).collect()
 	}
 
-	fn read_pin_bits(&self, index: usize, base: Register, pins_per_register: u8, bits_per_pin: u8) -> u32 {
-		crate::assert_pin_index(index);
+	/// Get the state of all pins, including the fields that depend on the SoC.
+	pub fn pins_for(&self, soc: Soc) -> Vec<PinInfo> {
+		(0..54).filter_map(|i| self.pin_for(i, soc).ok()).collect()
+	}
+
+	/// Read the function select bits of a pin that is known to be valid.
+	fn function_bits(&self, index: usize) -> u32 {
+		self.data[Register::GPFSEL0 as usize / 4 + index / 10] >> (index % 10 * 3) & 0b111
+	}
+
+	/// Read a pair of registers with a single bit per pin as one 64 bit value.
+	fn bit_register(&self, base: Register) -> u64 {
+		let index = base as usize / 4;
+		u64::from(self.data[index]) | u64::from(self.data[index + 1]) << 32
+	}
+
+	fn read_pin_bits(&self, index: usize, base: Register, pins_per_register: u8, bits_per_pin: u8) -> Result<u32, Error> {
+		crate::check_pin_index(index)?;
 
 		let pins_per_register = pins_per_register as usize;
 		let bits_per_pin      = bits_per_pin      as usize;
@@ -98,7 +358,79 @@ impl GpioState {
 		let index          = index % pins_per_register;
 
 		let value = self.data[register_index] >> (bits_per_pin * index);
-		let mask  = !(std::u32::MAX << bits_per_pin);
-		value & mask
+		let mask  = !(u32::MAX << bits_per_pin);
+		Ok(value & mask)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A state with a few pins configured, and distinct values in some other registers.
+	fn fixture() -> GpioState {
+		let mut state = GpioState::new();
+		let data = state.data_mut();
+		data[Register::GPFSEL1 as usize / 4] = 0b001 << 21;
+		data[Register::GPLEV0 as usize / 4]  = 1 << 17 | 1 << 4;
+		data[Register::GPREN1 as usize / 4]  = 1 << 8;
+		data[0xFC / 4] = 0xDEAD_BEEF;
+		state
+	}
+
+	#[test]
+	fn changes() {
+		let previous = GpioState::new();
+		let state = fixture();
+		let changes: Vec<_> = state.changes(&previous).collect();
+		assert_eq!(changes, [
+			PinChange { pin: 4, level: Some(Level::High), ..Default::default() },
+			PinChange { pin: 17, function: Some(PinFunction::Output), level: Some(Level::High), ..Default::default() },
+			PinChange { pin: 40, detect_rise: Some(true), ..Default::default() },
+		]);
+
+		let changes: Vec<_> = previous.changes(&state).collect();
+		assert_eq!(changes[1], PinChange { pin: 17, function: Some(PinFunction::Input), level: Some(Level::Low), ..Default::default() });
+		assert_eq!(state.changes(&state).count(), 0);
+	}
+
+	#[test]
+	fn bytes_round_trip() {
+		let state = fixture();
+		let bytes = state.to_bytes();
+		assert_eq!(bytes[0x04..0x08], [0x00, 0x00, 0x20, 0x00]);
+		assert_eq!(bytes[0xFC..0x100], [0xEF, 0xBE, 0xAD, 0xDE]);
+		assert_eq!(GpioState::from_bytes(&bytes).unwrap().data(), state.data());
+		assert_eq!(GpioState::from_bytes(&bytes[1..]).err().unwrap().kind(), ErrorKind::InvalidArgument);
+	}
+
+	#[test]
+	fn json_round_trip() {
+		let state = fixture();
+		let json = state.to_json();
+		assert!(json.contains("\"format\": \"bcm283x-gpio-state\""));
+		assert_eq!(GpioState::from_json(&json).unwrap().data(), state.data());
+	}
+
+	#[test]
+	fn invalid_json() {
+		let invalid = [
+			"",
+			"{}",
+			"[1, 2]",
+			"{\"format\": \"something-else\", \"version\": 1, \"registers\": []}",
+			"{\"format\": \"bcm283x-gpio-state\", \"version\": 2, \"registers\": []}",
+			"{\"format\": \"bcm283x-gpio-state\", \"version\": 1, \"registers\": [1, 2, 3]}",
+		];
+		for data in invalid.iter() {
+			assert_eq!(GpioState::from_json(data).err().unwrap().kind(), ErrorKind::InvalidArgument, "{:?}", data);
+		}
+	}
+
+	#[test]
+	fn deeply_nested_json() {
+		let data = "[".repeat(100_000);
+		let error = GpioState::from_json(&data).err().unwrap();
+		assert!(error.to_string().contains("nesting too deep"), "{}", error);
 	}
 }
@@ -0,0 +1,158 @@
+//! Real-time scheduling for timing-critical threads.
+//!
+//! The jitter of bit-banged signals is mostly caused by the scheduler preempting the thread that generates them.
+//! Running the thread with the `SCHED_FIFO` policy avoids preemption by normal processes.
+//! This requires root, `CAP_SYS_NICE` or a non-zero `RLIMIT_RTPRIO` limit.
+//!
+//! A thread with a real-time priority that never sleeps can starve the rest of the system on a single core,
+//! so only promote threads that regularly block or sleep.
+//!
+//! On multi-core systems, the jitter is reduced further by pinning the thread to a core
+//! that is isolated from the other processes, for example with the `isolcpus` kernel parameter.
+//!
+//! Page faults add latency too, the first time memory is touched or when it was swapped out.
+//! Use [`lock_memory`] and [`Gpio::lock_mapping`](crate::Gpio::lock_mapping) to avoid them in time-critical code.
+
+#[cfg(not(feature = "stub"))]
+use crate::ErrorKind;
+use crate::Error;
+
+/// Options for the background threads started by this crate, like the one of [`SoftPwm`](crate::SoftPwm).
+///
+/// The default options leave the scheduling of the thread unchanged.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ThreadOptions {
+	/// Run the thread with the `SCHED_FIFO` policy at this priority, see [`promote_current_thread`].
+	pub priority: Option<i32>,
+
+	/// Run the thread only on this CPU core, see [`pin_current_thread`].
+	pub cpu: Option<usize>,
+}
+
+impl ThreadOptions {
+	/// Create options that leave the scheduling of the thread unchanged.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Run the thread with the `SCHED_FIFO` policy at a priority.
+	pub fn with_priority(mut self, priority: i32) -> Self {
+		self.priority = Some(priority);
+		self
+	}
+
+	/// Run the thread only on a CPU core.
+	pub fn with_cpu(mut self, cpu: usize) -> Self {
+		self.cpu = Some(cpu);
+		self
+	}
+
+	/// Apply the options to the current thread.
+	pub(crate) fn apply(&self) -> Result<(), Error> {
+		if let Some(cpu) = self.cpu {
+			pin_current_thread(cpu)?;
+		}
+		if let Some(priority) = self.priority {
+			promote_current_thread(priority)?;
+		}
+		Ok(())
+	}
+}
+
+/// Run the current thread with the `SCHED_FIFO` real-time policy at a priority.
+///
+/// The priority must be in the range supported by the kernel, which is 1 to 99 on Linux.
+/// Only the calling thread is affected, not the rest of the process.
+#[cfg(not(feature = "stub"))]
+pub fn promote_current_thread(priority: i32) -> Result<(), Error> {
+	use nix::libc;
+
+	let min = unsafe { libc::sched_get_priority_min(libc::SCHED_FIFO) };
+	let max = unsafe { libc::sched_get_priority_max(libc::SCHED_FIFO) };
+	if priority < min || priority > max {
+		return Err(Error::new(ErrorKind::InvalidArgument, format!("real-time priority out of range [{}-{}]: {}", min, max, priority)));
+	}
+
+	// On Linux, a pid of 0 selects the calling thread.
+	let param = libc::sched_param { sched_priority: priority };
+	if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } != 0 {
+		let error = std::io::Error::last_os_error();
+		let message = match error.kind() {
+			std::io::ErrorKind::PermissionDenied => "failed to set real-time scheduling policy, this requires root, CAP_SYS_NICE or a non-zero RLIMIT_RTPRIO limit",
+			_ => "failed to set real-time scheduling policy",
+		};
+		return Err(Error::from_io(ErrorKind::Other, message, error));
+	}
+
+	Ok(())
+}
+
+/// Run the current thread with the `SCHED_FIFO` real-time policy at a priority.
+///
+/// This always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error with the `stub` feature.
+#[cfg(feature = "stub")]
+pub fn promote_current_thread(_priority: i32) -> Result<(), Error> {
+	Err(crate::procfs::unsupported("real-time scheduling"))
+}
+
+/// Run the current thread only on a CPU core.
+///
+/// Only the calling thread is affected, not the rest of the process.
+#[cfg(not(feature = "stub"))]
+pub fn pin_current_thread(cpu: usize) -> Result<(), Error> {
+	use nix::libc;
+
+	if cpu >= libc::CPU_SETSIZE as usize {
+		return Err(Error::new(ErrorKind::InvalidArgument, format!("CPU index out of range [0-{}]: {}", libc::CPU_SETSIZE - 1, cpu)));
+	}
+
+	// On Linux, a pid of 0 selects the calling thread.
+	let result = unsafe {
+		let mut set: libc::cpu_set_t = std::mem::zeroed();
+		libc::CPU_SET(cpu, &mut set);
+		libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+	};
+	if result != 0 {
+		let error = std::io::Error::last_os_error();
+		return match error.kind() {
+			std::io::ErrorKind::InvalidInput => Err(Error::new(ErrorKind::InvalidArgument, format!("CPU {} is not available to this process", cpu))),
+			_ => Err(Error::from_io(ErrorKind::Other, format!("failed to pin thread to CPU {}", cpu), error)),
+		};
+	}
+
+	Ok(())
+}
+
+/// Run the current thread only on a CPU core.
+///
+/// This always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error with the `stub` feature.
+#[cfg(feature = "stub")]
+pub fn pin_current_thread(_cpu: usize) -> Result<(), Error> {
+	Err(crate::procfs::unsupported("CPU affinity"))
+}
+
+/// Lock all current and future memory of the process in RAM.
+///
+/// This prevents page faults in time-critical code, including on the stacks of threads started later.
+/// All pages are touched when they are locked, which can take a moment for a large process.
+///
+/// Locked memory is limited by `RLIMIT_MEMLOCK` for processes without root or `CAP_IPC_LOCK`.
+/// When the limit is reached, later allocations and thread creation fail, so use this with care.
+#[cfg(not(feature = "stub"))]
+pub fn lock_memory() -> Result<(), Error> {
+	use nix::libc;
+
+	if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } != 0 {
+		let error = std::io::Error::last_os_error();
+		return Err(Error::from_io(ErrorKind::Other, "failed to lock process memory, this requires root, CAP_IPC_LOCK or a sufficient RLIMIT_MEMLOCK limit", error));
+	}
+	Ok(())
+}
+
+/// Lock all current and future memory of the process in RAM.
+///
+/// This always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error with the `stub` feature.
+#[cfg(feature = "stub")]
+pub fn lock_memory() -> Result<(), Error> {
+	Err(crate::procfs::unsupported("locking process memory"))
+}
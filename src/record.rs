@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::{Capabilities, Error, ErrorKind, Gpio, GpioBackend, Register, Soc};
+
+/// The kind of a recorded register access.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AccessKind {
+	Read,
+	Write,
+}
+
+/// A single register access in a [`Recording`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RecordedAccess {
+	/// The time of the access since the recording started.
+	pub time: Duration,
+	pub kind: AccessKind,
+	pub register: Register,
+	/// The value read from or written to the register.
+	pub value: u32,
+}
+
+/// A backend that records every register access of another backend.
+///
+/// Each access is written as one line with the time in microseconds since the backend was created,
+/// the kind of access, the register name and the value:
+///
+/// ```text
+/// 1532 write GPFSEL1 0x00200000
+/// 1544 read GPLEV0 0x00020000
+/// ```
+///
+/// The output can be loaded with [`Recording::open`] to replay the writes on another [`Gpio`].
+/// Failures to write the output are ignored, since the register functions can not report errors.
+pub struct RecordingBackend<B> {
+	inner: B,
+	output: RefCell<Box<dyn Write + Send>>,
+	start: Instant,
+}
+
+impl<B: GpioBackend> RecordingBackend<B> {
+	/// Record the register accesses of a backend to a writer.
+	pub fn new(inner: B, output: impl Write + Send + 'static) -> Self {
+		Self {
+			inner,
+			output: RefCell::new(Box::new(output)),
+			start: Instant::now(),
+		}
+	}
+
+	/// Record the register accesses of a backend to a file.
+	pub fn create(inner: B, path: impl AsRef<Path>) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let file = std::fs::File::create(path)
+			.map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to create {}", path.display()), e))?;
+		Ok(Self::new(inner, std::io::BufWriter::new(file)))
+	}
+
+	/// Get the recorded backend.
+	pub fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	fn record(&self, kind: AccessKind, reg: Register, value: u32) {
+		let access = RecordedAccess { time: self.start.elapsed(), kind, register: reg, value };
+		let _ = writeln!(self.output.borrow_mut(), "{}", access);
+	}
+}
+
+impl<B: GpioBackend> GpioBackend for RecordingBackend<B> {
+	fn name(&self) -> &str {
+		self.inner.name()
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		self.inner.capabilities()
+	}
+
+	fn soc(&self) -> Soc {
+		self.inner.soc()
+	}
+
+	// The control block is not exposed, so all accesses go through the recording.
+
+	fn read_register(&self, reg: Register) -> u32 {
+		let value = self.inner.read_register(reg);
+		self.record(AccessKind::Read, reg, value);
+		value
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		self.record(AccessKind::Write, reg, value);
+		self.inner.write_register(reg, value)
+	}
+}
+
+impl<B> Drop for RecordingBackend<B> {
+	fn drop(&mut self) {
+		let _ = self.output.get_mut().flush();
+	}
+}
+
+impl std::fmt::Display for RecordedAccess {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let kind = match self.kind {
+			AccessKind::Read  => "read",
+			AccessKind::Write => "write",
+		};
+		write!(f, "{} {} {} 0x{:08X}", self.time.as_micros(), kind, self.register, self.value)
+	}
+}
+
+impl std::str::FromStr for RecordedAccess {
+	type Err = Error;
+
+	/// Parse an access in the format written by [`RecordingBackend`].
+	fn from_str(line: &str) -> Result<Self, Self::Err> {
+		let invalid = || Error::new(ErrorKind::InvalidArgument, format!("invalid register access: {}", line));
+		let fields: Vec<_> = line.split_whitespace().collect();
+		let (time, kind, register, value) = match fields.as_slice() {
+			[time, kind, register, value] => (time, kind, register, value),
+			_ => return Err(invalid()),
+		};
+		Ok(Self {
+			time: Duration::from_micros(time.parse().map_err(|_| invalid())?),
+			kind: match *kind {
+				"read"  => AccessKind::Read,
+				"write" => AccessKind::Write,
+				_ => return Err(invalid()),
+			},
+			register: register.parse()?,
+			value: value.strip_prefix("0x")
+				.and_then(|x| u32::from_str_radix(x, 16).ok())
+				.ok_or_else(invalid)?,
+		})
+	}
+}
+
+/// A sequence of register accesses recorded by a [`RecordingBackend`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Recording {
+	accesses: Vec<RecordedAccess>,
+}
+
+impl Recording {
+	/// Load a recording from a file.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let data = std::fs::read_to_string(path)
+			.map_err(|e| Error::from_io(ErrorKind::Io, format!("failed to read {}", path.display()), e))?;
+		Self::parse(&data).map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+	}
+
+	/// Parse a recording, ignoring empty lines and lines starting with `#`.
+	pub fn parse(data: &str) -> Result<Self, Error> {
+		let mut accesses = Vec::new();
+		for (i, line) in data.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let access = line.parse().map_err(|e: Error| Error::new(e.kind(), format!("line {}: {}", i + 1, e)))?;
+			accesses.push(access);
+		}
+		Ok(Self { accesses })
+	}
+
+	/// Get the recorded accesses.
+	pub fn accesses(&self) -> &[RecordedAccess] {
+		&self.accesses
+	}
+
+	/// Write the recorded register writes to a GPIO peripheral, in order.
+	///
+	/// Reads are not replayed.
+	/// If `timed` is true, each write is delayed until the same time after the start of the replay
+	/// as it had after the start of the recording.
+	///
+	/// # Safety
+	/// The values are written to the hardware as-is, with the same precautions as for [`Gpio::write_register`].
+	pub unsafe fn replay(&self, gpio: &mut Gpio, timed: bool) {
+		let start = Instant::now();
+		for access in self.accesses.iter().filter(|x| x.kind == AccessKind::Write) {
+			if timed {
+				if let Some(delay) = access.time.checked_sub(start.elapsed()) {
+					std::thread::sleep(delay);
+				}
+			}
+			gpio.write_register(access.register, access.value);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_access() {
+		let access: RecordedAccess = "1500 write GPSET0 0x00020000".parse().unwrap();
+		assert_eq!(access, RecordedAccess {
+			time: Duration::from_micros(1500),
+			kind: AccessKind::Write,
+			register: Register::GPSET0,
+			value: 1 << 17,
+		});
+		assert_eq!(access.to_string(), "1500 write GPSET0 0x00020000");
+
+		let access: RecordedAccess = "  0   read gplev1   0xffffffff ".parse().unwrap();
+		assert_eq!(access.kind, AccessKind::Read);
+		assert_eq!(access.register, Register::GPLEV1);
+		assert_eq!(access.value, u32::MAX);
+	}
+
+	#[test]
+	fn parse_invalid_access() {
+		let invalid = [
+			"",
+			"1500 write GPSET0",
+			"1500 write GPSET0 0x1 extra",
+			"-1 write GPSET0 0x1",
+			"1500 poke GPSET0 0x1",
+			"1500 write GPNOPE0 0x1",
+			"1500 write GPSET0 1",
+			"1500 write GPSET0 0x100000000",
+		];
+		for line in invalid.iter() {
+			assert_eq!(line.parse::<RecordedAccess>().unwrap_err().kind(), ErrorKind::InvalidArgument, "{:?}", line);
+		}
+	}
+
+	#[test]
+	fn parse_recording() {
+		let recording = Recording::parse("# a comment\n\n0 read GPFSEL1 0x00000000\n10 write GPFSEL1 0x00200000\n").unwrap();
+		assert_eq!(recording.accesses().len(), 2);
+		assert_eq!(recording.accesses()[1].time, Duration::from_micros(10));
+
+		let error = Recording::parse("0 read GPFSEL1 0x0\nnonsense\n").unwrap_err();
+		assert!(error.to_string().contains("line 2"), "{}", error);
+	}
+
+	#[test]
+	fn replay_writes() {
+		let recording = Recording::parse("0 read GPFSEL1 0x00000000\n10 write GPFSEL1 0x00200000\n20 write GPSET0 0x00020000\n").unwrap();
+		let mut gpio = crate::MockGpio::new();
+		unsafe { recording.replay(&mut gpio, false) };
+		assert_eq!(gpio.read_pin(17).unwrap().level, crate::Level::High);
+	}
+}
@@ -1,3 +1,8 @@
+use alloc::format;
+use core::convert::TryFrom;
+
+use crate::{Error, ErrorKind};
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Register {
 	GPFSEL0 = 0x00,
@@ -40,110 +45,310 @@ pub enum Register {
 	GPPUD     = 0x94,
 	GPPUDCLK0 = 0x98,
 	GPPUDCLK1 = 0x9C,
+
+	GPPUPPDN0 = 0xE4,
+	GPPUPPDN1 = 0xE8,
+	GPPUPPDN2 = 0xEC,
+	GPPUPPDN3 = 0xF0,
 }
 
 impl Register {
-	pub fn fsel(index: usize) -> Self {
-		match index {
-			0 => Register::GPFSEL0,
-			1 => Register::GPFSEL1,
-			2 => Register::GPFSEL2,
-			3 => Register::GPFSEL3,
-			4 => Register::GPFSEL4,
-			5 => Register::GPFSEL5,
-			_ => panic!("GPFSEL register index must be in the range [0..6), got {}", index),
+	/// All registers, in order of their address.
+	///
+	/// The GPPUPPDN registers only exist on the BCM2711.
+	pub const ALL: [Register; 33] = [
+		Register::GPFSEL0,
+		Register::GPFSEL1,
+		Register::GPFSEL2,
+		Register::GPFSEL3,
+		Register::GPFSEL4,
+		Register::GPFSEL5,
+		Register::GPSET0,
+		Register::GPSET1,
+		Register::GPCLR0,
+		Register::GPCLR1,
+		Register::GPLEV0,
+		Register::GPLEV1,
+		Register::GPEDS0,
+		Register::GPEDS1,
+		Register::GPREN0,
+		Register::GPREN1,
+		Register::GPFEN0,
+		Register::GPFEN1,
+		Register::GPHEN0,
+		Register::GPHEN1,
+		Register::GPLEN0,
+		Register::GPLEN1,
+		Register::GPAREN0,
+		Register::GPAREN1,
+		Register::GPAFEN0,
+		Register::GPAFEN1,
+		Register::GPPUD,
+		Register::GPPUDCLK0,
+		Register::GPPUDCLK1,
+		Register::GPPUPPDN0,
+		Register::GPPUPPDN1,
+		Register::GPPUPPDN2,
+		Register::GPPUPPDN3,
+	];
+
+	/// Get the name of the register, as used in the datasheet.
+	pub fn name(self) -> &'static str {
+		match self {
+			Register::GPFSEL0   => "GPFSEL0",
+			Register::GPFSEL1   => "GPFSEL1",
+			Register::GPFSEL2   => "GPFSEL2",
+			Register::GPFSEL3   => "GPFSEL3",
+			Register::GPFSEL4   => "GPFSEL4",
+			Register::GPFSEL5   => "GPFSEL5",
+			Register::GPSET0    => "GPSET0",
+			Register::GPSET1    => "GPSET1",
+			Register::GPCLR0    => "GPCLR0",
+			Register::GPCLR1    => "GPCLR1",
+			Register::GPLEV0    => "GPLEV0",
+			Register::GPLEV1    => "GPLEV1",
+			Register::GPEDS0    => "GPEDS0",
+			Register::GPEDS1    => "GPEDS1",
+			Register::GPREN0    => "GPREN0",
+			Register::GPREN1    => "GPREN1",
+			Register::GPFEN0    => "GPFEN0",
+			Register::GPFEN1    => "GPFEN1",
+			Register::GPHEN0    => "GPHEN0",
+			Register::GPHEN1    => "GPHEN1",
+			Register::GPLEN0    => "GPLEN0",
+			Register::GPLEN1    => "GPLEN1",
+			Register::GPAREN0   => "GPAREN0",
+			Register::GPAREN1   => "GPAREN1",
+			Register::GPAFEN0   => "GPAFEN0",
+			Register::GPAFEN1   => "GPAFEN1",
+			Register::GPPUD     => "GPPUD",
+			Register::GPPUDCLK0 => "GPPUDCLK0",
+			Register::GPPUDCLK1 => "GPPUDCLK1",
+			Register::GPPUPPDN0 => "GPPUPPDN0",
+			Register::GPPUPPDN1 => "GPPUPPDN1",
+			Register::GPPUPPDN2 => "GPPUPPDN2",
+			Register::GPPUPPDN3 => "GPPUPPDN3",
 		}
 	}
 
-	pub fn set(index: usize) -> Self {
-		match index {
-			0 => Register::GPSET0,
-			1 => Register::GPSET1,
-			_ => panic!("GPSET register index must be in the range [0..2), got {}", index),
-		}
+	/// Check if writing any value to the register is safe.
+	///
+	/// This is true for the set, clear and event status registers,
+	/// which only affect the bits that are written as one, and never change pin configuration.
+	pub fn is_write_safe(self) -> bool {
+		matches!(self,
+			Register::GPSET0 | Register::GPSET1 |
+			Register::GPCLR0 | Register::GPCLR1 |
+			Register::GPEDS0 | Register::GPEDS1
+		)
 	}
 
-	pub fn clr(index: usize) -> Self {
-		match index {
-			0 => Register::GPCLR0,
-			1 => Register::GPCLR1,
-			_ => panic!("GPCLR register index must be in the range [0..2), got {}", index),
-		}
+	// The constructors below panic on invalid indices, so they are only used internally with indices known to be valid.
+	// Use the fallible `try_` variants from outside the crate.
+
+	pub(crate) fn fsel(index: usize) -> Self {
+		Self::try_fsel(index).unwrap_or_else(|e| panic!("{}", e))
 	}
 
-	pub fn lev(index: usize) -> Self {
-		match index {
-			0 => Register::GPLEV0,
-			1 => Register::GPLEV1,
-			_ => panic!("GPLEV register index must be in the range [0..2), got {}", index),
-		}
+	pub fn try_fsel(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Fsel, index))
 	}
 
-	pub fn eds(index: usize) -> Self {
-		match index {
-			0 => Register::GPEDS0,
-			1 => Register::GPEDS1,
-			_ => panic!("GPEDS register index must be in the range [0..2), got {}", index),
-		}
+	pub(crate) fn set(index: usize) -> Self {
+		Self::try_set(index).unwrap_or_else(|e| panic!("{}", e))
 	}
 
-	pub fn ren(index: usize) -> Self {
-		match index {
-			0 => Register::GPREN0,
-			1 => Register::GPREN1,
-			_ => panic!("GPREN register index must be in the range [0..2), got {}", index),
-		}
+	pub fn try_set(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Set, index))
 	}
 
-	pub fn fen(index: usize) -> Self {
-		match index {
-			0 => Register::GPFEN0,
-			1 => Register::GPFEN1,
-			_ => panic!("GPFEN register index must be in the range [0..2), got {}", index),
-		}
+	pub(crate) fn clr(index: usize) -> Self {
+		Self::try_clr(index).unwrap_or_else(|e| panic!("{}", e))
 	}
 
-	pub fn hen(index: usize) -> Self {
-		match index {
-			0 => Register::GPHEN0,
-			1 => Register::GPHEN1,
-			_ => panic!("GPHEN register index must be in the range [0..2), got {}", index),
-		}
+	pub fn try_clr(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Clr, index))
 	}
 
-	pub fn len(index: usize) -> Self {
-		match index {
-			0 => Register::GPLEN0,
-			1 => Register::GPLEN1,
-			_ => panic!("GPLEN register index must be in the range [0..2), got {}", index),
-		}
+	#[cfg(feature = "std")]
+	pub(crate) fn lev(index: usize) -> Self {
+		Self::try_lev(index).unwrap_or_else(|e| panic!("{}", e))
 	}
 
-	pub fn aren(index: usize) -> Self {
-		match index {
-			0 => Register::GPAREN0,
-			1 => Register::GPAREN1,
-			_ => panic!("GPAREN register index must be in the range [0..2), got {}", index),
-		}
+	pub fn try_lev(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Lev, index))
 	}
 
-	pub fn afen(index: usize) -> Self {
-		match index {
-			0 => Register::GPAFEN0,
-			1 => Register::GPAFEN1,
-			_ => panic!("GPAFEN register index must be in the range [0..2), got {}", index),
-		}
+	#[cfg(feature = "std")]
+	pub(crate) fn eds(index: usize) -> Self {
+		Self::try_eds(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_eds(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Eds, index))
+	}
+
+	pub(crate) fn ren(index: usize) -> Self {
+		Self::try_ren(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_ren(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Ren, index))
+	}
+
+	pub(crate) fn fen(index: usize) -> Self {
+		Self::try_fen(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_fen(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Fen, index))
+	}
+
+	pub(crate) fn hen(index: usize) -> Self {
+		Self::try_hen(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_hen(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Hen, index))
+	}
+
+	pub(crate) fn len(index: usize) -> Self {
+		Self::try_len(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_len(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Len, index))
+	}
+
+	pub(crate) fn aren(index: usize) -> Self {
+		Self::try_aren(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_aren(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Aren, index))
+	}
+
+	pub(crate) fn afen(index: usize) -> Self {
+		Self::try_afen(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_afen(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Afen, index))
 	}
 
 	pub fn pud() -> Self {
 		Register::GPPUD
 	}
 
-	pub fn pudclk(index: usize) -> Self {
-		match index {
-			0 => Register::GPPUDCLK0,
-			1 => Register::GPPUDCLK1,
-			_ => panic!("GPPUDCLK register index must be in the range [0..2), got {}", index),
+	pub fn try_pudclk(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Pudclk, index))
+	}
+
+	pub(crate) fn puppdn(index: usize) -> Self {
+		Self::try_puppdn(index).unwrap_or_else(|e| panic!("{}", e))
+	}
+
+	pub fn try_puppdn(index: usize) -> Result<Self, InvalidRegisterIndex> {
+		Self::try_from((RegisterKind::Puppdn, index))
+	}
+}
+
+impl core::fmt::Display for Register {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// A group of registers that is indexed by bank.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RegisterKind {
+	Fsel,
+	Set,
+	Clr,
+	Lev,
+	Eds,
+	Ren,
+	Fen,
+	Hen,
+	Len,
+	Aren,
+	Afen,
+	Pudclk,
+	Puppdn,
+}
+
+impl RegisterKind {
+	/// Get the registers of this kind, in order of their index.
+	pub fn registers(self) -> &'static [Register] {
+		match self {
+			RegisterKind::Fsel   => &[Register::GPFSEL0, Register::GPFSEL1, Register::GPFSEL2, Register::GPFSEL3, Register::GPFSEL4, Register::GPFSEL5],
+			RegisterKind::Set    => &[Register::GPSET0, Register::GPSET1],
+			RegisterKind::Clr    => &[Register::GPCLR0, Register::GPCLR1],
+			RegisterKind::Lev    => &[Register::GPLEV0, Register::GPLEV1],
+			RegisterKind::Eds    => &[Register::GPEDS0, Register::GPEDS1],
+			RegisterKind::Ren    => &[Register::GPREN0, Register::GPREN1],
+			RegisterKind::Fen    => &[Register::GPFEN0, Register::GPFEN1],
+			RegisterKind::Hen    => &[Register::GPHEN0, Register::GPHEN1],
+			RegisterKind::Len    => &[Register::GPLEN0, Register::GPLEN1],
+			RegisterKind::Aren   => &[Register::GPAREN0, Register::GPAREN1],
+			RegisterKind::Afen   => &[Register::GPAFEN0, Register::GPAFEN1],
+			RegisterKind::Pudclk => &[Register::GPPUDCLK0, Register::GPPUDCLK1],
+			RegisterKind::Puppdn => &[Register::GPPUPPDN0, Register::GPPUPPDN1, Register::GPPUPPDN2, Register::GPPUPPDN3],
 		}
 	}
+
+	/// Get the common prefix of the register names, as used in the datasheet.
+	pub fn prefix(self) -> &'static str {
+		match self {
+			RegisterKind::Fsel   => "GPFSEL",
+			RegisterKind::Set    => "GPSET",
+			RegisterKind::Clr    => "GPCLR",
+			RegisterKind::Lev    => "GPLEV",
+			RegisterKind::Eds    => "GPEDS",
+			RegisterKind::Ren    => "GPREN",
+			RegisterKind::Fen    => "GPFEN",
+			RegisterKind::Hen    => "GPHEN",
+			RegisterKind::Len    => "GPLEN",
+			RegisterKind::Aren   => "GPAREN",
+			RegisterKind::Afen   => "GPAFEN",
+			RegisterKind::Pudclk => "GPPUDCLK",
+			RegisterKind::Puppdn => "GPPUPPDN",
+		}
+	}
+}
+
+/// The error returned when a register index is out of range for its kind.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InvalidRegisterIndex {
+	pub kind: RegisterKind,
+	pub index: usize,
+}
+
+impl core::fmt::Display for InvalidRegisterIndex {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		write!(f, "{} register index must be in the range [0..{}), got {}", self.kind.prefix(), self.kind.registers().len(), self.index)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidRegisterIndex {}
+
+impl TryFrom<(RegisterKind, usize)> for Register {
+	type Error = InvalidRegisterIndex;
+
+	fn try_from((kind, index): (RegisterKind, usize)) -> Result<Self, Self::Error> {
+		kind.registers().get(index).cloned().ok_or(InvalidRegisterIndex { kind, index })
+	}
+}
+
+impl core::str::FromStr for Register {
+	type Err = Error;
+
+	/// Parse a register from its name, ignoring case.
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Register::ALL.iter()
+			.find(|reg| reg.name().eq_ignore_ascii_case(name))
+			.cloned()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidArgument, format!("unknown register: {}", name)))
+	}
 }
@@ -0,0 +1,61 @@
+use crate::{Gpio, Register};
+
+/// The original register values to restore when a [`Gpio`] handle is dropped.
+///
+/// Readable registers are restored to the value they had before the first write.
+/// Writes to the set and clear registers are undone by restoring the original level of the affected pins.
+/// Event status and pull up/down registers can not be read back, so they are not restored.
+#[derive(Default)]
+pub(crate) struct RestoreLog {
+	registers: Vec<(Register, u32)>,
+	touched_levels: [u32; 2],
+	original_levels: [u32; 2],
+}
+
+impl RestoreLog {
+	/// Record the original state before writing a value to a register.
+	pub(crate) fn record(&mut self, reg: Register, value: u32, read: impl Fn(Register) -> u32) {
+		match reg {
+			Register::GPSET0 | Register::GPCLR0 => self.record_levels(0, value, read),
+			Register::GPSET1 | Register::GPCLR1 => self.record_levels(1, value, read),
+			Register::GPLEV0 | Register::GPLEV1 => (),
+			Register::GPEDS0 | Register::GPEDS1 => (),
+			Register::GPPUD | Register::GPPUDCLK0 | Register::GPPUDCLK1 => (),
+			_ => {
+				if !self.registers.iter().any(|(x, _)| *x == reg) {
+					self.registers.push((reg, read(reg)));
+				}
+			},
+		}
+	}
+
+	fn record_levels(&mut self, bank: usize, bits: u32, read: impl Fn(Register) -> u32) {
+		let new = bits & !self.touched_levels[bank];
+		if new != 0 {
+			self.original_levels[bank] |= read(Register::lev(bank)) & new;
+			self.touched_levels[bank]  |= new;
+		}
+	}
+
+	/// Write the original state back to the hardware.
+	///
+	/// Levels are restored first, so pins that were outputs keep driving their original level
+	/// when their function is restored.
+	pub(crate) fn restore(self, gpio: &mut Gpio) {
+		unsafe {
+			for bank in 0..2 {
+				let set = self.touched_levels[bank] & self.original_levels[bank];
+				let clr = self.touched_levels[bank] & !self.original_levels[bank];
+				if set != 0 {
+					gpio.write_register(Register::set(bank), set);
+				}
+				if clr != 0 {
+					gpio.write_register(Register::clr(bank), clr);
+				}
+			}
+			for (reg, value) in self.registers {
+				gpio.write_register(reg, value);
+			}
+		}
+	}
+}
@@ -0,0 +1,80 @@
+//! Conversions between the types of this crate and the types of rppal.
+
+use rppal::gpio::{Bias, Mode};
+use std::convert::TryFrom;
+
+use crate::{Error, ErrorKind, Level, PinFunction, PullMode};
+
+impl From<Level> for rppal::gpio::Level {
+	fn from(level: Level) -> Self {
+		match level {
+			Level::Low  => rppal::gpio::Level::Low,
+			Level::High => rppal::gpio::Level::High,
+		}
+	}
+}
+
+impl From<rppal::gpio::Level> for Level {
+	fn from(level: rppal::gpio::Level) -> Self {
+		match level {
+			rppal::gpio::Level::Low  => Level::Low,
+			rppal::gpio::Level::High => Level::High,
+		}
+	}
+}
+
+impl From<PinFunction> for Mode {
+	fn from(function: PinFunction) -> Self {
+		match function {
+			PinFunction::Input  => Mode::Input,
+			PinFunction::Output => Mode::Output,
+			PinFunction::Alt0   => Mode::Alt0,
+			PinFunction::Alt1   => Mode::Alt1,
+			PinFunction::Alt2   => Mode::Alt2,
+			PinFunction::Alt3   => Mode::Alt3,
+			PinFunction::Alt4   => Mode::Alt4,
+			PinFunction::Alt5   => Mode::Alt5,
+		}
+	}
+}
+
+/// The conversion fails for the modes of the RP1, which do not exist on the BCM283x.
+impl TryFrom<Mode> for PinFunction {
+	type Error = Error;
+
+	fn try_from(mode: Mode) -> Result<Self, Self::Error> {
+		match mode {
+			Mode::Input  => Ok(PinFunction::Input),
+			Mode::Output => Ok(PinFunction::Output),
+			Mode::Alt0   => Ok(PinFunction::Alt0),
+			Mode::Alt1   => Ok(PinFunction::Alt1),
+			Mode::Alt2   => Ok(PinFunction::Alt2),
+			Mode::Alt3   => Ok(PinFunction::Alt3),
+			Mode::Alt4   => Ok(PinFunction::Alt4),
+			Mode::Alt5   => Ok(PinFunction::Alt5),
+			Mode::Alt6 | Mode::Alt7 | Mode::Alt8 | Mode::Null => {
+				Err(Error::new(ErrorKind::Unsupported, format!("pin mode {} is not supported by the BCM283x", mode)))
+			},
+		}
+	}
+}
+
+impl From<PullMode> for Bias {
+	fn from(mode: PullMode) -> Self {
+		match mode {
+			PullMode::Float    => Bias::Off,
+			PullMode::PullDown => Bias::PullDown,
+			PullMode::PullUp   => Bias::PullUp,
+		}
+	}
+}
+
+impl From<Bias> for PullMode {
+	fn from(bias: Bias) -> Self {
+		match bias {
+			Bias::Off      => PullMode::Float,
+			Bias::PullDown => PullMode::PullDown,
+			Bias::PullUp   => PullMode::PullUp,
+		}
+	}
+}
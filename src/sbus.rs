@@ -0,0 +1,212 @@
+use std::time::{Duration, Instant};
+
+use crate::{delay, Error, ErrorKind, Gpio, PinFunction};
+
+/// The size of an SBUS frame in bytes.
+pub const SBUS_FRAME_SIZE : usize = 25;
+
+/// The first byte of every SBUS frame.
+const SBUS_HEADER : u8 = 0x0F;
+
+/// The duration of a single bit at 100 kbaud.
+const BIT_TIME : Duration = Duration::from_micros(10);
+
+/// The minimum idle time before a frame, to synchronize with the start of a frame.
+///
+/// Bytes within a frame follow each other closely, while frames are separated by at least a few milliseconds.
+const FRAME_GAP : Duration = Duration::from_micros(1000);
+
+/// The maximum time between two bytes of a frame.
+const BYTE_TIMEOUT : Duration = Duration::from_micros(500);
+
+/// A decoded SBUS frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SbusFrame {
+	/// The 16 proportional channels, with 11 bit values in the range [0-2047].
+	pub channels: [u16; 16],
+	/// Digital channel 17.
+	pub channel17: bool,
+	/// Digital channel 18.
+	pub channel18: bool,
+	/// Set by the receiver when a frame from the transmitter was lost.
+	pub frame_lost: bool,
+	/// Set by the receiver when it lost the connection to the transmitter and sends failsafe values.
+	pub failsafe: bool,
+}
+
+impl SbusFrame {
+	/// Decode a raw SBUS frame.
+	///
+	/// The frame must start with the header byte 0x0F.
+	/// The footer byte must be 0x00, or have 0x4 in the lower nibble for SBUS2 telemetry slots.
+	pub fn parse(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != SBUS_FRAME_SIZE {
+			return Err(Error::new(ErrorKind::Protocol, format!("invalid SBUS frame length: {}, expected {}", data.len(), SBUS_FRAME_SIZE)));
+		}
+		if data[0] != SBUS_HEADER {
+			return Err(Error::new(ErrorKind::Protocol, format!("invalid SBUS frame header: 0x{:02X}", data[0])));
+		}
+		let footer = data[SBUS_FRAME_SIZE - 1];
+		if footer != 0x00 && footer & 0x0F != 0x04 {
+			return Err(Error::new(ErrorKind::Protocol, format!("invalid SBUS frame footer: 0x{:02X}", footer)));
+		}
+
+		// The channels are packed as 11 bit values, least significant bit first.
+		let mut channels = [0; 16];
+		for (i, channel) in channels.iter_mut().enumerate() {
+			let bit   = i * 11;
+			let byte  = 1 + bit / 8;
+			let value = u32::from(data[byte]) | u32::from(data[byte + 1]) << 8 | u32::from(data[byte + 2]) << 16;
+			*channel = (value >> (bit % 8) & 0x7FF) as u16;
+		}
+
+		let flags = data[23];
+		Ok(Self {
+			channels,
+			channel17:  flags & 0x01 != 0,
+			channel18:  flags & 0x02 != 0,
+			frame_lost: flags & 0x04 != 0,
+			failsafe:   flags & 0x08 != 0,
+		})
+	}
+}
+
+/// Bit-banged SBUS receiver on a single GPIO pin.
+///
+/// SBUS is an inverted serial signal at 100 kbaud with 8 data bits, even parity and 2 stop bits.
+/// The inversion is handled in software, so the pin can be connected to the receiver directly,
+/// as long as the signal is 3.3V.
+///
+/// The bits are sampled by busy-waiting, so a frame fails to decode if the process is preempted while receiving it.
+/// Such frames are reported as an error, so the caller can simply read the next frame.
+pub struct SbusReceiver<'a> {
+	gpio: &'a mut Gpio,
+	pin: usize,
+}
+
+impl<'a> SbusReceiver<'a> {
+	/// Create a new SBUS receiver on the given pin.
+	///
+	/// The pin is configured as input.
+	pub fn new(gpio: &'a mut Gpio, pin: usize) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+		gpio.write_function(pin, PinFunction::Input);
+		Ok(Self { gpio, pin })
+	}
+
+	/// Wait for the next frame and decode it.
+	pub fn read_frame(&mut self, timeout: Duration) -> Result<SbusFrame, Error> {
+		SbusFrame::parse(&self.read_raw_frame(timeout)?)
+	}
+
+	/// Wait for the next frame and return its raw bytes.
+	///
+	/// Every byte is checked for parity and stop bits, but the contents of the frame are not checked.
+	pub fn read_raw_frame(&mut self, timeout: Duration) -> Result<[u8; SBUS_FRAME_SIZE], Error> {
+		let deadline = Instant::now() + timeout;
+		self.wait_for_gap(deadline)?;
+
+		let mut frame = [0; SBUS_FRAME_SIZE];
+		for (i, byte) in frame.iter_mut().enumerate() {
+			// The first byte may come any time after the gap, the others follow closely.
+			let start_deadline = match i {
+				0 => deadline,
+				_ => Instant::now() + BYTE_TIMEOUT,
+			};
+			*byte = self.read_byte(start_deadline).map_err(|e| Error::new(e.kind(), format!("byte {} of SBUS frame: {}", i, e)))?;
+		}
+		Ok(frame)
+	}
+
+	/// Wait until the line has been idle for the minimum gap between frames.
+	fn wait_for_gap(&mut self, deadline: Instant) -> Result<(), Error> {
+		let mut idle_since = Instant::now();
+		loop {
+			let now = Instant::now();
+			if !self.line_level() {
+				idle_since = now;
+			} else if now - idle_since >= FRAME_GAP {
+				return Ok(());
+			}
+			if now >= deadline {
+				return Err(Error::new(ErrorKind::Timeout, "timed out waiting for the gap before an SBUS frame"));
+			}
+		}
+	}
+
+	/// Receive a single byte, waiting for its start bit until a deadline.
+	fn read_byte(&mut self, deadline: Instant) -> Result<u8, Error> {
+		let start = loop {
+			if !self.line_level() {
+				break Instant::now();
+			}
+			if Instant::now() >= deadline {
+				return Err(Error::new(ErrorKind::Timeout, "timed out waiting for a start bit"));
+			}
+		};
+
+		// Sample every bit in the middle, counting from the start of the start bit.
+		let mut bits = 0u16;
+		for i in 0..11 {
+			delay::sleep_until(start + BIT_TIME * (2 * i + 3) / 2);
+			bits |= u16::from(self.line_level()) << i;
+		}
+
+		let byte = bits as u8;
+		let parity = bits >> 8 & 1 == 1;
+		if parity != (byte.count_ones() % 2 == 1) {
+			return Err(Error::new(ErrorKind::Protocol, "parity error"));
+		}
+		if bits >> 9 & 0b11 != 0b11 {
+			return Err(Error::new(ErrorKind::Protocol, "framing error"));
+		}
+		Ok(byte)
+	}
+
+	/// Read the logical level of the line, which is high when the line is idle.
+	fn line_level(&self) -> bool {
+		!self.gpio.level(self.pin)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A frame with channels 1-16 at 172, 992, 1811, 0, 2047, 1024, 1, 2, 3, 4, 5, 6, 7, 8, 9 and 1000,
+	/// with channel 17 set and the frame lost and failsafe flags set.
+	const FRAME : [u8; SBUS_FRAME_SIZE] = [
+		0x0F, 0xAC, 0x00, 0xDF, 0xC4, 0x01, 0xF0, 0x7F, 0x00, 0x06, 0x40, 0x00, 0x03, 0x20, 0x40, 0x01,
+		0x0C, 0x70, 0x00, 0x04, 0x24, 0x00, 0x7D, 0x0D, 0x00,
+	];
+
+	#[test]
+	fn parse() {
+		let frame = SbusFrame::parse(&FRAME).unwrap();
+		assert_eq!(frame, SbusFrame {
+			channels: [172, 992, 1811, 0, 2047, 1024, 1, 2, 3, 4, 5, 6, 7, 8, 9, 1000],
+			channel17: true,
+			channel18: false,
+			frame_lost: true,
+			failsafe: true,
+		});
+
+		// SBUS2 telemetry slots have 0x4 in the lower nibble of the footer.
+		let mut data = FRAME;
+		data[24] = 0x14;
+		assert_eq!(SbusFrame::parse(&data).unwrap(), frame);
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert_eq!(SbusFrame::parse(&FRAME[..24]).unwrap_err().kind(), ErrorKind::Protocol);
+
+		let mut data = FRAME;
+		data[0] = 0x0E;
+		assert_eq!(SbusFrame::parse(&data).unwrap_err().kind(), ErrorKind::Protocol);
+
+		let mut data = FRAME;
+		data[24] = 0x01;
+		assert_eq!(SbusFrame::parse(&data).unwrap_err().kind(), ErrorKind::Protocol);
+	}
+}
@@ -0,0 +1,222 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, Gpio, Level};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+/// An operation on a pin that can be scheduled with a [`Scheduler`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PinAction {
+	/// Set the level of the pin.
+	Set(Level),
+	/// Invert the current level of the pin.
+	Toggle,
+}
+
+struct Task {
+	id: u64,
+	due: Instant,
+	interval: Option<Duration>,
+	pin: usize,
+	action: PinAction,
+}
+
+#[derive(Default)]
+struct State {
+	tasks: Vec<Task>,
+	next_id: u64,
+	stop: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+	state: Mutex<State>,
+	changed: Condvar,
+}
+
+impl Shared {
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Executes pin operations at scheduled times from a background thread.
+///
+/// Operations are scheduled at a point in time with [`at`](Self::at), after a delay with [`after`](Self::after),
+/// or repeatedly with [`every`](Self::every).
+/// Every scheduled operation returns a [`ScheduledTask`] that can cancel it.
+///
+/// The thread sleeps until the next operation is due, so the accuracy is in the order of a millisecond.
+/// The pins must already be configured as output.
+/// Dropping the scheduler cancels all operations.
+pub struct Scheduler {
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// A handle to an operation scheduled with a [`Scheduler`].
+///
+/// Dropping the handle does not cancel the operation.
+#[derive(Clone, Debug)]
+pub struct ScheduledTask {
+	id: u64,
+	shared: Weak<Shared>,
+}
+
+impl Scheduler {
+	/// Start the scheduler thread.
+	///
+	/// This opens a handle with [`Gpio::new`] for the scheduler thread.
+	pub fn start() -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?)
+	}
+
+	/// Start the scheduler thread, moving an existing handle to it.
+	pub fn start_with_gpio(gpio: Gpio) -> Result<Self, Error> {
+		let shared = Arc::new(Shared::default());
+		let thread = spawn_worker("scheduler", ThreadOptions::default(), gpio, {
+			let shared = shared.clone();
+			move |mut gpio| run(&mut gpio, &shared)
+		})?;
+
+		Ok(Self {
+			shared,
+			thread: Some(thread),
+		})
+	}
+
+	/// Perform an operation on a pin at a point in time.
+	///
+	/// Operations in the past are performed immediately.
+	pub fn at(&self, time: Instant, pin: usize, action: PinAction) -> Result<ScheduledTask, Error> {
+		self.schedule(time, None, pin, action)
+	}
+
+	/// Perform an operation on a pin after a delay.
+	pub fn after(&self, delay: Duration, pin: usize, action: PinAction) -> Result<ScheduledTask, Error> {
+		self.schedule(Instant::now() + delay, None, pin, action)
+	}
+
+	/// Perform an operation on a pin repeatedly, starting one interval from now.
+	///
+	/// If the scheduler falls behind, missed repetitions are skipped rather than performed in a burst.
+	pub fn every(&self, interval: Duration, pin: usize, action: PinAction) -> Result<ScheduledTask, Error> {
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "schedule interval must not be zero"));
+		}
+		self.schedule(Instant::now() + interval, Some(interval), pin, action)
+	}
+
+	/// Cancel all scheduled operations.
+	pub fn clear(&self) {
+		self.shared.state().tasks.clear();
+		self.shared.changed.notify_one();
+	}
+
+	/// Get the number of scheduled operations.
+	pub fn len(&self) -> usize {
+		self.shared.state().tasks.len()
+	}
+
+	/// Check if no operations are scheduled.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Stop the scheduler thread, cancelling all operations.
+	pub fn stop(mut self) {
+		self.stop_thread();
+	}
+
+	fn schedule(&self, due: Instant, interval: Option<Duration>, pin: usize, action: PinAction) -> Result<ScheduledTask, Error> {
+		crate::check_pin_index(pin)?;
+		let mut state = self.shared.state();
+		let id = state.next_id;
+		state.next_id += 1;
+		state.tasks.push(Task { id, due, interval, pin, action });
+		drop(state);
+		self.shared.changed.notify_one();
+		Ok(ScheduledTask { id, shared: Arc::downgrade(&self.shared) })
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.state().stop = true;
+		self.shared.changed.notify_one();
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for Scheduler {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+impl ScheduledTask {
+	/// Cancel the operation.
+	///
+	/// Returns false if the operation was already performed or cancelled, or the scheduler was stopped.
+	pub fn cancel(&self) -> bool {
+		let shared = match self.shared.upgrade() {
+			Some(x) => x,
+			None => return false,
+		};
+		let mut state = shared.state();
+		let count = state.tasks.len();
+		state.tasks.retain(|x| x.id != self.id);
+		count != state.tasks.len()
+	}
+
+	/// Check if the operation is still scheduled.
+	///
+	/// Repeating operations stay scheduled until they are cancelled.
+	pub fn is_pending(&self) -> bool {
+		match self.shared.upgrade() {
+			Some(shared) => shared.state().tasks.iter().any(|x| x.id == self.id),
+			None => false,
+		}
+	}
+}
+
+fn run(gpio: &mut Gpio, shared: &Shared) {
+	let mut state = shared.state();
+	while !state.stop {
+		let now = Instant::now();
+		let next = state.tasks.iter().map(|x| x.due).min();
+		match next {
+			None => {
+				state = shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+				continue;
+			},
+			Some(due) if due > now => {
+				state = shared.changed.wait_timeout(state, due - now).unwrap_or_else(|e| e.into_inner()).0;
+				continue;
+			},
+			Some(_) => (),
+		}
+
+		// Perform due tasks in order of their due time, and in order of scheduling for equal times.
+		state.tasks.sort_by_key(|x| (x.due, x.id));
+		for task in state.tasks.iter_mut().take_while(|x| x.due <= now) {
+			match task.action {
+				PinAction::Set(level) => gpio.write_level(task.pin, level.is_high()),
+				PinAction::Toggle => {
+					let level = gpio.level(task.pin);
+					gpio.write_level(task.pin, !level);
+				},
+			}
+			if let Some(interval) = task.interval {
+				task.due += interval;
+				if task.due <= now {
+					// Skip missed repetitions.
+					let behind = (now - task.due).as_nanos() / interval.as_nanos() + 1;
+					task.due += interval * behind.min(u32::MAX.into()) as u32;
+				}
+			}
+		}
+		state.tasks.retain(|x| x.due > now);
+	}
+}
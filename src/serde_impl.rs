@@ -0,0 +1,131 @@
+//! Serialize and Deserialize implementations for types with large arrays,
+//! which serde does not support out of the box.
+
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{GpioConfig, GpioPullConfig, GpioState, Level, PinFunction, PullMode};
+
+/// A GPIO state is serialized as the raw register values.
+#[derive(serde::Serialize)]
+#[serde(rename = "GpioState")]
+struct GpioStateRef<'a> {
+	registers: &'a [u32],
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "GpioState")]
+struct GpioStateOwned {
+	registers: Vec<u32>,
+}
+
+impl Serialize for GpioState {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		GpioStateRef { registers: &self.data()[..] }.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for GpioState {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let registers = GpioStateOwned::deserialize(deserializer)?.registers;
+		let mut state = GpioState::new();
+		if registers.len() != state.data().len() {
+			return Err(D::Error::invalid_length(registers.len(), &"256 registers"));
+		}
+		state.data_mut().copy_from_slice(&registers);
+		Ok(state)
+	}
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename = "GpioConfig")]
+struct GpioConfigRef<'a> {
+	function          : &'a [Option<PinFunction>],
+	level             : &'a [Option<Level>],
+	detect_rise       : &'a [Option<bool>],
+	detect_fall       : &'a [Option<bool>],
+	detect_high       : &'a [Option<bool>],
+	detect_low        : &'a [Option<bool>],
+	detect_async_rise : &'a [Option<bool>],
+	detect_async_fall : &'a [Option<bool>],
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "GpioConfig")]
+struct GpioConfigOwned {
+	function          : Vec<Option<PinFunction>>,
+	level             : Vec<Option<Level>>,
+	detect_rise       : Vec<Option<bool>>,
+	detect_fall       : Vec<Option<bool>>,
+	detect_high       : Vec<Option<bool>>,
+	detect_low        : Vec<Option<bool>>,
+	detect_async_rise : Vec<Option<bool>>,
+	detect_async_fall : Vec<Option<bool>>,
+}
+
+impl Serialize for GpioConfig {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		GpioConfigRef {
+			function          : &self.function,
+			level             : &self.level,
+			detect_rise       : &self.detect_rise,
+			detect_fall       : &self.detect_fall,
+			detect_high       : &self.detect_high,
+			detect_low        : &self.detect_low,
+			detect_async_rise : &self.detect_async_rise,
+			detect_async_fall : &self.detect_async_fall,
+		}.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for GpioConfig {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let config = GpioConfigOwned::deserialize(deserializer)?;
+		Ok(GpioConfig {
+			function          : pin_array(config.function)?,
+			level             : pin_array(config.level)?,
+			detect_rise       : pin_array(config.detect_rise)?,
+			detect_fall       : pin_array(config.detect_fall)?,
+			detect_high       : pin_array(config.detect_high)?,
+			detect_low        : pin_array(config.detect_low)?,
+			detect_async_rise : pin_array(config.detect_async_rise)?,
+			detect_async_fall : pin_array(config.detect_async_fall)?,
+			conflict_policy   : Default::default(),
+		})
+	}
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename = "GpioPullConfig")]
+struct GpioPullConfigRef<'a> {
+	pull_mode : &'a [Option<PullMode>],
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "GpioPullConfig")]
+struct GpioPullConfigOwned {
+	pull_mode : Vec<Option<PullMode>>,
+}
+
+impl Serialize for GpioPullConfig {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		GpioPullConfigRef { pull_mode: &self.pull_mode }.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for GpioPullConfig {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let config = GpioPullConfigOwned::deserialize(deserializer)?;
+		Ok(GpioPullConfig { pull_mode: pin_array(config.pull_mode)? })
+	}
+}
+
+/// Convert a list of per-pin settings to an array with one entry for each of the 54 pins.
+fn pin_array<T: Copy, E: serde::de::Error>(values: Vec<Option<T>>) -> Result<[Option<T>; 54], E> {
+	let mut array = [None; 54];
+	if values.len() != array.len() {
+		return Err(E::invalid_length(values.len(), &"54 pins"));
+	}
+	array.copy_from_slice(&values);
+	Ok(array)
+}
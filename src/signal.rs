@@ -0,0 +1,175 @@
+use nix::libc::pid_t;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{Edge, Error, ErrorKind, EventListener, Gpio};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+#[derive(Copy, Clone)]
+struct Rule {
+	pin: usize,
+	edge: Edge,
+	signal: Signal,
+}
+
+struct Shared {
+	rules: Mutex<Vec<Rule>>,
+
+	/// Incremented on every change of the rules, so the thread knows when to update its listener.
+	generation: AtomicU64,
+
+	/// Set to stop the bridge thread.
+	stop: AtomicBool,
+}
+
+impl Shared {
+	fn rules(&self) -> MutexGuard<'_, Vec<Rule>> {
+		self.rules.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Sends POSIX signals to a process on edges of GPIO pins.
+///
+/// This allows driving processes that can only react to signals, like many existing daemons, with GPIO edges.
+/// A background thread polls the pins with an [`EventListener`] at a fixed interval,
+/// so edges shorter than the interval can be missed.
+///
+/// Signals of the same kind that arrive before the process handled the previous one are merged by the kernel,
+/// so a process can not count edges this way.
+pub struct SignalBridge {
+	process: Pid,
+	shared: Arc<Shared>,
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl SignalBridge {
+	/// Start sending signals to a process, polling the pins at an interval.
+	///
+	/// No signals are sent until edges are added with [`signal_on`](Self::signal_on).
+	pub fn start(process: pid_t, interval: Duration) -> Result<Self, Error> {
+		Self::start_with_options(process, interval, ThreadOptions::default())
+	}
+
+	/// Start sending signals to a process, with options for the background thread.
+	///
+	/// This opens a handle with [`Gpio::new`] for the background thread.
+	pub fn start_with_options(process: pid_t, interval: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		Self::start_with_gpio(Gpio::new()?, process, interval, options)
+	}
+
+	/// Start sending signals to a process, moving an existing handle to the background thread.
+	pub fn start_with_gpio(gpio: Gpio, process: pid_t, interval: Duration, options: ThreadOptions) -> Result<Self, Error> {
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "signal bridge polling interval must not be zero"));
+		}
+		let process = Pid::from_raw(process);
+
+		let shared = Arc::new(Shared {
+			rules:      Mutex::new(Vec::new()),
+			generation: AtomicU64::new(0),
+			stop:       AtomicBool::new(false),
+		});
+
+		let thread = spawn_worker("signal bridge", options, gpio, {
+			let shared = shared.clone();
+			move |gpio| run(&gpio, process, interval, &shared)
+		})?;
+
+		Ok(Self {
+			process,
+			shared,
+			thread: Some(thread),
+		})
+	}
+
+	/// Start sending signals to the current process, see [`start`](Self::start).
+	pub fn start_current_process(interval: Duration) -> Result<Self, Error> {
+		Self::start(Pid::this().as_raw(), interval)
+	}
+
+	/// Get the process the signals are sent to.
+	pub fn process(&self) -> pid_t {
+		self.process.as_raw()
+	}
+
+	/// Send a signal to the process on an edge of a pin.
+	///
+	/// The signal is given by its number, like `libc::SIGUSR1`.
+	/// This replaces the signal for the same pin and edge, if any.
+	pub fn signal_on(&self, pin: usize, edge: Edge, signal: i32) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		let signal = Signal::from_c_int(signal).map_err(|_| Error::new(ErrorKind::InvalidArgument, format!("invalid signal number: {}", signal)))?;
+		let mut rules = self.shared.rules();
+		rules.retain(|x| x.pin != pin || x.edge != edge);
+		rules.push(Rule { pin, edge, signal });
+		self.shared.generation.fetch_add(1, Ordering::Relaxed);
+		Ok(())
+	}
+
+	/// Stop sending a signal on an edge of a pin.
+	///
+	/// Returns false if no signal was sent on the edge.
+	pub fn remove(&self, pin: usize, edge: Edge) -> bool {
+		let mut rules = self.shared.rules();
+		let count = rules.len();
+		rules.retain(|x| x.pin != pin || x.edge != edge);
+		self.shared.generation.fetch_add(1, Ordering::Relaxed);
+		count != rules.len()
+	}
+
+	/// Stop the bridge thread.
+	pub fn stop(mut self) {
+		self.stop_thread();
+	}
+
+	fn stop_thread(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			thread.thread().unpark();
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for SignalBridge {
+	fn drop(&mut self) {
+		self.stop_thread();
+	}
+}
+
+fn run(gpio: &Gpio, process: Pid, interval: Duration, shared: &Shared) {
+	let mut listener = EventListener::new();
+	let mut rules: Vec<Rule> = Vec::new();
+	let mut generation = None;
+
+	while !shared.stop.load(Ordering::Relaxed) {
+		let current = shared.generation.load(Ordering::Relaxed);
+		if generation != Some(current) {
+			for rule in rules.drain(..) {
+				let _ = listener.unwatch(rule.pin, rule.edge);
+			}
+			rules = shared.rules().clone();
+			for rule in &rules {
+				let _ = listener.watch(rule.pin, rule.edge);
+			}
+			generation = Some(current);
+		}
+
+		listener.poll(gpio);
+		while let Some(event) = listener.pop() {
+			let rule = rules.iter().find(|x| x.pin == event.pin && x.edge == event.edge);
+			if let Some(rule) = rule {
+				if let Err(_e) = kill(process, rule.signal) {
+					#[cfg(feature = "log")]
+					log::warn!(target: "bcm283x_linux_gpio::signal", "failed to send {:?} to process {}: {}", rule.signal, process, _e);
+				}
+			}
+		}
+
+		std::thread::park_timeout(interval);
+	}
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use crate::{Gpio, GpioState};
+#[cfg(all(feature = "async", not(feature = "stub")))]
+use crate::{AsyncFd, Error, ErrorKind};
+
+impl Gpio {
+	/// Read the full GPIO state periodically, blocking the current thread between snapshots.
+	///
+	/// The first snapshot is taken immediately, the next ones at a fixed rate.
+	/// If the caller falls behind by more than an interval, the missed snapshots are skipped.
+	/// With a zero interval, snapshots are taken as fast as the caller consumes them.
+	pub fn snapshots_blocking(&self, interval: Duration) -> Snapshots<'_> {
+		Snapshots {
+			gpio: self,
+			interval,
+			next: None,
+		}
+	}
+
+	/// Read the full GPIO state periodically, as a [`Stream`](futures_core::Stream).
+	///
+	/// The first snapshot is taken immediately, the next ones at a fixed rate.
+	/// The interval is timed by a timerfd registered with the reactor of the async runtime through `F`,
+	/// so this does not start a thread.
+	/// If the stream is not polled for more than an interval, the missed snapshots are skipped.
+	#[cfg(all(feature = "async", not(feature = "stub")))]
+	pub fn snapshots<F: AsyncFd>(&self, interval: Duration) -> Result<SnapshotStream<'_, F>, Error> {
+		use nix::libc;
+		use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "snapshot interval must not be zero"));
+		}
+
+		let fd = unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC) };
+		if fd < 0 {
+			return Err(Error::from_io(ErrorKind::Io, "failed to create timerfd", std::io::Error::last_os_error()));
+		}
+		// Safety: the file descriptor was just created and is not owned by anything else.
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+		let interval = libc::timespec {
+			tv_sec:  interval.as_secs() as libc::time_t,
+			tv_nsec: interval.subsec_nanos() as libc::c_long,
+		};
+		let spec = libc::itimerspec {
+			it_interval: interval,
+			it_value:    interval,
+		};
+		if unsafe { libc::timerfd_settime(fd.as_raw_fd(), 0, &spec, std::ptr::null_mut()) } < 0 {
+			return Err(Error::from_io(ErrorKind::Io, "failed to start timerfd", std::io::Error::last_os_error()));
+		}
+
+		let timer = F::new(fd).map_err(|e| Error::from_io(ErrorKind::Io, "failed to register timerfd with the async runtime", e))?;
+		Ok(SnapshotStream {
+			gpio: self,
+			timer,
+			first: true,
+		})
+	}
+}
+
+/// An iterator over periodic snapshots of the full GPIO state.
+///
+/// Created with [`Gpio::snapshots_blocking`].
+pub struct Snapshots<'a> {
+	gpio: &'a Gpio,
+	interval: Duration,
+	next: Option<Instant>,
+}
+
+impl Iterator for Snapshots<'_> {
+	type Item = GpioState;
+
+	fn next(&mut self) -> Option<GpioState> {
+		let now = Instant::now();
+		let deadline = match self.next {
+			Some(x) if x > now => {
+				crate::delay::sleep_until(x);
+				x
+			},
+			_ => now,
+		};
+		self.next = Some(deadline + self.interval);
+		Some(self.gpio.read_all())
+	}
+}
+
+/// A stream of periodic snapshots of the full GPIO state.
+///
+/// Created with [`Gpio::snapshots`].
+/// The stream never ends, unless reading the timerfd fails.
+#[cfg(all(feature = "async", not(feature = "stub")))]
+pub struct SnapshotStream<'a, F> {
+	gpio: &'a Gpio,
+	timer: F,
+	first: bool,
+}
+
+#[cfg(all(feature = "async", not(feature = "stub")))]
+impl<F: AsyncFd> futures_core::Stream for SnapshotStream<'_, F> {
+	type Item = GpioState;
+
+	fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<GpioState>> {
+		use std::task::Poll;
+
+		let this = self.get_mut();
+		if !std::mem::take(&mut this.first) {
+			// Reading returns the number of expirations since the last read, so missed ticks are merged.
+			let mut expirations = [0; 8];
+			match this.timer.poll_read(cx, &mut expirations) {
+				Poll::Ready(Ok(_)) => (),
+				Poll::Ready(Err(_e)) => {
+					#[cfg(feature = "log")]
+					log::error!(target: "bcm283x_linux_gpio::snapshot", "failed to read timerfd: {}", _e);
+					return Poll::Ready(None);
+				},
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+		Poll::Ready(Some(this.gpio.read_all()))
+	}
+}
@@ -0,0 +1,183 @@
+use crate::PinFunction;
+#[cfg(feature = "std")]
+use crate::{Error, ErrorKind};
+
+/// The SoC family of the GPIO peripheral.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Soc {
+	/// The BCM2835, BCM2836 and BCM2837, used up to the Raspberry Pi 3.
+	Bcm2835,
+
+	/// The BCM2711, used in the Raspberry Pi 4.
+	Bcm2711,
+}
+
+impl Soc {
+	/// Detect the SoC from the compatible property of the GPIO node in the device tree.
+	#[cfg(feature = "std")]
+	pub fn detect() -> Result<Self, Error> {
+		let compatible = crate::read_gpio_compatible()?;
+		match compatible.as_slice() {
+			b"brcm,bcm2835-gpio" => Ok(Soc::Bcm2835),
+			b"brcm,bcm2711-gpio" => Ok(Soc::Bcm2711),
+			_ => Err(Error::new(ErrorKind::UnsupportedSoC, format!("unsupported gpio peripheral type: {:?}", String::from_utf8_lossy(&compatible)))),
+		}
+	}
+
+	/// Check if the pull up/down configuration can be read back from the hardware.
+	///
+	/// Only the BCM2711 has readable pull up/down registers.
+	pub fn has_pull_readback(self) -> bool {
+		self == Soc::Bcm2711
+	}
+}
+
+/// A pin that is reserved for a special purpose on Raspberry Pi boards.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SpecialPin {
+	/// ID_SD or ID_SC, the I2C bus of the HAT ID EEPROM (pins 0 and 1).
+	IdEeprom,
+
+	/// A pin of the SD card interface (pins 48-53 on the BCM2835).
+	SdCard,
+}
+
+impl Soc {
+	/// Get the name of an alternate function of a pin, like `SDA1` for ALT0 of pin 2.
+	///
+	/// Returns `None` for the input and output functions, for reserved alternate functions,
+	/// and for pins without known alternate functions on the SoC.
+	pub fn alt_function_name(self, pin: usize, function: PinFunction) -> Option<&'static str> {
+		self.alt_function_entry(pin, function).filter(|x| !x.is_empty())
+	}
+
+	/// Check if an alternate function of a pin is reserved on the SoC.
+	///
+	/// Pins without known alternate functions are assumed to have no reserved ones.
+	pub(crate) fn is_reserved_alt_function(self, pin: usize, function: PinFunction) -> bool {
+		self.alt_function_entry(pin, function) == Some("")
+	}
+
+	/// Get the entry of the alternate function table for a pin, which is empty for reserved functions.
+	fn alt_function_entry(self, pin: usize, function: PinFunction) -> Option<&'static str> {
+		let alt = match function {
+			PinFunction::Input | PinFunction::Output => return None,
+			PinFunction::Alt0 => 0,
+			PinFunction::Alt1 => 1,
+			PinFunction::Alt2 => 2,
+			PinFunction::Alt3 => 3,
+			PinFunction::Alt4 => 4,
+			PinFunction::Alt5 => 5,
+		};
+		let table : &[[&str; 6]] = match self {
+			Soc::Bcm2835 => &BCM2835_ALT_FUNCTIONS,
+			Soc::Bcm2711 => &BCM2711_ALT_FUNCTIONS,
+		};
+		Some(table.get(pin)?[alt])
+	}
+
+	/// Get the special purpose of a pin on Raspberry Pi boards with this SoC, if any.
+	pub fn special_pin(self, pin: usize) -> Option<SpecialPin> {
+		match (self, pin) {
+			(_, 0..=1) => Some(SpecialPin::IdEeprom),
+			(Soc::Bcm2835, 48..=53) => Some(SpecialPin::SdCard),
+			_ => None,
+		}
+	}
+}
+
+/// The alternate functions of the BCM2835, from the datasheet.
+///
+/// ALT2 of pins 0-27 is the DPI interface, which is not documented in the datasheet.
+const BCM2835_ALT_FUNCTIONS : [[&str; 6]; 54] = [
+	["SDA0",       "SA5",   "DPI_PCLK",  "",           "",           ""],
+	["SCL0",       "SA4",   "DPI_DE",    "",           "",           ""],
+	["SDA1",       "SA3",   "DPI_VSYNC", "",           "",           ""],
+	["SCL1",       "SA2",   "DPI_HSYNC", "",           "",           ""],
+	["GPCLK0",     "SA1",   "DPI_D0",    "",           "",           "ARM_TDI"],
+	["GPCLK1",     "SA0",   "DPI_D1",    "",           "",           "ARM_TDO"],
+	["GPCLK2",     "SOE_N", "DPI_D2",    "",           "",           "ARM_RTCK"],
+	["SPI0_CE1_N", "SWE_N", "DPI_D3",    "",           "",           ""],
+	["SPI0_CE0_N", "SD0",   "DPI_D4",    "",           "",           ""],
+	["SPI0_MISO",  "SD1",   "DPI_D5",    "",           "",           ""],
+	["SPI0_MOSI",  "SD2",   "DPI_D6",    "",           "",           ""],
+	["SPI0_SCLK",  "SD3",   "DPI_D7",    "",           "",           ""],
+	["PWM0",       "SD4",   "DPI_D8",    "",           "",           "ARM_TMS"],
+	["PWM1",       "SD5",   "DPI_D9",    "",           "",           "ARM_TCK"],
+	["TXD0",       "SD6",   "DPI_D10",   "",           "",           "TXD1"],
+	["RXD0",       "SD7",   "DPI_D11",   "",           "",           "RXD1"],
+	["",           "SD8",   "DPI_D12",   "CTS0",       "SPI1_CE2_N", "CTS1"],
+	["",           "SD9",   "DPI_D13",   "RTS0",       "SPI1_CE1_N", "RTS1"],
+	["PCM_CLK",    "SD10",  "DPI_D14",   "BSCSL_SDA",  "SPI1_CE0_N", "PWM0"],
+	["PCM_FS",     "SD11",  "DPI_D15",   "BSCSL_SCL",  "SPI1_MISO",  "PWM1"],
+	["PCM_DIN",    "SD12",  "DPI_D16",   "BSCSL_MISO", "SPI1_MOSI",  "GPCLK0"],
+	["PCM_DOUT",   "SD13",  "DPI_D17",   "BSCSL_CE_N", "SPI1_SCLK",  "GPCLK1"],
+	["",           "SD14",  "DPI_D18",   "SD1_CLK",    "ARM_TRST",   ""],
+	["",           "SD15",  "DPI_D19",   "SD1_CMD",    "ARM_RTCK",   ""],
+	["",           "SD16",  "DPI_D20",   "SD1_DAT0",   "ARM_TDO",    ""],
+	["",           "SD17",  "DPI_D21",   "SD1_DAT1",   "ARM_TCK",    ""],
+	["",           "",      "DPI_D22",   "SD1_DAT2",   "ARM_TDI",    ""],
+	["",           "",      "DPI_D23",   "SD1_DAT3",   "ARM_TMS",    ""],
+	["SDA0",       "SA5",   "PCM_CLK",   "",           "",           ""],
+	["SCL0",       "SA4",   "PCM_FS",    "",           "",           ""],
+	["",           "SA3",   "PCM_DIN",   "CTS0",       "",           "CTS1"],
+	["",           "SA2",   "PCM_DOUT",  "RTS0",       "",           "RTS1"],
+	["GPCLK0",     "SA1",   "",          "TXD0",       "",           "TXD1"],
+	["",           "SA0",   "",          "RXD0",       "",           "RXD1"],
+	["GPCLK0",     "SOE_N", "",          "SD1_CLK",    "",           ""],
+	["SPI0_CE1_N", "SWE_N", "",          "SD1_CMD",    "",           ""],
+	["SPI0_CE0_N", "SD0",   "TXD0",      "SD1_DAT0",   "",           ""],
+	["SPI0_MISO",  "SD1",   "RXD0",      "SD1_DAT1",   "",           ""],
+	["SPI0_MOSI",  "SD2",   "RTS0",      "SD1_DAT2",   "",           ""],
+	["SPI0_SCLK",  "SD3",   "CTS0",      "SD1_DAT3",   "",           ""],
+	["PWM0",       "SD4",   "",          "",           "SPI2_MISO",  "TXD1"],
+	["PWM1",       "SD5",   "",          "",           "SPI2_MOSI",  "RXD1"],
+	["GPCLK1",     "SD6",   "",          "",           "SPI2_SCLK",  "RTS1"],
+	["GPCLK2",     "SD7",   "",          "",           "SPI2_CE0_N", "CTS1"],
+	["GPCLK1",     "SDA0",  "SDA1",      "",           "SPI2_CE1_N", ""],
+	["PWM1",       "SCL0",  "SCL1",      "",           "SPI2_CE2_N", ""],
+	["",           "",      "",          "",           "",           ""],
+	["",           "",      "",          "",           "",           ""],
+	["SD0_CLK",    "",      "",          "SD1_CLK",    "",           ""],
+	["SD0_CMD",    "",      "",          "SD1_CMD",    "",           ""],
+	["SD0_DAT0",   "",      "",          "SD1_DAT0",   "",           ""],
+	["SD0_DAT1",   "",      "",          "SD1_DAT1",   "",           ""],
+	["SD0_DAT2",   "",      "",          "SD1_DAT2",   "",           ""],
+	["SD0_DAT3",   "",      "",          "SD1_DAT3",   "",           ""],
+];
+
+/// The alternate functions of the pins on the 40 pin header of the BCM2711, from the datasheet.
+///
+/// The other pins are mostly used on the board itself, so they are not listed.
+const BCM2711_ALT_FUNCTIONS : [[&str; 6]; 28] = [
+	["SDA0",       "SA5",   "DPI_PCLK",  "SPI3_CE0_N", "TXD2",       "SDA6"],
+	["SCL0",       "SA4",   "DPI_DE",    "SPI3_MISO",  "RXD2",       "SCL6"],
+	["SDA1",       "SA3",   "DPI_VSYNC", "SPI3_MOSI",  "CTS2",       "SDA3"],
+	["SCL1",       "SA2",   "DPI_HSYNC", "SPI3_SCLK",  "RTS2",       "SCL3"],
+	["GPCLK0",     "SA1",   "DPI_D0",    "SPI4_CE0_N", "TXD3",       "SDA3"],
+	["GPCLK1",     "SA0",   "DPI_D1",    "SPI4_MISO",  "RXD3",       "SCL3"],
+	["GPCLK2",     "SOE_N", "DPI_D2",    "SPI4_MOSI",  "CTS3",       "SDA4"],
+	["SPI0_CE1_N", "SWE_N", "DPI_D3",    "SPI4_SCLK",  "RTS3",       "SCL4"],
+	["SPI0_CE0_N", "SD0",   "DPI_D4",    "BSCSL_CE_N", "TXD4",       "SDA4"],
+	["SPI0_MISO",  "SD1",   "DPI_D5",    "BSCSL_MISO", "RXD4",       "SCL4"],
+	["SPI0_MOSI",  "SD2",   "DPI_D6",    "BSCSL_SDA",  "CTS4",       "SDA5"],
+	["SPI0_SCLK",  "SD3",   "DPI_D7",    "BSCSL_SCL",  "RTS4",       "SCL5"],
+	["PWM0_0",     "SD4",   "DPI_D8",    "SPI5_CE0_N", "TXD5",       "SDA5"],
+	["PWM0_1",     "SD5",   "DPI_D9",    "SPI5_MISO",  "RXD5",       "SCL5"],
+	["TXD0",       "SD6",   "DPI_D10",   "SPI5_MOSI",  "CTS5",       "TXD1"],
+	["RXD0",       "SD7",   "DPI_D11",   "SPI5_SCLK",  "RTS5",       "RXD1"],
+	["",           "SD8",   "DPI_D12",   "CTS0",       "SPI1_CE2_N", "CTS1"],
+	["",           "SD9",   "DPI_D13",   "RTS0",       "SPI1_CE1_N", "RTS1"],
+	["PCM_CLK",    "SD10",  "DPI_D14",   "SPI6_CE0_N", "SPI1_CE0_N", "PWM0_0"],
+	["PCM_FS",     "SD11",  "DPI_D15",   "SPI6_MISO",  "SPI1_MISO",  "PWM0_1"],
+	["PCM_DIN",    "SD12",  "DPI_D16",   "SPI6_MOSI",  "SPI1_MOSI",  "GPCLK0"],
+	["PCM_DOUT",   "SD13",  "DPI_D17",   "SPI6_SCLK",  "SPI1_SCLK",  "GPCLK1"],
+	["SD0_CLK",    "SD14",  "DPI_D18",   "SD1_CLK",    "ARM_TRST",   "SDA6"],
+	["SD0_CMD",    "SD15",  "DPI_D19",   "SD1_CMD",    "ARM_RTCK",   "SCL6"],
+	["SD0_DAT0",   "SD16",  "DPI_D20",   "SD1_DAT0",   "ARM_TDO",    "SPI3_CE1_N"],
+	["SD0_DAT1",   "SD17",  "DPI_D21",   "SD1_DAT1",   "ARM_TCK",    "SPI4_CE1_N"],
+	["SD0_DAT2",   "",      "DPI_D22",   "SD1_DAT2",   "ARM_TDI",    "SPI5_CE1_N"],
+	["SD0_DAT3",   "",      "DPI_D23",   "SD1_DAT3",   "ARM_TMS",    "SPI6_CE1_N"],
+];
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use crate::{delay, Error, ErrorKind, Gpio, PinFunction};
+
+/// Bit-banged SPI master on GPIO pins.
+///
+/// The chip select line is active low.
+/// SCLK, MOSI and CS are configured as outputs and MISO as input when the bus is created.
+/// The pin functions are not restored when the bus is dropped.
+pub struct SoftSpi<'a> {
+	gpio: &'a mut Gpio,
+	sclk: usize,
+	mosi: usize,
+	miso: usize,
+	cs: usize,
+	mode: u8,
+	half_period: Duration,
+}
+
+impl<'a> SoftSpi<'a> {
+	/// Create a new SPI bus on the given pins with the given SPI mode (0 to 3) and clock frequency.
+	pub fn new(gpio: &'a mut Gpio, sclk: usize, mosi: usize, miso: usize, cs: usize, mode: u8, frequency: f64) -> Result<Self, Error> {
+		let pins = [sclk, mosi, miso, cs];
+		for (i, &pin) in pins.iter().enumerate() {
+			crate::check_pin_index(pin)?;
+			if pins[..i].contains(&pin) {
+				return Err(Error::new(ErrorKind::InvalidArgument, format!("pin {} is used for more than one SPI line", pin)));
+			}
+		}
+		if mode > 3 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid SPI mode: {}, expected 0 to 3", mode)));
+		}
+		if !frequency.is_finite() || frequency <= 0.0 {
+			return Err(Error::new(ErrorKind::InvalidArgument, format!("invalid SPI frequency: {}", frequency)));
+		}
+
+		// Set the idle levels before switching the pins to output.
+		gpio.write_level(cs, true);
+		gpio.write_level(sclk, mode & 0b10 != 0);
+		gpio.write_level(mosi, false);
+		gpio.write_function(cs, PinFunction::Output);
+		gpio.write_function(sclk, PinFunction::Output);
+		gpio.write_function(mosi, PinFunction::Output);
+		gpio.write_function(miso, PinFunction::Input);
+
+		Ok(Self {
+			gpio,
+			sclk,
+			mosi,
+			miso,
+			cs,
+			mode,
+			half_period: Duration::from_secs_f64(0.5 / frequency),
+		})
+	}
+
+	/// Perform a full duplex transfer.
+	///
+	/// The bytes in the buffer are sent, and replaced with the bytes received.
+	/// Chip select is asserted for the duration of the whole transfer.
+	pub fn transfer(&mut self, buffer: &mut [u8]) {
+		self.gpio.write_level(self.cs, false);
+		self.delay();
+		for byte in buffer.iter_mut() {
+			*byte = self.transfer_byte(*byte);
+		}
+		self.delay();
+		self.gpio.write_level(self.cs, true);
+		self.delay();
+	}
+
+	/// Get the GPIO handle of the bus, to use other pins while the bus exists.
+	pub(crate) fn gpio(&mut self) -> &mut Gpio {
+		self.gpio
+	}
+
+	fn transfer_byte(&mut self, byte: u8) -> u8 {
+		let idle  = self.mode & 0b10 != 0;
+		let phase = self.mode & 0b01 != 0;
+
+		let mut result = 0;
+		for i in (0..8).rev() {
+			let bit = byte >> i & 1 == 1;
+
+			// With CPHA=0 data is shifted out before the first clock edge and sampled on it.
+			// With CPHA=1 data is shifted out on the first clock edge and sampled on the second.
+			if phase {
+				self.gpio.write_level(self.sclk, !idle);
+			}
+			self.gpio.write_level(self.mosi, bit);
+			self.delay();
+			self.gpio.write_level(self.sclk, phase == idle);
+			result = result << 1 | u8::from(self.gpio.level(self.miso));
+			self.delay();
+			if !phase {
+				self.gpio.write_level(self.sclk, idle);
+			}
+		}
+
+		result
+	}
+
+	fn delay(&self) {
+		delay::sleep(self.half_period);
+	}
+}
@@ -0,0 +1,77 @@
+//! Stand-ins for the backends that need Linux, used with the `stub` feature.
+
+use core::convert::Infallible;
+
+use crate::{Capabilities, Error, GpioBackend, Register, Soc};
+
+/// The GPIO character device backend, which is not available with the `stub` feature.
+///
+/// [`CdevBackend::new`] always returns an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+pub struct CdevBackend {
+	never: Infallible,
+}
+
+impl CdevBackend {
+	/// Always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+	pub fn new() -> Result<Self, Error> {
+		Err(crate::procfs::unsupported("the GPIO character device"))
+	}
+}
+
+/// The read-only /dev/mem backend, which is not available with the `stub` feature.
+///
+/// [`PreadBackend::dev_mem`] always returns an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+pub struct PreadBackend {
+	never: Infallible,
+}
+
+impl PreadBackend {
+	/// Always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+	pub fn dev_mem() -> Result<Self, Error> {
+		Err(crate::procfs::unsupported("reading /dev/mem"))
+	}
+}
+
+/// The sysfs GPIO backend, which is not available with the `stub` feature.
+///
+/// [`SysfsBackend::new`] always returns an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+pub struct SysfsBackend {
+	never: Infallible,
+}
+
+impl SysfsBackend {
+	/// Always fails with an [`Unsupported`](crate::ErrorKind::Unsupported) error.
+	pub fn new() -> Result<Self, Error> {
+		Err(crate::procfs::unsupported("the sysfs GPIO interface"))
+	}
+}
+
+macro_rules! impl_never_backend {
+	($type:ty) => {
+		impl GpioBackend for $type {
+			fn name(&self) -> &str {
+				match self.never {}
+			}
+
+			fn capabilities(&self) -> Capabilities {
+				match self.never {}
+			}
+
+			fn soc(&self) -> Soc {
+				match self.never {}
+			}
+
+			fn read_register(&self, _reg: Register) -> u32 {
+				match self.never {}
+			}
+
+			unsafe fn write_register(&mut self, _reg: Register, _value: u32) {
+				match self.never {}
+			}
+		}
+	};
+}
+
+impl_never_backend!(CdevBackend);
+impl_never_backend!(PreadBackend);
+impl_never_backend!(SysfsBackend);
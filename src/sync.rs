@@ -0,0 +1,104 @@
+use crate::{Error, ErrorKind, Gpio, Level, PinMask, Register};
+
+/// A GPIO handle that can be shared between threads.
+///
+/// Created with [`Gpio::into_sync`].
+/// It only exposes the operations that are safe to perform concurrently without locking:
+/// reading the pin levels and writing the set and clear registers.
+/// Writes to the set and clear registers only affect the pins whose bits are written as one,
+/// so concurrent writes to different pins never interfere.
+///
+/// Levels changed through a shared handle are not recorded by [`Gpio::with_restore`].
+pub struct SyncGpio {
+	gpio: Gpio,
+}
+
+// Only the level, set and clear registers are accessed through a shared reference,
+// and none of those accesses is a read-modify-write cycle.
+unsafe impl Sync for SyncGpio {}
+
+impl Gpio {
+	/// Turn the handle into a handle that can be shared between threads.
+	///
+	/// This requires a backend that maps the GPIO peripheral into memory.
+	/// Use [`SyncGpio::into_inner`] to get the full handle back.
+	pub fn into_sync(self) -> Result<SyncGpio, Error> {
+		if self.mapping().is_none() {
+			return Err(Error::new(ErrorKind::Unsupported, format!("the {} backend can not be shared between threads", self.backend.name())));
+		}
+		Ok(SyncGpio { gpio: self })
+	}
+}
+
+impl SyncGpio {
+	/// Get the full handle back.
+	pub fn into_inner(self) -> Gpio {
+		self.gpio
+	}
+
+	/// Read the current level of a GPIO pin.
+	pub fn read_level(&self, index: usize) -> Result<Level, Error> {
+		crate::check_pin_index(index)?;
+		Ok((self.read(Register::lev(index / 32)) >> (index % 32) & 1 == 1).into())
+	}
+
+	/// Read the current level of all GPIO pins.
+	///
+	/// Bit N of the returned value holds the level of pin N.
+	pub fn read_levels(&self) -> u64 {
+		let low  = u64::from(self.read(Register::GPLEV0));
+		let high = u64::from(self.read(Register::GPLEV1));
+		(low | high << 32) & ((1 << 54) - 1)
+	}
+
+	/// Read the current level of all GPIO pins as a pin mask of the high pins.
+	pub fn read_level_mask(&self) -> PinMask {
+		PinMask::from_bits(self.read_levels())
+	}
+
+	/// Atomically set the level of a single GPIO pin.
+	pub fn set_level(&self, index: usize, value: impl Into<Level>) -> Result<(), Error> {
+		crate::check_pin_index(index)?;
+		let register = match value.into() {
+			Level::High => Register::set(index / 32),
+			Level::Low  => Register::clr(index / 32),
+		};
+		self.write(register, 1 << (index % 32));
+		Ok(())
+	}
+
+	/// Set all pins of the mask high.
+	pub fn set_pins(&self, mask: PinMask) {
+		self.write_words(Register::GPSET0, Register::GPSET1, mask);
+	}
+
+	/// Set all pins of the mask low.
+	pub fn clear_pins(&self, mask: PinMask) {
+		self.write_words(Register::GPCLR0, Register::GPCLR1, mask);
+	}
+
+	fn write_words(&self, low: Register, high: Register, mask: PinMask) {
+		let [low_bits, high_bits] = mask.words();
+		if low_bits != 0 {
+			self.write(low, low_bits);
+		}
+		if high_bits != 0 {
+			self.write(high, high_bits);
+		}
+	}
+
+	// The register accesses bypass the access counters of the handle,
+	// since those are not safe to update from multiple threads.
+
+	fn read(&self, register: Register) -> u32 {
+		let value = unsafe { self.gpio.register_address(register).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+
+	fn write(&self, register: Register, value: u32) {
+		crate::trace_write(register, value);
+		crate::peripheral_barrier();
+		unsafe { self.gpio.register_address_mut(register).write_volatile(value) }
+	}
+}
@@ -0,0 +1,291 @@
+use nix::poll::{poll, PollFd, PollFlags};
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::{Capabilities, Error, ErrorKind, GpioBackend, PinFunction, Register};
+
+/// The directory of the legacy sysfs GPIO interface.
+const SYSFS_GPIO : &str = "/sys/class/gpio";
+
+/// A backend using the legacy sysfs GPIO interface in /sys/class/gpio, for older systems and containers
+/// where neither the GPIO registers nor the GPIO character device are accessible.
+///
+/// The backend emulates the registers through the files of exported pins:
+///  - the function select registers switch the direction of pins between input and output;
+///  - the set and clear registers write the value of output pins;
+///  - the level registers read the value of pins;
+///  - the edge detect registers set the edge of pins, which is reported in the event status registers.
+///
+/// Alternate functions, pull up/down modes and level detection are not supported.
+/// Pins are exported when they are first accessed, and the pins exported by the backend are unexported when it is dropped.
+pub struct SysfsBackend {
+	name: String,
+	base: usize,
+	pins: RefCell<Vec<Option<SysfsPin>>>,
+	exported: Cell<u64>,
+	registers: [u32; crate::CONTROL_BLOCK_SIZE / 4],
+	latch: u64,
+	events: Cell<u64>,
+}
+
+/// The files of an exported pin.
+struct SysfsPin {
+	path: PathBuf,
+	value: File,
+}
+
+impl SysfsBackend {
+	/// Find the GPIO chip of the BCM283x pin controller in /sys/class/gpio.
+	///
+	/// The chip is found by its label, which is `pinctrl-bcm2835` or `pinctrl-bcm2711`.
+	pub fn new() -> Result<Self, Error> {
+		let entries = std::fs::read_dir(SYSFS_GPIO)
+			.map_err(|e| Error::from_io(ErrorKind::PeripheralNotFound, format!("failed to list {}", SYSFS_GPIO), e))?;
+
+		for entry in entries.filter_map(Result::ok) {
+			let path = entry.path();
+			if !entry.file_name().to_string_lossy().starts_with("gpiochip") {
+				continue;
+			}
+			let label = match read_string(&path.join("label")) {
+				Ok(x) => x,
+				Err(_) => continue,
+			};
+			if !label.starts_with("pinctrl-bcm2") {
+				continue;
+			}
+			let base = read_string(&path.join("base")).ok().and_then(|x| x.parse::<usize>().ok())
+				.ok_or_else(|| Error::new(ErrorKind::Io, format!("failed to read the GPIO base from {}", path.display())))?;
+			return Ok(Self {
+				name: path.display().to_string(),
+				base,
+				pins: RefCell::new((0..54).map(|_| None).collect()),
+				exported: Cell::new(0),
+				registers: [0; crate::CONTROL_BLOCK_SIZE / 4],
+				latch: 0,
+				events: Cell::new(0),
+			});
+		}
+
+		Err(Error::new(ErrorKind::PeripheralNotFound, format!("no GPIO chip found for the BCM283x pin controller in {}", SYSFS_GPIO)))
+	}
+
+	/// Get the sysfs directory of a pin.
+	fn pin_path(&self, pin: usize) -> PathBuf {
+		Path::new(SYSFS_GPIO).join(format!("gpio{}", self.base + pin))
+	}
+
+	/// Run a function with the files of a pin, exporting the pin first if needed.
+	///
+	/// Returns None if the pin could not be exported, for example because it is in use by the kernel.
+	fn with_pin<R>(&self, pin: usize, f: impl FnOnce(&mut SysfsPin) -> std::io::Result<R>) -> Option<R> {
+		let mut pins = self.pins.borrow_mut();
+		if pins[pin].is_none() {
+			let path = self.pin_path(pin);
+			if !path.exists() {
+				write_string(&Path::new(SYSFS_GPIO).join("export"), &(self.base + pin).to_string()).ok()?;
+				self.exported.set(self.exported.get() | 1 << pin);
+			}
+			let value = std::fs::OpenOptions::new().read(true).write(true).open(path.join("value"))
+				.or_else(|_| File::open(path.join("value")))
+				.ok()?;
+			pins[pin] = Some(SysfsPin { path, value });
+		}
+		pins[pin].as_mut().and_then(|x| f(x).ok())
+	}
+
+	/// Check if a pin is an output, without exporting it.
+	fn is_output(&self, pin: usize) -> bool {
+		read_string(&self.pin_path(pin).join("direction")).is_ok_and(|x| x == "out")
+	}
+
+	/// Read the levels of all pins.
+	fn read_pin_levels(&self) -> u64 {
+		let mut levels = 0;
+		for pin in 0..54 {
+			if self.with_pin(pin, |x| x.read_value()).unwrap_or(false) {
+				levels |= 1 << pin;
+			}
+		}
+		levels
+	}
+
+	/// Check the pins with edge detection for new events.
+	fn read_pin_events(&self) -> u64 {
+		let edges = self.pair(Register::GPREN0, Register::GPREN1)
+			| self.pair(Register::GPFEN0, Register::GPFEN1)
+			| self.pair(Register::GPAREN0, Register::GPAREN1)
+			| self.pair(Register::GPAFEN0, Register::GPAFEN1);
+		let mut events = self.events.get();
+		for pin in (0..54).filter(|pin| edges >> pin & 1 != 0) {
+			let triggered = self.with_pin(pin, |x| {
+				let mut fds = [PollFd::new(x.value.as_raw_fd(), PollFlags::POLLPRI)];
+				let ready = poll(&mut fds, 0).unwrap_or(0) > 0;
+				// Reading the value acknowledges the event.
+				x.read_value()?;
+				Ok(ready)
+			});
+			if triggered == Some(true) {
+				events |= 1 << pin;
+			}
+		}
+		self.events.set(events);
+		events
+	}
+
+	/// Write the function select register for ten pins.
+	///
+	/// Exported pins can only be inputs or outputs, so alternate functions are left out.
+	/// [`Gpio`](crate::Gpio) rejects them before they get here, since the capabilities don't include them.
+	fn write_functions(&mut self, first_pin: usize, value: u32) {
+		let old = self.read_register(Register::fsel(first_pin / 10));
+		for pin in first_pin..(first_pin + 10).min(54) {
+			let shift = (pin - first_pin) * 3;
+			if (old ^ value) >> shift & 0b111 == 0 {
+				continue;
+			}
+			let direction = match PinFunction::from_bits((value >> shift & 0b111) as u8) {
+				PinFunction::Input  => "in",
+				// Writing high or low sets the direction and the initial value at once.
+				PinFunction::Output if self.latch >> pin & 1 != 0 => "high",
+				PinFunction::Output => "low",
+				_ => continue,
+			};
+			self.with_pin(pin, |x| write_string(&x.path.join("direction"), direction));
+		}
+	}
+
+	/// Change the output latch, and write the new value to output pins.
+	fn write_latch(&mut self, bits: u64, value: bool) {
+		match value {
+			true  => self.latch |= bits,
+			false => self.latch &= !bits,
+		}
+		for pin in (0..54).filter(|pin| bits >> pin & 1 != 0) {
+			if self.is_output(pin) {
+				self.with_pin(pin, |x| x.write_value(value));
+			}
+		}
+	}
+
+	/// Update the edge of all pins from the edge detect registers.
+	fn update_edges(&mut self, old_rise: u64, old_fall: u64) {
+		let rise = self.pair(Register::GPREN0, Register::GPREN1) | self.pair(Register::GPAREN0, Register::GPAREN1);
+		let fall = self.pair(Register::GPFEN0, Register::GPFEN1) | self.pair(Register::GPAFEN0, Register::GPAFEN1);
+		for pin in (0..54).filter(|pin| ((rise ^ old_rise) | (fall ^ old_fall)) >> pin & 1 != 0) {
+			let edge = match (rise >> pin & 1 != 0, fall >> pin & 1 != 0) {
+				(false, false) => "none",
+				(true,  false) => "rising",
+				(false, true)  => "falling",
+				(true,  true)  => "both",
+			};
+			self.with_pin(pin, |x| write_string(&x.path.join("edge"), edge));
+		}
+	}
+
+	fn pair(&self, low: Register, high: Register) -> u64 {
+		u64::from(self.registers[low as usize / 4]) | u64::from(self.registers[high as usize / 4]) << 32
+	}
+}
+
+impl SysfsPin {
+	fn read_value(&mut self) -> std::io::Result<bool> {
+		let mut buffer = [0u8; 1];
+		self.value.seek(SeekFrom::Start(0))?;
+		self.value.read_exact(&mut buffer)?;
+		Ok(buffer[0] == b'1')
+	}
+
+	fn write_value(&mut self, value: bool) -> std::io::Result<()> {
+		self.value.seek(SeekFrom::Start(0))?;
+		self.value.write_all(if value { b"1" } else { b"0" })
+	}
+}
+
+impl GpioBackend for SysfsBackend {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities {
+			registers:     false,
+			alt_functions: false,
+			pull:          false,
+			events:        true,
+			read_only:     false,
+		}
+	}
+
+	fn read_register(&self, reg: Register) -> u32 {
+		match reg {
+			Register::GPFSEL0 | Register::GPFSEL1 | Register::GPFSEL2 | Register::GPFSEL3 | Register::GPFSEL4 | Register::GPFSEL5 => {
+				let first_pin = (reg as usize - Register::GPFSEL0 as usize) / 4 * 10;
+				let mut value = 0;
+				for pin in first_pin..(first_pin + 10).min(54) {
+					if self.is_output(pin) {
+						value |= u32::from(PinFunction::Output.to_bits()) << ((pin - first_pin) * 3);
+					}
+				}
+				value
+			},
+			Register::GPSET0 | Register::GPSET1 | Register::GPCLR0 | Register::GPCLR1 => 0,
+			Register::GPLEV0 => self.read_pin_levels() as u32,
+			Register::GPLEV1 => (self.read_pin_levels() >> 32) as u32,
+			Register::GPEDS0 => self.read_pin_events() as u32,
+			Register::GPEDS1 => (self.read_pin_events() >> 32) as u32,
+			_ => self.registers[reg as usize / 4],
+		}
+	}
+
+	unsafe fn write_register(&mut self, reg: Register, value: u32) {
+		match reg {
+			Register::GPFSEL0 | Register::GPFSEL1 | Register::GPFSEL2 | Register::GPFSEL3 | Register::GPFSEL4 | Register::GPFSEL5 => {
+				self.write_functions((reg as usize - Register::GPFSEL0 as usize) / 4 * 10, value);
+			},
+			Register::GPSET0 => self.write_latch(u64::from(value), true),
+			Register::GPSET1 => self.write_latch(u64::from(value) << 32, true),
+			Register::GPCLR0 => self.write_latch(u64::from(value), false),
+			Register::GPCLR1 => self.write_latch(u64::from(value) << 32, false),
+			Register::GPLEV0 | Register::GPLEV1 => (),
+			Register::GPEDS0 => self.events.set(self.read_pin_events() & !u64::from(value)),
+			Register::GPEDS1 => self.events.set(self.read_pin_events() & !(u64::from(value) << 32)),
+			_ => {
+				let old_rise = self.pair(Register::GPREN0, Register::GPREN1) | self.pair(Register::GPAREN0, Register::GPAREN1);
+				let old_fall = self.pair(Register::GPFEN0, Register::GPFEN1) | self.pair(Register::GPAFEN0, Register::GPAFEN1);
+				self.registers[reg as usize / 4] = value;
+				match reg {
+					Register::GPREN0 | Register::GPREN1 | Register::GPFEN0 | Register::GPFEN1 => self.update_edges(old_rise, old_fall),
+					Register::GPAREN0 | Register::GPAREN1 | Register::GPAFEN0 | Register::GPAFEN1 => self.update_edges(old_rise, old_fall),
+					_ => (),
+				}
+			},
+		}
+	}
+
+	fn read_levels(&self) -> u64 {
+		self.read_pin_levels()
+	}
+}
+
+impl Drop for SysfsBackend {
+	fn drop(&mut self) {
+		// Close the value files before unexporting the pins.
+		self.pins.get_mut().clear();
+		let exported = self.exported.get();
+		for pin in (0..54).filter(|pin| exported >> pin & 1 != 0) {
+			let _ = write_string(&Path::new(SYSFS_GPIO).join("unexport"), &(self.base + pin).to_string());
+		}
+	}
+}
+
+fn read_string(path: &Path) -> std::io::Result<String> {
+	Ok(std::fs::read_to_string(path)?.trim().to_string())
+}
+
+fn write_string(path: &Path, value: &str) -> std::io::Result<()> {
+	std::fs::OpenOptions::new().write(true).open(path)?.write_all(value.as_bytes())
+}
@@ -0,0 +1,98 @@
+//! Utilities for testing code that works with GPIO state, without real hardware.
+
+use crate::{GpioState, Level, PinFunction, Register};
+
+/// A builder for synthetic [`GpioState`] values.
+///
+/// Select a pin with [`pin`](Self::pin), and then set its properties,
+/// like `GpioStateBuilder::new().pin(17).function(PinFunction::Output).level(true).pin(4).detect_rise(true).build()`.
+/// All properties that are not set are zero, like with [`GpioState::new`].
+///
+/// The setters panic if no pin was selected, since this is only meant for tests.
+#[derive(Clone, Default)]
+pub struct GpioStateBuilder {
+	state: GpioState,
+	pin: Option<usize>,
+}
+
+impl GpioStateBuilder {
+	/// Create a builder with all registers set to zero.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Start from an existing state.
+	pub fn from_state(state: GpioState) -> Self {
+		Self { state, pin: None }
+	}
+
+	/// Select the pin to set properties for.
+	///
+	/// # Panics
+	/// This function panics if the pin index is out of range.
+	pub fn pin(mut self, index: usize) -> Self {
+		if let Err(e) = crate::check_pin_index(index) {
+			panic!("{}", e);
+		}
+		self.pin = Some(index);
+		self
+	}
+
+	pub fn function(self, function: PinFunction) -> Self {
+		self.with_bits(Register::GPFSEL0, 10, 3, u32::from(function.to_bits()))
+	}
+
+	pub fn level(self, value: impl Into<Level>) -> Self {
+		self.with_bits(Register::GPLEV0, 32, 1, value.into().is_high().into())
+	}
+
+	pub fn event(self, value: bool) -> Self {
+		self.with_bits(Register::GPEDS0, 32, 1, value.into())
+	}
+
+	pub fn detect_rise(self, value: bool) -> Self {
+		self.with_bits(Register::GPREN0, 32, 1, value.into())
+	}
+
+	pub fn detect_fall(self, value: bool) -> Self {
+		self.with_bits(Register::GPFEN0, 32, 1, value.into())
+	}
+
+	pub fn detect_high(self, value: bool) -> Self {
+		self.with_bits(Register::GPHEN0, 32, 1, value.into())
+	}
+
+	pub fn detect_low(self, value: bool) -> Self {
+		self.with_bits(Register::GPLEN0, 32, 1, value.into())
+	}
+
+	pub fn detect_async_rise(self, value: bool) -> Self {
+		self.with_bits(Register::GPAREN0, 32, 1, value.into())
+	}
+
+	pub fn detect_async_fall(self, value: bool) -> Self {
+		self.with_bits(Register::GPAFEN0, 32, 1, value.into())
+	}
+
+	/// Set the raw value of a register, for settings without a dedicated setter.
+	pub fn register(mut self, reg: Register, value: u32) -> Self {
+		self.state.data_mut()[reg as usize / 4] = value;
+		self
+	}
+
+	/// Get the built state.
+	pub fn build(self) -> GpioState {
+		self.state
+	}
+
+	fn with_bits(mut self, base: Register, pins_per_register: usize, bits_per_pin: usize, value: u32) -> Self {
+		let index = self.pin.expect("no pin selected, call GpioStateBuilder::pin first");
+		let register = base as usize / 4 + index / pins_per_register;
+		let shift    = index % pins_per_register * bits_per_pin;
+		let mask     = !(u32::MAX << bits_per_pin) << shift;
+
+		let data = self.state.data_mut();
+		data[register] = data[register] & !mask | value << shift & mask;
+		self
+	}
+}
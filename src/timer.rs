@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use crate::Error;
+
+/// Offset of the system timer control block from the GPIO control block.
+const TIMER_OFFSET : i64 = 0x003000 - 0x200000;
+const TIMER_SIZE : usize = 0x00000020;
+
+/// The lower 32 bits of the free-running counter.
+const CLO : usize = 0x04;
+
+/// The upper 32 bits of the free-running counter.
+const CHI : usize = 0x08;
+
+/// A handle to the system timer peripheral.
+///
+/// The system timer has a free-running 1 MHz counter,
+/// which is not affected by CPU frequency scaling.
+pub struct SystemTimer {
+	control_block: *mut std::ffi::c_void,
+}
+
+// The handle only reads the counter registers, which is safe from any thread.
+unsafe impl Send for SystemTimer {}
+unsafe impl Sync for SystemTimer {}
+
+impl SystemTimer {
+	/// Create a new handle to the system timer peripheral.
+	///
+	/// This maps a portion of /dev/mem just like [`Gpio::new`](crate::Gpio::new),
+	/// and may fail for the same reasons.
+	pub fn new() -> Result<Self, Error> {
+		let address       = crate::read_gpio_address()? + TIMER_OFFSET;
+		let control_block = crate::map_dev_mem("system timer", address, TIMER_SIZE)?;
+		Ok(Self { control_block })
+	}
+
+	/// Read the free-running counter, in microseconds.
+	pub fn counter(&self) -> u64 {
+		// Re-read if the upper half changed while reading the lower half.
+		loop {
+			let high = self.read(CHI);
+			let low  = self.read(CLO);
+			if self.read(CHI) == high {
+				return u64::from(high) << 32 | u64::from(low);
+			}
+		}
+	}
+
+	/// Busy-wait for at least the given duration.
+	///
+	/// The duration is rounded up to whole microseconds.
+	pub fn wait(&self, duration: Duration) {
+		// Add one tick, since the counter may be about to increment when we start.
+		let ticks = (duration.as_nanos() as u64).div_ceil(1000) + 1;
+		let start = self.counter();
+		while self.counter().wrapping_sub(start) < ticks {
+			std::hint::spin_loop();
+		}
+	}
+
+	/// Lock the mapping in memory and touch it, see [`Gpio::lock_mapping`](crate::Gpio::lock_mapping).
+	pub(crate) fn lock_mapping(&self) -> Result<(), Error> {
+		crate::procfs::lock_memory("system timer", self.control_block, TIMER_SIZE)?;
+		self.counter();
+		Ok(())
+	}
+
+	fn read(&self, offset: usize) -> u32 {
+		let value = unsafe { (self.control_block.wrapping_add(offset) as *const u32).read_volatile() };
+		crate::peripheral_barrier();
+		value
+	}
+}
+
+impl Drop for SystemTimer {
+	fn drop(&mut self) {
+		unsafe {
+			crate::procfs::unmap_memory(self.control_block, TIMER_SIZE);
+		}
+	}
+}
@@ -0,0 +1,75 @@
+//! Measure the timing accuracy the system can deliver.
+//!
+//! Software generated signals like [`SoftPwm`](crate::SoftPwm) depend on the thread waking up on time.
+//! How late it wakes up depends on the CPU frequency governor, the kernel configuration and the load of the system,
+//! so measure it before relying on accurate timing.
+
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, Gpio};
+
+/// Statistics of how late a periodic loop woke up, relative to its schedule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct JitterStats {
+	/// The number of iterations measured.
+	pub samples: usize,
+
+	/// The smallest deviation from the schedule.
+	pub min: Duration,
+
+	/// The mean deviation from the schedule.
+	pub mean: Duration,
+
+	/// The largest deviation from the schedule.
+	pub max: Duration,
+
+	/// The deviation that 99% of the iterations stayed within.
+	pub p99: Duration,
+}
+
+/// Toggle a pin in a periodic loop on the current thread and measure how late each iteration wakes up.
+///
+/// The loop sleeps until fixed deadlines one period apart and toggles the pin, the same way [`SoftPwm`](crate::SoftPwm) does,
+/// so the result shows the jitter to expect from it under the current conditions.
+/// The pin must already be configured as output, and the square wave on it can be checked with a scope.
+/// Apply the same [`realtime`](crate::realtime) settings to the current thread first, to measure their effect.
+///
+/// This blocks for about `period * samples`.
+pub fn measure_jitter(gpio: &mut Gpio, pin: usize, period: Duration, samples: usize) -> Result<JitterStats, Error> {
+	crate::check_pin_index(pin)?;
+	gpio.check_writable()?;
+	if period.is_zero() {
+		return Err(Error::new(ErrorKind::InvalidArgument, "jitter measurement period must not be zero"));
+	}
+	if samples == 0 {
+		return Err(Error::new(ErrorKind::InvalidArgument, "jitter measurement needs at least one sample"));
+	}
+
+	let mut deviations = Vec::with_capacity(samples);
+	let mut level = gpio.level(pin);
+	let mut deadline = Instant::now() + period;
+	for _ in 0..samples {
+		crate::delay::sleep_until(deadline);
+		let now = Instant::now();
+		level = !level;
+		gpio.write_level(pin, level);
+		deviations.push(now.saturating_duration_since(deadline));
+
+		// Don't try to catch up if we fell behind more than a full period.
+		deadline += period;
+		if now > deadline {
+			deadline = now + period;
+		}
+	}
+
+	deviations.sort_unstable();
+	let total: Duration = deviations.iter().sum();
+	let p99 = (samples * 99).div_ceil(100) - 1;
+	Ok(JitterStats {
+		samples,
+		min:  deviations[0],
+		mean: Duration::from_nanos((total.as_nanos() / samples as u128) as u64),
+		max:  deviations[samples - 1],
+		p99:  deviations[p99],
+	})
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::{Error, ErrorKind, Gpio, Level};
+use crate::realtime::ThreadOptions;
+use crate::worker::spawn_worker;
+
+impl Gpio {
+	/// Get a tokio watch channel that holds the current level of a pin.
+	///
+	/// A background thread polls the pin at the given interval and updates the channel when the level changes,
+	/// so async tasks can wait for changes with [`Receiver::changed`](watch::Receiver::changed).
+	/// Pulses shorter than the interval can be missed.
+	///
+	/// The handle is moved to the background thread, so this works with any backend.
+	/// The thread stops and closes the handle when all receivers are dropped.
+	pub fn into_level_watch(self, index: usize, interval: Duration) -> Result<watch::Receiver<Level>, Error> {
+		crate::check_pin_index(index)?;
+		if interval.is_zero() {
+			return Err(Error::new(ErrorKind::InvalidArgument, "level watch polling interval must not be zero"));
+		}
+
+		let (sender, receiver) = watch::channel(Level::from(self.level(index)));
+		spawn_worker("level watch", ThreadOptions::default(), self, move |gpio| run(&gpio, index, interval, &sender))?;
+		Ok(receiver)
+	}
+}
+
+fn run(gpio: &Gpio, index: usize, interval: Duration, sender: &watch::Sender<Level>) {
+	while !sender.is_closed() {
+		let level = Level::from(gpio.level(index));
+		sender.send_if_modified(|x| {
+			let modified = *x != level;
+			*x = level;
+			modified
+		});
+		std::thread::sleep(interval);
+	}
+}
@@ -0,0 +1,30 @@
+use std::thread::JoinHandle;
+
+use crate::{Error, ErrorKind, Gpio};
+use crate::realtime::ThreadOptions;
+
+/// Spawn a background thread that owns a GPIO handle, for the helpers that run on their own thread.
+///
+/// The handle is moved to the thread, so the helpers work with any backend.
+/// The thread options are applied on the new thread before the body runs,
+/// and an error applying them is returned from here.
+pub(crate) fn spawn_worker<F>(name: &str, options: ThreadOptions, gpio: Gpio, body: F) -> Result<JoinHandle<()>, Error>
+where
+	F: FnOnce(Gpio) + Send + 'static,
+{
+	let (result_tx, result_rx) = std::sync::mpsc::channel();
+	let thread = std::thread::spawn(move || {
+		if let Err(e) = options.apply() {
+			let _ = result_tx.send(Err(e));
+			return;
+		}
+		let _ = result_tx.send(Ok(()));
+		body(gpio)
+	});
+
+	match result_rx.recv() {
+		Ok(Ok(())) => Ok(thread),
+		Ok(Err(e)) => Err(e),
+		Err(_)     => Err(Error::new(ErrorKind::Other, format!("{} thread exited unexpectedly", name))),
+	}
+}
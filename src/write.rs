@@ -1,17 +1,110 @@
-use crate::{PinFunction, PullMode, Register, Gpio};
+use alloc::format;
+use alloc::vec::Vec;
+use core::time::Duration;
 
-/// Wait for one clock cycle.
-fn nop() {
-	unsafe { asm!("nop") }
+use crate::{Capabilities, Error, ErrorKind, GpioBackend, Level, PinFunction, PullMode, Register, Soc};
+#[cfg(feature = "std")]
+use crate::Gpio;
+
+/// The time to wait for the pull up/down control signals to settle.
+const PULL_SETUP_TIME: Duration = Duration::from_micros(5);
+
+/// A single register operation performed when applying a configuration.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RegisterOp {
+	/// Overwrite the register with a value.
+	Write(Register, u32),
+
+	/// Perform a bitwise AND on the contents of the register.
+	And(Register, u32),
+
+	/// Perform a bitwise OR on the contents of the register.
+	Or(Register, u32),
+
+	/// Replace the bits selected by a mask with the bits of a value, in a single read-modify-write.
+	Modify { register: Register, mask: u32, value: u32 },
+
+	/// Wait for a precise amount of time.
+	Delay(Duration),
+}
+
+impl RegisterOp {
+	/// Perform the operation.
+	///
+	/// # Safety
+	/// The operation is written to the hardware as-is, without any validation.
+	#[cfg(feature = "std")]
+	pub unsafe fn execute(self, gpio: &mut Gpio) {
+		match self {
+			RegisterOp::Write(reg, value) => gpio.write_register(reg, value),
+			RegisterOp::And(reg, value)   => gpio.and_register(reg, value),
+			RegisterOp::Or(reg, value)    => gpio.or_register(reg, value),
+			RegisterOp::Modify { register, mask, value } => gpio.modify_register(register, |x| x & !mask | value & mask),
+			RegisterOp::Delay(duration)   => gpio.delay(duration),
+		}
+	}
+
+	/// Perform the operation directly on a backend, without a [`Gpio`](crate::Gpio) handle.
+	///
+	/// The `no_std` core has no clock, so delays are performed by calling `delay`.
+	/// Unlike with a handle, read-modify-write cycles are not serialized with other threads.
+	///
+	/// # Safety
+	/// The operation is written to the hardware as-is, without any validation.
+	pub unsafe fn execute_on(self, backend: &mut dyn GpioBackend, delay: impl FnOnce(Duration)) {
+		let (reg, mask, value) = match self {
+			RegisterOp::Write(reg, value) => {
+				crate::trace_write(reg, value);
+				return backend.write_register(reg, value);
+			},
+			RegisterOp::And(reg, value)   => (reg, !value, 0),
+			RegisterOp::Or(reg, value)    => (reg, value, value),
+			RegisterOp::Modify { register, mask, value } => (register, mask, value),
+			RegisterOp::Delay(duration)   => return delay(duration),
+		};
+		let old = backend.read_register(reg);
+		let new = old & !mask | value & mask;
+		crate::trace_modify(reg, old, new);
+		backend.write_register(reg, new);
+	}
 }
 
-/// Wait for a number of clock cycles.
+impl core::fmt::Display for RegisterOp {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			RegisterOp::Write(reg, value) => write!(f, "{} = 0x{:08X}", reg, value),
+			RegisterOp::And(reg, value)   => write!(f, "{} &= 0x{:08X}", reg, value),
+			RegisterOp::Or(reg, value)    => write!(f, "{} |= 0x{:08X}", reg, value),
+			RegisterOp::Modify { register, mask, value } => write!(f, "{} = {} & 0x{:08X} | 0x{:08X}", register, register, !mask, value & mask),
+			RegisterOp::Delay(duration)   => write!(f, "wait {} us", duration.as_micros()),
+		}
+	}
+}
+
+/// The kinds of potentially dangerous settings a configuration may change.
 ///
-/// This function will probably wait for a bit more,
-/// since it is implemented using a nop-loop.
-fn wait_cycles(cycles: usize) {
-	for _ in 0..cycles {
-		nop();
+/// Changing pull up/down modes is not atomic, event detection can interfere with the interrupt handling of the kernel,
+/// and alternate functions hand pins over to other peripherals.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Permissions {
+	pub pull: bool,
+	pub detect: bool,
+	pub alt_functions: bool,
+}
+
+impl Permissions {
+	/// Permissions that allow nothing dangerous.
+	pub fn none() -> Self {
+		Self::default()
+	}
+
+	/// Permissions that allow everything.
+	pub fn all() -> Self {
+		Self {
+			pull: true,
+			detect: true,
+			alt_functions: true,
+		}
 	}
 }
 
@@ -19,16 +112,44 @@ fn wait_cycles(cycles: usize) {
 ///
 /// The configuration will only change the bits associated with the settings to apply.
 /// For example, setting the function of pin 1 will not change the function of pin 2.
+///
+/// Function changes are glitch-free as far as the hardware allows:
+///  - the function of a pin is changed with a single write of its function select field,
+///    so it goes directly from the old function to the new one, without passing through input;
+///  - pins that become or stay an output get their level before their function changes,
+///    so they never drive the previous level of the output latch;
+///  - pins that become an input get their level after their function changes,
+///    so they don't briefly drive the new level.
+///
+/// Setting a setting that is already set follows the [`conflict_policy`](Self::conflict_policy) of the configuration,
+/// which overwrites the old value by default.
 #[derive(Clone)]
 pub struct GpioConfig {
 	pub function          : [Option<PinFunction>; 54],
-	pub level             : [Option<bool>; 54],
+	pub level             : [Option<Level>; 54],
 	pub detect_rise       : [Option<bool>; 54],
 	pub detect_fall       : [Option<bool>; 54],
 	pub detect_high       : [Option<bool>; 54],
 	pub detect_low        : [Option<bool>; 54],
 	pub detect_async_rise : [Option<bool>; 54],
 	pub detect_async_fall : [Option<bool>; 54],
+
+	/// What the setters do when a setting of a pin is already set, see [`ConflictPolicy`].
+	pub conflict_policy   : ConflictPolicy,
+}
+
+/// What to do when a setting of a pin is set again in a [`GpioConfig`].
+///
+/// Setting the same value again is never a conflict.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConflictPolicy {
+	/// Replace the old value, so the last setting wins.
+	#[default]
+	Overwrite,
+	/// Keep the old value, so the first setting wins.
+	KeepFirst,
+	/// Return an error, to detect contradictory settings from different sources.
+	Error,
 }
 
 /// The configuration for GPIO pull up/down modes.
@@ -42,6 +163,12 @@ pub struct GpioPullConfig {
 	pub pull_mode : [Option<PullMode>; 54],
 }
 
+impl Default for GpioConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl GpioConfig {
 	pub fn new() -> Self {
 		Self {
@@ -53,57 +180,257 @@ impl GpioConfig {
 			detect_low        : [None; 54],
 			detect_async_fall : [None; 54],
 			detect_async_rise : [None; 54],
+			conflict_policy   : ConflictPolicy::Overwrite,
 		}
 	}
 
-	pub fn set_function(&mut self, pin: usize, function: PinFunction) {
-		self.function[pin] = Some(function);
+	/// Create an empty configuration with a conflict policy.
+	pub fn with_conflict_policy(conflict_policy: ConflictPolicy) -> Self {
+		Self { conflict_policy, ..Self::new() }
 	}
 
-	pub fn set_level(&mut self, pin: usize, level: bool) {
-		self.level[pin] = Some(level);
+	pub fn set_function(&mut self, pin: usize, function: PinFunction) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.function[pin], function, pin, "function")
 	}
 
-	pub fn set_detect_rise(&mut self, pin: usize, detect: bool) {
-		self.detect_rise[pin] = Some(detect);
+	pub fn set_level(&mut self, pin: usize, level: impl Into<Level>) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.level[pin], level.into(), pin, "level")
 	}
 
-	pub fn set_detect_fall(&mut self, pin: usize, detect: bool) {
-		self.detect_fall[pin] = Some(detect);
+	pub fn set_detect_rise(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_rise[pin], detect, pin, "detect-rise")
 	}
 
-	pub fn set_detect_high(&mut self, pin: usize, detect: bool) {
-		self.detect_high[pin] = Some(detect);
+	pub fn set_detect_fall(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_fall[pin], detect, pin, "detect-fall")
 	}
 
-	pub fn set_detect_low(&mut self, pin: usize, detect: bool) {
-		self.detect_low[pin] = Some(detect);
+	pub fn set_detect_high(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_high[pin], detect, pin, "detect-high")
 	}
 
-	pub fn set_detect_async_rise(&mut self, pin: usize, detect: bool) {
-		self.detect_async_rise[pin] = Some(detect);
+	pub fn set_detect_low(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_low[pin], detect, pin, "detect-low")
 	}
 
-	pub fn set_detect_async_fall(&mut self, pin: usize, detect: bool) {
-		self.detect_async_fall[pin] = Some(detect);
+	pub fn set_detect_async_rise(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_async_rise[pin], detect, pin, "detect-async-rise")
 	}
 
-	/// Apply the configuration.
-	pub fn apply(&self, gpio: &mut Gpio) {
-		unsafe {
-			self.apply_functions(gpio);
-			self.apply_levels(gpio);
+	pub fn set_detect_async_fall(&mut self, pin: usize, detect: bool) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
+		set_setting(self.conflict_policy, &mut self.detect_async_fall[pin], detect, pin, "detect-async-fall")
+	}
+
+	/// Add the settings of another configuration, following the conflict policy of this configuration.
+	///
+	/// Nothing is changed if a conflict is found with [`ConflictPolicy::Error`].
+	pub fn merge(&mut self, other: &GpioConfig) -> Result<(), Error> {
+		let mut merged = self.clone();
+		let policy = merged.conflict_policy;
+		for pin in 0..54 {
+			if let Some(function) = other.function[pin] {
+				set_setting(policy, &mut merged.function[pin], function, pin, "function")?;
+			}
+
+			if let Some(level) = other.level[pin] {
+				set_setting(policy, &mut merged.level[pin], level, pin, "level")?;
+			}
+
+			let settings = [
+				(&mut merged.detect_rise,       &other.detect_rise,       "detect-rise"),
+				(&mut merged.detect_fall,       &other.detect_fall,       "detect-fall"),
+				(&mut merged.detect_high,       &other.detect_high,       "detect-high"),
+				(&mut merged.detect_low,        &other.detect_low,        "detect-low"),
+				(&mut merged.detect_async_rise, &other.detect_async_rise, "detect-async-rise"),
+				(&mut merged.detect_async_fall, &other.detect_async_fall, "detect-async-fall"),
+			];
+			for (dest, source, name) in settings {
+				if let Some(value) = source[pin] {
+					set_setting(policy, &mut dest[pin], value, pin, name)?;
+				}
+			}
+		}
+		*self = merged;
+		Ok(())
+	}
+
+	/// Check the configuration for conflicting or nonsensical settings.
+	///
+	/// This rejects:
+	///  - setting the level of a pin that is configured for an alternate function,
+	///    since the level would have no effect.
+	///  - enabling both high and low level detection on a pin,
+	///    since that makes the event status bit permanently set.
+	///
+	/// Use [`validate_for`](Self::validate_for) to also check that a backend can apply the configuration.
+	pub fn validate(&self) -> Result<(), Error> {
+		for pin in 0..54 {
+			if let (Some(level), Some(function)) = (self.level[pin], self.function[pin]) {
+				if function != PinFunction::Input && function != PinFunction::Output {
+					return Err(Error::new(ErrorKind::InvalidArgument, format!(
+						"pin {}: can not set level {} while configuring function {:?}",
+						pin,
+						if level.is_high() { "high" } else { "low" },
+						function,
+					)));
+				}
+			}
+
+			if self.detect_high[pin] == Some(true) && self.detect_low[pin] == Some(true) {
+				return Err(Error::new(ErrorKind::InvalidArgument, format!("pin {}: can not detect both high and low levels", pin)));
+			}
+		}
 
-			apply_registers(gpio, Register::ren,  &self.detect_rise);
-			apply_registers(gpio, Register::fen,  &self.detect_fall);
-			apply_registers(gpio, Register::hen,  &self.detect_high);
-			apply_registers(gpio, Register::len,  &self.detect_low);
-			apply_registers(gpio, Register::aren, &self.detect_async_rise);
-			apply_registers(gpio, Register::afen, &self.detect_async_fall);
+		Ok(())
+	}
+
+	/// Check the configuration like [`validate`](Self::validate), and check that a backend on a SoC can apply it.
+	///
+	/// This also rejects settings that are not supported by the backend, see [`check_capabilities`](Self::check_capabilities),
+	/// and alternate functions that are reserved on the SoC.
+	/// A configuration that passes is applied completely, or not at all if the permissions do not allow it.
+	pub fn validate_for(&self, capabilities: Capabilities, soc: Soc) -> Result<(), Error> {
+		self.validate()?;
+		self.check_capabilities(capabilities)?;
+
+		for pin in 0..54 {
+			if let Some(function) = self.function[pin].filter(|&x| soc.is_reserved_alt_function(pin, x)) {
+				return Err(Error::new(ErrorKind::InvalidArgument, format!("pin {}: {:?} is reserved on the {:?}", pin, function, soc)));
+			}
 		}
+
+		Ok(())
 	}
 
-	unsafe fn apply_functions(&self, gpio: &mut Gpio) {
+	/// Check that the configuration only changes settings allowed by the permissions.
+	pub fn check_permissions(&self, permissions: Permissions) -> Result<(), Error> {
+		for pin in 0..54 {
+			if let Some(function) = self.function[pin] {
+				if !permissions.alt_functions && function != PinFunction::Input && function != PinFunction::Output {
+					return Err(not_allowed(pin, "setting an alternate function"));
+				}
+			}
+
+			let detect = [
+				self.detect_rise[pin],
+				self.detect_fall[pin],
+				self.detect_high[pin],
+				self.detect_low[pin],
+				self.detect_async_rise[pin],
+				self.detect_async_fall[pin],
+			];
+			if !permissions.detect && detect.iter().any(Option::is_some) {
+				return Err(not_allowed(pin, "changing event detection"));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Check that the configuration only changes settings supported by a backend.
+	pub fn check_capabilities(&self, capabilities: Capabilities) -> Result<(), Error> {
+		if let Some(&pin) = self.pins().first().filter(|_| capabilities.read_only) {
+			return Err(not_supported(pin, "changing the configuration"));
+		}
+
+		for pin in 0..54 {
+			if let Some(function) = self.function[pin] {
+				if !capabilities.alt_functions && function != PinFunction::Input && function != PinFunction::Output {
+					return Err(not_supported(pin, "setting an alternate function"));
+				}
+			}
+
+			let detect = [
+				self.detect_rise[pin],
+				self.detect_fall[pin],
+				self.detect_high[pin],
+				self.detect_low[pin],
+				self.detect_async_rise[pin],
+				self.detect_async_fall[pin],
+			];
+			if !capabilities.events && detect.iter().any(Option::is_some) {
+				return Err(not_supported(pin, "changing event detection"));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Get the pins that have at least one setting in the configuration.
+	pub fn pins(&self) -> Vec<usize> {
+		(0..54).filter(|&pin| {
+			self.function[pin].is_some()
+				|| self.level[pin].is_some()
+				|| self.detect_rise[pin].is_some()
+				|| self.detect_fall[pin].is_some()
+				|| self.detect_high[pin].is_some()
+				|| self.detect_low[pin].is_some()
+				|| self.detect_async_rise[pin].is_some()
+				|| self.detect_async_fall[pin].is_some()
+		}).collect()
+	}
+
+	/// Check the permissions, validate and apply the configuration.
+	///
+	/// Nothing is written if the configuration is not allowed or not valid.
+	#[cfg(feature = "std")]
+	pub fn apply_with(&self, gpio: &mut Gpio, permissions: Permissions) -> Result<(), Error> {
+		self.check_permissions(permissions)?;
+		self.apply(gpio)
+	}
+
+	/// Validate and apply the configuration.
+	///
+	/// Nothing is written if the configuration is not valid or not supported by the backend of the handle.
+	/// See [`validate_for`](Self::validate_for) for the checks performed.
+	#[cfg(feature = "std")]
+	pub fn apply(&self, gpio: &mut Gpio) -> Result<(), Error> {
+		self.validate_for(gpio.capabilities(), gpio.soc())?;
+		self.apply_unchecked(gpio);
+		Ok(())
+	}
+
+	/// Apply the configuration without validating it first.
+	///
+	/// This is useful to restore a previously saved state, which may contain unusual combinations.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(pins = ?self.pins())))]
+	#[cfg(feature = "std")]
+	pub fn apply_unchecked(&self, gpio: &mut Gpio) {
+		for op in self.register_ops() {
+			unsafe { op.execute(gpio) }
+		}
+	}
+
+	/// Get the register operations that [`apply`](Self::apply) would perform, in order.
+	///
+	/// The operations are planned to touch as few registers as possible:
+	/// registers without changes are skipped, registers that are changed entirely are overwritten,
+	/// and partially changed registers are updated with a single read-modify-write.
+	/// The level and function writes are ordered to avoid glitches, as described for [`GpioConfig`].
+	pub fn register_ops(&self) -> Vec<RegisterOp> {
+		let mut ops = Vec::new();
+		self.level_ops(&mut ops, |function| function != Some(PinFunction::Input));
+		self.function_ops(&mut ops);
+		self.level_ops(&mut ops, |function| function == Some(PinFunction::Input));
+
+		register_ops(&mut ops, Register::ren,  &self.detect_rise);
+		register_ops(&mut ops, Register::fen,  &self.detect_fall);
+		register_ops(&mut ops, Register::hen,  &self.detect_high);
+		register_ops(&mut ops, Register::len,  &self.detect_low);
+		register_ops(&mut ops, Register::aren, &self.detect_async_rise);
+		register_ops(&mut ops, Register::afen, &self.detect_async_fall);
+		ops
+	}
+
+	fn function_ops(&self, ops: &mut Vec<RegisterOp>) {
 		let mut mask  = [0u32; 6];
 		let mut value = [0u32; 6];
 
@@ -117,24 +444,23 @@ impl GpioConfig {
 		}
 
 		for i in 0..6 {
-			// Zero all pins that we're chaning.
-			// This will set them to inputs, but that should be safe.
-			gpio.and_register(Register::fsel(i), !mask[i]);
-
-			// Then set the actual functions.
-			gpio.or_register(Register::fsel(i), value[i]);
+			// Each function select register holds 10 pins.
+			// The BCM2711 has 58 pins, so the last register also holds pins we don't support, and is never overwritten.
+			let full = if i < 5 { (1 << 30) - 1 } else { u32::MAX };
+			plan_write(ops, Register::fsel(i), full, mask[i], value[i]);
 		}
 	}
 
-	unsafe fn apply_levels(&self, gpio: &mut Gpio) {
+	/// Plan the level writes for the pins with a target function accepted by the filter.
+	fn level_ops(&self, ops: &mut Vec<RegisterOp>, filter: impl Fn(Option<PinFunction>) -> bool) {
 		let mut set = [0u32; 2];
 		let mut clr = [0u32; 2];
 
 		for (pin, level) in self.level.iter().enumerate() {
-			if let Some(level) = level {
+			if let Some(level) = level.filter(|_| filter(self.function[pin])) {
 				let reg   = pin / 32;
 				let index = pin % 32;
-				if *level {
+				if level.is_high() {
 					set[reg] |= 1 << index;
 				} else {
 					clr[reg] |= 1 << index;
@@ -142,13 +468,24 @@ impl GpioConfig {
 			}
 		}
 
+		// Writing zero bits to the set and clear registers has no effect, so skip empty writes.
 		for i in 0..2 {
-			gpio.write_register(Register::set(i), set[i]);
-			gpio.write_register(Register::clr(i), clr[i]);
+			if set[i] != 0 {
+				ops.push(RegisterOp::Write(Register::set(i), set[i]));
+			}
+			if clr[i] != 0 {
+				ops.push(RegisterOp::Write(Register::clr(i), clr[i]));
+			}
 		}
 	}
 }
 
+impl Default for GpioPullConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl GpioPullConfig {
 	pub fn new() -> Self {
 		Self {
@@ -156,16 +493,109 @@ impl GpioPullConfig {
 		}
 	}
 
-	pub fn set_pull_mode(&mut self, pin: usize, mode: PullMode) {
+	pub fn set_pull_mode(&mut self, pin: usize, mode: PullMode) -> Result<(), Error> {
+		crate::check_pin_index(pin)?;
 		self.pull_mode[pin] = Some(mode);
+		Ok(())
 	}
 
 	/// Apply the configuration.
 	///
+	/// On SoCs with readable pull up/down registers, the configuration is read back after applying it,
+	/// and an error is returned listing the pins that do not have the requested pull mode.
+	/// Nothing is written if the backend of the handle does not support changing pull up/down modes.
+	///
+	/// # Safety
 	/// This is not atomic.
 	/// If another process or the kernel is trying to change pull up/down
 	/// settings at the same time, the wrong type of pull up/down may be applied to pins.
-	pub unsafe fn apply(&self, gpio: &mut Gpio) {
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err, fields(pins = ?self.pins(), soc = ?gpio.soc())))]
+	#[cfg(feature = "std")]
+	pub unsafe fn apply(&self, gpio: &mut Gpio) -> Result<(), Error> {
+		self.check_capabilities(gpio.capabilities())?;
+		for op in self.register_ops_for(gpio.soc()) {
+			op.execute(gpio);
+		}
+		for (tracked, mode) in gpio.pull_modes.iter_mut().zip(self.pull_mode.iter()) {
+			if mode.is_some() {
+				*tracked = *mode;
+			}
+		}
+
+		if gpio.soc().has_pull_readback() {
+			self.verify(gpio)?;
+		}
+
+		Ok(())
+	}
+
+	/// Check that the configuration only changes settings allowed by the permissions.
+	pub fn check_permissions(&self, permissions: Permissions) -> Result<(), Error> {
+		match self.pull_mode.iter().position(Option::is_some) {
+			Some(pin) if !permissions.pull => Err(not_allowed(pin, "changing the pull up/down mode")),
+			_ => Ok(()),
+		}
+	}
+
+	/// Check that the configuration only changes settings supported by a backend.
+	pub fn check_capabilities(&self, capabilities: Capabilities) -> Result<(), Error> {
+		match self.pull_mode.iter().position(Option::is_some) {
+			Some(pin) if !capabilities.pull || capabilities.read_only => Err(not_supported(pin, "changing the pull up/down mode")),
+			_ => Ok(()),
+		}
+	}
+
+	/// Get the pins that have a pull up/down mode in the configuration.
+	pub fn pins(&self) -> Vec<usize> {
+		(0..54).filter(|&pin| self.pull_mode[pin].is_some()).collect()
+	}
+
+	/// Check that the hardware has the configured pull up/down modes.
+	///
+	/// Pins without a configured mode are not checked.
+	/// On SoCs without readable pull up/down registers this always succeeds.
+	#[cfg(feature = "std")]
+	pub fn verify(&self, gpio: &Gpio) -> Result<(), Error> {
+		let mut mismatches = Vec::new();
+		for (pin, mode) in self.pull_mode.iter().enumerate() {
+			if let Some(mode) = mode {
+				match gpio.read_pull_mode(pin)? {
+					None => (),
+					Some(actual) if actual == *mode => (),
+					Some(actual) => mismatches.push(format!("pin {} is {:?} instead of {:?}", pin, actual, mode)),
+				}
+			}
+		}
+
+		if mismatches.is_empty() {
+			Ok(())
+		} else {
+			Err(Error::new(ErrorKind::VerificationFailed, format!("pull up/down mode was not applied: {}", mismatches.join(", "))))
+		}
+	}
+
+	/// Get the register operations that [`apply`](Self::apply) would perform on a BCM2835, in order.
+	///
+	/// For each pull mode that is set on any pin, this emits the full clocked sequence:
+	/// write the mode to GPPUD, wait, clock it into the pins with GPPUDCLK0/1, wait,
+	/// and clear GPPUD and the clock registers again.
+	/// The pull mode can not be read back on the BCM2835, so pins are never skipped.
+	pub fn register_ops(&self) -> Vec<RegisterOp> {
+		self.register_ops_for(Soc::Bcm2835)
+	}
+
+	/// Get the register operations that [`apply`](Self::apply) would perform on a specific SoC, in order.
+	///
+	/// The BCM2835 uses the clocked GPPUD sequence,
+	/// while the BCM2711 has a GPPUPPDN register with two bits per pin.
+	pub fn register_ops_for(&self, soc: Soc) -> Vec<RegisterOp> {
+		match soc {
+			Soc::Bcm2835 => self.bcm2835_ops(),
+			Soc::Bcm2711 => self.bcm2711_ops(),
+		}
+	}
+
+	fn bcm2835_ops(&self) -> Vec<RegisterOp> {
 		let mut float_clk     = [0u32; 2];
 		let mut pull_up_clk   = [0u32; 2];
 		let mut pull_down_clk = [0u32; 2];
@@ -179,36 +609,69 @@ impl GpioPullConfig {
 			}
 		}
 
-		Self::apply_pull_mode(gpio, 0b00, float_clk);
-		Self::apply_pull_mode(gpio, 0b10, pull_up_clk);
-		Self::apply_pull_mode(gpio, 0b01, pull_down_clk);
+		let mut ops = Vec::new();
+		Self::pull_mode_ops(&mut ops, 0b00, float_clk);
+		Self::pull_mode_ops(&mut ops, 0b10, pull_up_clk);
+		Self::pull_mode_ops(&mut ops, 0b01, pull_down_clk);
+		ops
 	}
 
-	unsafe fn apply_pull_mode(gpio: &mut Gpio, mode: u32, pins: [u32; 2]) {
+	fn bcm2711_ops(&self) -> Vec<RegisterOp> {
+		let mut mask  = [0u32; 4];
+		let mut value = [0u32; 4];
+
+		for (pin, mode) in self.pull_mode.iter().enumerate() {
+			if let Some(mode) = mode {
+				let reg   = pin / 16;
+				let shift = pin % 16 * 2;
+				mask[reg]  |= 0b11 << shift;
+				value[reg] |= mode.to_bcm2711_bits() << shift;
+			}
+		}
+
+		let mut ops = Vec::new();
+		for i in 0..4 {
+			// The BCM2711 has 58 pins, so the last register holds 10 pins, but we only support 54.
+			let full = if i < 3 { u32::MAX } else { (1 << 20) - 1 };
+			plan_write(&mut ops, Register::puppdn(i), full, mask[i], value[i]);
+		}
+		ops
+	}
+
+	fn pull_mode_ops(ops: &mut Vec<RegisterOp>, mode: u32, pins: [u32; 2]) {
 		// Do nothing if not necessary.
 		if pins[0] == 0 && pins[1] == 0 {
 			return;
 		}
 
-		// Set the pull up/down bits and wait for 150 cycles.
-		gpio.write_register(Register::GPPUDCLK0, 0);
-		gpio.write_register(Register::GPPUDCLK1, 0);
-		gpio.write_register(Register::GPPUD, mode);
-		wait_cycles(150);
+		// Set the pull up/down bits and wait for the control signal to settle.
+		// The datasheet asks for 150 cycles, which is well within the setup time at any clock speed.
+		ops.push(RegisterOp::Write(Register::GPPUDCLK0, 0));
+		ops.push(RegisterOp::Write(Register::GPPUDCLK1, 0));
+		ops.push(RegisterOp::Write(Register::GPPUD, mode));
+		ops.push(RegisterOp::Delay(PULL_SETUP_TIME));
 
-		// Set the clock for the pins to modify and wait 150 cycles.
-		gpio.write_register(Register::GPPUDCLK0, pins[0]);
-		gpio.write_register(Register::GPPUDCLK1, pins[1]);
-		wait_cycles(150);
+		// Set the clock for the pins to modify and wait again.
+		ops.push(RegisterOp::Write(Register::GPPUDCLK0, pins[0]));
+		ops.push(RegisterOp::Write(Register::GPPUDCLK1, pins[1]));
+		ops.push(RegisterOp::Delay(PULL_SETUP_TIME));
 
 		// Clear the signal and the clocks.
-		gpio.write_register(Register::GPPUDCLK0, 0);
-		gpio.write_register(Register::GPPUDCLK1, 0);
-		gpio.write_register(Register::GPPUD,     0);
+		ops.push(RegisterOp::Write(Register::GPPUDCLK0, 0));
+		ops.push(RegisterOp::Write(Register::GPPUDCLK1, 0));
+		ops.push(RegisterOp::Write(Register::GPPUD,     0));
 	}
 }
 
-unsafe fn apply_registers<F>(gpio: &mut Gpio, register: F, values: &[Option<bool>; 54])
+fn not_allowed(pin: usize, what: &str) -> Error {
+	Error::new(ErrorKind::NotAllowed, format!("pin {}: {} is not allowed", pin, what))
+}
+
+fn not_supported(pin: usize, what: &str) -> Error {
+	Error::new(ErrorKind::Unsupported, format!("pin {}: {} is not supported by the backend", pin, what))
+}
+
+fn register_ops<F>(ops: &mut Vec<RegisterOp>, register: F, values: &[Option<bool>; 54])
 where
 	F: Fn(usize) -> Register,
 {
@@ -225,10 +688,177 @@ where
 	}
 
 	for i in 0..2 {
-		// Zero all bits that we're changing.
-		gpio.and_register(register(i), !out_l[i]);
+		// The BCM2711 has 58 pins, so the second register also holds pins we don't support, and is never overwritten.
+		plan_write(ops, register(i), u32::MAX, out_l[i], out_h[i]);
+	}
+}
+
+/// Set a single setting of a pin, following a conflict policy.
+fn set_setting<T: Copy + PartialEq + core::fmt::Debug>(policy: ConflictPolicy, setting: &mut Option<T>, value: T, pin: usize, name: &str) -> Result<(), Error> {
+	match (*setting, policy) {
+		(Some(old), ConflictPolicy::Error) if old != value => Err(Error::new(ErrorKind::InvalidArgument, format!(
+			"pin {}: {} is already set to {:?}, can not set it to {:?}",
+			pin,
+			name,
+			old,
+			value,
+		))),
+		(Some(_), ConflictPolicy::KeepFirst) => Ok(()),
+		_ => {
+			*setting = Some(value);
+			Ok(())
+		},
+	}
+}
+
+/// Plan the cheapest operation to change the bits of a register selected by a mask.
+///
+/// Nothing is done if the mask is empty, and the register is overwritten if the mask covers all used bits.
+fn plan_write(ops: &mut Vec<RegisterOp>, register: Register, full: u32, mask: u32, value: u32) {
+	if mask == 0 {
+		return;
+	}
+
+	if mask & full == full {
+		ops.push(RegisterOp::Write(register, value));
+	} else {
+		ops.push(RegisterOp::Modify { register, mask, value });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn register_ops_skip_untouched_registers() {
+		assert_eq!(GpioConfig::new().register_ops(), []);
+
+		let mut config = GpioConfig::new();
+		config.set_detect_rise(40, true).unwrap();
+		config.set_detect_fall(3, false).unwrap();
+		assert_eq!(config.register_ops(), [
+			RegisterOp::Modify { register: Register::GPREN1, mask: 1 << 8, value: 1 << 8 },
+			RegisterOp::Modify { register: Register::GPFEN0, mask: 1 << 3, value: 0 },
+		]);
+	}
+
+	#[test]
+	fn register_ops_overwrite_only_fully_modeled_registers() {
+		let mut config = GpioConfig::new();
+		for pin in 50..54 {
+			config.set_function(pin, PinFunction::Output).unwrap();
+		}
+		for pin in 32..54 {
+			config.set_detect_high(pin, true).unwrap();
+		}
+		for pin in 0..32 {
+			config.set_detect_low(pin, false).unwrap();
+		}
+		config.set_function(4, PinFunction::Alt0).unwrap();
+		config.set_function(7, PinFunction::Input).unwrap();
+		assert_eq!(config.register_ops(), [
+			RegisterOp::Modify { register: Register::GPFSEL0, mask: 0b111 << 21 | 0b111 << 12, value: 0b100 << 12 },
+			RegisterOp::Modify { register: Register::GPFSEL5, mask: 0xFFF, value: 0b001_001_001_001 },
+			RegisterOp::Modify { register: Register::GPHEN1, mask: (1 << 22) - 1, value: (1 << 22) - 1 },
+			RegisterOp::Write(Register::GPLEN0, 0),
+		]);
+
+		let mut config = GpioConfig::new();
+		for pin in 0..10 {
+			config.set_function(pin, PinFunction::Output).unwrap();
+		}
+		assert_eq!(config.register_ops(), [RegisterOp::Write(Register::GPFSEL0, 0x0924_9249)]);
+	}
+
+	#[test]
+	fn register_ops_are_glitch_free() {
+		let mut config = GpioConfig::new();
+		config.set_function(17, PinFunction::Output).unwrap();
+		config.set_level(17, Level::High).unwrap();
+		config.set_function(4, PinFunction::Input).unwrap();
+		config.set_level(4, Level::Low).unwrap();
+		config.set_level(27, Level::Low).unwrap();
+
+		// Pin 17 gets its level before it becomes an output, pin 4 only after it became an input.
+		// Pin 27 keeps its function, so it may be an output and is handled like pin 17.
+		assert_eq!(config.register_ops(), [
+			RegisterOp::Write(Register::GPSET0, 1 << 17),
+			RegisterOp::Write(Register::GPCLR0, 1 << 27),
+			RegisterOp::Modify { register: Register::GPFSEL0, mask: 0b111 << 12, value: 0 },
+			RegisterOp::Modify { register: Register::GPFSEL1, mask: 0b111 << 21, value: 0b001 << 21 },
+			RegisterOp::Write(Register::GPCLR0, 1 << 4),
+		]);
+	}
+
+	#[test]
+	fn conflict_policy() {
+		let mut config = GpioConfig::new();
+		config.set_level(17, Level::High).unwrap();
+		config.set_level(17, Level::Low).unwrap();
+		assert_eq!(config.level[17], Some(Level::Low));
+
+		let mut config = GpioConfig::with_conflict_policy(ConflictPolicy::KeepFirst);
+		config.set_level(17, Level::High).unwrap();
+		config.set_level(17, Level::Low).unwrap();
+		assert_eq!(config.level[17], Some(Level::High));
+
+		let mut config = GpioConfig::with_conflict_policy(ConflictPolicy::Error);
+		config.set_level(17, Level::High).unwrap();
+		config.set_level(17, Level::High).unwrap();
+		assert_eq!(config.set_level(17, Level::Low).unwrap_err().kind(), ErrorKind::InvalidArgument);
+		assert_eq!(config.level[17], Some(Level::High));
+	}
+
+	#[test]
+	fn merge() {
+		let mut other = GpioConfig::new();
+		other.set_function(17, PinFunction::Output).unwrap();
+		other.set_level(17, Level::Low).unwrap();
+		other.set_detect_fall(22, true).unwrap();
+
+		let mut config = GpioConfig::new();
+		config.set_level(17, Level::High).unwrap();
+		config.merge(&other).unwrap();
+		assert_eq!(config.function[17], Some(PinFunction::Output));
+		assert_eq!(config.level[17], Some(Level::Low));
+		assert_eq!(config.detect_fall[22], Some(true));
+
+		let mut config = GpioConfig::with_conflict_policy(ConflictPolicy::KeepFirst);
+		config.set_level(17, Level::High).unwrap();
+		config.merge(&other).unwrap();
+		assert_eq!(config.function[17], Some(PinFunction::Output));
+		assert_eq!(config.level[17], Some(Level::High));
+	}
+
+	#[test]
+	fn merge_conflict_changes_nothing() {
+		let mut other = GpioConfig::new();
+		other.set_function(4, PinFunction::Input).unwrap();
+		other.set_level(17, Level::Low).unwrap();
+
+		let mut config = GpioConfig::with_conflict_policy(ConflictPolicy::Error);
+		config.set_level(17, Level::High).unwrap();
+		assert_eq!(config.merge(&other).unwrap_err().kind(), ErrorKind::InvalidArgument);
+		assert_eq!(config.function[4], None);
+		assert_eq!(config.level[17], Some(Level::High));
+	}
+
+	#[test]
+	fn validate_for_backend_and_soc() {
+		let mut config = GpioConfig::new();
+		config.set_function(40, PinFunction::Alt0).unwrap();
+		config.validate_for(Capabilities::all(), Soc::Bcm2711).unwrap();
+
+		let capabilities = Capabilities { alt_functions: false, ..Capabilities::all() };
+		assert_eq!(config.validate_for(capabilities, Soc::Bcm2711).unwrap_err().kind(), ErrorKind::Unsupported);
+
+		let capabilities = Capabilities { read_only: true, ..Capabilities::all() };
+		assert_eq!(config.validate_for(capabilities, Soc::Bcm2711).unwrap_err().kind(), ErrorKind::Unsupported);
 
-		// Then or the ones into them.
-		gpio.or_register(register(i), out_h[i]);
+		// ALT0 of pin 16 is reserved on both SoCs.
+		config.set_function(16, PinFunction::Alt0).unwrap();
+		assert_eq!(config.validate_for(Capabilities::all(), Soc::Bcm2835).unwrap_err().kind(), ErrorKind::InvalidArgument);
+		assert_eq!(config.validate_for(Capabilities::all(), Soc::Bcm2711).unwrap_err().kind(), ErrorKind::InvalidArgument);
 	}
 }
@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+use crate::{Error, Gpio, PinFunction};
+
+/// The high time of a 0 bit.
+const T0H: Duration = Duration::from_nanos(400);
+
+/// The high time of a 1 bit.
+const T1H: Duration = Duration::from_nanos(800);
+
+/// The total time of one bit.
+const BIT_TIME: Duration = Duration::from_nanos(1250);
+
+/// The low time that latches the data, long enough for newer WS2812B revisions too.
+const RESET_TIME: Duration = Duration::from_micros(300);
+
+/// Bit-banged driver for a strip of WS2812 (NeoPixel) LEDs.
+///
+/// The signal is generated by busy-waiting, so it is sensitive to preemption and interrupts.
+/// Good enough for testing the wiring of a strip, but expect the occasional wrong color.
+/// The pin is configured as output, and left as output with a low level.
+pub struct Ws2812<'a> {
+	gpio: &'a mut Gpio,
+	pin: usize,
+}
+
+impl<'a> Ws2812<'a> {
+	/// Create a new driver for a LED strip connected to the given pin.
+	pub fn new(gpio: &'a mut Gpio, pin: usize) -> Result<Self, Error> {
+		crate::check_pin_index(pin)?;
+		gpio.write_level(pin, false);
+		gpio.write_function(pin, PinFunction::Output);
+		Ok(Self { gpio, pin })
+	}
+
+	/// Send RGB colors to the strip, one for each LED.
+	pub fn write(&mut self, colors: &[[u8; 3]]) {
+		let mut data = Vec::with_capacity(colors.len() * 3);
+		for &[red, green, blue] in colors {
+			data.extend_from_slice(&[green, red, blue]);
+		}
+
+		// Make sure the previous data is latched before sending new data.
+		self.gpio.write_level(self.pin, false);
+		spin_until(Instant::now() + RESET_TIME);
+
+		let mut start = Instant::now();
+		for byte in data {
+			for i in (0..8).rev() {
+				let high = if byte >> i & 1 == 1 { T1H } else { T0H };
+				self.gpio.write_level(self.pin, true);
+				spin_until(start + high);
+				self.gpio.write_level(self.pin, false);
+				start += BIT_TIME;
+				spin_until(start);
+			}
+		}
+
+		spin_until(Instant::now() + RESET_TIME);
+	}
+}
+
+fn spin_until(deadline: Instant) {
+	while Instant::now() < deadline {
+		std::hint::spin_loop();
+	}
+}